@@ -0,0 +1 @@
+ALTER TABLE price_interval_attestations DROP COLUMN aggregate_value;
@@ -0,0 +1,3 @@
+ALTER TABLE price_interval_attestations ADD COLUMN aggregate_value BIGINT;
+UPDATE price_interval_attestations SET aggregate_value = value;
+ALTER TABLE price_interval_attestations ALTER COLUMN aggregate_value SET NOT NULL;
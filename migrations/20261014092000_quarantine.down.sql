@@ -0,0 +1,2 @@
+DROP TABLE quarantined_validators;
+DROP TABLE validator_offences;
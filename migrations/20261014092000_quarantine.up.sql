@@ -0,0 +1,14 @@
+CREATE TABLE validator_offences (
+    validator_public_key TEXT NOT NULL,
+    offence TEXT NOT NULL,
+    slot_number BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX validator_offences_validator_public_key_offence_idx
+    ON validator_offences (validator_public_key, offence);
+
+CREATE TABLE quarantined_validators (
+    validator_public_key TEXT NOT NULL PRIMARY KEY,
+    reason TEXT NOT NULL,
+    quarantined_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
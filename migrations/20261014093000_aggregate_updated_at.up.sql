@@ -0,0 +1 @@
+ALTER TABLE aggregate_interval_attestations ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
@@ -0,0 +1,2 @@
+ALTER TABLE price_value_attestations DROP COLUMN signing_preimage;
+ALTER TABLE price_interval_attestations DROP COLUMN signing_preimage;
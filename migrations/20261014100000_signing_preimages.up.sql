@@ -0,0 +1,2 @@
+ALTER TABLE price_value_attestations ADD COLUMN signing_preimage TEXT;
+ALTER TABLE price_interval_attestations ADD COLUMN signing_preimage TEXT;
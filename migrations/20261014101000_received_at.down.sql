@@ -0,0 +1,2 @@
+ALTER TABLE price_value_attestations DROP COLUMN received_at;
+ALTER TABLE price_interval_attestations DROP COLUMN received_at;
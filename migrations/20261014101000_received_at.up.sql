@@ -0,0 +1,2 @@
+ALTER TABLE price_value_attestations ADD COLUMN received_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+ALTER TABLE price_interval_attestations ADD COLUMN received_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
@@ -0,0 +1,8 @@
+DROP INDEX aggregate_interval_attestations_asset_key_idx;
+ALTER TABLE aggregate_interval_attestations DROP COLUMN asset;
+
+ALTER TABLE price_interval_attestations DROP COLUMN asset;
+
+ALTER TABLE price_value_attestations DROP CONSTRAINT price_value_attestations_pkey;
+ALTER TABLE price_value_attestations ADD PRIMARY KEY (validator_public_key, slot_number);
+ALTER TABLE price_value_attestations DROP COLUMN asset;
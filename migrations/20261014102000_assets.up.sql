@@ -0,0 +1,9 @@
+ALTER TABLE price_value_attestations ADD COLUMN asset TEXT NOT NULL DEFAULT 'ETHUSD';
+ALTER TABLE price_value_attestations DROP CONSTRAINT price_value_attestations_pkey;
+ALTER TABLE price_value_attestations ADD PRIMARY KEY (validator_public_key, asset, slot_number);
+
+ALTER TABLE price_interval_attestations ADD COLUMN asset TEXT NOT NULL DEFAULT 'ETHUSD';
+
+ALTER TABLE aggregate_interval_attestations ADD COLUMN asset TEXT NOT NULL DEFAULT 'ETHUSD';
+CREATE UNIQUE INDEX aggregate_interval_attestations_asset_key_idx
+    ON aggregate_interval_attestations (asset, slot_number, interval_size, value);
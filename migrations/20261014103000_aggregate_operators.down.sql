@@ -0,0 +1,2 @@
+ALTER TABLE aggregate_interval_attestations DROP COLUMN num_operators;
+ALTER TABLE aggregate_interval_attestations DROP COLUMN operators;
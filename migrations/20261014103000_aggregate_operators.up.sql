@@ -0,0 +1,18 @@
+ALTER TABLE aggregate_interval_attestations ADD COLUMN operators TEXT[] NOT NULL DEFAULT '{}';
+ALTER TABLE aggregate_interval_attestations ADD COLUMN num_operators BIGINT NOT NULL DEFAULT 0;
+-- Without known operators every validator counts as its own operator.
+UPDATE aggregate_interval_attestations aggregate SET operators = ARRAY(
+    SELECT DISTINCT
+        validator_public_key
+    FROM
+        price_interval_attestations attestation
+    WHERE
+        attestation.asset = aggregate.asset
+    AND
+        attestation.slot_number = aggregate.slot_number
+    AND
+        attestation.interval_size = aggregate.interval_size
+    AND
+        attestation.aggregate_value = aggregate.value
+);
+UPDATE aggregate_interval_attestations SET num_operators = cardinality(operators);
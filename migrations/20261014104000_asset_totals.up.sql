@@ -0,0 +1,20 @@
+CREATE TABLE asset_totals (
+    asset TEXT NOT NULL PRIMARY KEY,
+    num_value_attestations BIGINT NOT NULL DEFAULT 0,
+    num_interval_attestations BIGINT NOT NULL DEFAULT 0
+);
+INSERT INTO asset_totals (asset, num_value_attestations, num_interval_attestations)
+SELECT
+    asset,
+    SUM(num_value_attestations),
+    SUM(num_interval_attestations)
+FROM (
+    SELECT asset, COUNT(*) AS num_value_attestations, 0 AS num_interval_attestations
+    FROM price_value_attestations
+    GROUP BY asset
+    UNION ALL
+    SELECT asset, 0, COUNT(*)
+    FROM price_interval_attestations
+    GROUP BY asset
+) counts
+GROUP BY asset;
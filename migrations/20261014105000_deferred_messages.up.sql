@@ -0,0 +1,6 @@
+CREATE TABLE deferred_messages (
+    id BIGSERIAL PRIMARY KEY,
+    slot_number BIGINT NOT NULL,
+    message TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
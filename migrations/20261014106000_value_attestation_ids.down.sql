@@ -0,0 +1 @@
+ALTER TABLE price_value_attestations DROP COLUMN id;
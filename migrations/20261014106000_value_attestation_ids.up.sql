@@ -0,0 +1,2 @@
+ALTER TABLE price_value_attestations ADD COLUMN id BIGSERIAL;
+CREATE UNIQUE INDEX price_value_attestations_id_idx ON price_value_attestations (id);
@@ -0,0 +1,5 @@
+CREATE TABLE validator_set_sizes (
+    from_slot BIGINT PRIMARY KEY,
+    num_validators BIGINT NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
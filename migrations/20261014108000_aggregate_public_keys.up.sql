@@ -0,0 +1,28 @@
+ALTER TABLE aggregate_interval_attestations ADD COLUMN public_keys TEXT[] NOT NULL DEFAULT '{}';
+
+UPDATE aggregate_interval_attestations AS aggregate
+SET
+    public_keys = contributions.public_keys
+FROM (
+    SELECT
+        asset,
+        slot_number,
+        interval_size,
+        aggregate_value,
+        ARRAY_AGG(DISTINCT validator_public_key) AS public_keys
+    FROM
+        price_interval_attestations
+    GROUP BY
+        asset,
+        slot_number,
+        interval_size,
+        aggregate_value
+) AS contributions
+WHERE
+    aggregate.asset = contributions.asset
+AND
+    aggregate.slot_number = contributions.slot_number
+AND
+    aggregate.interval_size = contributions.interval_size
+AND
+    aggregate.value = contributions.aggregate_value;
@@ -0,0 +1 @@
+ALTER TABLE aggregate_interval_attestations DROP COLUMN signatures;
@@ -0,0 +1,28 @@
+ALTER TABLE aggregate_interval_attestations ADD COLUMN signatures TEXT[] NOT NULL DEFAULT '{}';
+
+UPDATE aggregate_interval_attestations AS aggregate
+SET
+    signatures = contributions.signatures
+FROM (
+    SELECT
+        asset,
+        slot_number,
+        interval_size,
+        aggregate_value,
+        ARRAY_AGG(signature) AS signatures
+    FROM
+        price_interval_attestations
+    GROUP BY
+        asset,
+        slot_number,
+        interval_size,
+        aggregate_value
+) AS contributions
+WHERE
+    aggregate.asset = contributions.asset
+AND
+    aggregate.slot_number = contributions.slot_number
+AND
+    aggregate.interval_size = contributions.interval_size
+AND
+    aggregate.value = contributions.aggregate_value;
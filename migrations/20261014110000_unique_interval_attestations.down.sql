@@ -0,0 +1 @@
+DROP INDEX price_interval_attestations_validator_slot_value_idx;
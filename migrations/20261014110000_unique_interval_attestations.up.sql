@@ -0,0 +1,7 @@
+CREATE UNIQUE INDEX price_interval_attestations_validator_slot_value_idx ON price_interval_attestations (
+    validator_public_key,
+    asset,
+    slot_number,
+    interval_size,
+    value
+);
@@ -0,0 +1,13 @@
+CREATE TABLE finalized_slots (
+    slot_number BIGINT PRIMARY KEY,
+    finalized_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- Best aggregate reaching quorum when the slot was finalized, null if none did.
+    asset TEXT,
+    value BIGINT,
+    interval_size BIGINT,
+    aggregate_signature TEXT,
+    aggregate_public_key TEXT,
+    public_keys TEXT[],
+    num_validators BIGINT,
+    num_operators BIGINT
+);
@@ -0,0 +1,3 @@
+ALTER TABLE aggregate_interval_attestations ADD COLUMN total_weight BIGINT NOT NULL DEFAULT 0;
+-- Without configured weights every validator weighs one.
+UPDATE aggregate_interval_attestations SET total_weight = num_validators;
@@ -0,0 +1 @@
+DROP TABLE equivocations;
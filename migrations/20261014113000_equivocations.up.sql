@@ -0,0 +1,14 @@
+-- Both signed value messages of a validator equivocating on a slot, kept as evidence.
+CREATE TABLE equivocations (
+    validator_public_key TEXT NOT NULL,
+    asset TEXT NOT NULL,
+    slot_number BIGINT NOT NULL,
+    -- The value message that was stored.
+    value BIGINT NOT NULL,
+    signature TEXT NOT NULL,
+    -- The value message that was rejected.
+    conflicting_value BIGINT NOT NULL,
+    conflicting_signature TEXT NOT NULL,
+    detected_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (validator_public_key, asset, slot_number, conflicting_value)
+);
@@ -0,0 +1,3 @@
+-- Unix timestamp (in seconds) at which the validator observed the price, null for messages
+-- without one.
+ALTER TABLE price_value_attestations ADD COLUMN price_timestamp BIGINT;
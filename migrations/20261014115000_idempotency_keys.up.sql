@@ -0,0 +1,9 @@
+-- Outcomes of submissions carrying an idempotency key, replayed when the key is reused.
+CREATE TABLE idempotency_keys (
+    validator_public_key TEXT NOT NULL,
+    idempotency_key TEXT NOT NULL,
+    status_code INTEGER NOT NULL,
+    body TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (validator_public_key, idempotency_key)
+);
@@ -0,0 +1,2 @@
+DROP INDEX idempotency_keys_created_at_idx;
+ALTER TABLE idempotency_keys DROP COLUMN body_digest;
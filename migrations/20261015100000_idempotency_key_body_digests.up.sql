@@ -0,0 +1,5 @@
+-- Responses are only replayed to retries of the same message, the digest of which is stored
+-- along with them. Stored responses predate the digests and are dropped.
+DELETE FROM idempotency_keys;
+ALTER TABLE idempotency_keys ADD COLUMN body_digest TEXT NOT NULL;
+CREATE INDEX idempotency_keys_created_at_idx ON idempotency_keys (created_at);
@@ -0,0 +1,4 @@
+ALTER TABLE deferred_messages
+    DROP CONSTRAINT deferred_messages_validator_public_key_asset_slot_number_key,
+    DROP COLUMN validator_public_key,
+    DROP COLUMN asset;
@@ -0,0 +1,23 @@
+-- Like stored value attestations, deferred messages are unique per validator, asset and slot so
+-- resubmissions don't pile up until their slot enters the value window.
+ALTER TABLE deferred_messages
+    ADD COLUMN validator_public_key TEXT,
+    ADD COLUMN asset TEXT;
+UPDATE deferred_messages SET
+    validator_public_key = message::json->>'validator_public_key',
+    asset = message::json->>'asset';
+DELETE FROM deferred_messages duplicate
+USING deferred_messages original
+WHERE
+    duplicate.id > original.id
+AND
+    duplicate.validator_public_key = original.validator_public_key
+AND
+    duplicate.asset = original.asset
+AND
+    duplicate.slot_number = original.slot_number;
+ALTER TABLE deferred_messages
+    ALTER COLUMN validator_public_key SET NOT NULL,
+    ALTER COLUMN asset SET NOT NULL,
+    ADD CONSTRAINT deferred_messages_validator_public_key_asset_slot_number_key
+        UNIQUE (validator_public_key, asset, slot_number);
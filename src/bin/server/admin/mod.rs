@@ -0,0 +1,783 @@
+use crate::attestations::{validate_message, DomainType, SignedMessage};
+use crate::db::DbPool;
+use crate::slots::CurrentSlot;
+use crate::state::AppState;
+use axum::{
+    body::{Body, Bytes},
+    extract::{FromRequest, Query, State},
+    http::{header::AUTHORIZATION, request::Parts, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use bls::{PublicKey, Signature};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use ssz_derive::Encode;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const GENESIS_TIME_SETTING: &str = "genesis_time";
+/// How long an admin challenge can be used to sign a request with.
+const ADMIN_CHALLENGE_TTL: Duration = Duration::from_secs(300);
+/// Maximum number of challenges handed out and neither used nor expired yet, as anyone can ask
+/// for one.
+const MAX_ADMIN_CHALLENGES: usize = 1000;
+const ADMIN_PUBLIC_KEY_HEADER: &str = "X-Admin-Public-Key";
+const ADMIN_NONCE_HEADER: &str = "X-Admin-Nonce";
+const ADMIN_SIGNATURE_HEADER: &str = "X-Admin-Signature";
+
+/// Rejects requests that neither carry one of the configured admin tokens as bearer token, nor
+/// are signed by one of the configured admin keys, and records every authorized request in the
+/// audit log.
+pub async fn require_admin_token(
+    State(state): State<Arc<AppState>>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Result<Response, StatusCode> {
+    // Buffer the body so it can be verified and its params recorded, admin requests are small.
+    let (parts, body) = request.into_parts();
+    let params = Bytes::from_request(Request::new(body), &state)
+        .await
+        .map_err(|rejection| rejection.into_response().status())?;
+    let action = parts
+        .uri
+        .path_and_query()
+        .map(|path_and_query| path_and_query.to_string())
+        .unwrap_or_default();
+    let admin_id = get_token_admin_id(&state, &parts)
+        .or_else(|| get_signing_admin_id(&state, &parts, &action, &params))
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    tracing::info!("Admin request by {}: {}", admin_id, parts.uri);
+
+    let response = next
+        .run(Request::from_parts(parts, Body::from(params.clone())))
+        .await;
+
+    let audit_entry = save_audit_entry(
+        &state.db_pool,
+        &admin_id,
+        &action,
+        &String::from_utf8_lossy(&params),
+        response.status(),
+    )
+    .await;
+    if let Err(e) = audit_entry {
+        tracing::error!("Error saving admin audit entry: {:?}", e);
+    }
+    Ok(response)
+}
+
+fn get_token_admin_id(state: &AppState, parts: &Parts) -> Option<String> {
+    let token = parts
+        .headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?;
+    state
+        .config
+        .admin_tokens
+        .iter()
+        .find(|admin_token| admin_token.token == token)
+        .map(|admin_token| admin_token.id.clone())
+}
+
+/// What an admin signs to authenticate a request, binding a challenge nonce to the exact
+/// request so neither can be replayed.
+#[derive(Clone, Debug, Encode)]
+pub struct AdminRequestMessage {
+    pub nonce: Vec<u8>,
+    pub method: Vec<u8>,
+    /// Path and query of the request.
+    pub action: Vec<u8>,
+    pub body: Vec<u8>,
+}
+
+impl SignedMessage for AdminRequestMessage {
+    const DOMAIN_TYPE: DomainType = DomainType::Admin;
+}
+
+fn get_signing_admin_id(
+    state: &AppState,
+    parts: &Parts,
+    action: &str,
+    body: &[u8],
+) -> Option<String> {
+    let header = |name: &str| parts.headers.get(name)?.to_str().ok();
+    let public_key: PublicKey = header(ADMIN_PUBLIC_KEY_HEADER)?.parse().ok()?;
+    let nonce = hex::decode(header(ADMIN_NONCE_HEADER)?).ok()?;
+    let signature: Signature = header(ADMIN_SIGNATURE_HEADER)?.parse().ok()?;
+    let admin_key = state
+        .config
+        .admin_keys
+        .iter()
+        .find(|admin_key| admin_key.public_key == public_key)?;
+
+    // Challenges are single use, even if the signature turns out to be invalid.
+    let issued_at = state.admin_challenges.lock().unwrap().remove(&nonce)?;
+    if issued_at.elapsed() > ADMIN_CHALLENGE_TTL {
+        return None;
+    }
+    let message = AdminRequestMessage {
+        nonce,
+        method: parts.method.as_str().as_bytes().to_vec(),
+        action: action.as_bytes().to_vec(),
+        body: body.to_vec(),
+    };
+    validate_message(
+        &public_key,
+        &message,
+        &signature,
+        state.config.digest_config(),
+    )
+    .then(|| admin_key.id.clone())
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AdminChallenge {
+    /// Hex encoded nonce to sign the next admin request with.
+    pub nonce: String,
+}
+
+/// Hands out a single use nonce for an admin to sign their next request with, refusing with 503
+/// while `MAX_ADMIN_CHALLENGES` are outstanding.
+pub async fn get_admin_challenge(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<AdminChallenge>, StatusCode> {
+    let mut admin_challenges = state.admin_challenges.lock().unwrap();
+    admin_challenges.retain(|_, issued_at| issued_at.elapsed() <= ADMIN_CHALLENGE_TTL);
+    if admin_challenges.len() >= MAX_ADMIN_CHALLENGES {
+        tracing::warn!("Refusing admin challenge, too many are outstanding");
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    let mut nonce = vec![0; 32];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    admin_challenges.insert(nonce.clone(), Instant::now());
+    Ok(Json(AdminChallenge {
+        nonce: hex::encode(nonce),
+    }))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AdminAuditEntry {
+    pub id: i64,
+    pub actor: String,
+    pub action: String,
+    pub params: String,
+    pub status_code: i32,
+    /// Unix timestamp (in seconds) of the request.
+    pub timestamp: i64,
+}
+
+async fn save_audit_entry(
+    db_pool: &DbPool,
+    actor: &str,
+    action: &str,
+    params: &str,
+    status_code: StatusCode,
+) -> eyre::Result<()> {
+    sqlx::query!(
+        "
+        INSERT INTO admin_audit(
+            actor,
+            action,
+            params,
+            status_code
+        )
+        VALUES (
+            $1,
+            $2,
+            $3,
+            $4
+        );
+        ",
+        actor,
+        action,
+        params,
+        status_code.as_u16() as i32,
+    )
+    .execute(db_pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_audit_log(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<AdminAuditEntry>>, StatusCode> {
+    let entries = sqlx::query!(
+        r#"
+        SELECT
+            id,
+            actor,
+            action,
+            params,
+            status_code,
+            EXTRACT(EPOCH FROM created_at)::BIGINT AS "timestamp!"
+        FROM
+            admin_audit
+        ORDER BY
+            id;
+        "#
+    )
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error getting admin audit log: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .into_iter()
+    .map(|row| AdminAuditEntry {
+        id: row.id,
+        actor: row.actor,
+        action: row.action,
+        params: row.params,
+        status_code: row.status_code,
+        timestamp: row.timestamp,
+    })
+    .collect();
+    Ok(Json(entries))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SetGenesisRequest {
+    pub genesis_time: u64,
+}
+
+/// Adjusts the genesis time used for all slot calculations, persisting it across restarts.
+pub async fn post_set_genesis(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SetGenesisRequest>,
+) -> Result<Json<CurrentSlot>, StatusCode> {
+    save_setting(
+        &state.db_pool,
+        GENESIS_TIME_SETTING,
+        &request.genesis_time.to_string(),
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!("Error saving genesis time: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    state.slot_clock.set_genesis_time(request.genesis_time);
+    tracing::warn!("Genesis time set to {}", request.genesis_time);
+    Ok(Json(CurrentSlot::from_slot_clock(&state.slot_clock)))
+}
+
+/// Tables holding rows keyed by slot number, deleted by `post_delete_slots`.
+const SLOT_TABLES: [&str; 5] = [
+    "price_value_attestations",
+    "price_interval_attestations",
+    "aggregate_interval_attestations",
+    "validator_offences",
+    "equivocations",
+];
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeleteSlotsQuery {
+    pub from: i64,
+    pub to: i64,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeleteSlotsResponse {
+    pub dry_run: bool,
+    /// Number of rows deleted per table, or that would be deleted in a dry run.
+    pub deleted: BTreeMap<String, u64>,
+}
+
+/// Deletes all rows in the (inclusive) slot range. Aggregates only ever hold attestations of
+/// their own slot, so they are deleted along with them. A dry run rolls the deletion back.
+pub async fn post_delete_slots(
+    Query(query): Query<DeleteSlotsQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<DeleteSlotsResponse>, StatusCode> {
+    if query.from > query.to {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let mut transaction = state.db_pool.begin().await.map_err(|e| {
+        tracing::error!("Error starting transaction: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let mut deleted = BTreeMap::new();
+    for table in SLOT_TABLES {
+        // Table names can't be bound as parameters, they all come from the constant above.
+        let rows_affected = sqlx::query(&format!(
+            "DELETE FROM {table} WHERE slot_number >= $1 AND slot_number <= $2;"
+        ))
+        .bind(query.from)
+        .bind(query.to)
+        .execute(&mut transaction)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error deleting slots from {}: {:?}", table, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .rows_affected();
+        deleted.insert(table.to_string(), rows_affected);
+    }
+
+    let result = if query.dry_run {
+        transaction.rollback().await
+    } else {
+        transaction.commit().await
+    };
+    result.map_err(|e| {
+        tracing::error!("Error finishing transaction: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if !query.dry_run {
+        // The deleted slots may include validators' latest values.
+        state.latest_values.lock().unwrap().clear();
+        tracing::warn!(
+            "Deleted slots {} to {}: {:?}",
+            query.from,
+            query.to,
+            deleted
+        );
+    }
+    Ok(Json(DeleteSlotsResponse {
+        dry_run: query.dry_run,
+        deleted,
+    }))
+}
+
+/// Applies settings changed at runtime by an admin, which take precedence over the config.
+pub async fn load_persisted_settings(state: &AppState) -> eyre::Result<()> {
+    if let Some(genesis_time) = get_setting(&state.db_pool, GENESIS_TIME_SETTING).await? {
+        state.slot_clock.set_genesis_time(genesis_time.parse()?);
+    }
+    Ok(())
+}
+
+async fn get_setting(db_pool: &DbPool, key: &str) -> eyre::Result<Option<String>> {
+    let value = sqlx::query!(
+        "
+        SELECT
+            value
+        FROM
+            settings
+        WHERE
+            key = $1;
+        ",
+        key
+    )
+    .fetch_optional(db_pool)
+    .await?
+    .map(|row| row.value);
+    Ok(value)
+}
+
+async fn save_setting(db_pool: &DbPool, key: &str, value: &str) -> eyre::Result<()> {
+    sqlx::query!(
+        "
+        INSERT INTO settings(
+            key,
+            value
+        )
+        VALUES (
+            $1,
+            $2
+        )
+        ON CONFLICT (key) DO UPDATE SET
+            value = EXCLUDED.value;
+        ",
+        key,
+        value,
+    )
+    .execute(db_pool)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::attestations::PriceIntervalEntry;
+    use crate::config::{AdminKey, Config};
+    use crate::test_utils::{
+        admin_config, get_test_message, sign_message, state_at_slot, TestApp, ADMIN_AUTHORIZATION,
+        TEST_MESSAGE_SLOT,
+    };
+    use axum::body::Body;
+    use bls::SecretKey;
+    use ssz::Encode;
+
+    #[sqlx::test]
+    async fn rejects_admin_requests_without_valid_token(db_pool: DbPool) {
+        let test_app = TestApp::with_config(db_pool, admin_config()).await;
+        let body = r#"{"genesis_time": 1606824035}"#;
+
+        test_app
+            .post_expect(
+                "/admin/set-genesis",
+                Body::from(body),
+                StatusCode::UNAUTHORIZED,
+            )
+            .await;
+        test_app
+            .post_expect_with_headers(
+                "/admin/set-genesis",
+                Body::from(body),
+                &[("Authorization", "Bearer wrong-token")],
+                StatusCode::UNAUTHORIZED,
+            )
+            .await;
+    }
+
+    async fn get_nonce(test_app: &TestApp) -> String {
+        let response = test_app
+            .get_expect("/admin/challenge", StatusCode::OK)
+            .await;
+        let challenge: AdminChallenge = serde_json::from_slice(&response).unwrap();
+        challenge.nonce
+    }
+
+    fn sign_admin_request(
+        private_key: &SecretKey,
+        nonce: &str,
+        action: &str,
+        body: &str,
+    ) -> String {
+        let message = AdminRequestMessage {
+            nonce: hex::decode(nonce).unwrap(),
+            method: b"POST".to_vec(),
+            action: action.as_bytes().to_vec(),
+            body: body.as_bytes().to_vec(),
+        };
+        sign_message(&message, private_key).to_string()
+    }
+
+    /// An admin request signed in the domain of value messages.
+    struct ValueDomainAdminRequest(AdminRequestMessage);
+
+    impl Encode for ValueDomainAdminRequest {
+        fn is_ssz_fixed_len() -> bool {
+            false
+        }
+
+        fn ssz_bytes_len(&self) -> usize {
+            self.0.ssz_bytes_len()
+        }
+
+        fn ssz_append(&self, buf: &mut Vec<u8>) {
+            self.0.ssz_append(buf)
+        }
+    }
+
+    impl SignedMessage for ValueDomainAdminRequest {
+        const DOMAIN_TYPE: DomainType = DomainType::Value;
+    }
+
+    #[sqlx::test]
+    async fn accepts_admin_requests_signed_by_admin_key(db_pool: DbPool) {
+        let private_key = SecretKey::random();
+        let config = Config {
+            admin_keys: vec![AdminKey {
+                id: "key-admin".to_string(),
+                public_key: private_key.public_key(),
+            }],
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool.clone(), config).await;
+        let public_key = private_key.public_key().to_string();
+        let body = format!(
+            r#"{{"genesis_time": {}}}"#,
+            Config::default().genesis_time + 12
+        );
+
+        let nonce = get_nonce(&test_app).await;
+        let signature = sign_admin_request(&private_key, &nonce, "/admin/set-genesis", &body);
+        let headers = [
+            (ADMIN_PUBLIC_KEY_HEADER, public_key.as_str()),
+            (ADMIN_NONCE_HEADER, nonce.as_str()),
+            (ADMIN_SIGNATURE_HEADER, signature.as_str()),
+        ];
+        test_app
+            .post_expect_with_headers(
+                "/admin/set-genesis",
+                Body::from(body.clone()),
+                &headers,
+                StatusCode::OK,
+            )
+            .await;
+
+        // The nonce can't be replayed
+        test_app
+            .post_expect_with_headers(
+                "/admin/set-genesis",
+                Body::from(body),
+                &headers,
+                StatusCode::UNAUTHORIZED,
+            )
+            .await;
+
+        let actor: String = sqlx::query_scalar("SELECT actor FROM admin_audit;")
+            .fetch_one(&db_pool)
+            .await
+            .unwrap();
+        assert_eq!(actor, "key-admin");
+    }
+
+    #[sqlx::test]
+    async fn rejects_admin_requests_with_invalid_signature(db_pool: DbPool) {
+        let private_key = SecretKey::random();
+        let config = Config {
+            admin_keys: vec![AdminKey {
+                id: "key-admin".to_string(),
+                public_key: private_key.public_key(),
+            }],
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        let body = r#"{"genesis_time": 1606824035}"#;
+
+        // Signed by a key that isn't an admin key
+        let other_key = SecretKey::random();
+        let nonce = get_nonce(&test_app).await;
+        let signature = sign_admin_request(&other_key, &nonce, "/admin/set-genesis", body);
+        let other_public_key = other_key.public_key().to_string();
+        test_app
+            .post_expect_with_headers(
+                "/admin/set-genesis",
+                Body::from(body),
+                &[
+                    (ADMIN_PUBLIC_KEY_HEADER, other_public_key.as_str()),
+                    (ADMIN_NONCE_HEADER, nonce.as_str()),
+                    (ADMIN_SIGNATURE_HEADER, signature.as_str()),
+                ],
+                StatusCode::UNAUTHORIZED,
+            )
+            .await;
+
+        // Signed over a different body
+        let public_key = private_key.public_key().to_string();
+        let nonce = get_nonce(&test_app).await;
+        let signature = sign_admin_request(&private_key, &nonce, "/admin/set-genesis", "{}");
+        test_app
+            .post_expect_with_headers(
+                "/admin/set-genesis",
+                Body::from(body),
+                &[
+                    (ADMIN_PUBLIC_KEY_HEADER, public_key.as_str()),
+                    (ADMIN_NONCE_HEADER, nonce.as_str()),
+                    (ADMIN_SIGNATURE_HEADER, signature.as_str()),
+                ],
+                StatusCode::UNAUTHORIZED,
+            )
+            .await;
+
+        // Signed over the same bytes, but as an oracle message
+        let nonce = get_nonce(&test_app).await;
+        let message = AdminRequestMessage {
+            nonce: hex::decode(&nonce).unwrap(),
+            method: b"POST".to_vec(),
+            action: b"/admin/set-genesis".to_vec(),
+            body: body.as_bytes().to_vec(),
+        };
+        let signature = sign_message(&ValueDomainAdminRequest(message), &private_key).to_string();
+        test_app
+            .post_expect_with_headers(
+                "/admin/set-genesis",
+                Body::from(body),
+                &[
+                    (ADMIN_PUBLIC_KEY_HEADER, public_key.as_str()),
+                    (ADMIN_NONCE_HEADER, nonce.as_str()),
+                    (ADMIN_SIGNATURE_HEADER, signature.as_str()),
+                ],
+                StatusCode::UNAUTHORIZED,
+            )
+            .await;
+
+        // Signed over a nonce that was never handed out
+        let nonce = hex::encode([0; 32]);
+        let signature = sign_admin_request(&private_key, &nonce, "/admin/set-genesis", body);
+        test_app
+            .post_expect_with_headers(
+                "/admin/set-genesis",
+                Body::from(body),
+                &[
+                    (ADMIN_PUBLIC_KEY_HEADER, public_key.as_str()),
+                    (ADMIN_NONCE_HEADER, nonce.as_str()),
+                    (ADMIN_SIGNATURE_HEADER, signature.as_str()),
+                ],
+                StatusCode::UNAUTHORIZED,
+            )
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn caps_outstanding_admin_challenges(db_pool: DbPool) {
+        let state = Arc::new(state_at_slot(db_pool, Config::default(), TEST_MESSAGE_SLOT));
+        for _ in 0..MAX_ADMIN_CHALLENGES {
+            assert!(get_admin_challenge(State(state.clone())).await.is_ok());
+        }
+        assert_eq!(
+            get_admin_challenge(State(state.clone())).await.err(),
+            Some(StatusCode::SERVICE_UNAVAILABLE)
+        );
+
+        // Using a challenge makes room for another one
+        let nonce = state
+            .admin_challenges
+            .lock()
+            .unwrap()
+            .keys()
+            .next()
+            .unwrap()
+            .clone();
+        state.admin_challenges.lock().unwrap().remove(&nonce);
+        assert!(get_admin_challenge(State(state)).await.is_ok());
+    }
+
+    #[sqlx::test]
+    async fn can_set_genesis_time(db_pool: DbPool) {
+        let config = admin_config();
+        let genesis_time = config.genesis_time;
+        let test_app = TestApp::with_config(db_pool.clone(), config.clone()).await;
+
+        let response = test_app.get_expect("/slot", StatusCode::OK).await;
+        let slot: CurrentSlot = serde_json::from_slice(&response).unwrap();
+        assert_eq!(slot.slot_number, TEST_MESSAGE_SLOT);
+        assert_eq!(slot.genesis_time, genesis_time);
+
+        // Moving genesis ten slots later puts the clock ten slots back
+        let new_genesis_time = genesis_time + 10 * config.seconds_per_slot;
+        test_app
+            .post_expect_with_headers(
+                "/admin/set-genesis",
+                Body::from(format!(r#"{{"genesis_time": {new_genesis_time}}}"#)),
+                &[ADMIN_AUTHORIZATION],
+                StatusCode::OK,
+            )
+            .await;
+
+        let response = test_app.get_expect("/slot", StatusCode::OK).await;
+        let slot: CurrentSlot = serde_json::from_slice(&response).unwrap();
+        assert_eq!(slot.slot_number, TEST_MESSAGE_SLOT - 10);
+        assert_eq!(slot.genesis_time, new_genesis_time);
+
+        // The adjusted genesis time survives a restart
+        let restarted_app = TestApp::with_config(db_pool, config).await;
+        let response = restarted_app.get_expect("/slot", StatusCode::OK).await;
+        let slot: CurrentSlot = serde_json::from_slice(&response).unwrap();
+        assert_eq!(slot.slot_number, TEST_MESSAGE_SLOT - 10);
+    }
+
+    #[sqlx::test]
+    async fn records_admin_actions_in_audit_log(db_pool: DbPool) {
+        let test_app = TestApp::with_config(db_pool, admin_config()).await;
+        let body = r#"{"genesis_time": 1606824035}"#;
+        test_app
+            .post_expect_with_headers(
+                "/admin/set-genesis",
+                Body::from(body),
+                &[ADMIN_AUTHORIZATION],
+                StatusCode::OK,
+            )
+            .await;
+        // Unauthorized requests have no actor and aren't recorded
+        test_app
+            .post_expect(
+                "/admin/set-genesis",
+                Body::from(body),
+                StatusCode::UNAUTHORIZED,
+            )
+            .await;
+
+        let response = test_app
+            .get_expect_with_headers("/admin/audit-log", &[ADMIN_AUTHORIZATION], StatusCode::OK)
+            .await;
+        let entries: Vec<AdminAuditEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].actor, "test-admin");
+        assert_eq!(entries[0].action, "/admin/set-genesis");
+        assert_eq!(entries[0].params, body);
+        assert_eq!(entries[0].status_code, 200);
+        assert!(entries[0].timestamp > 0);
+    }
+
+    async fn delete_slots(test_app: &TestApp, query: &str) -> DeleteSlotsResponse {
+        let response = test_app
+            .post_expect_with_headers(
+                &format!("/admin/delete-slots?{query}"),
+                Body::empty(),
+                &[ADMIN_AUTHORIZATION],
+                StatusCode::OK,
+            )
+            .await;
+        serde_json::from_slice(&response).unwrap()
+    }
+
+    fn expected_deleted(num_values: u64, num_intervals: u64) -> BTreeMap<String, u64> {
+        [
+            ("aggregate_interval_attestations", num_intervals),
+            ("equivocations", 0),
+            ("price_interval_attestations", num_intervals),
+            ("price_value_attestations", num_values),
+            ("validator_offences", 0),
+        ]
+        .into_iter()
+        .map(|(table, count)| (table.to_string(), count))
+        .collect()
+    }
+
+    #[sqlx::test]
+    async fn dry_run_counts_slot_rows_without_deleting(db_pool: DbPool) {
+        let test_message = get_test_message();
+        let num_intervals = test_message.interval_inclusion_messages.len() as u64;
+        let test_app = TestApp::with_config(db_pool, admin_config()).await;
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+
+        let response = delete_slots(
+            &test_app,
+            &format!("from={TEST_MESSAGE_SLOT}&to={TEST_MESSAGE_SLOT}&dry_run=true"),
+        )
+        .await;
+        assert!(response.dry_run);
+        assert_eq!(response.deleted, expected_deleted(1, num_intervals));
+
+        let response = test_app
+            .get_expect("/price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len() as u64, num_intervals);
+    }
+
+    #[sqlx::test]
+    async fn deletes_slot_rows(db_pool: DbPool) {
+        let test_message = get_test_message();
+        let num_intervals = test_message.interval_inclusion_messages.len() as u64;
+        let test_app = TestApp::with_config(db_pool, admin_config()).await;
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+
+        // Slots before the message are untouched
+        let response =
+            delete_slots(&test_app, &format!("from=0&to={}", TEST_MESSAGE_SLOT - 1)).await;
+        assert_eq!(response.deleted, expected_deleted(0, 0));
+
+        let response = delete_slots(
+            &test_app,
+            &format!("from={TEST_MESSAGE_SLOT}&to={}", TEST_MESSAGE_SLOT + 10),
+        )
+        .await;
+        assert!(!response.dry_run);
+        assert_eq!(response.deleted, expected_deleted(1, num_intervals));
+
+        for uri in [
+            "/price_value_attestations",
+            "/price_interval_attestations",
+            "/aggregate_price_interval_attestations",
+        ] {
+            let response = test_app.get_expect(uri, StatusCode::OK).await;
+            assert_eq!(&response[..], b"[]");
+        }
+    }
+}
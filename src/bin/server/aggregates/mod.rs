@@ -0,0 +1,1743 @@
+use crate::attestations::{
+    get_list_limit, get_operator, get_validator_weight, verify_aggregate_entry,
+    AggregatePriceIntervalEntry, PriceIntervalEntry,
+};
+use crate::canonical::{encode_public_key, encode_signature};
+use crate::db::DbPool;
+use crate::state::AppState;
+use axum::{
+    body::{boxed, Body, Bytes},
+    extract::{Path, Query, State},
+    http::{header::CONTENT_TYPE, HeaderName, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use bls::{AggregatePublicKey, AggregateSignature, PublicKey, Signature, INFINITY_SIGNATURE};
+use futures_util::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AggregateQuery {
+    pub slot: i64,
+    pub value: i64,
+    pub interval_size: i64,
+}
+
+/// The single aggregate of the slot, value and interval size, sparing consumers that know which
+/// aggregate they need the transfer of the whole table.
+pub async fn get_aggregate_by_key(
+    Query(query): Query<AggregateQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<AggregatePriceIntervalEntry>, StatusCode> {
+    let aggregate = get_aggregate(&state.db_pool, query.slot, query.interval_size, query.value)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting aggregate: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(aggregate))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FinalAggregatesQuery {
+    pub from_slot: Option<i64>,
+    /// Inclusive.
+    pub to_slot: Option<i64>,
+    /// Defaults to `DEFAULT_LIST_LIMIT`, capped at `MAX_LIST_LIMIT`.
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// The aggregates with at least `Config::finality_threshold` validators, which consumers can
+/// trust without judging the number of signers themselves.
+pub async fn get_final_aggregates(
+    Query(query): Query<FinalAggregatesQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<AggregatePriceIntervalEntry>>, StatusCode> {
+    let aggregates = sqlx::query_as::<_, AggregatePriceIntervalEntry>(
+        "
+        SELECT
+            asset,
+            value,
+            slot_number,
+            aggregate_signature,
+            aggregate_public_key,
+            public_keys,
+            interval_size,
+            num_validators,
+            num_operators
+        FROM
+            aggregate_interval_attestations
+        WHERE
+            num_validators >= $1
+        AND
+            ($2::BIGINT IS NULL OR slot_number >= $2)
+        AND
+            ($3::BIGINT IS NULL OR slot_number <= $3)
+        ORDER BY
+            slot_number,
+            asset,
+            interval_size,
+            value
+        LIMIT $4
+        OFFSET $5;
+        ",
+    )
+    .bind(state.config.finality_threshold)
+    .bind(query.from_slot)
+    .bind(query.to_slot)
+    .bind(get_list_limit(query.limit))
+    .bind(query.offset.unwrap_or(0))
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error getting final aggregates: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .into_iter()
+    .map(|aggregate| AggregatePriceIntervalEntry {
+        finalized: Some(true),
+        ..aggregate
+    })
+    .collect();
+    Ok(Json(aggregates))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AggregateSignatureInfo {
+    /// Length of the hex decoded signature, if it is valid hex.
+    pub byte_length: Option<usize>,
+    pub is_infinity: bool,
+    pub deserializes: bool,
+    pub error: Option<String>,
+}
+
+pub async fn get_aggregate_signature_info(
+    Path((slot_number, interval_size, value)): Path<(i64, i64, i64)>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<AggregateSignatureInfo>, StatusCode> {
+    let aggregate = get_aggregate(&state.db_pool, slot_number, interval_size, value)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting aggregate: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let info = match hex::decode(&aggregate.aggregate_signature) {
+        Ok(bytes) => {
+            let deserialize_error = AggregateSignature::deserialize(&bytes)
+                .err()
+                .map(|err| format!("{:?}", err));
+            AggregateSignatureInfo {
+                byte_length: Some(bytes.len()),
+                is_infinity: bytes == INFINITY_SIGNATURE,
+                deserializes: deserialize_error.is_none(),
+                error: deserialize_error,
+            }
+        }
+        Err(err) => AggregateSignatureInfo {
+            byte_length: None,
+            is_infinity: false,
+            deserializes: false,
+            error: Some(err.to_string()),
+        },
+    };
+    Ok(Json(info))
+}
+
+/// The raw bytes of the aggregate signature, for clients that don't want to decode hex.
+pub async fn get_aggregate_signature_bytes(
+    Path((slot_number, interval_size, value)): Path<(i64, i64, i64)>,
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let aggregate = get_aggregate(&state.db_pool, slot_number, interval_size, value)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting aggregate: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let signature = hex::decode(&aggregate.aggregate_signature).map_err(|e| {
+        tracing::error!("Invalid aggregate signature in DB: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(([(CONTENT_TYPE, "application/octet-stream")], signature))
+}
+
+/// Response header listing the public keys of `signatures.bin`, comma separated.
+pub const PUBLIC_KEYS_HEADER: &str = "x-public-keys";
+
+/// The individual signatures the aggregate was built from as concatenated 96 byte signatures,
+/// for contracts that verify them one by one. The public keys of the signers, in the same order,
+/// are listed in the `x-public-keys` header.
+pub async fn get_aggregate_individual_signature_bytes(
+    Path((slot_number, interval_size, value)): Path<(i64, i64, i64)>,
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let row = sqlx::query!(
+        "
+        SELECT
+            public_keys,
+            signatures
+        FROM
+            aggregate_interval_attestations
+        WHERE
+            slot_number = $1
+        AND
+            interval_size = $2
+        AND
+            value = $3;
+        ",
+        slot_number,
+        interval_size,
+        value,
+    )
+    .fetch_optional(&state.db_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error getting aggregate: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let mut signature_bytes = Vec::with_capacity(row.signatures.len() * 96);
+    for signature in &row.signatures {
+        let bytes =
+            hex::decode(signature.strip_prefix("0x").unwrap_or(signature)).map_err(|e| {
+                tracing::error!("Invalid signature in DB: {:?}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        signature_bytes.extend(bytes);
+    }
+    Ok((
+        [
+            (CONTENT_TYPE, "application/octet-stream".to_string()),
+            (
+                HeaderName::from_static(PUBLIC_KEYS_HEADER),
+                row.public_keys.join(","),
+            ),
+        ],
+        signature_bytes,
+    ))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AggregateStreamQuery {
+    pub from_slot: Option<i64>,
+    pub to_slot: Option<i64>,
+}
+
+/// Streams all aggregates within the (inclusive) slot range as JSON lines, reading them from a
+/// database cursor so the full result set is never held in memory.
+pub async fn get_aggregates_stream(
+    Query(query): Query<AggregateStreamQuery>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        let mut rows = sqlx::query!(
+            "
+            SELECT
+                asset,
+                value,
+                slot_number,
+                aggregate_signature,
+                aggregate_public_key,
+                public_keys,
+                interval_size,
+                num_validators,
+                num_operators
+            FROM
+                aggregate_interval_attestations
+            WHERE
+                ($1::BIGINT IS NULL OR slot_number >= $1)
+            AND
+                ($2::BIGINT IS NULL OR slot_number <= $2)
+            ORDER BY
+                slot_number,
+                interval_size,
+                value;
+            ",
+            query.from_slot,
+            query.to_slot,
+        )
+        .fetch(&state.db_pool);
+
+        loop {
+            let row = match rows.try_next().await {
+                Ok(Some(row)) => row,
+                Ok(None) => break,
+                Err(e) => {
+                    tracing::error!("Error streaming aggregates: {:?}", e);
+                    sender.abort();
+                    break;
+                }
+            };
+            let entry = AggregatePriceIntervalEntry {
+                asset: row.asset,
+                value: row.value,
+                slot_number: row.slot_number,
+                aggregate_signature: row.aggregate_signature,
+                aggregate_public_key: row.aggregate_public_key,
+                public_keys: row.public_keys,
+                interval_size: row.interval_size,
+                num_validators: row.num_validators,
+                num_operators: row.num_operators,
+                finalized: None,
+            };
+            let mut line = serde_json::to_vec(&entry).expect("expect aggregate to serialize");
+            line.push(b'\n');
+            if sender.send_data(Bytes::from(line)).await.is_err() {
+                // The client went away, no point in reading further.
+                break;
+            }
+        }
+    });
+    ([(CONTENT_TYPE, "application/x-ndjson")], boxed(body))
+}
+
+/// Returns the aggregate of the slot that most recently received a contribution.
+pub async fn get_latest_updated_aggregate(
+    Path(slot_number): Path<i64>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<AggregatePriceIntervalEntry>, StatusCode> {
+    let entry = sqlx::query!(
+        "
+        SELECT
+            asset,
+            value,
+            slot_number,
+            aggregate_signature,
+            aggregate_public_key,
+            public_keys,
+            interval_size,
+            num_validators,
+            num_operators
+        FROM
+            aggregate_interval_attestations
+        WHERE
+            slot_number = $1
+        ORDER BY
+            updated_at DESC
+        LIMIT 1;
+        ",
+        slot_number,
+    )
+    .fetch_optional(&state.db_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error getting latest updated aggregate: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .map(|row| AggregatePriceIntervalEntry {
+        asset: row.asset,
+        value: row.value,
+        slot_number: row.slot_number,
+        aggregate_signature: row.aggregate_signature,
+        aggregate_public_key: row.aggregate_public_key,
+        public_keys: row.public_keys,
+        interval_size: row.interval_size,
+        num_validators: row.num_validators,
+        num_operators: row.num_operators,
+        finalized: None,
+    })
+    .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(entry))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FullAggregate {
+    pub aggregate: AggregatePriceIntervalEntry,
+    /// The individual interval attestations the aggregate was built from, in order of arrival.
+    pub attestations: Vec<PriceIntervalEntry>,
+}
+
+/// The aggregate together with its individual attestations, to audit it in one call.
+pub async fn get_full_aggregate(
+    Path((slot_number, interval_size, value)): Path<(i64, i64, i64)>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<FullAggregate>, StatusCode> {
+    let db_pool = &state.db_pool;
+    let aggregate = get_aggregate(db_pool, slot_number, interval_size, value)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting aggregate: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let attestations = sqlx::query!(
+        r#"
+        SELECT
+            validator_public_key,
+            asset,
+            value,
+            slot_number,
+            signature,
+            interval_size,
+            aggregate_value,
+            signing_preimage,
+            (EXTRACT(EPOCH FROM received_at) * 1000)::BIGINT AS "received_at!"
+        FROM
+            price_interval_attestations
+        WHERE
+            asset = $1
+        AND
+            slot_number = $2
+        AND
+            interval_size = $3
+        AND
+            aggregate_value = $4
+        ORDER BY
+            received_at;
+        "#,
+        aggregate.asset,
+        aggregate.slot_number,
+        aggregate.interval_size,
+        aggregate.value,
+    )
+    .fetch_all(db_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error getting interval attestations: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .into_iter()
+    .map(|row| PriceIntervalEntry {
+        validator_public_key: row.validator_public_key,
+        asset: row.asset,
+        value: row.value,
+        slot_number: row.slot_number,
+        signature: row.signature,
+        interval_size: row.interval_size,
+        aggregate_value: row.aggregate_value,
+        signing_preimage: row.signing_preimage,
+        received_at: row.received_at,
+    })
+    .collect();
+    Ok(Json(FullAggregate {
+        aggregate,
+        attestations,
+    }))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CostEstimateQuery {
+    /// Gas price in gwei.
+    pub gas_price: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CostEstimate {
+    pub num_validators: i64,
+    pub gas: u64,
+    pub gas_price: u64,
+    /// Cost of submitting the aggregate in gwei.
+    pub cost: u64,
+}
+
+/// Estimates the cost of submitting the aggregate on-chain, from a fixed verification overhead
+/// plus the gas of every validator whose public key goes along with it.
+pub async fn get_aggregate_cost_estimate(
+    Path((slot_number, interval_size, value)): Path<(i64, i64, i64)>,
+    Query(query): Query<CostEstimateQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<CostEstimate>, StatusCode> {
+    let aggregate = get_aggregate(&state.db_pool, slot_number, interval_size, value)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting aggregate: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let gas = state.config.cost_estimate_base_gas
+        + state.config.cost_estimate_gas_per_validator * aggregate.num_validators as u64;
+    Ok(Json(CostEstimate {
+        num_validators: aggregate.num_validators,
+        gas,
+        gas_price: query.gas_price,
+        cost: gas.saturating_mul(query.gas_price),
+    }))
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct GrowthPoint {
+    /// Unix timestamp (in milliseconds) at which the contribution was received.
+    pub received_at: i64,
+    /// Number of validators in the aggregate after the contribution.
+    pub num_validators: i64,
+}
+
+/// How the aggregate grew over the slot, one point per contribution in order of arrival. Derived
+/// from the arrival of the interval attestations the aggregate was built from.
+pub async fn get_aggregate_growth(
+    Path((slot_number, interval_size, value)): Path<(i64, i64, i64)>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<GrowthPoint>>, StatusCode> {
+    let db_pool = &state.db_pool;
+    let aggregate = get_aggregate(db_pool, slot_number, interval_size, value)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting aggregate: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let growth = sqlx::query!(
+        r#"
+        SELECT
+            (EXTRACT(EPOCH FROM received_at) * 1000)::BIGINT AS "received_at!"
+        FROM
+            price_interval_attestations
+        WHERE
+            asset = $1
+        AND
+            slot_number = $2
+        AND
+            interval_size = $3
+        AND
+            aggregate_value = $4
+        ORDER BY
+            received_at;
+        "#,
+        aggregate.asset,
+        aggregate.slot_number,
+        aggregate.interval_size,
+        aggregate.value,
+    )
+    .fetch_all(db_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error getting interval attestations: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .into_iter()
+    .enumerate()
+    .map(|(index, row)| GrowthPoint {
+        received_at: row.received_at,
+        num_validators: index as i64 + 1,
+    })
+    .collect();
+    Ok(Json(growth))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ValueParticipation {
+    pub asset: String,
+    pub value: i64,
+    pub num_validators: i64,
+}
+
+/// How validators split across the aggregates of one interval size, most participated first.
+pub async fn get_aggregate_distribution(
+    Path((slot_number, interval_size)): Path<(i64, i64)>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<ValueParticipation>>, StatusCode> {
+    let distribution: Vec<ValueParticipation> = sqlx::query!(
+        "
+        SELECT
+            asset,
+            value,
+            num_validators
+        FROM
+            aggregate_interval_attestations
+        WHERE
+            slot_number = $1
+        AND
+            interval_size = $2
+        ORDER BY
+            num_validators DESC,
+            value;
+        ",
+        slot_number,
+        interval_size,
+    )
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error getting aggregate distribution: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .into_iter()
+    .map(|row| ValueParticipation {
+        asset: row.asset,
+        value: row.value,
+        num_validators: row.num_validators,
+    })
+    .collect();
+    if distribution.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(Json(distribution))
+}
+
+/// Recounts the contributors of an aggregate from the stored interval attestations, resetting the
+/// validator and operator counts and re-deriving the aggregate signature and public key.
+pub async fn post_repair_aggregate(
+    Path((slot_number, interval_size, value)): Path<(i64, i64, i64)>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<AggregatePriceIntervalEntry>, StatusCode> {
+    let db_pool = &state.db_pool;
+    let aggregate = get_aggregate(db_pool, slot_number, interval_size, value)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting aggregate: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let repaired_aggregate = repair_aggregate(&state, aggregate).await.map_err(|e| {
+        tracing::error!("Error repairing aggregate: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(repaired_aggregate))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AggregateVerification {
+    pub num_verified: u64,
+    /// Banded aggregates, which can't be verified against their own value, see
+    /// `verify_aggregate_entry`.
+    pub num_skipped: u64,
+    /// Aggregates whose signature doesn't verify against their public keys.
+    pub failed: Vec<AggregatePriceIntervalEntry>,
+}
+
+/// Verifies the signature of every stored aggregate, reading them from a database cursor, to
+/// check the integrity of the aggregates after an upgrade. Failed aggregates can be rebuilt with
+/// `post_repair_aggregate`.
+pub async fn get_verify_aggregates(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<AggregateVerification>, StatusCode> {
+    let digest_config = state.config.digest_config();
+    let mut aggregates = sqlx::query_as::<_, AggregatePriceIntervalEntry>(
+        "
+        SELECT
+            asset,
+            value,
+            slot_number,
+            aggregate_signature,
+            aggregate_public_key,
+            public_keys,
+            interval_size,
+            num_validators,
+            num_operators
+        FROM
+            aggregate_interval_attestations
+        ORDER BY
+            slot_number,
+            asset,
+            interval_size,
+            value;
+        ",
+    )
+    .fetch(&state.db_pool);
+
+    let mut verification = AggregateVerification {
+        num_verified: 0,
+        num_skipped: 0,
+        failed: Vec::new(),
+    };
+    while let Some(aggregate) = aggregates.try_next().await.map_err(|e| {
+        tracing::error!("Error getting aggregates: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })? {
+        let is_banded = state
+            .config
+            .interval_value_bands
+            .get(&(aggregate.interval_size as u64))
+            .is_some_and(|band| *band > 1);
+        if is_banded {
+            verification.num_skipped += 1;
+        } else if verify_aggregate_entry(&aggregate, digest_config) {
+            verification.num_verified += 1;
+        } else {
+            tracing::warn!(
+                "Aggregate of value {} in slot {} doesn't verify",
+                aggregate.value,
+                aggregate.slot_number
+            );
+            verification.failed.push(aggregate);
+        }
+    }
+    Ok(Json(verification))
+}
+
+/// A validator's stored interval attestation that went into an aggregate.
+struct Contribution {
+    public_key: PublicKey,
+    signature: Signature,
+}
+
+async fn get_contributions(
+    db_pool: &DbPool,
+    aggregate: &AggregatePriceIntervalEntry,
+) -> eyre::Result<Vec<Contribution>> {
+    // A validator only counts once, even if they somehow got multiple attestations stored.
+    let rows = sqlx::query!(
+        "
+        SELECT DISTINCT ON (validator_public_key)
+            validator_public_key,
+            signature
+        FROM
+            price_interval_attestations
+        WHERE
+            asset = $1
+        AND
+            slot_number = $2
+        AND
+            interval_size = $3
+        AND
+            aggregate_value = $4
+        ORDER BY
+            validator_public_key,
+            received_at;
+        ",
+        aggregate.asset,
+        aggregate.slot_number,
+        aggregate.interval_size,
+        aggregate.value,
+    )
+    .fetch_all(db_pool)
+    .await?;
+    rows.into_iter()
+        .map(|row| {
+            Ok(Contribution {
+                public_key: row
+                    .validator_public_key
+                    .parse()
+                    .map_err(|e| eyre::eyre!("Invalid public key in DB: {:?}", e))?,
+                signature: row
+                    .signature
+                    .parse()
+                    .map_err(|e| eyre::eyre!("Invalid signature in DB: {:?}", e))?,
+            })
+        })
+        .collect()
+}
+
+/// Aggregates the signatures and public keys of non-empty contributions.
+fn aggregate_contributions(
+    contributions: &[Contribution],
+) -> eyre::Result<(AggregateSignature, PublicKey)> {
+    let mut aggregate_signature = AggregateSignature::infinity();
+    for contribution in contributions {
+        aggregate_signature.add_assign(&contribution.signature);
+    }
+    let public_keys: Vec<PublicKey> = contributions
+        .iter()
+        .map(|contribution| contribution.public_key.clone())
+        .collect();
+    let aggregate_public_key = AggregatePublicKey::aggregate(&public_keys)
+        .map_err(|_| eyre::eyre!("Invalid aggregate public key"))?;
+    Ok((aggregate_signature, aggregate_public_key.to_public_key()))
+}
+
+pub async fn repair_aggregate(
+    state: &AppState,
+    aggregate: AggregatePriceIntervalEntry,
+) -> eyre::Result<AggregatePriceIntervalEntry> {
+    let db_pool = &state.db_pool;
+    let contributions = get_contributions(db_pool, &aggregate).await?;
+    if contributions.is_empty() {
+        return Err(eyre::eyre!("Aggregate has no stored contributions"));
+    }
+
+    let (aggregate_signature, aggregate_public_key) = aggregate_contributions(&contributions)?;
+    let mut operators = Vec::new();
+    for contribution in &contributions {
+        let operator = get_operator(state, &contribution.public_key);
+        if !operators.contains(&operator) {
+            operators.push(operator);
+        }
+    }
+
+    let total_weight: i64 = contributions
+        .iter()
+        .map(|contribution| get_validator_weight(state, &contribution.public_key))
+        .sum();
+
+    let repaired_aggregate = AggregatePriceIntervalEntry {
+        aggregate_signature: hex::encode(aggregate_signature.serialize()),
+        aggregate_public_key: hex::encode(aggregate_public_key.serialize()),
+        num_validators: contributions.len() as i64,
+        num_operators: operators.len() as i64,
+        public_keys: contributions
+            .iter()
+            .map(|contribution| encode_public_key(&contribution.public_key))
+            .collect(),
+        ..aggregate
+    };
+    tracing::info!(
+        "Repairing aggregate of value {} in slot {}, {} validators according to the aggregate, {} contributions stored",
+        aggregate.value,
+        aggregate.slot_number,
+        aggregate.num_validators,
+        repaired_aggregate.num_validators
+    );
+    let signatures: Vec<String> = contributions
+        .iter()
+        .map(|contribution| encode_signature(&contribution.signature))
+        .collect();
+    sqlx::query!(
+        "
+        UPDATE aggregate_interval_attestations
+        SET
+            num_validators = $1,
+            aggregate_signature = $2,
+            aggregate_public_key = $3,
+            operators = $4,
+            num_operators = $5,
+            public_keys = $10,
+            signatures = $11,
+            total_weight = $12,
+            updated_at = now()
+        WHERE
+            asset = $6
+        AND
+            interval_size = $7
+        AND
+            slot_number = $8
+        AND
+            value = $9;
+        ",
+        repaired_aggregate.num_validators,
+        repaired_aggregate.aggregate_signature,
+        repaired_aggregate.aggregate_public_key,
+        &operators,
+        repaired_aggregate.num_operators,
+        repaired_aggregate.asset,
+        repaired_aggregate.interval_size,
+        repaired_aggregate.slot_number,
+        repaired_aggregate.value,
+        &repaired_aggregate.public_keys,
+        &signatures,
+        total_weight,
+    )
+    .execute(db_pool)
+    .await?;
+    Ok(repaired_aggregate)
+}
+
+/// Recomputes every aggregate of the most recent slots from its stored attestations, returning how
+/// many were recomputed.
+pub async fn recompute_recent_aggregates(state: &AppState, num_slots: u64) -> eyre::Result<usize> {
+    let from_slot = state.slot_clock.current_slot().saturating_sub(num_slots) as i64;
+    let aggregates: Vec<AggregatePriceIntervalEntry> = sqlx::query!(
+        "
+        SELECT
+            asset,
+            value,
+            slot_number,
+            aggregate_signature,
+            aggregate_public_key,
+            public_keys,
+            interval_size,
+            num_validators,
+            num_operators
+        FROM
+            aggregate_interval_attestations
+        WHERE
+            slot_number >= $1;
+        ",
+        from_slot
+    )
+    .fetch_all(&state.db_pool)
+    .await?
+    .into_iter()
+    .map(|row| AggregatePriceIntervalEntry {
+        asset: row.asset,
+        value: row.value,
+        slot_number: row.slot_number,
+        aggregate_signature: row.aggregate_signature,
+        aggregate_public_key: row.aggregate_public_key,
+        public_keys: row.public_keys,
+        interval_size: row.interval_size,
+        num_validators: row.num_validators,
+        num_operators: row.num_operators,
+        finalized: None,
+    })
+    .collect();
+
+    let mut num_recomputed = 0;
+    for aggregate in aggregates {
+        let (slot_number, value) = (aggregate.slot_number, aggregate.value);
+        match repair_aggregate(state, aggregate).await {
+            Ok(_) => num_recomputed += 1,
+            Err(e) => tracing::warn!(
+                "Error recomputing aggregate of value {} in slot {}: {:?}",
+                value,
+                slot_number,
+                e
+            ),
+        }
+    }
+    Ok(num_recomputed)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SubsetAggregateRequest {
+    pub validator_public_keys: Vec<PublicKey>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SubsetAggregate {
+    pub aggregate_signature: String,
+    pub aggregate_public_key: String,
+    /// Number of requested validators that contributed to the aggregate.
+    pub num_validators: usize,
+    /// Requested validators that didn't contribute to the aggregate, and so are left out.
+    pub missing_validator_public_keys: Vec<PublicKey>,
+}
+
+/// Re-aggregates the stored signatures of just the given validators' contributions to an
+/// aggregate, for ad-hoc analysis. Nothing is stored.
+pub async fn post_subset_aggregate(
+    Path((slot_number, interval_size, value)): Path<(i64, i64, i64)>,
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SubsetAggregateRequest>,
+) -> Result<Json<SubsetAggregate>, StatusCode> {
+    let db_pool = &state.db_pool;
+    let aggregate = get_aggregate(db_pool, slot_number, interval_size, value)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting aggregate: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let contributions: Vec<Contribution> = get_contributions(db_pool, &aggregate)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting contributions: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .into_iter()
+        .filter(|contribution| {
+            request
+                .validator_public_keys
+                .contains(&contribution.public_key)
+        })
+        .collect();
+    if contributions.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let (aggregate_signature, aggregate_public_key) = aggregate_contributions(&contributions)
+        .map_err(|e| {
+            tracing::error!("Error aggregating contributions: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let missing_validator_public_keys = request
+        .validator_public_keys
+        .into_iter()
+        .filter(|public_key| {
+            !contributions
+                .iter()
+                .any(|contribution| &contribution.public_key == public_key)
+        })
+        .collect();
+    Ok(Json(SubsetAggregate {
+        aggregate_signature: hex::encode(aggregate_signature.serialize()),
+        aggregate_public_key: hex::encode(aggregate_public_key.serialize()),
+        num_validators: contributions.len(),
+        missing_validator_public_keys,
+    }))
+}
+
+pub async fn get_aggregate(
+    db_pool: &DbPool,
+    slot_number: i64,
+    interval_size: i64,
+    value: i64,
+) -> eyre::Result<Option<AggregatePriceIntervalEntry>> {
+    let entry = sqlx::query!(
+        "
+        SELECT
+            asset,
+            value,
+            slot_number,
+            aggregate_signature,
+            aggregate_public_key,
+            public_keys,
+            interval_size,
+            num_validators,
+            num_operators
+        FROM
+            aggregate_interval_attestations
+        WHERE
+            slot_number = $1
+        AND
+            interval_size = $2
+        AND
+            value = $3;
+        ",
+        slot_number,
+        interval_size,
+        value,
+    )
+    .fetch_optional(db_pool)
+    .await?
+    .map(|row| AggregatePriceIntervalEntry {
+        asset: row.asset,
+        value: row.value,
+        slot_number: row.slot_number,
+        aggregate_signature: row.aggregate_signature,
+        aggregate_public_key: row.aggregate_public_key,
+        public_keys: row.public_keys,
+        interval_size: row.interval_size,
+        num_validators: row.num_validators,
+        num_operators: row.num_operators,
+        finalized: None,
+    });
+    Ok(entry)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::attestations::{get_message_digest, DigestConfig, OracleMessage};
+    use crate::config::Config;
+    use crate::test_utils::{
+        admin_config, get_test_message, sign_oracle_message_with_new_key,
+        signature_from_random_signer, TestApp, ADMIN_AUTHORIZATION, TEST_MESSAGE_SLOT,
+    };
+    use bls::SecretKey;
+
+    #[sqlx::test]
+    async fn returns_and_flags_final_aggregates(db_pool: DbPool) {
+        let config = Config {
+            finality_threshold: 2,
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(2);
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+        // A second validator only attests to the second interval
+        let mut second_message = test_message.clone();
+        second_message.interval_inclusion_messages.remove(0);
+        let second_message = sign_oracle_message_with_new_key(second_message, &SecretKey::random());
+        test_app
+            .post_oracle_message(&second_message, StatusCode::OK)
+            .await;
+        let final_value = test_message.interval_inclusion_messages[1].message.value as i64;
+
+        let response = test_app
+            .get_expect("/aggregate/final", StatusCode::OK)
+            .await;
+        let aggregates: Vec<AggregatePriceIntervalEntry> =
+            serde_json::from_slice(&response).unwrap();
+        assert_eq!(aggregates.len(), 1);
+        assert_eq!(aggregates[0].value, final_value);
+        assert_eq!(aggregates[0].num_validators, 2);
+        assert_eq!(aggregates[0].finalized, Some(true));
+
+        let response = test_app
+            .get_expect("/aggregate_price_interval_attestations", StatusCode::OK)
+            .await;
+        let aggregates: Vec<AggregatePriceIntervalEntry> =
+            serde_json::from_slice(&response).unwrap();
+        assert_eq!(aggregates.len(), 2);
+        for aggregate in aggregates {
+            assert_eq!(aggregate.finalized, Some(aggregate.value == final_value));
+        }
+
+        let response = test_app
+            .get_expect(
+                &format!("/aggregate/final?from_slot={}", TEST_MESSAGE_SLOT + 1),
+                StatusCode::OK,
+            )
+            .await;
+        let aggregates: Vec<AggregatePriceIntervalEntry> =
+            serde_json::from_slice(&response).unwrap();
+        assert!(aggregates.is_empty());
+    }
+
+    #[sqlx::test]
+    async fn looks_up_single_aggregate(db_pool: DbPool) {
+        let test_message = get_test_message();
+        let test_app = TestApp::new(db_pool).await;
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+
+        let interval_message = &test_message.interval_inclusion_messages[1].message;
+        let response = test_app
+            .get_expect(
+                &format!(
+                    "/aggregate?slot={}&value={}&interval_size={}",
+                    interval_message.slot_number,
+                    interval_message.value,
+                    interval_message.interval_size
+                ),
+                StatusCode::OK,
+            )
+            .await;
+        let aggregate: AggregatePriceIntervalEntry = serde_json::from_slice(&response).unwrap();
+        assert_eq!(aggregate.slot_number, interval_message.slot_number as i64);
+        assert_eq!(aggregate.value, interval_message.value as i64);
+        assert_eq!(
+            aggregate.interval_size,
+            interval_message.interval_size as i64
+        );
+        assert_eq!(aggregate.num_validators, 1);
+        assert_eq!(
+            aggregate.aggregate_signature,
+            hex::encode(
+                test_message.interval_inclusion_messages[1]
+                    .signature
+                    .serialize()
+            )
+        );
+
+        test_app
+            .get_expect(
+                &format!(
+                    "/aggregate?slot={}&value=1&interval_size={}",
+                    interval_message.slot_number, interval_message.interval_size
+                ),
+                StatusCode::NOT_FOUND,
+            )
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn returns_signature_info_for_valid_aggregate(db_pool: DbPool) {
+        let test_message = get_test_message();
+        let test_app = TestApp::new(db_pool).await;
+        let body = Body::from(serde_json::to_string(&test_message).unwrap());
+        test_app
+            .post_expect("/post_oracle_message", body, StatusCode::OK)
+            .await;
+
+        let interval_message = &test_message.interval_inclusion_messages[0].message;
+        let response = test_app
+            .get_expect(
+                &format!(
+                    "/aggregate/{}/{}/{}/sig-info",
+                    interval_message.slot_number,
+                    interval_message.interval_size,
+                    interval_message.value
+                ),
+                StatusCode::OK,
+            )
+            .await;
+        let info: AggregateSignatureInfo = serde_json::from_slice(&response).unwrap();
+        assert_eq!(info.byte_length, Some(96));
+        assert!(!info.is_infinity);
+        assert!(info.deserializes);
+        assert!(info.error.is_none());
+    }
+
+    #[sqlx::test]
+    async fn returns_raw_aggregate_signature(db_pool: DbPool) {
+        let test_message = get_test_message();
+        let test_app = TestApp::new(db_pool.clone()).await;
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+
+        let interval_message = &test_message.interval_inclusion_messages[0].message;
+        let response = test_app
+            .get_expect(
+                &format!(
+                    "/aggregate/{}/{}/{}/signature.bin",
+                    interval_message.slot_number,
+                    interval_message.interval_size,
+                    interval_message.value
+                ),
+                StatusCode::OK,
+            )
+            .await;
+        assert_eq!(response.len(), 96);
+        let aggregate = get_aggregate(
+            &db_pool,
+            interval_message.slot_number as i64,
+            interval_message.interval_size as i64,
+            interval_message.value as i64,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(hex::encode(&response), aggregate.aggregate_signature);
+    }
+
+    #[sqlx::test]
+    async fn returns_individual_signatures(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(1);
+        let messages: Vec<OracleMessage> = (0..3)
+            .map(|_| sign_oracle_message_with_new_key(test_message.clone(), &SecretKey::random()))
+            .collect();
+        for message in &messages {
+            test_app.post_oracle_message(message, StatusCode::OK).await;
+        }
+
+        let interval_message = &test_message.interval_inclusion_messages[0].message;
+        let response = test_app
+            .get_response(
+                &format!(
+                    "/aggregate/{}/{}/{}/signatures.bin",
+                    interval_message.slot_number,
+                    interval_message.interval_size,
+                    interval_message.value
+                ),
+                &[],
+            )
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let public_keys = response.headers()[PUBLIC_KEYS_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body.len(), 96 * messages.len());
+        for ((message, public_key), signature) in messages
+            .iter()
+            .zip(public_keys.split(','))
+            .zip(body.chunks(96))
+        {
+            assert_eq!(public_key, message.validator_public_key.to_string());
+            assert_eq!(
+                signature,
+                message.interval_inclusion_messages[0].signature.serialize()
+            );
+        }
+    }
+
+    #[sqlx::test]
+    async fn returns_signature_info_for_malformed_aggregate(db_pool: DbPool) {
+        sqlx::query(
+            "
+            INSERT INTO aggregate_interval_attestations(
+                value,
+                interval_size,
+                slot_number,
+                num_validators,
+                aggregate_signature,
+                aggregate_public_key
+            )
+            VALUES (100, 20, 1, 1, 'abcd', '');
+            ",
+        )
+        .execute(&db_pool)
+        .await
+        .unwrap();
+        let test_app = TestApp::new(db_pool).await;
+
+        let response = test_app
+            .get_expect("/aggregate/1/20/100/sig-info", StatusCode::OK)
+            .await;
+        let info: AggregateSignatureInfo = serde_json::from_slice(&response).unwrap();
+        assert_eq!(info.byte_length, Some(2));
+        assert!(!info.is_infinity);
+        assert!(!info.deserializes);
+        assert!(info.error.is_some());
+
+        test_app
+            .get_expect("/aggregate/1/20/101/sig-info", StatusCode::NOT_FOUND)
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn streams_aggregates_as_json_lines(db_pool: DbPool) {
+        let test_message = get_test_message();
+        let test_app = TestApp::new(db_pool).await;
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+        let slot_number = test_message.value_message.message.slot_number;
+
+        let response = test_app
+            .get_expect(
+                &format!("/aggregates/stream?from_slot={slot_number}&to_slot={slot_number}"),
+                StatusCode::OK,
+            )
+            .await;
+        let lines: Vec<AggregatePriceIntervalEntry> = response
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), test_message.interval_inclusion_messages.len());
+        assert!(lines
+            .iter()
+            .all(|entry| entry.slot_number == slot_number as i64));
+
+        let response = test_app
+            .get_expect(
+                &format!("/aggregates/stream?to_slot={}", slot_number - 1),
+                StatusCode::OK,
+            )
+            .await;
+        assert!(response.is_empty());
+    }
+
+    #[sqlx::test]
+    async fn returns_latest_updated_aggregate_of_slot(db_pool: DbPool) {
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(2);
+        let slot_number = test_message.value_message.message.slot_number;
+        let test_app = TestApp::new(db_pool).await;
+        test_app
+            .get_expect(
+                &format!("/aggregate/{slot_number}/latest-updated"),
+                StatusCode::NOT_FOUND,
+            )
+            .await;
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+
+        // Contribute to each of the two aggregates in turn, checking the last one is returned
+        for index in [1, 0] {
+            let mut message = test_message.clone();
+            message.interval_inclusion_messages =
+                vec![message.interval_inclusion_messages[index].clone()];
+            let message = sign_oracle_message_with_new_key(message, &SecretKey::random());
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+
+            let response = test_app
+                .get_expect(
+                    &format!("/aggregate/{slot_number}/latest-updated"),
+                    StatusCode::OK,
+                )
+                .await;
+            let entry: AggregatePriceIntervalEntry = serde_json::from_slice(&response).unwrap();
+            assert_eq!(
+                entry.value,
+                test_message.interval_inclusion_messages[index]
+                    .message
+                    .value as i64
+            );
+            assert_eq!(entry.num_validators, 2);
+        }
+    }
+
+    #[sqlx::test]
+    async fn repairs_drifted_validator_count(db_pool: DbPool) {
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(1);
+        let interval_message = test_message.interval_inclusion_messages[0].message.clone();
+        let (slot_number, interval_size, value) = (
+            interval_message.slot_number as i64,
+            interval_message.interval_size as i64,
+            interval_message.value as i64,
+        );
+        let test_app = TestApp::with_config(db_pool.clone(), admin_config()).await;
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+        let message = sign_oracle_message_with_new_key(test_message, &SecretKey::random());
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
+        let intact_aggregate = get_aggregate(&db_pool, slot_number, interval_size, value)
+            .await
+            .unwrap()
+            .unwrap();
+
+        sqlx::query(
+            "
+            UPDATE aggregate_interval_attestations
+            SET
+                num_validators = 5,
+                aggregate_signature = $1;
+            ",
+        )
+        .bind(hex::encode(INFINITY_SIGNATURE))
+        .execute(&db_pool)
+        .await
+        .unwrap();
+
+        let path = format!("/admin/repair-aggregate/{slot_number}/{interval_size}/{value}");
+        let response = test_app
+            .post_expect_with_headers(&path, Body::empty(), &[ADMIN_AUTHORIZATION], StatusCode::OK)
+            .await;
+        let repaired_aggregate: AggregatePriceIntervalEntry =
+            serde_json::from_slice(&response).unwrap();
+        assert_eq!(repaired_aggregate.num_validators, 2);
+        assert_eq!(repaired_aggregate.num_operators, 2);
+        assert_eq!(
+            repaired_aggregate.aggregate_signature,
+            intact_aggregate.aggregate_signature
+        );
+
+        let stored_aggregate = get_aggregate(&db_pool, slot_number, interval_size, value)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored_aggregate.num_validators, 2);
+        assert_eq!(
+            stored_aggregate.aggregate_signature,
+            intact_aggregate.aggregate_signature
+        );
+
+        test_app
+            .post_expect_with_headers(
+                &format!(
+                    "/admin/repair-aggregate/{slot_number}/{interval_size}/{}",
+                    value + 1
+                ),
+                Body::empty(),
+                &[ADMIN_AUTHORIZATION],
+                StatusCode::NOT_FOUND,
+            )
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn reports_aggregates_that_fail_verification(db_pool: DbPool) {
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(2);
+        let test_app = TestApp::with_config(db_pool.clone(), admin_config()).await;
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+
+        // A well-formed signature, but by a validator that isn't among the public keys
+        let corrupted_message = &test_message.interval_inclusion_messages[1].message;
+        let foreign_signature = signature_from_random_signer(corrupted_message);
+        sqlx::query(
+            "
+            UPDATE aggregate_interval_attestations
+            SET
+                aggregate_signature = $1
+            WHERE
+                value = $2;
+            ",
+        )
+        .bind(hex::encode(foreign_signature.serialize()))
+        .bind(corrupted_message.value as i64)
+        .execute(&db_pool)
+        .await
+        .unwrap();
+
+        let response = test_app
+            .get_expect_with_headers(
+                "/admin/verify-aggregates",
+                &[ADMIN_AUTHORIZATION],
+                StatusCode::OK,
+            )
+            .await;
+        let verification: AggregateVerification = serde_json::from_slice(&response).unwrap();
+        assert_eq!(verification.num_verified, 1);
+        assert_eq!(verification.num_skipped, 0);
+        assert_eq!(verification.failed.len(), 1);
+        assert_eq!(verification.failed[0].value, corrupted_message.value as i64);
+    }
+
+    #[sqlx::test]
+    async fn aggregates_subset_of_contributions(db_pool: DbPool) {
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(1);
+        let interval_message = test_message.interval_inclusion_messages[0].message.clone();
+        let test_app = TestApp::new(db_pool).await;
+        let private_keys = [
+            SecretKey::random(),
+            SecretKey::random(),
+            SecretKey::random(),
+        ];
+        for private_key in &private_keys {
+            let message = sign_oracle_message_with_new_key(test_message.clone(), private_key);
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+        }
+
+        let non_contributor = SecretKey::random().public_key();
+        let request = SubsetAggregateRequest {
+            validator_public_keys: vec![
+                private_keys[0].public_key(),
+                private_keys[2].public_key(),
+                non_contributor.clone(),
+            ],
+        };
+        let path = format!(
+            "/aggregate/{}/{}/{}/subset",
+            interval_message.slot_number, interval_message.interval_size, interval_message.value
+        );
+        let response = test_app
+            .post_expect(
+                &path,
+                Body::from(serde_json::to_string(&request).unwrap()),
+                StatusCode::OK,
+            )
+            .await;
+        let subset: SubsetAggregate = serde_json::from_slice(&response).unwrap();
+        assert_eq!(subset.num_validators, 2);
+        assert_eq!(subset.missing_validator_public_keys, vec![non_contributor]);
+
+        let aggregate_signature =
+            AggregateSignature::deserialize(&hex::decode(subset.aggregate_signature).unwrap())
+                .unwrap();
+        let message_digest = get_message_digest(&interval_message, DigestConfig::default());
+        let subset_keys = [private_keys[0].public_key(), private_keys[2].public_key()];
+        assert!(aggregate_signature
+            .fast_aggregate_verify(message_digest, &subset_keys.iter().collect::<Vec<_>>()));
+        let all_keys: Vec<PublicKey> = private_keys.iter().map(|key| key.public_key()).collect();
+        assert!(!aggregate_signature
+            .fast_aggregate_verify(message_digest, &all_keys.iter().collect::<Vec<_>>()));
+
+        let request = SubsetAggregateRequest {
+            validator_public_keys: vec![SecretKey::random().public_key()],
+        };
+        test_app
+            .post_expect(
+                &path,
+                Body::from(serde_json::to_string(&request).unwrap()),
+                StatusCode::BAD_REQUEST,
+            )
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn recomputes_recent_aggregates_on_startup(db_pool: DbPool) {
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(2);
+        let test_app = TestApp::new(db_pool.clone()).await;
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+        let message = sign_oracle_message_with_new_key(test_message.clone(), &SecretKey::random());
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
+
+        let interval_message = &test_message.interval_inclusion_messages[0].message;
+        let (slot_number, interval_size, value) = (
+            interval_message.slot_number as i64,
+            interval_message.interval_size as i64,
+            interval_message.value as i64,
+        );
+        let get_test_aggregate = || async {
+            get_aggregate(&db_pool, slot_number, interval_size, value)
+                .await
+                .unwrap()
+                .unwrap()
+        };
+        let intact_aggregate = get_test_aggregate().await;
+        sqlx::query(
+            "
+            UPDATE aggregate_interval_attestations
+            SET
+                num_validators = 1,
+                aggregate_signature = $1
+            WHERE
+                value = $2;
+            ",
+        )
+        .bind(
+            &test_message.interval_inclusion_messages[0]
+                .signature
+                .to_string()[2..],
+        )
+        .bind(value)
+        .execute(&db_pool)
+        .await
+        .unwrap();
+
+        let config = Config {
+            startup_recompute_slots: Some(10),
+            ..Config::default()
+        };
+        // The slot is too long ago to be recomputed
+        TestApp::with_config_at_slot(db_pool.clone(), config.clone(), TEST_MESSAGE_SLOT + 20).await;
+        assert_eq!(get_test_aggregate().await.num_validators, 1);
+
+        TestApp::with_config(db_pool.clone(), config).await;
+        let recomputed_aggregate = get_test_aggregate().await;
+        assert_eq!(recomputed_aggregate.num_validators, 2);
+        assert_eq!(
+            recomputed_aggregate.aggregate_signature,
+            intact_aggregate.aggregate_signature
+        );
+    }
+
+    #[sqlx::test]
+    async fn splits_validators_across_competing_values(db_pool: DbPool) {
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(3);
+        let test_app = TestApp::new(db_pool).await;
+        // Each competing value is attested to by one validator fewer than the one before
+        for num_intervals in [1, 3, 2] {
+            let mut message = test_message.clone();
+            message.interval_inclusion_messages.truncate(num_intervals);
+            let message = sign_oracle_message_with_new_key(message, &SecretKey::random());
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+        }
+
+        let interval_messages = &test_message.interval_inclusion_messages;
+        let (slot_number, interval_size) = (
+            interval_messages[0].message.slot_number,
+            interval_messages[0].message.interval_size,
+        );
+        let response = test_app
+            .get_expect(
+                &format!("/aggregate/{slot_number}/{interval_size}/distribution"),
+                StatusCode::OK,
+            )
+            .await;
+        let distribution: Vec<ValueParticipation> = serde_json::from_slice(&response).unwrap();
+        let participation: Vec<(i64, i64)> = distribution
+            .iter()
+            .map(|entry| (entry.value, entry.num_validators))
+            .collect();
+        assert_eq!(
+            participation,
+            vec![
+                (interval_messages[0].message.value as i64, 3),
+                (interval_messages[1].message.value as i64, 2),
+                (interval_messages[2].message.value as i64, 1),
+            ]
+        );
+
+        test_app
+            .get_expect(
+                &format!(
+                    "/aggregate/{slot_number}/{}/distribution",
+                    interval_size + 1
+                ),
+                StatusCode::NOT_FOUND,
+            )
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn appends_contributing_public_keys(db_pool: DbPool) {
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(1);
+        let interval_message = test_message.interval_inclusion_messages[0].message.clone();
+        let (slot_number, interval_size, value) = (
+            interval_message.slot_number as i64,
+            interval_message.interval_size as i64,
+            interval_message.value as i64,
+        );
+        let test_app = TestApp::new(db_pool.clone()).await;
+        let private_keys: Vec<SecretKey> = (0..2).map(|_| SecretKey::random()).collect();
+        let mut expected_public_keys = Vec::new();
+        for private_key in &private_keys {
+            let message = sign_oracle_message_with_new_key(test_message.clone(), private_key);
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+            expected_public_keys.push(private_key.public_key().to_string());
+
+            let aggregate = get_aggregate(&db_pool, slot_number, interval_size, value)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(aggregate.public_keys, expected_public_keys);
+        }
+    }
+
+    #[sqlx::test]
+    async fn estimates_cost_by_number_of_validators(db_pool: DbPool) {
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(1);
+        let config = Config {
+            cost_estimate_base_gas: 100_000,
+            cost_estimate_gas_per_validator: 1_000,
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        let interval_message = &test_message.interval_inclusion_messages[0].message;
+        let uri = format!(
+            "/aggregate/{}/{}/{}/cost-estimate?gas_price=20",
+            interval_message.slot_number, interval_message.interval_size, interval_message.value
+        );
+        test_app.get_expect(&uri, StatusCode::NOT_FOUND).await;
+
+        for num_validators in 1..=3 {
+            let message =
+                sign_oracle_message_with_new_key(test_message.clone(), &SecretKey::random());
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+
+            let response = test_app.get_expect(&uri, StatusCode::OK).await;
+            let estimate: CostEstimate = serde_json::from_slice(&response).unwrap();
+            assert_eq!(estimate.num_validators, num_validators);
+            assert_eq!(estimate.gas, 100_000 + 1_000 * num_validators as u64);
+            assert_eq!(estimate.cost, estimate.gas * 20);
+        }
+    }
+
+    #[sqlx::test]
+    async fn tracks_aggregate_growth_per_contribution(db_pool: DbPool) {
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(1);
+        let test_app = TestApp::new(db_pool).await;
+        let interval_message = &test_message.interval_inclusion_messages[0].message;
+        let uri = format!(
+            "/aggregate/{}/{}/{}/growth",
+            interval_message.slot_number, interval_message.interval_size, interval_message.value
+        );
+        test_app.get_expect(&uri, StatusCode::NOT_FOUND).await;
+
+        for num_validators in 1..=3 {
+            let message =
+                sign_oracle_message_with_new_key(test_message.clone(), &SecretKey::random());
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+
+            let response = test_app.get_expect(&uri, StatusCode::OK).await;
+            let growth: Vec<GrowthPoint> = serde_json::from_slice(&response).unwrap();
+            assert_eq!(
+                growth
+                    .iter()
+                    .map(|point| point.num_validators)
+                    .collect::<Vec<_>>(),
+                (1..=num_validators).collect::<Vec<_>>()
+            );
+            assert!(growth
+                .windows(2)
+                .all(|points| points[0].received_at <= points[1].received_at));
+        }
+    }
+
+    #[sqlx::test]
+    async fn returns_attestations_composing_the_aggregate(db_pool: DbPool) {
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(2);
+        let test_app = TestApp::new(db_pool).await;
+        for _ in 0..3 {
+            let message =
+                sign_oracle_message_with_new_key(test_message.clone(), &SecretKey::random());
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+        }
+
+        let interval_message = &test_message.interval_inclusion_messages[0].message;
+        let response = test_app
+            .get_expect(
+                &format!(
+                    "/aggregate/{}/{}/{}/full",
+                    interval_message.slot_number,
+                    interval_message.interval_size,
+                    interval_message.value
+                ),
+                StatusCode::OK,
+            )
+            .await;
+        let full_aggregate: FullAggregate = serde_json::from_slice(&response).unwrap();
+        assert_eq!(full_aggregate.aggregate.num_validators, 3);
+        assert_eq!(full_aggregate.attestations.len(), 3);
+        assert!(full_aggregate
+            .attestations
+            .iter()
+            .all(|attestation| attestation.value == interval_message.value as i64));
+
+        let mut aggregate_signature = AggregateSignature::infinity();
+        for attestation in &full_aggregate.attestations {
+            aggregate_signature.add_assign(&attestation.signature.parse().unwrap());
+        }
+        assert_eq!(
+            hex::encode(aggregate_signature.serialize()),
+            full_aggregate.aggregate.aggregate_signature
+        );
+
+        test_app
+            .get_expect(
+                &format!(
+                    "/aggregate/{}/{}/{}/full",
+                    interval_message.slot_number,
+                    interval_message.interval_size,
+                    interval_message.value + 1000
+                ),
+                StatusCode::NOT_FOUND,
+            )
+            .await;
+    }
+}
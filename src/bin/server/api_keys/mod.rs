@@ -0,0 +1,120 @@
+//! Signatures already authenticate what gets stored, but checking them is the expensive part of a
+//! submission. Requiring an API key keeps senders without one from flooding that path.
+
+use crate::state::AppState;
+use axum::{
+    extract::State,
+    http::{header::AUTHORIZATION, Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+/// Rejects requests that don't carry one of the configured submission API keys as bearer token,
+/// if any are configured.
+pub async fn require_api_key<B>(
+    State(state): State<Arc<AppState>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Result<Response, StatusCode> {
+    let api_keys = &state.config.submission_api_keys;
+    if api_keys.is_empty() {
+        return Ok(next.run(request).await);
+    }
+    let api_key = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match api_key {
+        Some(api_key) if api_keys.iter().any(|accepted_key| accepted_key == api_key) => {
+            Ok(next.run(request).await)
+        }
+        _ => {
+            tracing::warn!("Rejecting submission without a valid API key");
+            Err(StatusCode::UNAUTHORIZED)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::Config;
+    use crate::db::DbPool;
+    use crate::test_utils::{get_test_message, TestApp};
+    use axum::{body::Body, http::StatusCode};
+
+    fn api_key_config() -> Config {
+        Config {
+            submission_api_keys: vec!["key-1".to_string(), "key-2".to_string()],
+            ..Config::default()
+        }
+    }
+
+    fn test_message_body() -> Body {
+        Body::from(serde_json::to_string(&get_test_message()).unwrap())
+    }
+
+    #[sqlx::test]
+    async fn rejects_submission_without_api_key(db_pool: DbPool) {
+        let test_app = TestApp::with_config(db_pool, api_key_config()).await;
+        test_app
+            .post_oracle_message(&get_test_message(), StatusCode::UNAUTHORIZED)
+            .await;
+        let batch = serde_json::to_string(&vec![get_test_message()]).unwrap();
+        test_app
+            .post_expect(
+                "/oracle_messages/batch",
+                Body::from(batch),
+                StatusCode::UNAUTHORIZED,
+            )
+            .await;
+        // Reading stays public
+        test_app
+            .get_expect("/price_value_attestations", StatusCode::OK)
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn rejects_submission_with_wrong_api_key(db_pool: DbPool) {
+        let test_app = TestApp::with_config(db_pool, api_key_config()).await;
+        test_app
+            .post_expect_with_headers(
+                "/post_oracle_message",
+                test_message_body(),
+                &[("Authorization", "Bearer key-3")],
+                StatusCode::UNAUTHORIZED,
+            )
+            .await;
+        test_app
+            .post_expect_with_headers(
+                "/post_oracle_message",
+                test_message_body(),
+                &[("Authorization", "key-1")],
+                StatusCode::UNAUTHORIZED,
+            )
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn validates_submission_with_api_key(db_pool: DbPool) {
+        let test_app = TestApp::with_config(db_pool, api_key_config()).await;
+        test_app
+            .post_expect_with_headers(
+                "/post_oracle_message",
+                test_message_body(),
+                &[("Authorization", "Bearer key-2")],
+                StatusCode::OK,
+            )
+            .await;
+        // Past the key, the message is validated as usual
+        test_app
+            .post_expect_with_headers(
+                "/post_oracle_message",
+                test_message_body(),
+                &[("Authorization", "Bearer key-1")],
+                StatusCode::CONFLICT,
+            )
+            .await;
+    }
+}
@@ -1,22 +1,120 @@
+use crate::admin::{
+    get_admin_challenge, get_audit_log, load_persisted_settings, post_delete_slots,
+    post_set_genesis, require_admin_token,
+};
+use crate::aggregates::{
+    get_aggregate_by_key, get_aggregate_cost_estimate, get_aggregate_distribution,
+    get_aggregate_growth, get_aggregate_individual_signature_bytes, get_aggregate_signature_bytes,
+    get_aggregate_signature_info, get_aggregates_stream, get_final_aggregates, get_full_aggregate,
+    get_latest_updated_aggregate, get_verify_aggregates, post_repair_aggregate,
+    post_subset_aggregate, recompute_recent_aggregates,
+};
+use crate::api_keys::require_api_key;
+use crate::assets::get_asset_totals;
 use crate::attestations::{
     get_aggregate_price_interval_attestations, get_price_aggregate,
-    get_price_interval_attestations, get_price_value_attestations, post_oracle_message,
+    get_price_interval_attestations, get_price_value_attestations,
+    get_price_value_attestations_ndjson, post_oracle_message, post_oracle_messages_batch,
+};
+use crate::bundle::get_signed_slot_bundle;
+use crate::config::{get_config, Config};
+use crate::consensus::{
+    get_agreement, get_consensus, get_consistency, get_intervals_containing,
+    get_weighted_consensus, post_verify_median,
+};
+use crate::cors::cors_layer;
+use crate::csv::{
+    get_aggregate_attestations_csv, get_price_interval_attestations_csv,
+    get_price_value_attestations_csv,
+};
+use crate::db::{run_migrations, DbPool};
+use crate::deferred::run_deferred_message_worker;
+use crate::export::{get_contributions, get_price_values_export};
+use crate::finalization::{get_finalized_slot, run_finalization_worker};
+use crate::health::{get_health_live, get_health_ready, get_ready_deep, get_status};
+use crate::idempotency::run_idempotency_key_expiry_worker;
+use crate::import::post_import;
+use crate::metrics::{get_metrics, track_request_duration};
+use crate::notifications::run_notification_worker;
+use crate::price::{
+    get_latest, get_price_confidence, get_price_latest, get_price_median, get_price_twap,
+};
+use crate::proof::get_price_proof;
+use crate::pruning::run_pruning_worker;
+use crate::quarantine::post_clear_quarantine;
+use crate::quorum::{
+    get_near_quorum_aggregates, get_slot_quorum_threshold, record_validator_set_size,
+};
+use crate::reconciliation::run_reconciliation_worker;
+use crate::registry::{load_registered_validators, post_reload_validators};
+use crate::request_id::propagate_request_id;
+use crate::scheme::require_min_pk_scheme;
+use crate::slashing::{get_equivocations, get_slashing_report};
+use crate::slots::{
+    get_participation, get_retention, get_slot, get_slot_completeness, get_slot_timing,
 };
-use crate::db::{get_db_pool, DbPool};
 use crate::state::AppState;
+use crate::stats::{get_outliers, get_summary, get_value_diversity};
+use crate::stream::get_attestation_stream;
+use crate::validators::{
+    get_validator_consensus_agreement, get_validator_latest, get_validator_streak,
+};
+use crate::verify::{post_inspect_oracle_message, post_verify_batch};
 use axum::{
+    extract::DefaultBodyLimit,
+    middleware,
     routing::{get, post},
     Router,
 };
 use std::sync::Arc;
 
-pub async fn get_router() -> Router {
-    let db_pool = get_db_pool().await;
-    get_router_with_db_pool(db_pool)
+pub async fn get_router(db_pool: DbPool, config: Config) -> Router {
+    run_migrations(&db_pool)
+        .await
+        .expect("expect DB migrations to apply");
+    let state = AppState::new(db_pool, config);
+    initialize_state(&state)
+        .await
+        .expect("expect state to initialize");
+    let shared_state = Arc::new(state);
+    tokio::spawn(run_notification_worker(shared_state.clone()));
+    tokio::spawn(run_reconciliation_worker(shared_state.clone()));
+    tokio::spawn(run_finalization_worker(shared_state.clone()));
+    tokio::spawn(run_deferred_message_worker(shared_state.clone()));
+    tokio::spawn(run_pruning_worker(shared_state.clone()));
+    tokio::spawn(run_idempotency_key_expiry_worker(shared_state.clone()));
+    get_router_with_state(shared_state)
 }
 
-fn get_router_with_db_pool(db_pool: DbPool) -> Router {
-    let shared_state = Arc::new(AppState { db_pool });
+/// Work that has to be done before serving requests.
+pub async fn initialize_state(state: &AppState) -> eyre::Result<()> {
+    load_persisted_settings(state).await?;
+    record_validator_set_size(state).await?;
+    load_registered_validators(state).await?;
+    if let Some(num_slots) = state.config.startup_recompute_slots {
+        let num_recomputed = recompute_recent_aggregates(state, num_slots).await?;
+        tracing::info!("Recomputed {} recent aggregates", num_recomputed);
+    }
+    Ok(())
+}
+
+pub fn get_router_with_state(shared_state: Arc<AppState>) -> Router {
+    let admin_router = Router::new()
+        .route("/admin/set-genesis", post(post_set_genesis))
+        .route("/admin/clear-quarantine", post(post_clear_quarantine))
+        .route("/admin/audit-log", get(get_audit_log))
+        .route("/admin/delete-slots", post(post_delete_slots))
+        .route("/admin/reload-validators", post(post_reload_validators))
+        .route(
+            "/admin/repair-aggregate/:slot/:interval_size/:value",
+            post(post_repair_aggregate),
+        )
+        .route("/admin/verify-aggregates", get(get_verify_aggregates))
+        .route("/admin/import", post(post_import))
+        .route_layer(middleware::from_fn_with_state(
+            shared_state.clone(),
+            require_admin_token,
+        ));
     Router::new()
         .route(
             "/aggregate_price_interval_attestations",
@@ -30,103 +128,175 @@ fn get_router_with_db_pool(db_pool: DbPool) -> Router {
             "/price_interval_attestations",
             get(get_price_interval_attestations),
         )
-        .route("/post_oracle_message", post(post_oracle_message))
+        .route("/attestations/stream", get(get_attestation_stream))
+        .route(
+            "/attestations/value.ndjson",
+            get(get_price_value_attestations_ndjson),
+        )
+        .route(
+            "/attestations/price_value.csv",
+            get(get_price_value_attestations_csv),
+        )
+        .route(
+            "/attestations/price_interval.csv",
+            get(get_price_interval_attestations_csv),
+        )
+        .route(
+            "/attestations/aggregate.csv",
+            get(get_aggregate_attestations_csv),
+        )
+        .route("/export/price-values", get(get_price_values_export))
+        .route("/contributions", get(get_contributions))
+        .route(
+            "/post_oracle_message",
+            post(post_oracle_message)
+                .route_layer(middleware::from_fn_with_state(
+                    shared_state.clone(),
+                    require_min_pk_scheme,
+                ))
+                .route_layer(middleware::from_fn_with_state(
+                    shared_state.clone(),
+                    require_api_key,
+                )),
+        )
+        .route(
+            "/oracle_messages/batch",
+            post(post_oracle_messages_batch)
+                .route_layer(middleware::from_fn_with_state(
+                    shared_state.clone(),
+                    require_min_pk_scheme,
+                ))
+                .route_layer(middleware::from_fn_with_state(
+                    shared_state.clone(),
+                    require_api_key,
+                )),
+        )
         .route("/price_aggregate", get(get_price_aggregate))
+        .route("/health/live", get(get_health_live))
+        .route("/health/ready", get(get_health_ready))
+        .route("/ready/deep", get(get_ready_deep))
+        .route("/status", get(get_status))
+        .route("/metrics", get(get_metrics))
+        .route("/aggregate", get(get_aggregate_by_key))
+        .route("/aggregate/final", get(get_final_aggregates))
+        .route(
+            "/aggregate/:slot/:interval_size/:value/sig-info",
+            get(get_aggregate_signature_info),
+        )
+        .route(
+            "/aggregate/:slot/:interval_size/:value/signature.bin",
+            get(get_aggregate_signature_bytes),
+        )
+        .route(
+            "/aggregate/:slot/:interval_size/:value/signatures.bin",
+            get(get_aggregate_individual_signature_bytes),
+        )
+        .route(
+            "/aggregate/:slot/:interval_size/distribution",
+            get(get_aggregate_distribution),
+        )
+        .route(
+            "/aggregate/:slot/latest-updated",
+            get(get_latest_updated_aggregate),
+        )
+        .route(
+            "/aggregate/:slot/:interval_size/:value/full",
+            get(get_full_aggregate),
+        )
+        .route(
+            "/aggregate/:slot/:interval_size/:value/growth",
+            get(get_aggregate_growth),
+        )
+        .route(
+            "/aggregate/:slot/:interval_size/:value/cost-estimate",
+            get(get_aggregate_cost_estimate),
+        )
+        .route(
+            "/aggregate/:slot/:interval_size/:value/subset",
+            post(post_subset_aggregate),
+        )
+        .route("/aggregates/stream", get(get_aggregates_stream))
+        .route("/admin/challenge", get(get_admin_challenge))
+        .route("/slot", get(get_slot))
+        .route("/config", get(get_config))
+        .route("/retention", get(get_retention))
+        .route("/bundle/:slot", get(get_signed_slot_bundle))
+        .route("/assets/:asset/totals", get(get_asset_totals))
+        .route("/slot/:slot/completeness", get(get_slot_completeness))
+        .route("/participation", get(get_participation))
+        .route("/timing/:slot", get(get_slot_timing))
+        .route("/latest", get(get_latest))
+        .route("/price/latest", get(get_price_latest))
+        .route("/price/median", get(get_price_median))
+        .route("/price/twap", get(get_price_twap))
+        .route("/price/:slot/confidence", get(get_price_confidence))
+        .route("/proof", get(get_price_proof))
+        .route("/summary", get(get_summary))
+        .route("/value-diversity/:slot", get(get_value_diversity))
+        .route("/consensus/:slot", get(get_consensus))
+        .route("/consensus/:slot/weighted", get(get_weighted_consensus))
+        .route("/finalized/:slot", get(get_finalized_slot))
+        .route("/agreement/:slot", get(get_agreement))
+        .route("/consistency/:slot", get(get_consistency))
+        .route("/intervals/containing", get(get_intervals_containing))
+        .route("/outliers/:slot", get(get_outliers))
+        .route("/slashing-report", get(get_slashing_report))
+        .route("/equivocations", get(get_equivocations))
+        .route("/quorum-threshold/:slot", get(get_slot_quorum_threshold))
+        .route("/near-quorum/:slot", get(get_near_quorum_aggregates))
+        .route("/validators/:public_key/streak", get(get_validator_streak))
+        .route("/validators/:public_key/latest", get(get_validator_latest))
+        .route(
+            "/validators/:public_key/consensus-agreement",
+            get(get_validator_consensus_agreement),
+        )
+        .route("/verify/batch", post(post_verify_batch))
+        .route("/oracle-message/inspect", post(post_inspect_oracle_message))
+        .route("/verify/median/:slot", post(post_verify_median))
+        .merge(admin_router)
+        .layer(middleware::from_fn_with_state(
+            shared_state.clone(),
+            track_request_duration,
+        ))
+        .layer(middleware::from_fn(propagate_request_id))
+        .layer(cors_layer(&shared_state.config))
+        .layer(DefaultBodyLimit::max(shared_state.config.max_body_bytes))
         .with_state(shared_state)
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::attestations::get_message_digest;
     use crate::attestations::{
-        AggregatePriceIntervalEntry, OracleMessage, PriceIntervalEntry, PriceValueEntry,
+        find_invalid_interval_signatures, find_invalid_message_signatures, get_message_digest,
+        ingest_oracle_message, validate_message, verify_aggregate_entry,
+        AggregatePriceIntervalEntry, AnnotatedEntry, BatchMessageResult, DigestConfig, Envelope,
+        HashFunction, IntervalInclusionMessage, OracleMessage, OracleMessageError,
+        OrphanIntervalPolicy, Price, PriceIntervalEntry, PriceValueEntry, PriceValueMessage,
+        DEFAULT_LIST_LIMIT,
+    };
+    use crate::consensus::ConsensusValue;
+    use crate::db::DbPool;
+    use crate::test_utils::{
+        get_test_message, sign_oracle_message_with_new_key, signature_from_random_signer,
+        state_at_slot, value_message, value_message_from_new_validator, TestApp, TEST_MESSAGE_SLOT,
     };
-    use axum::{body::Body, http::Request};
-    use bls::{AggregateSignature, SecretKey, Signature};
-    use bytes::Bytes;
+    use axum::body::Body;
+    use bls::{AggregateSignature, Hash256, PublicKey, SecretKey, Signature};
     use hyper::http::StatusCode;
     use itertools::Itertools;
-    use tower::ServiceExt;
-
-    enum TestRequest {
-        Get(),
-        Post(Body),
-    }
-    struct TestApp {
-        db_pool: DbPool,
-    }
-
-    impl TestApp {
-        pub async fn new(db_pool: DbPool) -> Self {
-            TestApp { db_pool }
-        }
-
-        pub async fn get_expect(&self, uri: &str, expected_code: StatusCode) -> Bytes {
-            self.send_request(TestRequest::Get(), uri, expected_code)
-                .await
-        }
-
-        pub async fn post_expect(&self, uri: &str, body: Body, expected_code: StatusCode) -> Bytes {
-            self.send_request(TestRequest::Post(body), uri, expected_code)
-                .await
-        }
-
-        async fn send_request(
-            &self,
-            request: TestRequest,
-            uri: &str,
-            expected_code: StatusCode,
-        ) -> Bytes {
-            let app = get_router_with_db_pool(self.db_pool.clone());
-            let req = match request {
-                TestRequest::Get() => Request::builder()
-                    .uri(uri)
-                    .method("GET")
-                    .body(Body::empty())
-                    .unwrap(),
-                TestRequest::Post(body) => Request::builder()
-                    .uri(uri)
-                    .method("POST")
-                    .header("Content-Type", "application/json")
-                    .body(body)
-                    .unwrap(),
-            };
-
-            let response = app.oneshot(req).await.unwrap();
-            assert_eq!(response.status(), expected_code);
-            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
-            body
-        }
-    }
-
-    fn get_test_message() -> OracleMessage {
-        let test_data_file = std::fs::File::open("./test_data/input/6556020.json").unwrap();
-        serde_json::from_reader(test_data_file).unwrap()
-    }
-
-    fn sign_oracle_message_with_new_key(
-        mut message: OracleMessage,
-        private_key: &SecretKey,
-    ) -> OracleMessage {
-        message.validator_public_key = private_key.public_key();
-        message.value_message.signature =
-            sign_message(&message.value_message.message, &private_key);
-        for interval_message in message.interval_inclusion_messages.iter_mut() {
-            interval_message.signature = sign_message(&interval_message.message, &private_key);
-        }
-        message
-    }
+    use ssz::{Decode, Encode};
+    use std::sync::atomic::Ordering;
 
     #[sqlx::test]
     async fn can_aggregate_multiple_messages(db_pool: DbPool) {
-        let num_validators = 1;
+        let num_validators = 3;
         let private_keys: Vec<SecretKey> =
             (0..num_validators).map(|_| SecretKey::random()).collect();
         let test_message = get_test_message();
         let messages: Vec<OracleMessage> = private_keys
             .iter()
-            .map(|private_key| sign_oracle_message_with_new_key(test_message.clone(), &private_key))
+            .map(|private_key| sign_oracle_message_with_new_key(test_message.clone(), private_key))
             .collect();
 
         let test_app = TestApp::new(db_pool).await;
@@ -174,17 +344,18 @@ mod test {
             );
             assert_eq!(entry.num_validators, num_validators);
 
-            // TODO: Find out how to verify the aggregate signature with aggregate public key
-            // let signature = Signature::deserialize(&hex::decode(entry.aggregate_signature.clone()).unwrap())
-            //     .expect("Failed to deserialize signature");
-            // let public_key = bls::PublicKey::deserialize(&hex::decode(entry.aggregate_public_key.clone()).unwrap())
-            //     .expect("Failed to deserialize public key");
-            // assert!(
-            //     signature.verify(
-            //         &public_key,
-            //         get_message_digest(&test_message.interval_inclusion_messages[i].message),
-            //     )
-            // );
+            // All validators signed the same message, so the aggregate signature verifies against
+            // the aggregate of their public keys as if it were a single signature.
+            let signature: Signature = format!("0x{}", entry.aggregate_signature).parse().unwrap();
+            let public_key: PublicKey =
+                format!("0x{}", entry.aggregate_public_key).parse().unwrap();
+            assert!(signature.verify(
+                &public_key,
+                get_message_digest(
+                    &test_message.interval_inclusion_messages[i].message,
+                    DigestConfig::default()
+                ),
+            ));
         }
     }
 
@@ -320,13 +491,13 @@ mod test {
         test_app
             .get_expect(
                 &format!("/price_aggregate?slot_number={}", 1),
-                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::NOT_FOUND,
             )
             .await;
         test_app
             .get_expect(
                 &format!("/price_aggregate?interval_size={}", 1),
-                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::NOT_FOUND,
             )
             .await;
     }
@@ -390,13 +561,1807 @@ mod test {
         assert_eq!(entries.len(), 42);
     }
 
-    fn signature_from_random_signer<T: ssz::Encode>(message: &T) -> Signature {
+    #[sqlx::test]
+    async fn batch_verification_pinpoints_invalid_interval_signature(db_pool: DbPool) {
+        let mut test_message = get_test_message();
+        let config = Config {
+            batch_verify_intervals: true,
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+
+        assert!(find_invalid_interval_signatures(
+            &test_message.validator_public_key,
+            &test_message.interval_inclusion_messages,
+            DigestConfig::default()
+        )
+        .is_empty());
+        test_message.interval_inclusion_messages[42].signature =
+            signature_from_random_signer(&test_message.interval_inclusion_messages[42].message);
+        assert_eq!(
+            find_invalid_interval_signatures(
+                &test_message.validator_public_key,
+                &test_message.interval_inclusion_messages,
+                DigestConfig::default()
+            ),
+            vec![42]
+        );
+
+        // Swapped signatures add up to the same aggregate, but are caught all the same
+        let mut swapped_message =
+            sign_oracle_message_with_new_key(get_test_message(), &SecretKey::random());
+        let intervals = &mut swapped_message.interval_inclusion_messages;
+        let signature = intervals[1].signature.clone();
+        intervals[1].signature = intervals[2].signature.clone();
+        intervals[2].signature = signature;
+        assert_eq!(
+            find_invalid_interval_signatures(
+                &swapped_message.validator_public_key,
+                &swapped_message.interval_inclusion_messages,
+                DigestConfig::default()
+            ),
+            vec![1, 2]
+        );
+        test_app
+            .post_oracle_message(&swapped_message, StatusCode::BAD_REQUEST)
+            .await;
+
+        test_app
+            .post_oracle_message(&test_message, StatusCode::BAD_REQUEST)
+            .await;
+        // Unlike verifying one by one, none of the interval messages are saved
+        let interval_response = test_app
+            .get_expect("/price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceIntervalEntry> = serde_json::from_slice(&interval_response).unwrap();
+        assert_eq!(entries.len(), 0);
+
+        let valid_message =
+            sign_oracle_message_with_new_key(get_test_message(), &SecretKey::random());
+        test_app
+            .post_oracle_message(&valid_message, StatusCode::OK)
+            .await;
+        let interval_response = test_app
+            .get_expect("/price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceIntervalEntry> = serde_json::from_slice(&interval_response).unwrap();
+        assert_eq!(entries.len(), 724);
+    }
+
+    fn value_message_in_network(private_key: &SecretKey, network_id: Option<u64>) -> OracleMessage {
+        let mut message = value_message(private_key, TEST_MESSAGE_SLOT, 1811093163);
+        message.value_message.signature = private_key.sign(get_message_digest(
+            &message.value_message.message,
+            DigestConfig {
+                network_id,
+                ..DigestConfig::default()
+            },
+        ));
+        message
+    }
+
+    #[sqlx::test]
+    async fn verifies_with_configured_hash_function(db_pool: DbPool) {
+        let keccak_config = DigestConfig {
+            hash_function: HashFunction::Keccak256,
+            ..DigestConfig::default()
+        };
+        let test_message = get_test_message();
+        let price_message = &test_message.value_message.message;
+        assert_ne!(
+            get_message_digest(price_message, DigestConfig::default()),
+            get_message_digest(price_message, keccak_config)
+        );
+
+        let config = Config {
+            hash_function: HashFunction::Keccak256,
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        let response = test_app.get_expect("/config", StatusCode::OK).await;
+        let public_config: serde_json::Value = serde_json::from_slice(&response).unwrap();
+        assert_eq!(public_config["hash_function"], "keccak256");
+
+        // The test message is signed over sha3-256 digests
+        test_app
+            .post_oracle_message(
+                &value_message_from_new_validator(1811093163),
+                StatusCode::BAD_REQUEST,
+            )
+            .await;
+        let private_key = SecretKey::random();
+        let mut message = value_message(&private_key, TEST_MESSAGE_SLOT, 1811093163);
+        message.value_message.signature = private_key.sign(get_message_digest(
+            &message.value_message.message,
+            keccak_config,
+        ));
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
+    }
+
+    #[sqlx::test]
+    async fn rejects_signatures_from_another_network(db_pool: DbPool) {
+        let config = Config {
+            signing_network_id: Some(1),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        for network_id in [Some(2), None] {
+            let message = value_message_in_network(&SecretKey::random(), network_id);
+            test_app
+                .post_oracle_message(&message, StatusCode::BAD_REQUEST)
+                .await;
+        }
+        let message = value_message_in_network(&SecretKey::random(), Some(1));
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
+
+        let value_message = &message.value_message;
+        assert!(validate_message(
+            &message.validator_public_key,
+            &value_message.message,
+            &value_message.signature,
+            DigestConfig {
+                network_id: Some(1),
+                ..DigestConfig::default()
+            }
+        ));
+        assert!(!validate_message(
+            &message.validator_public_key,
+            &value_message.message,
+            &value_message.signature,
+            DigestConfig {
+                network_id: Some(2),
+                ..DigestConfig::default()
+            }
+        ));
+    }
+
+    #[test]
+    fn separates_signatures_of_message_types() {
         let private_key = SecretKey::random();
-        sign_message(message, &private_key)
+        let public_key = private_key.public_key();
+        let interval_message = IntervalInclusionMessage {
+            value: 1811093163,
+            interval_size: 20,
+            slot_number: TEST_MESSAGE_SLOT,
+        };
+        // A timed value message with the same bytes as the interval message
+        let value_message = PriceValueMessage {
+            price: Price {
+                value: 1811093163,
+                timestamp: Some(20),
+            },
+            slot_number: TEST_MESSAGE_SLOT,
+        };
+        assert_eq!(
+            value_message.as_ssz_bytes(),
+            interval_message.as_ssz_bytes()
+        );
+        for network_id in [None, Some(1)] {
+            let digest_config = DigestConfig {
+                network_id,
+                ..DigestConfig::default()
+            };
+            let value_signature =
+                private_key.sign(get_message_digest(&value_message, digest_config));
+            let interval_signature =
+                private_key.sign(get_message_digest(&interval_message, digest_config));
+            assert!(validate_message(
+                &public_key,
+                &value_message,
+                &value_signature,
+                digest_config
+            ));
+            assert!(!validate_message(
+                &public_key,
+                &interval_message,
+                &value_signature,
+                digest_config
+            ));
+            assert!(!validate_message(
+                &public_key,
+                &value_message,
+                &interval_signature,
+                digest_config
+            ));
+        }
+    }
+
+    #[sqlx::test]
+    async fn rejects_resubmitted_message_as_conflict(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let message = value_message_from_new_validator(1811093163);
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
+        let response = test_app
+            .post_oracle_message(&message, StatusCode::CONFLICT)
+            .await;
+        assert_eq!(&response[..], b"attestation was already submitted");
+    }
+
+    #[sqlx::test]
+    async fn rejects_replayed_interval_attestations(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool.clone()).await;
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(2);
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+        test_app
+            .post_oracle_message(&test_message, StatusCode::CONFLICT)
+            .await;
+        let response = test_app
+            .get_expect("/aggregate_price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<AggregatePriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert!(entries.iter().all(|entry| entry.num_validators == 1));
+
+        // A validator attests to an interval value once per slot, whatever the signature
+        let interval_message = &test_message.interval_inclusion_messages[0].message;
+        let result = sqlx::query(
+            "
+            INSERT INTO price_interval_attestations(
+                validator_public_key,
+                signature,
+                slot_number,
+                value,
+                interval_size,
+                aggregate_value
+            )
+            VALUES ($1, $2, $3, $4, $5, $4);
+            ",
+        )
+        .bind(test_message.validator_public_key.to_string())
+        .bind(signature_from_random_signer(interval_message).to_string())
+        .bind(interval_message.slot_number as i64)
+        .bind(interval_message.value as i64)
+        .bind(interval_message.interval_size as i64)
+        .execute(&db_pool)
+        .await;
+        assert!(matches!(
+            OracleMessageError::from(result.unwrap_err()),
+            OracleMessageError::Duplicate
+        ));
+    }
+
+    #[sqlx::test]
+    async fn responds_with_500_when_saving_fails(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool.clone()).await;
+        db_pool.close().await;
+        let response = test_app
+            .post_oracle_message(&get_test_message(), StatusCode::INTERNAL_SERVER_ERROR)
+            .await;
+        // Details of internal errors are only logged
+        assert_eq!(&response[..], b"internal error");
+    }
+
+    #[sqlx::test]
+    async fn rejects_value_message_outside_value_window(db_pool: DbPool) {
+        let test_message = get_test_message();
+        let config = Config {
+            value_window_slots: 2,
+            interval_window_slots: 10,
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config_at_slot(db_pool, config, TEST_MESSAGE_SLOT + 3).await;
+
+        let body = Body::from(serde_json::to_string(&test_message).unwrap());
+        test_app
+            .post_expect("/post_oracle_message", body, StatusCode::BAD_REQUEST)
+            .await;
+
+        let response = test_app
+            .get_expect("/price_value_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceValueEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 0);
+    }
+
+    #[sqlx::test]
+    async fn rejects_interval_messages_outside_interval_window(db_pool: DbPool) {
+        let test_message = get_test_message();
+        let config = Config {
+            value_window_slots: 10,
+            interval_window_slots: 2,
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config_at_slot(db_pool, config, TEST_MESSAGE_SLOT + 3).await;
+
+        let body = Body::from(serde_json::to_string(&test_message).unwrap());
+        test_app
+            .post_expect("/post_oracle_message", body, StatusCode::BAD_REQUEST)
+            .await;
+
+        // The value message is within its own window and is still saved
+        let response = test_app
+            .get_expect("/price_value_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceValueEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 1);
+
+        let response = test_app
+            .get_expect("/price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 0);
+    }
+
+    #[sqlx::test]
+    async fn accepts_interval_messages_of_allowed_size(db_pool: DbPool) {
+        let config = Config {
+            allowed_interval_sizes: [10, 20].into(),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        test_app
+            .post_oracle_message(&get_test_message(), StatusCode::OK)
+            .await;
+
+        let response = test_app
+            .get_expect("/price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 724);
+    }
+
+    #[sqlx::test]
+    async fn rejects_interval_messages_of_disallowed_size(db_pool: DbPool) {
+        let config = Config {
+            allowed_interval_sizes: [10].into(),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        let body = Body::from(serde_json::to_string(&get_test_message()).unwrap());
+        let response = test_app
+            .post_expect("/post_oracle_message", body, StatusCode::BAD_REQUEST)
+            .await;
+        assert_eq!(&response[..], b"interval size 20 is not allowed");
+
+        let response = test_app
+            .get_expect("/price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 0);
+    }
+
+    #[sqlx::test]
+    async fn rejects_bodies_over_the_size_limit(db_pool: DbPool) {
+        let test_message = get_test_message();
+        let body = serde_json::to_string(&test_message).unwrap();
+        let config = Config {
+            max_body_bytes: body.len() - 1,
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        test_app
+            .post_expect(
+                "/post_oracle_message",
+                Body::from(body),
+                StatusCode::PAYLOAD_TOO_LARGE,
+            )
+            .await;
+
+        // Smaller messages still get through
+        let message = value_message_from_new_validator(1811093163);
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
+    }
+
+    #[sqlx::test]
+    async fn rejects_messages_with_too_many_interval_messages(db_pool: DbPool) {
+        let config = Config {
+            max_interval_messages: Some(10),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        let body = Body::from(serde_json::to_string(&get_test_message()).unwrap());
+        let response = test_app
+            .post_expect("/post_oracle_message", body, StatusCode::PAYLOAD_TOO_LARGE)
+            .await;
+        assert_eq!(
+            &response[..],
+            b"message holds more than 10 interval messages"
+        );
+
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(10);
+        let message = sign_oracle_message_with_new_key(test_message, &SecretKey::random());
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
     }
 
-    fn sign_message<T: ssz::Encode>(message: &T, private_key: &SecretKey) -> Signature {
-        let message_digest = get_message_digest(message);
-        private_key.sign(message_digest)
+    #[sqlx::test]
+    async fn rejects_value_messages_far_outside_the_window(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        for slot_number in [0, 10u64.pow(18), TEST_MESSAGE_SLOT + 6] {
+            let response = test_app
+                .post_oracle_message(
+                    &value_message(&SecretKey::random(), slot_number, 1811093163),
+                    StatusCode::BAD_REQUEST,
+                )
+                .await;
+            assert_eq!(
+                String::from_utf8(response.to_vec()).unwrap(),
+                format!("value message slot {slot_number} is outside of the accepted window")
+            );
+        }
+        test_app
+            .post_oracle_message(
+                &value_message(&SecretKey::random(), TEST_MESSAGE_SLOT + 5, 1811093163),
+                StatusCode::OK,
+            )
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn accepts_messages_within_both_windows(db_pool: DbPool) {
+        let test_message = get_test_message();
+        let config = Config {
+            value_window_slots: 3,
+            interval_window_slots: 3,
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config_at_slot(db_pool, config, TEST_MESSAGE_SLOT - 3).await;
+
+        let body = Body::from(serde_json::to_string(&test_message).unwrap());
+        test_app
+            .post_expect("/post_oracle_message", body, StatusCode::OK)
+            .await;
+
+        let response = test_app
+            .get_expect("/price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(
+            entries.len(),
+            test_message.interval_inclusion_messages.len()
+        );
+    }
+
+    #[sqlx::test]
+    async fn aggregates_values_within_the_same_band(db_pool: DbPool) {
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(1);
+        let interval_size = test_message.interval_inclusion_messages[0]
+            .message
+            .interval_size;
+        let config = Config {
+            interval_value_bands: [(interval_size, 10)].into(),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+
+        for value in [180741, 180748] {
+            let mut message = test_message.clone();
+            message.interval_inclusion_messages[0].message.value = value;
+            let message = sign_oracle_message_with_new_key(message, &SecretKey::random());
+            test_app
+                .post_expect(
+                    "/post_oracle_message",
+                    Body::from(serde_json::to_string(&message).unwrap()),
+                    StatusCode::OK,
+                )
+                .await;
+        }
+
+        let response = test_app
+            .get_expect("/aggregate_price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<AggregatePriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].value, 180740);
+        assert_eq!(entries[0].num_validators, 2);
+
+        let response = test_app
+            .get_expect("/price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        let values: Vec<(i64, i64)> = entries
+            .iter()
+            .map(|entry| (entry.value, entry.aggregate_value))
+            .sorted()
+            .collect();
+        assert_eq!(values, vec![(180741, 180740), (180748, 180740)]);
+    }
+
+    #[sqlx::test]
+    async fn aggregates_each_validator_once_per_band(db_pool: DbPool) {
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(2);
+        let interval_size = test_message.interval_inclusion_messages[0]
+            .message
+            .interval_size;
+        let config = Config {
+            interval_value_bands: [(interval_size, 10)].into(),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        // Both values fall into the same band
+        test_message.interval_inclusion_messages[0].message.value = 180741;
+        test_message.interval_inclusion_messages[1].message.value = 180748;
+        let private_key = SecretKey::random();
+        let message = sign_oracle_message_with_new_key(test_message, &private_key);
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
+
+        let response = test_app
+            .get_expect("/aggregate_price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<AggregatePriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].num_validators, 1);
+        assert_eq!(
+            entries[0].public_keys,
+            vec![private_key.public_key().to_string()]
+        );
+        // Only the first signature went into the aggregate
+        let signature = message.interval_inclusion_messages[0].signature.to_string();
+        assert_eq!(
+            entries[0].aggregate_signature,
+            signature.strip_prefix("0x").unwrap()
+        );
+
+        // Both attestations are still stored
+        let entries = get_interval_entries(&test_app).await;
+        assert_eq!(entries.len(), 2);
+    }
+
+    #[sqlx::test]
+    async fn aggregates_identical_signatures_once(db_pool: DbPool) {
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(1);
+        let test_app = TestApp::new(db_pool.clone()).await;
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+        let response = test_app
+            .get_expect("/aggregate_price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<AggregatePriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+
+        // Once the stored attestations are gone, they no longer catch the resubmission
+        sqlx::query("DELETE FROM price_value_attestations;")
+            .execute(&db_pool)
+            .await
+            .unwrap();
+        sqlx::query("DELETE FROM price_interval_attestations;")
+            .execute(&db_pool)
+            .await
+            .unwrap();
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+        let response = test_app
+            .get_expect("/aggregate_price_interval_attestations", StatusCode::OK)
+            .await;
+        let resubmitted_entries: Vec<AggregatePriceIntervalEntry> =
+            serde_json::from_slice(&response).unwrap();
+        assert_eq!(resubmitted_entries.len(), 1);
+        assert_eq!(resubmitted_entries[0].num_validators, 1);
+        assert_eq!(
+            resubmitted_entries[0].aggregate_signature,
+            entries[0].aggregate_signature
+        );
+    }
+
+    #[sqlx::test]
+    async fn rejects_messages_without_intervals_when_required(db_pool: DbPool) {
+        let config = Config {
+            require_interval_messages: true,
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+
+        test_app
+            .post_oracle_message(
+                &value_message_from_new_validator(1811093163),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            )
+            .await;
+        let response = test_app
+            .get_expect("/price_value_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceValueEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 0);
+
+        test_app
+            .post_oracle_message(&get_test_message(), StatusCode::OK)
+            .await;
+    }
+
+    /// Two interval messages for the slot before the one of the value message.
+    fn orphan_interval_message(private_key: &SecretKey) -> OracleMessage {
+        let mut message = get_test_message();
+        message.interval_inclusion_messages.truncate(2);
+        for interval_message in message.interval_inclusion_messages.iter_mut() {
+            interval_message.message.slot_number = TEST_MESSAGE_SLOT - 1;
+        }
+        sign_oracle_message_with_new_key(message, private_key)
+    }
+
+    async fn get_interval_entries(test_app: &TestApp) -> Vec<PriceIntervalEntry> {
+        let response = test_app
+            .get_expect("/price_interval_attestations", StatusCode::OK)
+            .await;
+        serde_json::from_slice(&response).unwrap()
+    }
+
+    #[sqlx::test]
+    async fn rejects_orphan_interval_messages_when_configured(db_pool: DbPool) {
+        let config = Config {
+            orphan_interval_policy: OrphanIntervalPolicy::Reject,
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        let private_key = SecretKey::random();
+
+        test_app
+            .post_oracle_message(
+                &orphan_interval_message(&private_key),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            )
+            .await;
+        assert!(get_interval_entries(&test_app).await.is_empty());
+
+        // With a value message for the earlier slot they're no longer orphans
+        test_app
+            .post_oracle_message(
+                &value_message(&private_key, TEST_MESSAGE_SLOT - 1, 1811093163),
+                StatusCode::OK,
+            )
+            .await;
+        test_app
+            .post_oracle_message(&orphan_interval_message(&private_key), StatusCode::OK)
+            .await;
+        assert_eq!(get_interval_entries(&test_app).await.len(), 2);
+    }
+
+    #[sqlx::test]
+    async fn enforces_value_messages_arriving_before_intervals(db_pool: DbPool) {
+        let config = Config {
+            enforce_arrival_order: true,
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+
+        // In order, the value message for the earlier slot before its interval messages
+        let private_key = SecretKey::random();
+        test_app
+            .post_oracle_message(
+                &value_message(&private_key, TEST_MESSAGE_SLOT - 1, 1811093163),
+                StatusCode::OK,
+            )
+            .await;
+        test_app
+            .post_oracle_message(&orphan_interval_message(&private_key), StatusCode::OK)
+            .await;
+
+        // Out of order, the value message for the earlier slot after its interval messages
+        let private_key = SecretKey::random();
+        test_app
+            .post_oracle_message(&orphan_interval_message(&private_key), StatusCode::OK)
+            .await;
+        let response = test_app
+            .post_oracle_message(
+                &value_message(&private_key, TEST_MESSAGE_SLOT - 1, 1811093163),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            )
+            .await;
+        assert!(String::from_utf8(response.to_vec())
+            .unwrap()
+            .contains("arrived after interval messages"));
+    }
+
+    #[sqlx::test]
+    async fn accepts_orphan_interval_messages_by_default(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+
+        test_app
+            .post_oracle_message(
+                &orphan_interval_message(&SecretKey::random()),
+                StatusCode::OK,
+            )
+            .await;
+        let entries = get_interval_entries(&test_app).await;
+        assert_eq!(entries.len(), 2);
+        assert!(entries
+            .iter()
+            .all(|entry| entry.slot_number == TEST_MESSAGE_SLOT as i64 - 1));
+    }
+
+    #[sqlx::test]
+    async fn accepts_messages_without_intervals_by_default(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+
+        test_app
+            .post_oracle_message(
+                &value_message_from_new_validator(1811093163),
+                StatusCode::OK,
+            )
+            .await;
+        test_app
+            .post_oracle_message(&get_test_message(), StatusCode::OK)
+            .await;
+
+        let response = test_app
+            .get_expect("/price_value_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceValueEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 2);
+    }
+
+    #[sqlx::test]
+    async fn stops_creating_aggregates_at_max_per_slot(db_pool: DbPool) {
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(3);
+        let config = Config {
+            max_aggregates_per_slot: Some(2),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+
+        let response = test_app
+            .get_expect("/aggregate_price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<AggregatePriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 2);
+
+        // Existing aggregates still get extended
+        let message = sign_oracle_message_with_new_key(test_message, &SecretKey::random());
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
+        let response = test_app
+            .get_expect("/aggregate_price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<AggregatePriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|entry| entry.num_validators == 2));
+
+        // All individual attestations are still stored
+        let response = test_app
+            .get_expect("/price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 6);
+    }
+
+    #[sqlx::test]
+    async fn aggregates_concurrent_submissions_without_losing_any(db_pool: DbPool) {
+        let num_validators = 8;
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(1);
+        let test_app = TestApp::new(db_pool).await;
+        let messages: Vec<OracleMessage> = (0..num_validators)
+            .map(|_| sign_oracle_message_with_new_key(test_message.clone(), &SecretKey::random()))
+            .collect();
+        futures_util::future::join_all(
+            messages
+                .iter()
+                .map(|message| test_app.post_oracle_message(message, StatusCode::OK)),
+        )
+        .await;
+
+        let response = test_app
+            .get_expect("/aggregate_price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<AggregatePriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].num_validators, num_validators);
+        assert!(verify_aggregate_entry(&entries[0], DigestConfig::default()));
+    }
+
+    #[sqlx::test]
+    async fn stops_extending_aggregates_at_max_validators(db_pool: DbPool) {
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(1);
+        let private_keys: Vec<SecretKey> = (0..3).map(|_| SecretKey::random()).collect();
+        // Only two validators can legitimately contribute
+        let config = Config {
+            validator_allowlist: private_keys[..2]
+                .iter()
+                .map(|private_key| private_key.public_key())
+                .collect(),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        for private_key in &private_keys {
+            let message = sign_oracle_message_with_new_key(test_message.clone(), private_key);
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+        }
+
+        let response = test_app
+            .get_expect("/aggregate_price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<AggregatePriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].num_validators, 2);
+        assert_eq!(entries[0].public_keys.len(), 2);
+
+        // The individual attestation is still stored
+        let entries = get_interval_entries(&test_app).await;
+        assert_eq!(entries.len(), 3);
+    }
+
+    #[sqlx::test]
+    async fn stores_signing_preimages_when_configured(db_pool: DbPool) {
+        let test_message = get_test_message();
+        let config = Config {
+            store_signing_preimages: true,
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+
+        let response = test_app
+            .get_expect("/price_value_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceValueEntry> = serde_json::from_slice(&response).unwrap();
+        let value_message = PriceValueMessage {
+            price: Price {
+                value: entries[0].value as u64,
+                timestamp: None,
+            },
+            slot_number: entries[0].slot_number as u64,
+        };
+        assert_eq!(
+            entries[0].signing_preimage,
+            Some(hex::encode(value_message.as_ssz_bytes()))
+        );
+
+        let response = test_app
+            .get_expect("/price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(
+            entries.len(),
+            test_message.interval_inclusion_messages.len()
+        );
+        for entry in entries {
+            let interval_message = IntervalInclusionMessage {
+                value: entry.value as u64,
+                interval_size: entry.interval_size as u64,
+                slot_number: entry.slot_number as u64,
+            };
+            assert_eq!(
+                entry.signing_preimage,
+                Some(hex::encode(interval_message.as_ssz_bytes()))
+            );
+        }
+    }
+
+    #[sqlx::test]
+    async fn does_not_store_signing_preimages_by_default(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        test_app
+            .post_oracle_message(&get_test_message(), StatusCode::OK)
+            .await;
+
+        let response = test_app
+            .get_expect("/price_value_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceValueEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries[0].signing_preimage, None);
+    }
+
+    #[sqlx::test]
+    async fn rejects_interval_signature_as_timed_value_signature(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let mut message = get_test_message();
+        message.interval_inclusion_messages.truncate(1);
+        let message = sign_oracle_message_with_new_key(message, &SecretKey::random());
+        let interval_message = &message.interval_inclusion_messages[0];
+
+        // A timed value message with the bytes and signature of the interval message
+        let mut replayed_message = message.clone();
+        replayed_message.value_message.message = PriceValueMessage {
+            price: Price {
+                value: interval_message.message.value,
+                timestamp: Some(interval_message.message.interval_size),
+            },
+            slot_number: interval_message.message.slot_number,
+        };
+        replayed_message.value_message.signature = interval_message.signature.clone();
+        replayed_message.interval_inclusion_messages.clear();
+        assert_eq!(
+            replayed_message.value_message.message.as_ssz_bytes(),
+            interval_message.message.as_ssz_bytes()
+        );
+        assert!(!validate_message(
+            &message.validator_public_key,
+            &replayed_message.value_message.message,
+            &replayed_message.value_message.signature,
+            DigestConfig::default()
+        ));
+        test_app
+            .post_oracle_message(&replayed_message, StatusCode::BAD_REQUEST)
+            .await;
+
+        // The validator's own value message is neither taken nor an equivocation
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
+    }
+
+    #[sqlx::test]
+    async fn stores_signed_price_timestamps(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let private_key = SecretKey::random();
+        let mut message = value_message(&private_key, TEST_MESSAGE_SLOT, 1811093163);
+        message.value_message.message.price.timestamp = Some(1685000000);
+        let message = sign_oracle_message_with_new_key(message, &private_key);
+        let value_message = &message.value_message.message;
+        let decoded = PriceValueMessage::from_ssz_bytes(&value_message.as_ssz_bytes()).unwrap();
+        assert_eq!(decoded.price.timestamp, Some(1685000000));
+
+        // The signature covers the timestamp
+        for tampered_timestamp in [Some(1685000001), None] {
+            let mut tampered_message = message.clone();
+            tampered_message.value_message.message.price.timestamp = tampered_timestamp;
+            test_app
+                .post_oracle_message(&tampered_message, StatusCode::BAD_REQUEST)
+                .await;
+        }
+
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
+        let response = test_app
+            .get_expect("/price_value_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceValueEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries[0].price_timestamp, Some(1685000000));
+    }
+
+    #[sqlx::test]
+    async fn responds_with_500_when_the_db_is_unavailable(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool.clone()).await;
+        db_pool.close().await;
+
+        for uri in [
+            "/price_value_attestations",
+            "/aggregate_price_interval_attestations",
+            "/price_interval_attestations",
+            "/price_aggregate",
+            "/price_aggregate?slot_number=1&interval_size=1",
+        ] {
+            test_app
+                .get_expect(uri, StatusCode::INTERNAL_SERVER_ERROR)
+                .await;
+        }
+    }
+
+    #[sqlx::test]
+    async fn responds_with_404_for_price_aggregate_without_data(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        for uri in [
+            "/price_aggregate",
+            "/price_aggregate?slot_number=1",
+            "/price_aggregate?slot_number=1&interval_size=1",
+        ] {
+            test_app.get_expect(uri, StatusCode::NOT_FOUND).await;
+        }
+    }
+
+    #[sqlx::test]
+    async fn filters_attestations_by_received_at(db_pool: DbPool) {
+        // Received at 1000, 2000 and 3000 seconds after the epoch
+        for received_at in [1000, 2000, 3000] {
+            sqlx::query(
+                "
+                INSERT INTO price_value_attestations(
+                    validator_public_key,
+                    slot_number,
+                    value,
+                    signature,
+                    received_at
+                )
+                VALUES ($1, 1, 100, '', to_timestamp($2));
+                ",
+            )
+            .bind(format!("validator-{received_at}"))
+            .bind(received_at as f64)
+            .execute(&db_pool)
+            .await
+            .unwrap();
+            sqlx::query(
+                "
+                INSERT INTO price_interval_attestations(
+                    validator_public_key,
+                    signature,
+                    slot_number,
+                    value,
+                    interval_size,
+                    aggregate_value,
+                    received_at
+                )
+                VALUES ($1, $1, 1, 100, 20, 100, to_timestamp($2));
+                ",
+            )
+            .bind(format!("validator-{received_at}"))
+            .bind(received_at as f64)
+            .execute(&db_pool)
+            .await
+            .unwrap();
+        }
+        let test_app = TestApp::new(db_pool).await;
+
+        let response = test_app
+            .get_expect(
+                "/price_value_attestations?received_after=2000000",
+                StatusCode::OK,
+            )
+            .await;
+        let entries: Vec<PriceValueEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].received_at, 3000000);
+
+        let response = test_app
+            .get_expect(
+                "/price_interval_attestations?received_after=1999999",
+                StatusCode::OK,
+            )
+            .await;
+        let entries: Vec<PriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        let received_at: Vec<i64> = entries
+            .iter()
+            .map(|entry| entry.received_at)
+            .sorted()
+            .collect();
+        assert_eq!(received_at, vec![2000000, 3000000]);
+
+        let response = test_app
+            .get_expect("/price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 3);
+    }
+
+    #[sqlx::test]
+    async fn aggregates_assets_independently(db_pool: DbPool) {
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(1);
+        let test_app = TestApp::new(db_pool).await;
+        for asset in ["ETHUSD", "BTCUSD"] {
+            let mut message = test_message.clone();
+            message.asset = asset.to_string();
+            let message = sign_oracle_message_with_new_key(message, &SecretKey::random());
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+        }
+
+        let response = test_app
+            .get_expect("/aggregate_price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<AggregatePriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        let assets: Vec<&str> = entries
+            .iter()
+            .map(|entry| entry.asset.as_str())
+            .sorted()
+            .collect();
+        assert_eq!(assets, vec!["BTCUSD", "ETHUSD"]);
+        assert!(entries.iter().all(|entry| entry.num_validators == 1
+            && entry.value == test_message.interval_inclusion_messages[0].message.value as i64));
+    }
+
+    #[sqlx::test]
+    async fn rejects_message_replayed_for_another_asset(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let message = value_message_from_new_validator(1811093163);
+        let mut replayed_message = message.clone();
+        replayed_message.asset = "BTCUSD".to_string();
+        test_app
+            .post_oracle_message(&replayed_message, StatusCode::BAD_REQUEST)
+            .await;
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
+    }
+
+    #[sqlx::test]
+    async fn counts_validators_of_one_operator_once_for_diversity(db_pool: DbPool) {
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(1);
+        let private_keys = [
+            SecretKey::random(),
+            SecretKey::random(),
+            SecretKey::random(),
+        ];
+        let config = Config {
+            validator_operators: private_keys[..2]
+                .iter()
+                .map(|private_key| (private_key.public_key().to_string(), "operator".to_string()))
+                .collect(),
+            quorum_threshold: Some(2),
+            quorum_min_operators: Some(2),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+
+        let mut num_operators = Vec::new();
+        let mut reached_quorum = Vec::new();
+        for private_key in &private_keys {
+            let message = sign_oracle_message_with_new_key(test_message.clone(), private_key);
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+            let response = test_app
+                .get_expect("/aggregate_price_interval_attestations", StatusCode::OK)
+                .await;
+            let entries: Vec<AggregatePriceIntervalEntry> =
+                serde_json::from_slice(&response).unwrap();
+            num_operators.push((entries[0].num_validators, entries[0].num_operators));
+
+            let response = test_app
+                .get_expect(&format!("/consensus/{TEST_MESSAGE_SLOT}"), StatusCode::OK)
+                .await;
+            let consensus: ConsensusValue = serde_json::from_slice(&response).unwrap();
+            reached_quorum.push(consensus.interval_aggregate.is_some());
+        }
+        assert_eq!(num_operators, vec![(1, 1), (2, 1), (3, 2)]);
+        // Two validators of the same operator don't reach quorum on their own
+        assert_eq!(reached_quorum, vec![false, false, true]);
+    }
+
+    #[sqlx::test]
+    async fn sorts_list_endpoints(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        for (slot_offset, value) in [(1, 300), (0, 100), (2, 200)] {
+            test_app
+                .post_oracle_message(
+                    &value_message(&SecretKey::random(), TEST_MESSAGE_SLOT - slot_offset, value),
+                    StatusCode::OK,
+                )
+                .await;
+        }
+
+        let slot = |offset: i64| TEST_MESSAGE_SLOT as i64 - offset;
+        for (sort, expected) in [
+            ("", vec![(slot(2), 200), (slot(1), 300), (slot(0), 100)]),
+            (
+                "?sort=slot_asc",
+                vec![(slot(2), 200), (slot(1), 300), (slot(0), 100)],
+            ),
+            (
+                "?sort=slot_desc",
+                vec![(slot(0), 100), (slot(1), 300), (slot(2), 200)],
+            ),
+            (
+                "?sort=value_asc",
+                vec![(slot(0), 100), (slot(2), 200), (slot(1), 300)],
+            ),
+            (
+                "?sort=value_desc",
+                vec![(slot(1), 300), (slot(2), 200), (slot(0), 100)],
+            ),
+        ] {
+            let response = test_app
+                .get_expect(&format!("/price_value_attestations{sort}"), StatusCode::OK)
+                .await;
+            let entries: Vec<PriceValueEntry> = serde_json::from_slice(&response).unwrap();
+            let slots_and_values: Vec<(i64, i64)> = entries
+                .iter()
+                .map(|entry| (entry.slot_number, entry.value))
+                .collect();
+            assert_eq!(slots_and_values, expected, "sort {sort}");
+        }
+
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(3);
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+        let response = test_app
+            .get_expect(
+                "/aggregate_price_interval_attestations?sort=value_desc",
+                StatusCode::OK,
+            )
+            .await;
+        let entries: Vec<AggregatePriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        let values: Vec<i64> = entries.iter().map(|entry| entry.value).collect();
+        assert_eq!(values, vec![180749, 180748, 180747]);
+
+        for path in [
+            "/price_value_attestations",
+            "/price_interval_attestations",
+            "/aggregate_price_interval_attestations",
+        ] {
+            test_app
+                .get_expect(
+                    &format!("{path}?sort=slot_number;DROP"),
+                    StatusCode::BAD_REQUEST,
+                )
+                .await;
+        }
+    }
+
+    async fn get_value_slots(test_app: &TestApp, query: &str) -> Vec<i64> {
+        let response = test_app
+            .get_expect(&format!("/price_value_attestations{query}"), StatusCode::OK)
+            .await;
+        serde_json::from_slice::<Vec<PriceValueEntry>>(&response)
+            .unwrap()
+            .iter()
+            .map(|entry| entry.slot_number)
+            .collect()
+    }
+
+    #[sqlx::test]
+    async fn filters_and_limits_list_endpoints(db_pool: DbPool) {
+        let config = Config {
+            value_window_slots: 50,
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        for slot_offset in 0..50 {
+            test_app
+                .post_oracle_message(
+                    &value_message(
+                        &SecretKey::random(),
+                        TEST_MESSAGE_SLOT - slot_offset,
+                        1811093163,
+                    ),
+                    StatusCode::OK,
+                )
+                .await;
+        }
+        let slot = |offset: i64| TEST_MESSAGE_SLOT as i64 - offset;
+
+        assert_eq!(get_value_slots(&test_app, "").await.len(), 50);
+        assert_eq!(
+            get_value_slots(
+                &test_app,
+                &format!("?from_slot={}&to_slot={}", slot(9), slot(5))
+            )
+            .await,
+            (5..=9).rev().map(slot).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            get_value_slots(&test_app, "?limit=3&offset=2").await,
+            vec![slot(47), slot(46), slot(45)]
+        );
+        assert!(
+            get_value_slots(&test_app, &format!("?from_slot={}", TEST_MESSAGE_SLOT + 1))
+                .await
+                .is_empty()
+        );
+
+        // Beyond the default limit
+        for _ in 0..2 {
+            let message =
+                sign_oracle_message_with_new_key(get_test_message(), &SecretKey::random());
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+        }
+        let response = test_app
+            .get_expect("/price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len() as i64, DEFAULT_LIST_LIMIT);
+        let response = test_app
+            .get_expect(
+                &format!(
+                    "/aggregate_price_interval_attestations?to_slot={TEST_MESSAGE_SLOT}&limit=2"
+                ),
+                StatusCode::OK,
+            )
+            .await;
+        let entries: Vec<AggregatePriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 2);
+        let response = test_app
+            .get_expect(
+                &format!(
+                    "/aggregate_price_interval_attestations?to_slot={}",
+                    TEST_MESSAGE_SLOT - 1
+                ),
+                StatusCode::OK,
+            )
+            .await;
+        assert_eq!(response.as_ref(), b"[]");
+    }
+
+    #[sqlx::test]
+    async fn rejects_intervals_inconsistent_with_the_value(db_pool: DbPool) {
+        let config = Config {
+            reject_inconsistent_intervals: true,
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        let test_message = get_test_message();
+
+        let mut mismatched_slot = test_message.clone();
+        mismatched_slot.interval_inclusion_messages[3]
+            .message
+            .slot_number -= 1;
+        let mismatched_slot =
+            sign_oracle_message_with_new_key(mismatched_slot, &SecretKey::random());
+        let response = test_app
+            .post_oracle_message(&mismatched_slot, StatusCode::BAD_REQUEST)
+            .await;
+        assert!(String::from_utf8(response.to_vec())
+            .unwrap()
+            .starts_with("interval_inclusion_messages[3] is for slot"));
+
+        let mut out_of_interval = test_message.clone();
+        out_of_interval.value_message.message.price.value = 1000000000;
+        let out_of_interval =
+            sign_oracle_message_with_new_key(out_of_interval, &SecretKey::random());
+        test_app
+            .post_oracle_message(&out_of_interval, StatusCode::BAD_REQUEST)
+            .await;
+
+        // Nothing of the rejected messages was stored
+        let response = test_app
+            .get_expect("/price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert!(entries.is_empty());
+
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+    }
+
+    async fn post_message_with_extra_field(test_app: &TestApp, expected_code: StatusCode) {
+        let mut message =
+            serde_json::to_value(value_message_from_new_validator(1811093163)).unwrap();
+        message["extra_field"] = serde_json::json!(1);
+        test_app
+            .post_expect(
+                "/post_oracle_message",
+                Body::from(message.to_string()),
+                expected_code,
+            )
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn ignores_unknown_fields_when_lenient(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        post_message_with_extra_field(&test_app, StatusCode::OK).await;
+    }
+
+    #[sqlx::test]
+    async fn rejects_unknown_fields_when_strict(db_pool: DbPool) {
+        let config = Config {
+            reject_unknown_fields: true,
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        post_message_with_extra_field(&test_app, StatusCode::UNPROCESSABLE_ENTITY).await;
+        test_app
+            .post_oracle_message(
+                &value_message_from_new_validator(1811093163),
+                StatusCode::OK,
+            )
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn wraps_list_responses_in_envelope(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        test_app
+            .post_oracle_message(&get_test_message(), StatusCode::OK)
+            .await;
+
+        let now_ms = || {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64
+        };
+        let before_ms = now_ms();
+        let response = test_app
+            .get_expect("/price_value_attestations?envelope=true", StatusCode::OK)
+            .await;
+        let after_ms = now_ms();
+        let envelope: Envelope<PriceValueEntry> = serde_json::from_slice(&response).unwrap();
+        assert!((before_ms..=after_ms).contains(&envelope.server_time_ms));
+        assert_eq!(envelope.slot, TEST_MESSAGE_SLOT);
+        assert_eq!(envelope.items.len(), 1);
+
+        let response = test_app
+            .get_expect(
+                "/aggregate_price_interval_attestations?envelope=true&sort=value_desc",
+                StatusCode::OK,
+            )
+            .await;
+        let envelope: Envelope<AggregatePriceIntervalEntry> =
+            serde_json::from_slice(&response).unwrap();
+        assert_eq!(envelope.items.len(), 724);
+
+        // Without the envelope the items are returned as is
+        let response = test_app
+            .get_expect(
+                "/price_interval_attestations?envelope=false",
+                StatusCode::OK,
+            )
+            .await;
+        let entries: Vec<PriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 724);
+    }
+
+    fn verifies_against_digest(
+        message_digest: &Option<String>,
+        signature: &str,
+        public_key: &str,
+    ) -> bool {
+        let message_digest =
+            Hash256::from_slice(&hex::decode(message_digest.as_ref().unwrap()).unwrap());
+        let signature: Signature = signature.parse().unwrap();
+        let public_key: PublicKey = public_key.parse().unwrap();
+        signature.verify(&public_key, message_digest)
+    }
+
+    #[sqlx::test]
+    async fn includes_message_digests_on_request(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(2);
+        let private_key = SecretKey::random();
+        let message = sign_oracle_message_with_new_key(test_message, &private_key);
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
+
+        let response = test_app
+            .get_expect(
+                "/price_value_attestations?include_digest=true",
+                StatusCode::OK,
+            )
+            .await;
+        let entries: Vec<AnnotatedEntry<PriceValueEntry>> =
+            serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(verifies_against_digest(
+            &entries[0].message_digest,
+            &entries[0].entry.signature,
+            &entries[0].entry.validator_public_key
+        ));
+
+        let response = test_app
+            .get_expect(
+                "/price_interval_attestations?include_digest=true&envelope=true",
+                StatusCode::OK,
+            )
+            .await;
+        let envelope: Envelope<AnnotatedEntry<PriceIntervalEntry>> =
+            serde_json::from_slice(&response).unwrap();
+        assert_eq!(envelope.items.len(), 2);
+        for item in &envelope.items {
+            assert!(verifies_against_digest(
+                &item.message_digest,
+                &item.entry.signature,
+                &item.entry.validator_public_key
+            ));
+        }
+
+        let response = test_app
+            .get_expect(
+                "/aggregate_price_interval_attestations?include_digest=true",
+                StatusCode::OK,
+            )
+            .await;
+        let entries: Vec<AnnotatedEntry<AggregatePriceIntervalEntry>> =
+            serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 2);
+        for entry in &entries {
+            let signature = AggregateSignature::deserialize(
+                &hex::decode(&entry.entry.aggregate_signature).unwrap(),
+            )
+            .unwrap();
+            let message_digest =
+                Hash256::from_slice(&hex::decode(entry.message_digest.as_ref().unwrap()).unwrap());
+            assert!(signature.fast_aggregate_verify(message_digest, &[&private_key.public_key()]));
+        }
+
+        // Left out unless requested
+        let response = test_app
+            .get_expect("/price_value_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<serde_json::Value> = serde_json::from_slice(&response).unwrap();
+        assert!(entries[0].get("message_digest").is_none());
+    }
+
+    #[sqlx::test]
+    async fn includes_slot_timestamps_on_request(db_pool: DbPool) {
+        let config = Config {
+            genesis_time: 1606824023,
+            seconds_per_slot: 12,
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        let private_key = SecretKey::random();
+        for slot_number in [TEST_MESSAGE_SLOT - 1, TEST_MESSAGE_SLOT] {
+            let message = value_message(&private_key, slot_number, 1811093163);
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+        }
+
+        let response = test_app
+            .get_expect("/price_value_attestations?with_time=true", StatusCode::OK)
+            .await;
+        let entries: Vec<AnnotatedEntry<PriceValueEntry>> =
+            serde_json::from_slice(&response).unwrap();
+        let slot_timestamps: Vec<Option<u64>> =
+            entries.iter().map(|entry| entry.slot_timestamp).collect();
+        assert_eq!(slot_timestamps, [Some(1685496251), Some(1685496263)]);
+        assert!(entries[0].message_digest.is_none());
+
+        // Left out unless requested
+        let response = test_app
+            .get_expect("/price_value_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<serde_json::Value> = serde_json::from_slice(&response).unwrap();
+        assert!(entries[0].get("slot_timestamp").is_none());
+    }
+
+    #[sqlx::test]
+    async fn leaves_out_unverified_aggregates_on_request(db_pool: DbPool) {
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(2);
+        let test_app = TestApp::new(db_pool.clone()).await;
+        let private_key = SecretKey::random();
+        let message = sign_oracle_message_with_new_key(test_message.clone(), &private_key);
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+
+        let response = test_app
+            .get_expect(
+                "/aggregate_price_interval_attestations?verified=true",
+                StatusCode::OK,
+            )
+            .await;
+        let entries: Vec<AggregatePriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 2);
+        for entry in &entries {
+            assert_eq!(entry.public_keys.len(), 2);
+            assert!(entry
+                .public_keys
+                .contains(&test_message.validator_public_key.to_string()));
+            assert!(entry
+                .public_keys
+                .contains(&private_key.public_key().to_string()));
+        }
+
+        // A valid signature, but not the aggregate of the contributors
+        let corrupted_value = test_message.interval_inclusion_messages[0].message.value as i64;
+        let signature = message.interval_inclusion_messages[0].signature.to_string();
+        sqlx::query(
+            "
+            UPDATE aggregate_interval_attestations
+            SET
+                aggregate_signature = $1
+            WHERE
+                value = $2;
+            ",
+        )
+        .bind(signature.strip_prefix("0x").unwrap())
+        .bind(corrupted_value)
+        .execute(&db_pool)
+        .await
+        .unwrap();
+
+        let response = test_app
+            .get_expect(
+                "/aggregate_price_interval_attestations?verified=true",
+                StatusCode::OK,
+            )
+            .await;
+        let entries: Vec<AggregatePriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_ne!(entries[0].value, corrupted_value);
+
+        let response = test_app
+            .get_expect("/aggregate_price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<AggregatePriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 2);
+    }
+
+    #[sqlx::test]
+    async fn streams_value_attestations_as_json_lines(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        for slot_number in [TEST_MESSAGE_SLOT - 1, TEST_MESSAGE_SLOT] {
+            for _ in 0..3 {
+                test_app
+                    .post_oracle_message(
+                        &value_message(&SecretKey::random(), slot_number, 1811093163),
+                        StatusCode::OK,
+                    )
+                    .await;
+            }
+        }
+
+        let response = test_app
+            .get_expect(
+                &format!("/attestations/value.ndjson?from_slot={TEST_MESSAGE_SLOT}"),
+                StatusCode::OK,
+            )
+            .await;
+        let lines: Vec<&[u8]> = response
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        for line in lines {
+            let entry: PriceValueEntry = serde_json::from_slice(line).unwrap();
+            assert_eq!(entry.slot_number, TEST_MESSAGE_SLOT as i64);
+        }
+
+        let response = test_app
+            .get_expect("/attestations/value.ndjson", StatusCode::OK)
+            .await;
+        assert_eq!(response.iter().filter(|byte| **byte == b'\n').count(), 6);
+    }
+
+    async fn post_batch(test_app: &TestApp, messages: &[OracleMessage]) -> Vec<BatchMessageResult> {
+        let body = Body::from(serde_json::to_string(messages).unwrap());
+        let response = test_app
+            .post_expect("/oracle_messages/batch", body, StatusCode::OK)
+            .await;
+        serde_json::from_slice(&response).unwrap()
+    }
+
+    #[sqlx::test]
+    async fn ingests_a_batch_of_valid_messages(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let messages: Vec<OracleMessage> = (0..3)
+            .map(|i| value_message_from_new_validator(1811093163 + i))
+            .collect();
+
+        let results = post_batch(&test_app, &messages).await;
+        assert_eq!(results.len(), 3);
+        for (index, result) in results.iter().enumerate() {
+            assert_eq!(result.index, index);
+            assert_eq!(result.status, 200);
+            assert_eq!(result.error, None);
+        }
+        assert_eq!(get_value_slots(&test_app, "").await.len(), 3);
+    }
+
+    #[sqlx::test]
+    async fn reports_failures_per_message_in_a_batch(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let mut messages: Vec<OracleMessage> = (0..3)
+            .map(|i| value_message_from_new_validator(1811093163 + i))
+            .collect();
+        messages[1].value_message.signature =
+            signature_from_random_signer(&messages[1].value_message.message);
+
+        let results = post_batch(&test_app, &messages).await;
+        let statuses: Vec<u16> = results.iter().map(|result| result.status).collect();
+        assert_eq!(statuses, vec![200, 400, 200]);
+        assert!(results[1].error.is_some());
+        // The messages around the rejected one are still committed
+        assert_eq!(get_value_slots(&test_app, "").await.len(), 2);
+    }
+
+    #[sqlx::test]
+    async fn accepts_an_empty_batch(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        assert!(post_batch(&test_app, &[]).await.is_empty());
+    }
+
+    #[sqlx::test]
+    async fn detects_quorum_value_flips(db_pool: DbPool) {
+        let config = Config {
+            quorum_threshold: Some(2),
+            detect_value_flips: true,
+            webhook_url: Some("http://localhost:1".to_string()),
+            ..Config::default()
+        };
+        let state = state_at_slot(db_pool.clone(), config, TEST_MESSAGE_SLOT);
+        let test_message = get_test_message();
+        let message_for_interval = |index: usize| {
+            let mut message = test_message.clone();
+            message.interval_inclusion_messages =
+                vec![test_message.interval_inclusion_messages[index].clone()];
+            sign_oracle_message_with_new_key(message, &SecretKey::random())
+        };
+        // Two validators reach quorum on the first interval, then a second interval catches up
+        // without flipping before overtaking it
+        for index in [0, 0, 1, 1] {
+            ingest_oracle_message(&state, &message_for_interval(index))
+                .await
+                .unwrap();
+        }
+        assert_eq!(state.num_value_flips.load(Ordering::Relaxed), 0);
+        ingest_oracle_message(&state, &message_for_interval(1))
+            .await
+            .unwrap();
+        assert_eq!(state.num_value_flips.load(Ordering::Relaxed), 1);
+
+        let events: Vec<String> =
+            sqlx::query_scalar("SELECT payload FROM pending_notifications ORDER BY id")
+                .fetch_all(&db_pool)
+                .await
+                .unwrap()
+                .iter()
+                .map(|payload: &String| {
+                    serde_json::from_str::<serde_json::Value>(payload).unwrap()["event"]
+                        .as_str()
+                        .unwrap()
+                        .to_string()
+                })
+                .collect();
+        assert_eq!(
+            events,
+            vec!["quorum_reached", "quorum_reached", "value_flipped"]
+        );
+    }
+
+    #[sqlx::test]
+    async fn batch_verifies_submissions(db_pool: DbPool) {
+        let config = Config {
+            batch_verify_submissions: true,
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        let mut messages: Vec<OracleMessage> = (0..3)
+            .map(|i| value_message_from_new_validator(1811093163 + i))
+            .collect();
+        messages.push(get_test_message());
+        assert!(find_invalid_message_signatures(&messages, DigestConfig::default()).is_empty());
+
+        messages[3].interval_inclusion_messages[5].signature =
+            signature_from_random_signer(&messages[3].interval_inclusion_messages[5].message);
+        messages[1].value_message.signature =
+            signature_from_random_signer(&messages[1].value_message.message);
+        assert_eq!(
+            find_invalid_message_signatures(&messages, DigestConfig::default()),
+            vec![1, 3]
+        );
+
+        let results = post_batch(&test_app, &messages).await;
+        let statuses: Vec<u16> = results.iter().map(|result| result.status).collect();
+        assert_eq!(statuses, vec![200, 400, 200, 400]);
+        assert_eq!(get_value_slots(&test_app, "").await.len(), 2);
+
+        // Signatures swapped between messages add up to the same aggregate, but are caught
+        let mut swapped_messages: Vec<OracleMessage> = (0..3)
+            .map(|i| value_message_from_new_validator(1811093163 + i))
+            .collect();
+        let signature = swapped_messages[0].value_message.signature.clone();
+        swapped_messages[0].value_message.signature =
+            swapped_messages[2].value_message.signature.clone();
+        swapped_messages[2].value_message.signature = signature;
+        assert_eq!(
+            find_invalid_message_signatures(&swapped_messages, DigestConfig::default()),
+            vec![0, 2]
+        );
+        let results = post_batch(&test_app, &swapped_messages).await;
+        let statuses: Vec<u16> = results.iter().map(|result| result.status).collect();
+        assert_eq!(statuses, vec![400, 200, 400]);
+    }
+
+    /// Compares verifying the signatures of a batch at once with verifying them one by one, run
+    /// with `cargo test --release -- --ignored --nocapture benchmark_batch_verification`.
+    #[test]
+    #[ignore]
+    fn benchmark_batch_verification() {
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(10);
+        let messages: Vec<OracleMessage> = (0..100)
+            .map(|_| sign_oracle_message_with_new_key(test_message.clone(), &SecretKey::random()))
+            .collect();
+        let digest_config = DigestConfig::default();
+
+        let start = std::time::Instant::now();
+        for message in &messages {
+            assert!(validate_message(
+                &message.validator_public_key,
+                &message.value_message.message,
+                &message.value_message.signature,
+                digest_config
+            ));
+            for interval_message in &message.interval_inclusion_messages {
+                assert!(validate_message(
+                    &message.validator_public_key,
+                    &interval_message.message,
+                    &interval_message.signature,
+                    digest_config
+                ));
+            }
+        }
+        let one_by_one = start.elapsed();
+
+        let start = std::time::Instant::now();
+        assert!(find_invalid_message_signatures(&messages, digest_config).is_empty());
+        let at_once = start.elapsed();
+
+        println!(
+            "Verified {} signatures one by one in {:?} and at once in {:?}",
+            messages.len() * 11,
+            one_by_one,
+            at_once
+        );
     }
 }
@@ -0,0 +1,121 @@
+//! Lifetime attestation counts per asset, kept in their own table so they survive restarts as
+//! well as attestations being deleted.
+
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use std::sync::Arc;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AssetTotals {
+    pub asset: String,
+    pub num_value_attestations: i64,
+    pub num_interval_attestations: i64,
+}
+
+pub async fn increment_asset_totals(
+    connection: &mut PgConnection,
+    asset: &str,
+    num_value_attestations: i64,
+    num_interval_attestations: i64,
+) -> eyre::Result<()> {
+    sqlx::query!(
+        "
+        INSERT INTO asset_totals(
+            asset,
+            num_value_attestations,
+            num_interval_attestations
+        )
+        VALUES (
+            $1,
+            $2,
+            $3
+        )
+        ON CONFLICT (asset) DO UPDATE SET
+            num_value_attestations = asset_totals.num_value_attestations + $2,
+            num_interval_attestations = asset_totals.num_interval_attestations + $3;
+        ",
+        asset,
+        num_value_attestations,
+        num_interval_attestations,
+    )
+    .execute(connection)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_asset_totals(
+    Path(asset): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<AssetTotals>, StatusCode> {
+    let totals = sqlx::query!(
+        "
+        SELECT
+            num_value_attestations,
+            num_interval_attestations
+        FROM
+            asset_totals
+        WHERE
+            asset = $1;
+        ",
+        asset
+    )
+    .fetch_optional(&state.db_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error getting asset totals: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    // Nothing was ever attested for an unknown asset
+    let (num_value_attestations, num_interval_attestations) = totals
+        .map(|row| (row.num_value_attestations, row.num_interval_attestations))
+        .unwrap_or((0, 0));
+    Ok(Json(AssetTotals {
+        asset,
+        num_value_attestations,
+        num_interval_attestations,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::attestations::DEFAULT_ASSET;
+    use crate::db::DbPool;
+    use crate::test_utils::{get_test_message, value_message_from_new_validator, TestApp};
+
+    async fn get_totals(test_app: &TestApp, asset: &str) -> AssetTotals {
+        let response = test_app
+            .get_expect(&format!("/assets/{asset}/totals"), StatusCode::OK)
+            .await;
+        serde_json::from_slice(&response).unwrap()
+    }
+
+    #[sqlx::test]
+    async fn counts_attestations_across_restarts(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool.clone()).await;
+        test_app
+            .post_oracle_message(&get_test_message(), StatusCode::OK)
+            .await;
+        test_app
+            .post_oracle_message(
+                &value_message_from_new_validator(1811093163),
+                StatusCode::OK,
+            )
+            .await;
+
+        let restarted_app = TestApp::new(db_pool).await;
+        let totals = get_totals(&restarted_app, DEFAULT_ASSET).await;
+        assert_eq!(totals.num_value_attestations, 2);
+        assert_eq!(totals.num_interval_attestations, 724);
+
+        let totals = get_totals(&restarted_app, "BTCUSD").await;
+        assert_eq!(totals.num_value_attestations, 0);
+        assert_eq!(totals.num_interval_attestations, 0);
+    }
+}
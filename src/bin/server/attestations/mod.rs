@@ -1,61 +1,355 @@
-use crate::db::DbPool;
+use crate::assets::increment_asset_totals;
+use crate::canonical::{encode_public_key, encode_signature};
+use crate::consensus::interval_message_admits;
+use crate::db::{is_db_unavailable, Backoff, DbPool};
+use crate::deferred::{defer_message, is_deferrable};
+use crate::encoding::{
+    accepts_ssz, ssz_list_response, JsonOrSsz, SszIntervalAttestation, SszValueAttestation,
+};
+use crate::idempotency::{
+    get_idempotency_key, get_stored_response, is_replayable, store_response, StoredResponse,
+};
+use crate::notifications::{enqueue_notification, enqueue_quorum_notification};
+use crate::quarantine::{is_quarantined, record_offence, Offence};
+use crate::quorum::get_quorum_threshold;
+use crate::registry::is_registered;
+use crate::slashing::{record_equivocation, SignedValue};
+use crate::slot_clock::slot_to_timestamp;
 use crate::state::AppState;
 use axum::{
+    body::{boxed, Body, Bytes},
     extract::{Query, State},
+    http::{header::CONTENT_TYPE, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
-use bls::{AggregatePublicKey, AggregateSignature, Hash256, PublicKey, Signature};
+use bls::{
+    verify_signature_sets, AggregatePublicKey, AggregateSignature, Hash256, PublicKey, Signature,
+    SignatureSet,
+};
+use futures_util::TryStreamExt;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
-use sha3::{Digest, Sha3_256};
+use sha3::{Digest, Keccak256, Sha3_256};
+use sqlx::{Connection, PgConnection};
+use ssz::{Decode, DecodeError, Encode};
 use ssz_derive::{Decode, Encode};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tracing::Instrument;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, sqlx::FromRow)]
 pub struct PriceValueEntry {
     pub validator_public_key: String,
+    pub asset: String,
     pub value: i64,
     pub slot_number: i64,
     pub signature: String,
+    /// Hex encoded SSZ bytes the signature's digest was computed over, if stored.
+    pub signing_preimage: Option<String>,
+    /// Unix timestamp (in seconds) at which the validator observed the price, if it signed one.
+    pub price_timestamp: Option<i64>,
+    /// Unix timestamp (in milliseconds) at which the attestation was received.
+    pub received_at: i64,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, sqlx::FromRow)]
 pub struct PriceIntervalEntry {
     pub validator_public_key: String,
+    pub asset: String,
     pub value: i64,
     pub slot_number: i64,
     pub signature: String,
     pub interval_size: i64,
+    /// Value of the aggregate this attestation was added to, see `get_aggregate_value`.
+    pub aggregate_value: i64,
+    /// Hex encoded SSZ bytes the signature's digest was computed over, if stored.
+    pub signing_preimage: Option<String>,
+    /// Unix timestamp (in milliseconds) at which the attestation was received.
+    pub received_at: i64,
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, sqlx::FromRow)]
 pub struct AggregatePriceIntervalEntry {
+    pub asset: String,
     pub value: i64,
     pub slot_number: i64,
     pub aggregate_signature: String,
     pub aggregate_public_key: String,
     pub interval_size: i64,
     pub num_validators: i64,
+    /// Number of distinct operators among the validators, see `Config::validator_operators`.
+    pub num_operators: i64,
+    /// Public keys of the validators whose signatures were aggregated.
+    #[serde(default)]
+    pub public_keys: Vec<String>,
+    /// Whether the aggregate has at least `Config::finality_threshold` validators. Only set by the
+    /// aggregate list endpoints, left out elsewhere.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[sqlx(default)]
+    pub finalized: Option<bool>,
+}
+
+impl AggregatePriceIntervalEntry {
+    pub fn is_final(&self, finality_threshold: i64) -> bool {
+        self.num_validators >= finality_threshold
+    }
 }
 
-#[derive(Clone, Debug, Encode, Decode, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Price {
-    pub value: u64, // TODO: Check if we need to add further info here such as timestamp
+    pub value: u64,
+    /// Unix timestamp (in seconds) at which the price was observed, covered by the signature.
+    /// Optional as messages from before it was added don't have one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+}
+
+/// Asset pair of messages that don't specify one, from before multiple assets were supported.
+pub const DEFAULT_ASSET: &str = "ETHUSD";
+
+pub fn default_asset() -> String {
+    DEFAULT_ASSET.to_string()
+}
+
+/// Interval messages are expected to come with a value message for the same slot, those for a
+/// slot the validator has no value message for are suspect.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrphanIntervalPolicy {
+    /// Store orphan interval messages like any other, logging a warning.
+    #[default]
+    Accept,
+    /// Reject the whole oracle message.
+    Reject,
+}
+
+impl FromStr for OrphanIntervalPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "accept" => Ok(OrphanIntervalPolicy::Accept),
+            "reject" => Ok(OrphanIntervalPolicy::Reject),
+            _ => Err(format!("unknown orphan interval policy {s}")),
+        }
+    }
+}
+
+/// Why an oracle message was rejected, which determines the status code of the response.
+#[derive(Debug)]
+pub enum OracleMessageError {
+    InvalidSignature(String),
+    OutsideWindow(String),
+    Quarantined,
+    /// The validator is not in the registry.
+    Unregistered,
+    /// The validator already submitted this attestation.
+    Duplicate,
+    /// The validator already submitted a different value for the slot.
+    Equivocation(u64),
+    /// The interval messages contradict the value message.
+    Inconsistent(String),
+    /// The validator used up its submission budget.
+    RateLimited,
+    /// The interval size is not one of `Config::allowed_interval_sizes`.
+    DisallowedIntervalSize(u64),
+    /// The message holds more than `Config::max_interval_messages` interval messages.
+    TooManyIntervalMessages(usize),
+    Unprocessable(String),
+    /// The DB is too busy to store the message right now, see `is_db_unavailable`.
+    Unavailable,
+    Internal(eyre::Report),
+}
+
+impl OracleMessageError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            OracleMessageError::InvalidSignature(_)
+            | OracleMessageError::OutsideWindow(_)
+            | OracleMessageError::Inconsistent(_)
+            | OracleMessageError::DisallowedIntervalSize(_) => StatusCode::BAD_REQUEST,
+            OracleMessageError::Quarantined | OracleMessageError::Unregistered => {
+                StatusCode::FORBIDDEN
+            }
+            OracleMessageError::Duplicate | OracleMessageError::Equivocation(_) => {
+                StatusCode::CONFLICT
+            }
+            OracleMessageError::Unprocessable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            OracleMessageError::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            OracleMessageError::TooManyIntervalMessages(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            OracleMessageError::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            OracleMessageError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl std::fmt::Display for OracleMessageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OracleMessageError::InvalidSignature(detail)
+            | OracleMessageError::OutsideWindow(detail)
+            | OracleMessageError::Inconsistent(detail)
+            | OracleMessageError::Unprocessable(detail) => write!(f, "{detail}"),
+            OracleMessageError::Quarantined => write!(f, "validator is quarantined"),
+            OracleMessageError::Unregistered => write!(f, "validator is not registered"),
+            OracleMessageError::Duplicate => write!(f, "attestation was already submitted"),
+            OracleMessageError::RateLimited => write!(f, "validator exceeded its rate limit"),
+            OracleMessageError::DisallowedIntervalSize(interval_size) => {
+                write!(f, "interval size {interval_size} is not allowed")
+            }
+            OracleMessageError::TooManyIntervalMessages(max_interval_messages) => write!(
+                f,
+                "message holds more than {max_interval_messages} interval messages"
+            ),
+            OracleMessageError::Equivocation(slot_number) => write!(
+                f,
+                "value message conflicts with an earlier one for slot {slot_number}"
+            ),
+            OracleMessageError::Unavailable => write!(f, "database is busy, retry later"),
+            OracleMessageError::Internal(_) => write!(f, "internal error"),
+        }
+    }
+}
+
+impl From<eyre::Report> for OracleMessageError {
+    fn from(err: eyre::Report) -> Self {
+        match err.downcast_ref::<sqlx::Error>() {
+            Some(sqlx_err) if is_db_unavailable(sqlx_err) => OracleMessageError::Unavailable,
+            _ => OracleMessageError::Internal(err),
+        }
+    }
+}
+
+impl From<sqlx::Error> for OracleMessageError {
+    fn from(err: sqlx::Error) -> Self {
+        // Concurrent resubmissions can get past the duplicate checks, the primary keys catch them.
+        match &err {
+            sqlx::Error::Database(db_err) if db_err.code().as_deref() == Some(UNIQUE_VIOLATION) => {
+                OracleMessageError::Duplicate
+            }
+            err if is_db_unavailable(err) => OracleMessageError::Unavailable,
+            _ => OracleMessageError::Internal(err.into()),
+        }
+    }
+}
+
+impl IntoResponse for OracleMessageError {
+    fn into_response(self) -> Response {
+        match &self {
+            OracleMessageError::Internal(err) => {
+                tracing::error!("Error ingesting oracle message: {:?}", err)
+            }
+            err => tracing::warn!("Rejecting oracle message: {}", err),
+        }
+        (self.status_code(), self.to_string()).into_response()
+    }
 }
 
+/// Postgres error code of a unique or primary key constraint violation.
+const UNIQUE_VIOLATION: &str = "23505";
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct OracleMessage {
+    /// Asset pair the price is for. Signed as part of the digest unless it is the default one,
+    /// see `DigestConfig::for_asset`.
+    #[serde(default = "default_asset")]
+    pub asset: String,
     pub value_message: SignedPriceValueMessage,
     pub interval_inclusion_messages: Vec<SignedIntervalInclusionMessage>,
     pub validator_public_key: PublicKey,
+    /// Unrecognized top-level fields, rejected when configured to be strict about them.
+    #[serde(flatten, skip_serializing)]
+    pub unknown_fields: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Clone, Debug, Decode, Encode, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PriceValueMessage {
     pub price: Price,
     pub slot_number: u64,
 }
 
+/// SSZ encoding of value messages without a timestamp, the same as before timestamps were added
+/// so that their signatures stay valid.
+#[derive(Debug, Decode, Encode)]
+pub struct UntimedPriceValueMessage {
+    pub value: u64,
+    pub slot_number: u64,
+}
+
+/// Laid out like `IntervalInclusionMessage`, their signatures are kept apart by the domains of
+/// their types, see `DomainType`.
+#[derive(Decode, Encode)]
+struct TimedPriceValueMessage {
+    value: u64,
+    timestamp: u64,
+    slot_number: u64,
+}
+
+/// The encoding signatures are over. Its length depends on whether there is a timestamp, the SSZ
+/// containers of the encoding module embed the fixed length `UntimedPriceValueMessage` instead and
+/// carry the timestamp in a trailing field.
+impl Encode for PriceValueMessage {
+    fn is_ssz_fixed_len() -> bool {
+        false
+    }
+
+    fn ssz_bytes_len(&self) -> usize {
+        match self.price.timestamp {
+            Some(_) => <TimedPriceValueMessage as Encode>::ssz_fixed_len(),
+            None => <UntimedPriceValueMessage as Encode>::ssz_fixed_len(),
+        }
+    }
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        match self.price.timestamp {
+            Some(timestamp) => TimedPriceValueMessage {
+                value: self.price.value,
+                timestamp,
+                slot_number: self.slot_number,
+            }
+            .ssz_append(buf),
+            None => UntimedPriceValueMessage {
+                value: self.price.value,
+                slot_number: self.slot_number,
+            }
+            .ssz_append(buf),
+        }
+    }
+}
+
+impl Decode for PriceValueMessage {
+    fn is_ssz_fixed_len() -> bool {
+        false
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        if bytes.len() == <TimedPriceValueMessage as Decode>::ssz_fixed_len() {
+            let message = TimedPriceValueMessage::from_ssz_bytes(bytes)?;
+            Ok(PriceValueMessage {
+                price: Price {
+                    value: message.value,
+                    timestamp: Some(message.timestamp),
+                },
+                slot_number: message.slot_number,
+            })
+        } else {
+            let message = UntimedPriceValueMessage::from_ssz_bytes(bytes)?;
+            Ok(PriceValueMessage {
+                price: Price {
+                    value: message.value,
+                    timestamp: None,
+                },
+                slot_number: message.slot_number,
+            })
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SignedPriceValueMessage {
     pub message: PriceValueMessage,
@@ -75,33 +369,351 @@ pub struct SignedIntervalInclusionMessage {
     pub signature: Signature,
 }
 
+/// Ordering of the list endpoints.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListSort {
+    #[default]
+    SlotAsc,
+    SlotDesc,
+    ValueAsc,
+    ValueDesc,
+}
+
+impl ListSort {
+    /// The ORDER BY clause of this ordering, ties are broken by the other column.
+    fn order_by(&self) -> &'static str {
+        match self {
+            ListSort::SlotAsc => "slot_number ASC, value ASC",
+            ListSort::SlotDesc => "slot_number DESC, value DESC",
+            ListSort::ValueAsc => "value ASC, slot_number ASC",
+            ListSort::ValueDesc => "value DESC, slot_number DESC",
+        }
+    }
+}
+
+/// Number of items a list endpoint returns unless a limit is given.
+pub const DEFAULT_LIST_LIMIT: i64 = 1000;
+/// Maximum number of items a list endpoint returns, larger limits are capped to it.
+pub const MAX_LIST_LIMIT: i64 = 10_000;
+
+pub fn get_list_limit(limit: Option<i64>) -> i64 {
+    limit.unwrap_or(DEFAULT_LIST_LIMIT).clamp(0, MAX_LIST_LIMIT)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AttestationsQueryParams {
+    /// Only return attestations received after this unix timestamp (in milliseconds).
+    received_after: Option<i64>,
+    /// Only return attestations from this slot on.
+    from_slot: Option<i64>,
+    /// Only return attestations up to and including this slot.
+    to_slot: Option<i64>,
+    /// Defaults to `DEFAULT_LIST_LIMIT`, capped at `MAX_LIST_LIMIT`.
+    limit: Option<i64>,
+    offset: Option<i64>,
+    #[serde(default)]
+    sort: ListSort,
+    #[serde(default)]
+    envelope: bool,
+    /// Add the digest each signature is over to the returned attestations.
+    #[serde(default)]
+    include_digest: bool,
+    /// Add the unix timestamp at which their slot starts to the returned attestations.
+    #[serde(default)]
+    with_time: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AggregatesQueryParams {
+    /// Only return aggregates from this slot on.
+    from_slot: Option<i64>,
+    /// Only return aggregates up to and including this slot.
+    to_slot: Option<i64>,
+    /// Defaults to `DEFAULT_LIST_LIMIT`, capped at `MAX_LIST_LIMIT`.
+    limit: Option<i64>,
+    offset: Option<i64>,
+    #[serde(default)]
+    sort: ListSort,
+    #[serde(default)]
+    envelope: bool,
+    /// Leave out aggregates whose signature doesn't verify against their contributors.
+    #[serde(default)]
+    verified: bool,
+    /// Add the digest each signature is over to the returned aggregates.
+    #[serde(default)]
+    include_digest: bool,
+    /// Add the unix timestamp at which their slot starts to the returned aggregates.
+    #[serde(default)]
+    with_time: bool,
+}
+
+/// Wraps list responses on request, adding the server's time and slot so clients can detect
+/// clock skew and measure latency.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Envelope<T> {
+    /// Unix timestamp (in milliseconds) of the server's clock when responding.
+    pub server_time_ms: u64,
+    pub slot: u64,
+    pub items: Vec<T>,
+}
+
+fn list_response<T: Serialize>(state: &AppState, envelope: bool, items: Vec<T>) -> Response {
+    if !envelope {
+        return Json(items).into_response();
+    }
+    let server_time_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("expect system time to be after the unix epoch")
+        .as_millis() as u64;
+    Json(Envelope {
+        server_time_ms,
+        slot: state.slot_clock.current_slot(),
+        items,
+    })
+    .into_response()
+}
+
+/// A stored attestation whose signature is over the digest of the message it was built from.
+pub trait SignedEntry {
+    fn slot_number(&self) -> i64;
+
+    fn message_digest(&self, digest_config: DigestConfig) -> Hash256;
+}
+
+impl SignedEntry for PriceValueEntry {
+    fn slot_number(&self) -> i64 {
+        self.slot_number
+    }
+
+    fn message_digest(&self, digest_config: DigestConfig) -> Hash256 {
+        let message = PriceValueMessage {
+            price: Price {
+                value: self.value as u64,
+                timestamp: self.price_timestamp.map(|timestamp| timestamp as u64),
+            },
+            slot_number: self.slot_number as u64,
+        };
+        get_message_digest(&message, digest_config.for_asset(&self.asset))
+    }
+}
+
+fn interval_message_digest(
+    value: i64,
+    interval_size: i64,
+    slot_number: i64,
+    digest_config: DigestConfig,
+) -> Hash256 {
+    let message = IntervalInclusionMessage {
+        value: value as u64,
+        interval_size: interval_size as u64,
+        slot_number: slot_number as u64,
+    };
+    get_message_digest(&message, digest_config)
+}
+
+impl SignedEntry for PriceIntervalEntry {
+    fn slot_number(&self) -> i64 {
+        self.slot_number
+    }
+
+    fn message_digest(&self, digest_config: DigestConfig) -> Hash256 {
+        interval_message_digest(
+            self.value,
+            self.interval_size,
+            self.slot_number,
+            digest_config.for_asset(&self.asset),
+        )
+    }
+}
+
+impl SignedEntry for AggregatePriceIntervalEntry {
+    fn slot_number(&self) -> i64 {
+        self.slot_number
+    }
+
+    fn message_digest(&self, digest_config: DigestConfig) -> Hash256 {
+        interval_message_digest(
+            self.value,
+            self.interval_size,
+            self.slot_number,
+            digest_config.for_asset(&self.asset),
+        )
+    }
+}
+
+/// An entry with what can be derived from it added on request.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AnnotatedEntry<T> {
+    #[serde(flatten)]
+    pub entry: T,
+    /// Hex encoded digest the signature is over, so clients can verify the signature without
+    /// reconstructing the message and its encoding themselves.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_digest: Option<String>,
+    /// Unix timestamp (in seconds) at which the slot starts, see `slot_to_timestamp`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slot_timestamp: Option<u64>,
+}
+
+/// Lists the entries, with their digests and slot timestamps if requested.
+fn signed_list_response<T: SignedEntry + Serialize>(
+    state: &AppState,
+    envelope: bool,
+    include_digest: bool,
+    with_time: bool,
+    entries: Vec<T>,
+) -> Response {
+    if !include_digest && !with_time {
+        return list_response(state, envelope, entries);
+    }
+    let digest_config = state.config.digest_config();
+    let genesis_time = state.slot_clock.genesis_time();
+    let seconds_per_slot = state.slot_clock.seconds_per_slot();
+    let entries = entries
+        .into_iter()
+        .map(|entry| AnnotatedEntry {
+            message_digest: include_digest
+                .then(|| hex::encode(entry.message_digest(digest_config))),
+            slot_timestamp: with_time
+                .then(|| u64::try_from(entry.slot_number()).ok())
+                .flatten()
+                .and_then(|slot_number| {
+                    slot_to_timestamp(slot_number, genesis_time, seconds_per_slot)
+                }),
+            entry,
+        })
+        .collect();
+    list_response(state, envelope, entries)
+}
+
 pub async fn get_price_value_attestations(
+    Query(query): Query<AttestationsQueryParams>,
+    headers: HeaderMap,
     State(state): State<Arc<AppState>>,
-) -> Json<Vec<PriceValueEntry>> {
+) -> Result<Response, StatusCode> {
     let db_pool = &state.db_pool;
-    let entries: Vec<PriceValueEntry> = sqlx::query!(
+    let entries: Vec<PriceValueEntry> = sqlx::query_as(&format!(
         "
         SELECT
             validator_public_key,
+            asset,
             value,
             slot_number,
-            signature
+            signature,
+            signing_preimage,
+            price_timestamp,
+            (EXTRACT(EPOCH FROM received_at) * 1000)::BIGINT AS received_at
         FROM
-            price_value_attestations;
-        "
-    )
+            price_value_attestations
+        WHERE
+            ($1::BIGINT IS NULL OR received_at > to_timestamp($1 / 1000.0))
+        AND
+            ($2::BIGINT IS NULL OR slot_number >= $2)
+        AND
+            ($3::BIGINT IS NULL OR slot_number <= $3)
+        ORDER BY
+            {}
+        LIMIT $4
+        OFFSET $5;
+        ",
+        query.sort.order_by()
+    ))
+    .bind(query.received_after)
+    .bind(query.from_slot)
+    .bind(query.to_slot)
+    .bind(get_list_limit(query.limit))
+    .bind(query.offset.unwrap_or(0))
     .fetch_all(db_pool)
     .await
-    .unwrap()
-    .into_iter()
-    .map(|row| PriceValueEntry {
-        validator_public_key: row.validator_public_key,
-        value: row.value,
-        slot_number: row.slot_number,
-        signature: row.signature,
-    })
-    .collect();
-    Json(entries)
+    .map_err(|e| {
+        tracing::error!("Error getting value attestations: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if accepts_ssz(&headers) {
+        return ssz_list_response::<_, SszValueAttestation>(&entries).map_err(|e| {
+            tracing::error!("Error encoding attestations: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        });
+    }
+    Ok(signed_list_response(
+        &state,
+        query.envelope,
+        query.include_digest,
+        query.with_time,
+        entries,
+    ))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AttestationStreamQuery {
+    pub from_slot: Option<i64>,
+    pub to_slot: Option<i64>,
+}
+
+/// Streams all value attestations within the (inclusive) slot range as JSON lines, for ingestion
+/// pipelines, reading them from a database cursor so the full result set is never held in memory.
+pub async fn get_price_value_attestations_ndjson(
+    Query(query): Query<AttestationStreamQuery>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        let mut rows = sqlx::query!(
+            r#"
+            SELECT
+                validator_public_key,
+                asset,
+                value,
+                slot_number,
+                signature,
+                signing_preimage,
+                price_timestamp,
+                (EXTRACT(EPOCH FROM received_at) * 1000)::BIGINT AS "received_at!"
+            FROM
+                price_value_attestations
+            WHERE
+                ($1::BIGINT IS NULL OR slot_number >= $1)
+            AND
+                ($2::BIGINT IS NULL OR slot_number <= $2)
+            ORDER BY
+                slot_number,
+                validator_public_key;
+            "#,
+            query.from_slot,
+            query.to_slot,
+        )
+        .fetch(&state.db_pool);
+
+        loop {
+            let row = match rows.try_next().await {
+                Ok(Some(row)) => row,
+                Ok(None) => break,
+                Err(e) => {
+                    tracing::error!("Error streaming value attestations: {:?}", e);
+                    sender.abort();
+                    break;
+                }
+            };
+            let entry = PriceValueEntry {
+                validator_public_key: row.validator_public_key,
+                asset: row.asset,
+                value: row.value,
+                slot_number: row.slot_number,
+                signature: row.signature,
+                signing_preimage: row.signing_preimage,
+                price_timestamp: row.price_timestamp,
+                received_at: row.received_at,
+            };
+            let mut line = serde_json::to_vec(&entry).expect("expect attestation to serialize");
+            line.push(b'\n');
+            if sender.send_data(Bytes::from(line)).await.is_err() {
+                // The client went away, no point in reading further.
+                break;
+            }
+        }
+    });
+    ([(CONTENT_TYPE, "application/x-ndjson")], boxed(body))
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -116,38 +728,40 @@ pub async fn get_price_aggregate(
 ) -> Result<Json<AggregatePriceIntervalEntry>, axum::http::StatusCode> {
     let db_pool = &state.db_pool;
     tracing::debug!("Query: {:?}", query);
-    // TODO: Improve error / none handling - remove unwrap
-    let slot_number = query.slot_number.unwrap_or(
-        get_slot_number(db_pool)
+    let slot_number = match query.slot_number {
+        Some(slot_number) => slot_number,
+        None => get_slot_number(db_pool)
             .await
             .map_err(|e| {
                 tracing::error!("Error getting slot number: {:?}", e);
                 axum::http::StatusCode::INTERNAL_SERVER_ERROR
             })?
-            .unwrap(),
-    );
+            .ok_or(axum::http::StatusCode::NOT_FOUND)?,
+    };
 
-    let interval_size = query.interval_size.unwrap_or(
-        get_most_common_interval_size(db_pool, slot_number)
+    let interval_size = match query.interval_size {
+        Some(interval_size) => interval_size,
+        None => get_most_common_interval_size(db_pool, slot_number)
             .await
             .map_err(|e| {
                 tracing::error!("Error getting most common interval size: {:?}", e);
                 axum::http::StatusCode::INTERNAL_SERVER_ERROR
             })?
-            .unwrap(),
-    );
+            .ok_or(axum::http::StatusCode::NOT_FOUND)?,
+    };
 
     let aggregate_price = get_price_aggregate_for_params(db_pool, slot_number, interval_size)
         .await
         .map_err(|e| {
             tracing::error!("Error getting aggregate price: {:?}", e);
             axum::http::StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        })?
+        .ok_or(axum::http::StatusCode::NOT_FOUND)?;
 
     Ok(Json(aggregate_price))
 }
 
-async fn get_most_common_interval_size(
+pub async fn get_most_common_interval_size(
     db_pool: &DbPool,
     slot_number: i64,
 ) -> eyre::Result<Option<i64>> {
@@ -167,10 +781,10 @@ async fn get_most_common_interval_size(
         ",
         slot_number
     )
-    .fetch_one(db_pool)
+    .fetch_optional(db_pool)
     .await?
-    .interval_size;
-    Ok(Some(interval_size))
+    .map(|row| row.interval_size);
+    Ok(interval_size)
 }
 
 async fn get_slot_number(db_pool: &DbPool) -> eyre::Result<Option<i64>> {
@@ -185,26 +799,29 @@ async fn get_slot_number(db_pool: &DbPool) -> eyre::Result<Option<i64>> {
         LIMIT 1;
         "
     )
-    .fetch_one(db_pool)
+    .fetch_optional(db_pool)
     .await?
-    .slot_number;
-    Ok(Some(slot_number))
+    .map(|row| row.slot_number);
+    Ok(slot_number)
 }
 
-async fn get_price_aggregate_for_params(
+pub async fn get_price_aggregate_for_params(
     db_pool: &DbPool,
     slot_number: i64,
     interval_size: i64,
-) -> eyre::Result<AggregatePriceIntervalEntry> {
+) -> eyre::Result<Option<AggregatePriceIntervalEntry>> {
     let entries: Vec<AggregatePriceIntervalEntry> = sqlx::query!(
         "
         SELECT
+            asset,
             value,
             slot_number,
             aggregate_signature,
             aggregate_public_key,
+            public_keys,
             interval_size,
-            num_validators
+            num_validators,
+            num_operators
         FROM
             aggregate_interval_attestations 
         WHERE
@@ -216,23 +833,25 @@ async fn get_price_aggregate_for_params(
         interval_size
     )
     .fetch_all(db_pool)
-    .await
-    .unwrap()
+    .await?
     .into_iter()
     .map(|row| AggregatePriceIntervalEntry {
+        asset: row.asset,
         value: row.value,
         slot_number: row.slot_number,
         aggregate_signature: row.aggregate_signature,
         aggregate_public_key: row.aggregate_public_key,
+        public_keys: row.public_keys,
         interval_size: row.interval_size,
         num_validators: row.num_validators,
+        num_operators: row.num_operators,
+        finalized: None,
     })
     .collect();
-    let max_num_validators = entries
-        .iter()
-        .map(|entry| entry.num_validators)
-        .max()
-        .ok_or(eyre::eyre!("No entries found"))?;
+    let max_num_validators = match entries.iter().map(|entry| entry.num_validators).max() {
+        Some(max_num_validators) => max_num_validators,
+        None => return Ok(None),
+    };
     let entries_with_max_num_validators_ordered_by_value: Vec<AggregatePriceIntervalEntry> =
         entries
             .into_iter()
@@ -242,250 +861,1493 @@ async fn get_price_aggregate_for_params(
 
     let median_index = entries_with_max_num_validators_ordered_by_value.len() / 2;
     let median_entry = entries_with_max_num_validators_ordered_by_value[median_index].clone();
-    Ok(median_entry)
+    Ok(Some(median_entry))
 }
 
 pub async fn get_aggregate_price_interval_attestations(
+    Query(query): Query<AggregatesQueryParams>,
     State(state): State<Arc<AppState>>,
-) -> Json<Vec<AggregatePriceIntervalEntry>> {
+) -> Result<Response, StatusCode> {
     let db_pool = &state.db_pool;
-    let entries: Vec<AggregatePriceIntervalEntry> = sqlx::query!(
+    let entries: Vec<AggregatePriceIntervalEntry> = sqlx::query_as(&format!(
         "
         SELECT
+            asset,
             value,
             slot_number,
             aggregate_signature,
             aggregate_public_key,
+            public_keys,
             interval_size,
-            num_validators
+            num_validators,
+            num_operators
         FROM
-            aggregate_interval_attestations 
-        "
-    )
+            aggregate_interval_attestations
+        WHERE
+            ($1::BIGINT IS NULL OR slot_number >= $1)
+        AND
+            ($2::BIGINT IS NULL OR slot_number <= $2)
+        ORDER BY
+            {}
+        LIMIT $3
+        OFFSET $4;
+        ",
+        query.sort.order_by()
+    ))
+    .bind(query.from_slot)
+    .bind(query.to_slot)
+    .bind(get_list_limit(query.limit))
+    .bind(query.offset.unwrap_or(0))
     .fetch_all(db_pool)
     .await
-    .unwrap()
-    .into_iter()
-    .map(|row| AggregatePriceIntervalEntry {
-        value: row.value,
-        slot_number: row.slot_number,
-        aggregate_signature: row.aggregate_signature,
-        aggregate_public_key: row.aggregate_public_key,
-        interval_size: row.interval_size,
-        num_validators: row.num_validators,
-    })
-    .collect();
-    Json(entries)
+    .map_err(|e| {
+        tracing::error!("Error getting aggregates: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let entries = if query.verified {
+        entries
+            .into_iter()
+            .filter(|entry| {
+                let is_valid = verify_aggregate_entry(entry, state.config.digest_config());
+                if !is_valid {
+                    tracing::warn!(
+                        "Leaving out aggregate of value {} in slot {} that doesn't verify",
+                        entry.value,
+                        entry.slot_number
+                    );
+                }
+                is_valid
+            })
+            .collect()
+    } else {
+        entries
+    };
+    let entries = entries
+        .into_iter()
+        .map(|entry| AggregatePriceIntervalEntry {
+            finalized: Some(entry.is_final(state.config.finality_threshold)),
+            ..entry
+        })
+        .collect();
+    Ok(signed_list_response(
+        &state,
+        query.envelope,
+        query.include_digest,
+        query.with_time,
+        entries,
+    ))
+}
+
+/// Whether the aggregate signature verifies against the public keys of its contributors, who
+/// should all have signed the interval message of the aggregate. Banded aggregates don't verify,
+/// see `get_aggregate_value`.
+pub fn verify_aggregate_entry(
+    entry: &AggregatePriceIntervalEntry,
+    digest_config: DigestConfig,
+) -> bool {
+    let aggregate_signature = match hex::decode(&entry.aggregate_signature)
+        .ok()
+        .and_then(|bytes| AggregateSignature::deserialize(&bytes).ok())
+    {
+        Some(aggregate_signature) => aggregate_signature,
+        None => return false,
+    };
+    let public_keys: Option<Vec<PublicKey>> = entry
+        .public_keys
+        .iter()
+        .map(|public_key| public_key.parse().ok())
+        .collect();
+    let public_keys = match public_keys {
+        Some(public_keys) if public_keys.len() as i64 == entry.num_validators => public_keys,
+        _ => return false,
+    };
+    aggregate_signature.fast_aggregate_verify(
+        entry.message_digest(digest_config),
+        &public_keys.iter().collect::<Vec<_>>(),
+    )
 }
 
 pub async fn get_price_interval_attestations(
+    Query(query): Query<AttestationsQueryParams>,
+    headers: HeaderMap,
     State(state): State<Arc<AppState>>,
-) -> Json<Vec<PriceIntervalEntry>> {
+) -> Result<Response, StatusCode> {
     let db_pool = &state.db_pool;
-    let entries: Vec<PriceIntervalEntry> = sqlx::query!(
+    let entries: Vec<PriceIntervalEntry> = sqlx::query_as(&format!(
         "
         SELECT
             validator_public_key,
+            asset,
             value,
             slot_number,
             signature,
-            interval_size
+            interval_size,
+            aggregate_value,
+            signing_preimage,
+            (EXTRACT(EPOCH FROM received_at) * 1000)::BIGINT AS received_at
         FROM
-            price_interval_attestations;
-        "
-    )
+            price_interval_attestations
+        WHERE
+            ($1::BIGINT IS NULL OR received_at > to_timestamp($1 / 1000.0))
+        AND
+            ($2::BIGINT IS NULL OR slot_number >= $2)
+        AND
+            ($3::BIGINT IS NULL OR slot_number <= $3)
+        ORDER BY
+            {}
+        LIMIT $4
+        OFFSET $5;
+        ",
+        query.sort.order_by()
+    ))
+    .bind(query.received_after)
+    .bind(query.from_slot)
+    .bind(query.to_slot)
+    .bind(get_list_limit(query.limit))
+    .bind(query.offset.unwrap_or(0))
     .fetch_all(db_pool)
     .await
-    .unwrap()
-    .into_iter()
-    .map(|row| PriceIntervalEntry {
-        validator_public_key: row.validator_public_key,
-        value: row.value,
-        slot_number: row.slot_number,
-        signature: row.signature,
-        interval_size: row.interval_size,
-    })
-    .collect();
-    Json(entries)
+    .map_err(|e| {
+        tracing::error!("Error getting interval attestations: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if accepts_ssz(&headers) {
+        return ssz_list_response::<_, SszIntervalAttestation>(&entries).map_err(|e| {
+            tracing::error!("Error encoding attestations: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        });
+    }
+    Ok(signed_list_response(
+        &state,
+        query.envelope,
+        query.include_digest,
+        query.with_time,
+        entries,
+    ))
 }
 
 pub async fn post_oracle_message(
     State(state): State<Arc<AppState>>,
-    Json(message): Json<OracleMessage>,
-) -> Result<(), axum::http::StatusCode> {
-    tracing::info!("Received oracle message");
-    let db_pool = &state.db_pool;
-    let validator_public_key = message.validator_public_key;
-    // TODO: Improve error handling instead of returning "BAD REQUEST" for any kind of error
-    save_price_value_attestation(db_pool, &message.value_message, &validator_public_key)
-        .await
-        .map_err(|err| {
-            tracing::error!("Error saving price value attestation: {:?}", err);
-            axum::http::StatusCode::BAD_REQUEST
-        })?;
-    save_price_interval_attestations(
-        db_pool,
-        &message.interval_inclusion_messages,
-        &validator_public_key,
-    )
+    headers: HeaderMap,
+    JsonOrSsz(message): JsonOrSsz<OracleMessage>,
+) -> Response {
+    async {
+        tracing::info!("Received oracle message");
+        match get_idempotency_key(&headers) {
+            Some(idempotency_key) => submit_idempotently(&state, &message, idempotency_key).await,
+            None => submit_oracle_message(&state, &message, false)
+                .await
+                .into_response(),
+        }
+    }
+    .instrument(submission_span(&message))
     .await
-    .map_err(|err| {
-        tracing::error!("Error saving price interval attestation: {:?}", err);
-        axum::http::StatusCode::BAD_REQUEST
-    })?;
-    Ok(())
 }
 
-async fn save_price_value_attestation(
-    db_pool: &DbPool,
-    message: &SignedPriceValueMessage,
-    validator_public_key: &PublicKey,
-) -> eyre::Result<()> {
-    if !validate_message(validator_public_key, &message.message, &message.signature) {
-        return Err(eyre::eyre!("Invalid signature"));
-    }
-    let value = message.message.price.value as i64;
-    let slot_number = message.message.slot_number as i64;
-    let signature = &message.signature.to_string();
-    let pk_string = validator_public_key.to_string();
+/// Length of the prefix of public keys identifying validators in logs, the full key being noise.
+const LOGGED_PUBLIC_KEY_LENGTH: usize = 18;
 
-    // Save price_value_message in DB
-    sqlx::query!(
-        "
-        INSERT INTO price_value_attestations(
-            validator_public_key,
-            value,
-            slot_number,
-            signature
-        )
-        VALUES (
-            $1,
-            $2,
-            $3,
-            $4
-        );
-        ",
-        pk_string,
-        value,
-        slot_number,
-        signature,
+/// A span carrying the validator and slot of a submission, so its log lines can be told apart
+/// without logging the message itself.
+fn submission_span(message: &OracleMessage) -> tracing::Span {
+    let public_key = message.validator_public_key.to_string();
+    tracing::info_span!(
+        "submission",
+        validator = &public_key[..LOGGED_PUBLIC_KEY_LENGTH.min(public_key.len())],
+        slot = message.value_message.message.slot_number,
     )
-    .execute(db_pool)
-    .await?;
-    Ok(())
 }
 
-async fn save_price_interval_attestations(
-    db_pool: &DbPool,
-    messages: &Vec<SignedIntervalInclusionMessage>,
-    validator_public_key: &PublicKey,
-) -> eyre::Result<()> {
-    for message in messages {
-        save_price_interval_attestation(db_pool, message, validator_public_key).await?;
-    }
-    Ok(())
+/// Hex encoded sha3 hash of the JSON encoding of the message, telling retries of a submission
+/// apart from other messages sent with the same idempotency key.
+fn get_body_digest(message: &OracleMessage) -> String {
+    let bytes = serde_json::to_vec(message).expect("expect oracle message to serialize");
+    hex::encode(HashFunction::Sha3_256.hash(&[&bytes]).as_bytes())
 }
 
-async fn save_price_interval_attestation(
-    db_pool: &DbPool,
-    message: &SignedIntervalInclusionMessage,
-    validator_public_key: &PublicKey,
-) -> eyre::Result<()> {
-    if !validate_message(validator_public_key, &message.message, &message.signature) {
-        return Err(eyre::eyre!("Invalid signature"));
+/// Submits the message unless the validator already submitted one with the key, returning the
+/// response of the first submission then. Reusing the key for another message is rejected.
+async fn submit_idempotently(
+    state: &AppState,
+    message: &OracleMessage,
+    idempotency_key: &str,
+) -> Response {
+    let db_pool = &state.db_pool;
+    let pk_string = encode_public_key(&message.validator_public_key);
+    let ttl_seconds = state.config.idempotency_key_ttl_seconds;
+    let body_digest = get_body_digest(message);
+    match get_stored_response(db_pool, &pk_string, idempotency_key, ttl_seconds).await {
+        Ok(Some(stored_response)) if stored_response.body_digest != body_digest => {
+            tracing::warn!(
+                "Rejecting reuse of idempotency key {} for another message",
+                idempotency_key
+            );
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "idempotency key was already used for another message",
+            )
+                .into_response();
+        }
+        Ok(Some(stored_response)) => {
+            tracing::info!("Replaying response to idempotency key {}", idempotency_key);
+            return (stored_response.status_code, stored_response.body).into_response();
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::error!("Error getting stored response: {:?}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
     }
-    let value = message.message.value as i64;
-    let interval_size = message.message.interval_size as i64;
-    let slot_number = message.message.slot_number as i64;
-    let signature = &message.signature.to_string();
-    let pk_string = validator_public_key.to_string();
 
-    // Save price_value_message in DB
-    sqlx::query!(
-        "
-        INSERT INTO price_interval_attestations(
-            validator_public_key,
-            value,
-            interval_size,
+    let result = submit_oracle_message(state, message, false).await;
+    let response = match &result {
+        Ok(status_code) => StoredResponse {
+            status_code: *status_code,
+            body: String::new(),
+            body_digest,
+        },
+        Err(err) => StoredResponse {
+            status_code: err.status_code(),
+            body: err.to_string(),
+            body_digest,
+        },
+    };
+    // Rejections of messages that aren't known to be from the validator are left unstored, so
+    // they can't claim its keys.
+    let is_authenticated = !matches!(
+        result,
+        Err(OracleMessageError::InvalidSignature(_)
+            | OracleMessageError::Unregistered
+            | OracleMessageError::Unprocessable(_))
+    );
+    if is_authenticated && is_replayable(response.status_code) {
+        if let Err(e) =
+            store_response(db_pool, &pk_string, idempotency_key, ttl_seconds, &response).await
+        {
+            // The message is stored either way, a retry then gets a conflict instead.
+            tracing::error!("Error storing response to idempotency key: {:?}", e);
+        }
+    }
+    result.into_response()
+}
+
+/// Most messages a single batch may hold.
+pub const MAX_BATCH_SIZE: usize = 1000;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchMessageResult {
+    /// Position of the message in the batch.
+    pub index: usize,
+    /// The status the message would have gotten if submitted on its own.
+    pub status: u16,
+    pub error: Option<String>,
+}
+
+/// Submits each message of the batch as if it were posted on its own, in order, and reports the
+/// outcome per message. The batch is stored in a single transaction, each message in a savepoint
+/// of its own: a message failing on the DB is rolled back to its savepoint, without rolling back
+/// the messages around it, so a client only needs to resubmit the ones that failed. Each aggregate
+/// the batch extends stays locked until the whole batch is committed, large batches over many
+/// slots may need Postgres' `max_locks_per_transaction` raised for that.
+pub async fn post_oracle_messages_batch(
+    State(state): State<Arc<AppState>>,
+    Json(messages): Json<Vec<OracleMessage>>,
+) -> Result<Json<Vec<BatchMessageResult>>, (StatusCode, String)> {
+    tracing::info!("Received batch of {} oracle messages", messages.len());
+    if messages.len() > MAX_BATCH_SIZE {
+        return Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("batch holds more than {MAX_BATCH_SIZE} messages"),
+        ));
+    }
+    let invalid_indices = if state.config.batch_verify_submissions {
+        find_invalid_message_signatures(&messages, state.config.digest_config())
+    } else {
+        Vec::new()
+    };
+    let mut transaction = state.db_pool.begin().await.map_err(|e| {
+        tracing::error!("Error starting batch transaction: {:?}", e);
+        let err = OracleMessageError::from(e);
+        (err.status_code(), err.to_string())
+    })?;
+    let mut value_entries = Vec::new();
+    let mut results = Vec::with_capacity(messages.len());
+    for (index, message) in messages.iter().enumerate() {
+        state.metrics.messages_received.inc();
+        let submission = if invalid_indices.contains(&index) {
+            Err(OracleMessageError::InvalidSignature(
+                "invalid signature of value or interval messages".to_string(),
+            ))
+        } else {
+            match check_oracle_message(&state, message) {
+                Ok(()) => {
+                    defer_or_ingest_oracle_message(
+                        &state,
+                        &mut transaction,
+                        message,
+                        state.config.batch_verify_submissions,
+                        &mut value_entries,
+                    )
+                    .instrument(submission_span(message))
+                    .await
+                }
+                Err(err) => Err(err),
+            }
+        };
+        count_failed_submission(&state, &submission);
+        let result = match submission {
+            Ok(status) => BatchMessageResult {
+                index,
+                status: status.as_u16(),
+                error: None,
+            },
+            Err(err) => {
+                submission_span(message).in_scope(|| match &err {
+                    OracleMessageError::Internal(err) => {
+                        tracing::error!("Error ingesting oracle message {}: {:?}", index, err)
+                    }
+                    err => tracing::warn!("Rejecting oracle message {}: {}", index, err),
+                });
+                BatchMessageResult {
+                    index,
+                    status: err.status_code().as_u16(),
+                    error: Some(err.to_string()),
+                }
+            }
+        };
+        results.push(result);
+    }
+    transaction.commit().await.map_err(|e| {
+        tracing::error!("Error committing batch: {:?}", e);
+        let err = OracleMessageError::from(e);
+        (err.status_code(), err.to_string())
+    })?;
+    publish_value_entries(&state, value_entries);
+    Ok(Json(results))
+}
+
+/// Checks, then defers or ingests a single submitted message.
+async fn submit_oracle_message(
+    state: &AppState,
+    message: &OracleMessage,
+    signatures_verified: bool,
+) -> Result<StatusCode, OracleMessageError> {
+    state.metrics.messages_received.inc();
+    let result = async {
+        check_oracle_message(state, message)?;
+        let mut connection = state.db_pool.acquire().await?;
+        let mut value_entries = Vec::new();
+        let result = defer_or_ingest_oracle_message(
+            state,
+            &mut connection,
+            message,
+            signatures_verified,
+            &mut value_entries,
+        )
+        .await;
+        publish_value_entries(state, value_entries);
+        result
+    }
+    .await;
+    count_failed_submission(state, &result);
+    result
+}
+
+fn count_failed_submission(state: &AppState, result: &Result<StatusCode, OracleMessageError>) {
+    match result {
+        Err(OracleMessageError::InvalidSignature(_)) => state.metrics.signature_failures.inc(),
+        Err(OracleMessageError::Internal(_) | OracleMessageError::Unavailable) => {
+            state.metrics.db_errors.inc()
+        }
+        _ => {}
+    }
+}
+
+/// The checks of a submitted message that don't need the DB.
+fn check_oracle_message(
+    state: &AppState,
+    message: &OracleMessage,
+) -> Result<(), OracleMessageError> {
+    if let Some(rate_limiter) = &state.rate_limiter {
+        let is_within_budget = rate_limiter
+            .lock()
+            .unwrap()
+            .try_acquire(message.validator_public_key.clone(), Instant::now());
+        if !is_within_budget {
+            tracing::warn!("Rejecting message from rate limited validator");
+            return Err(OracleMessageError::RateLimited);
+        }
+    }
+    if let Some(max_interval_messages) = state.config.max_interval_messages {
+        if message.interval_inclusion_messages.len() > max_interval_messages {
+            return Err(OracleMessageError::TooManyIntervalMessages(
+                max_interval_messages,
+            ));
+        }
+    }
+    if state.config.reject_unknown_fields && !message.unknown_fields.is_empty() {
+        let unknown_fields = message.unknown_fields.keys().collect::<Vec<_>>();
+        tracing::warn!(
+            "Rejecting message with unknown fields: {:?}",
+            unknown_fields
+        );
+        return Err(OracleMessageError::Unprocessable(format!(
+            "unknown fields {unknown_fields:?}"
+        )));
+    }
+    Ok(())
+}
+
+async fn defer_or_ingest_oracle_message(
+    state: &AppState,
+    connection: &mut PgConnection,
+    message: &OracleMessage,
+    signatures_verified: bool,
+    value_entries: &mut Vec<(PublicKey, PriceValueEntry)>,
+) -> Result<StatusCode, OracleMessageError> {
+    if is_deferrable(state, message) {
+        // Deferred messages are only checked again once their slot is in the window, so
+        // messages that would be rejected anyway aren't kept until then.
+        check_validator(state, connection, &message.validator_public_key).await?;
+        let mut transaction = connection.begin().await?;
+        if !defer_message(&mut transaction, message).await? {
+            tracing::warn!("Rejecting message already deferred for the slot");
+            return Err(OracleMessageError::Duplicate);
+        }
+        transaction.commit().await?;
+        tracing::info!(
+            "Deferred message for slot {}",
+            message.value_message.message.slot_number
+        );
+        return Ok(StatusCode::ACCEPTED);
+    }
+    ingest_in_transaction(
+        state,
+        connection,
+        message,
+        signatures_verified,
+        MessageOrigin::Submitted,
+        value_entries,
+    )
+    .await?;
+    Ok(StatusCode::OK)
+}
+
+/// Describes the first interval message that is for another slot than the value message, or
+/// whose interval doesn't contain the attested value.
+fn find_inconsistent_interval_message(message: &OracleMessage) -> Option<String> {
+    let value_message = &message.value_message.message;
+    message
+        .interval_inclusion_messages
+        .iter()
+        .enumerate()
+        .find_map(|(index, interval_message)| {
+            let interval_message = &interval_message.message;
+            if interval_message.slot_number != value_message.slot_number {
+                Some(format!(
+                    "interval_inclusion_messages[{index}] is for slot {}, the value message for slot {}",
+                    interval_message.slot_number, value_message.slot_number
+                ))
+            } else if !interval_message_admits(
+                interval_message.value as i64,
+                interval_message.interval_size as i64,
+                value_message.price.value as i64,
+            ) {
+                Some(format!(
+                    "interval_inclusion_messages[{index}] doesn't contain the value {}",
+                    value_message.price.value
+                ))
+            } else {
+                None
+            }
+        })
+}
+
+/// Whether a message is submitted by its validator or imported from earlier collected ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MessageOrigin {
+    Submitted,
+    /// Collected earlier, possibly by another node, so the slot windows don't apply.
+    Imported,
+}
+
+/// Validates and stores the attestations of an oracle message.
+pub async fn ingest_oracle_message(
+    state: &AppState,
+    message: &OracleMessage,
+) -> Result<(), OracleMessageError> {
+    ingest_on_new_connection(state, message, false, MessageOrigin::Submitted).await
+}
+
+/// Stores an oracle message collected earlier, however far its slot is from the current one.
+/// Every signature is verified before anything is stored, so an invalid message is rejected as a
+/// whole.
+pub async fn import_oracle_message(
+    state: &AppState,
+    message: &OracleMessage,
+) -> Result<(), OracleMessageError> {
+    let digest_config = state.config.digest_config().for_asset(&message.asset);
+    let validator_public_key = &message.validator_public_key;
+    let is_valid = validate_message(
+        validator_public_key,
+        &message.value_message.message,
+        &message.value_message.signature,
+        digest_config,
+    ) && message
+        .interval_inclusion_messages
+        .iter()
+        .all(|interval_message| {
+            validate_message(
+                validator_public_key,
+                &interval_message.message,
+                &interval_message.signature,
+                digest_config,
+            )
+        });
+    if !is_valid {
+        return Err(OracleMessageError::InvalidSignature(
+            "invalid signature of value or interval messages".to_string(),
+        ));
+    }
+    ingest_on_new_connection(state, message, true, MessageOrigin::Imported).await
+}
+
+/// Ingests the message on a connection of its own, see `ingest_in_transaction`.
+async fn ingest_on_new_connection(
+    state: &AppState,
+    message: &OracleMessage,
+    signatures_verified: bool,
+    origin: MessageOrigin,
+) -> Result<(), OracleMessageError> {
+    let mut connection = state.db_pool.acquire().await?;
+    let mut value_entries = Vec::new();
+    let result = ingest_in_transaction(
+        state,
+        &mut connection,
+        message,
+        signatures_verified,
+        origin,
+        &mut value_entries,
+    )
+    .await;
+    publish_value_entries(state, value_entries);
+    result
+}
+
+/// Ingests the message in a transaction of its own, a savepoint if the connection is in a
+/// transaction already. A message failing on the DB is rolled back, and tried again while the DB
+/// is busy. Other rejections keep what was stored before them, e.g. the offences recorded against
+/// the validator. Stored value attestations are added to `value_entries`, to be published once
+/// the outermost transaction is committed.
+async fn ingest_in_transaction(
+    state: &AppState,
+    connection: &mut PgConnection,
+    message: &OracleMessage,
+    signatures_verified: bool,
+    origin: MessageOrigin,
+    value_entries: &mut Vec<(PublicKey, PriceValueEntry)>,
+) -> Result<(), OracleMessageError> {
+    let mut backoff = Backoff::new(&state.config);
+    loop {
+        let mut transaction = connection.begin().await?;
+        let num_value_entries = value_entries.len();
+        let result = ingest_verified_oracle_message(
+            state,
+            &mut transaction,
+            message,
+            signatures_verified,
+            origin,
+            value_entries,
+        )
+        .await;
+        match &result {
+            Err(OracleMessageError::Internal(_) | OracleMessageError::Unavailable) => {
+                transaction.rollback().await?;
+                value_entries.truncate(num_value_entries);
+                // Within the transaction the DB can only be unavailable as it is busy.
+                if matches!(result, Err(OracleMessageError::Unavailable)) && backoff.wait().await {
+                    continue;
+                }
+            }
+            _ => transaction.commit().await?,
+        }
+        return result;
+    }
+}
+
+/// Announces committed value attestations to subscribers and the latest value cache.
+fn publish_value_entries(state: &AppState, value_entries: Vec<(PublicKey, PriceValueEntry)>) {
+    for (validator_public_key, entry) in value_entries {
+        // Fails only when nobody is subscribed.
+        let _ = state.value_attestations.send(entry.clone());
+        cache_latest_value(state, &validator_public_key, entry);
+    }
+}
+
+/// Rejects messages from validators that are not registered or quarantined.
+async fn check_validator(
+    state: &AppState,
+    connection: &mut PgConnection,
+    validator_public_key: &PublicKey,
+) -> Result<(), OracleMessageError> {
+    if !is_registered(state, validator_public_key) {
+        tracing::warn!("Rejecting message from unregistered validator");
+        return Err(OracleMessageError::Unregistered);
+    }
+    if is_quarantined(connection, validator_public_key).await? {
+        tracing::warn!("Rejecting message from quarantined validator");
+        return Err(OracleMessageError::Quarantined);
+    }
+    Ok(())
+}
+
+/// Like `ingest_oracle_message`, skipping the signature checks if they were already done.
+async fn ingest_verified_oracle_message(
+    state: &AppState,
+    connection: &mut PgConnection,
+    message: &OracleMessage,
+    signatures_verified: bool,
+    origin: MessageOrigin,
+    value_entries: &mut Vec<(PublicKey, PriceValueEntry)>,
+) -> Result<(), OracleMessageError> {
+    let validator_public_key = &message.validator_public_key;
+    check_validator(state, connection, validator_public_key).await?;
+    if state.config.require_interval_messages && message.interval_inclusion_messages.is_empty() {
+        tracing::warn!("Rejecting message without interval inclusion messages");
+        return Err(OracleMessageError::Unprocessable(
+            "message has no interval inclusion messages".to_string(),
+        ));
+    }
+    if state.config.reject_inconsistent_intervals {
+        if let Some(inconsistency) = find_inconsistent_interval_message(message) {
+            tracing::warn!("Rejecting inconsistent message: {}", inconsistency);
+            return Err(OracleMessageError::Inconsistent(inconsistency));
+        }
+    }
+    let orphan_slots = get_orphan_interval_slots(connection, message).await?;
+    if !orphan_slots.is_empty() {
+        match state.config.orphan_interval_policy {
+            OrphanIntervalPolicy::Accept => tracing::warn!(
+                "Accepting interval messages for slots {:?} without a value message",
+                orphan_slots
+            ),
+            OrphanIntervalPolicy::Reject => {
+                tracing::warn!(
+                    "Rejecting interval messages for slots {:?} without a value message",
+                    orphan_slots
+                );
+                return Err(OracleMessageError::Unprocessable(format!(
+                    "interval messages for slots {orphan_slots:?} without a value message"
+                )));
+            }
+        }
+    }
+    let value_entry = save_price_value_attestation(
+        state,
+        connection,
+        &message.asset,
+        &message.value_message,
+        validator_public_key,
+        signatures_verified,
+        origin,
+    )
+    .await?;
+    value_entries.push((validator_public_key.clone(), value_entry));
+    save_price_interval_attestations(
+        state,
+        connection,
+        &message.asset,
+        &message.interval_inclusion_messages,
+        validator_public_key,
+        signatures_verified,
+        origin,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Slots of the interval messages for which neither the oracle message nor an earlier one from the
+/// same validator carries a value message.
+async fn get_orphan_interval_slots(
+    connection: &mut PgConnection,
+    message: &OracleMessage,
+) -> eyre::Result<Vec<u64>> {
+    let pk_string = encode_public_key(&message.validator_public_key);
+    let candidate_slots: Vec<u64> = message
+        .interval_inclusion_messages
+        .iter()
+        .map(|interval_message| interval_message.message.slot_number)
+        .filter(|slot_number| *slot_number != message.value_message.message.slot_number)
+        .unique()
+        .collect();
+    let mut orphan_slots = Vec::new();
+    for slot_number in candidate_slots {
+        let has_value_message = sqlx::query!(
+            r#"
+            SELECT EXISTS (
+                SELECT
+                    1
+                FROM
+                    price_value_attestations
+                WHERE
+                    validator_public_key = $1
+                AND
+                    asset = $2
+                AND
+                    slot_number = $3
+            ) AS "exists!";
+            "#,
+            pk_string,
+            message.asset,
+            slot_number as i64,
+        )
+        .fetch_one(&mut *connection)
+        .await?
+        .exists;
+        if !has_value_message {
+            orphan_slots.push(slot_number);
+        }
+    }
+    Ok(orphan_slots)
+}
+
+async fn save_price_value_attestation(
+    state: &AppState,
+    connection: &mut PgConnection,
+    asset: &str,
+    message: &SignedPriceValueMessage,
+    validator_public_key: &PublicKey,
+    signature_verified: bool,
+    origin: MessageOrigin,
+) -> Result<PriceValueEntry, OracleMessageError> {
+    if !signature_verified
+        && !verify_message_signature(
+            state,
+            asset,
+            validator_public_key,
+            &message.message,
+            &message.signature,
+        )
+    {
+        return Err(OracleMessageError::InvalidSignature(
+            "invalid signature of value message".to_string(),
+        ));
+    }
+    if origin == MessageOrigin::Submitted
+        && !state
+            .slot_clock
+            .is_within_window(message.message.slot_number, state.config.value_window_slots)
+    {
+        record_offence(
+            state,
+            connection,
+            validator_public_key,
+            Offence::OutOfBounds,
+            message.message.slot_number,
+        )
+        .await?;
+        return Err(OracleMessageError::OutsideWindow(format!(
+            "value message slot {} is outside of the accepted window",
+            message.message.slot_number
+        )));
+    }
+    let value = message.message.price.value as i64;
+    let price_timestamp = message
+        .message
+        .price
+        .timestamp
+        .map(|timestamp| timestamp as i64);
+    let slot_number = message.message.slot_number as i64;
+    let signature = &encode_signature(&message.signature);
+    let pk_string = encode_public_key(validator_public_key);
+
+    let seen_key = (&pk_string, asset, slot_number);
+    let is_within_dedup_window = state.config.dedup_window_slots.is_none_or(|window_slots| {
+        message.message.slot_number.saturating_add(window_slots) >= state.slot_clock.current_slot()
+    });
+    let may_have_been_seen = is_within_dedup_window
+        && match &state.seen_values {
+            Some(seen_values) => seen_values.lock().unwrap().may_contain(&seen_key),
+            None => true,
+        };
+    let submitted_value = SignedValue {
+        value,
+        signature: signature.clone(),
+    };
+    if may_have_been_seen {
+        state.num_dedup_lookups.fetch_add(1, Ordering::Relaxed);
+        if let Some(stored_value) =
+            get_stored_value(connection, &pk_string, asset, slot_number).await?
+        {
+            return Err(reject_resubmitted_value(
+                state,
+                connection,
+                validator_public_key,
+                asset,
+                slot_number,
+                &stored_value,
+                &submitted_value,
+            )
+            .await?);
+        }
+    }
+    if state.config.enforce_arrival_order
+        && has_interval_attestations(connection, &pk_string, asset, slot_number).await?
+    {
+        tracing::warn!(
+            "Rejecting value message for slot {} arriving after interval messages for it",
+            slot_number
+        );
+        return Err(OracleMessageError::Unprocessable(format!(
+            "value message for slot {slot_number} arrived after interval messages for it"
+        )));
+    }
+
+    // Save price_value_message in DB
+    let signing_preimage = get_signing_preimage(state, &message.message);
+    let insert_started_at = Instant::now();
+    // A conflict would abort the transaction the message is stored in, so concurrent
+    // resubmissions that got past the duplicate check are skipped instead.
+    let received_at = sqlx::query!(
+        r#"
+        INSERT INTO price_value_attestations(
+            validator_public_key,
+            asset,
+            value,
             slot_number,
-            signature
+            signature,
+            signing_preimage,
+            price_timestamp
         )
         VALUES (
             $1,
             $2,
             $3,
             $4,
-            $5
+            $5,
+            $6,
+            $7
+        )
+        ON CONFLICT DO NOTHING
+        RETURNING
+            (EXTRACT(EPOCH FROM received_at) * 1000)::BIGINT AS "received_at!";
+        "#,
+        pk_string,
+        asset,
+        value,
+        slot_number,
+        signature,
+        signing_preimage,
+        price_timestamp,
+    )
+    .fetch_optional(&mut *connection)
+    .await?;
+    let received_at = match received_at {
+        Some(row) => row.received_at,
+        // The fast paths skip the lookup only for what is likely new, so a conflict still has to
+        // be told apart from an equivocation.
+        None => {
+            let stored_value = get_stored_value(connection, &pk_string, asset, slot_number)
+                .await?
+                .ok_or(OracleMessageError::Duplicate)?;
+            return Err(reject_resubmitted_value(
+                state,
+                connection,
+                validator_public_key,
+                asset,
+                slot_number,
+                &stored_value,
+                &submitted_value,
+            )
+            .await?);
+        }
+    };
+    state
+        .metrics
+        .db_insert_seconds
+        .observe(insert_started_at.elapsed());
+    if let Some(seen_values) = &state.seen_values {
+        seen_values.lock().unwrap().insert(&seen_key);
+    }
+    increment_asset_totals(connection, asset, 1, 0).await?;
+
+    Ok(PriceValueEntry {
+        validator_public_key: pk_string,
+        asset: asset.to_string(),
+        value,
+        slot_number,
+        signature: signature.to_string(),
+        signing_preimage,
+        price_timestamp,
+        received_at,
+    })
+}
+
+async fn get_stored_value(
+    connection: &mut PgConnection,
+    pk_string: &str,
+    asset: &str,
+    slot_number: i64,
+) -> sqlx::Result<Option<SignedValue>> {
+    let stored_value = sqlx::query!(
+        "
+        SELECT
+            value,
+            signature
+        FROM
+            price_value_attestations
+        WHERE
+            validator_public_key = $1
+        AND
+            asset = $2
+        AND
+            slot_number = $3;
+        ",
+        pk_string,
+        asset,
+        slot_number,
+    )
+    .fetch_optional(connection)
+    .await?
+    .map(|row| SignedValue {
+        value: row.value,
+        signature: row.signature,
+    });
+    Ok(stored_value)
+}
+
+/// The error for a value message of a slot the validator already has a value attestation for,
+/// recording an equivocation if the values differ.
+async fn reject_resubmitted_value(
+    state: &AppState,
+    connection: &mut PgConnection,
+    validator_public_key: &PublicKey,
+    asset: &str,
+    slot_number: i64,
+    stored_value: &SignedValue,
+    submitted_value: &SignedValue,
+) -> Result<OracleMessageError, OracleMessageError> {
+    if stored_value.value == submitted_value.value {
+        return Ok(OracleMessageError::Duplicate);
+    }
+    record_equivocation(
+        connection,
+        &encode_public_key(validator_public_key),
+        asset,
+        slot_number,
+        stored_value,
+        submitted_value,
+    )
+    .await?;
+    record_offence(
+        state,
+        connection,
+        validator_public_key,
+        Offence::Equivocation,
+        slot_number as u64,
+    )
+    .await?;
+    Ok(OracleMessageError::Equivocation(slot_number as u64))
+}
+
+/// Remembers a validator's value attestation if it is for their most recent slot so far.
+pub fn cache_latest_value(
+    state: &AppState,
+    validator_public_key: &PublicKey,
+    entry: PriceValueEntry,
+) {
+    let mut latest_values = state.latest_values.lock().unwrap();
+    let is_latest = latest_values
+        .get(validator_public_key)
+        .is_none_or(|latest| entry.slot_number >= latest.slot_number);
+    if is_latest {
+        latest_values.insert(validator_public_key.clone(), entry);
+    }
+}
+
+async fn save_price_interval_attestations(
+    state: &AppState,
+    connection: &mut PgConnection,
+    asset: &str,
+    messages: &Vec<SignedIntervalInclusionMessage>,
+    validator_public_key: &PublicKey,
+    signatures_verified: bool,
+    origin: MessageOrigin,
+) -> Result<(), OracleMessageError> {
+    if !signatures_verified && state.config.batch_verify_intervals {
+        let invalid_indices = find_invalid_interval_signatures(
+            validator_public_key,
+            messages,
+            state.config.digest_config().for_asset(asset),
         );
+        if !invalid_indices.is_empty() {
+            return Err(OracleMessageError::InvalidSignature(format!(
+                "invalid signature of interval messages {invalid_indices:?}"
+            )));
+        }
+    }
+    let signatures_verified = signatures_verified || state.config.batch_verify_intervals;
+    for message in messages {
+        save_price_interval_attestation(
+            state,
+            connection,
+            asset,
+            message,
+            validator_public_key,
+            signatures_verified,
+            origin,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Indices of the oracle messages with any invalid signature. The signatures of all messages are
+/// verified at once first, see `verify_signatures_at_once`, only verifying them message by message
+/// to pinpoint the invalid ones if that fails.
+pub fn find_invalid_message_signatures(
+    messages: &[OracleMessage],
+    digest_config: DigestConfig,
+) -> Vec<usize> {
+    if messages.is_empty() {
+        return Vec::new();
+    }
+    let mut signatures = Vec::new();
+    for message in messages {
+        let digest_config = digest_config.for_asset(&message.asset);
+        signatures.push((
+            &message.validator_public_key,
+            get_message_digest(&message.value_message.message, digest_config),
+            &message.value_message.signature,
+        ));
+        for interval_message in &message.interval_inclusion_messages {
+            signatures.push((
+                &message.validator_public_key,
+                get_message_digest(&interval_message.message, digest_config),
+                &interval_message.signature,
+            ));
+        }
+    }
+    if verify_signatures_at_once(&signatures) {
+        return Vec::new();
+    }
+    messages
+        .iter()
+        .enumerate()
+        .filter(|(_, message)| {
+            let digest_config = digest_config.for_asset(&message.asset);
+            !validate_message(
+                &message.validator_public_key,
+                &message.value_message.message,
+                &message.value_message.signature,
+                digest_config,
+            ) || !find_invalid_interval_signatures(
+                &message.validator_public_key,
+                &message.interval_inclusion_messages,
+                digest_config,
+            )
+            .is_empty()
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Indices of the interval messages with an invalid signature. All signatures are verified at
+/// once first, see `verify_signatures_at_once`, only verifying them one by one to pinpoint the
+/// invalid ones if that fails.
+pub fn find_invalid_interval_signatures(
+    validator_public_key: &PublicKey,
+    messages: &[SignedIntervalInclusionMessage],
+    digest_config: DigestConfig,
+) -> Vec<usize> {
+    if messages.is_empty() {
+        return Vec::new();
+    }
+    let signatures: Vec<(&PublicKey, Hash256, &Signature)> = messages
+        .iter()
+        .map(|message| {
+            (
+                validator_public_key,
+                get_message_digest(&message.message, digest_config),
+                &message.signature,
+            )
+        })
+        .collect();
+    if verify_signatures_at_once(&signatures) {
+        return Vec::new();
+    }
+    messages
+        .iter()
+        .enumerate()
+        .filter(|(_, message)| {
+            !validate_message(
+                validator_public_key,
+                &message.message,
+                &message.signature,
+                digest_config,
+            )
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Whether all signatures are valid over their digests. They are verified at once against a
+/// random linear combination, so unlike a plain aggregate check, invalid signatures crafted to
+/// cancel each other out don't pass.
+fn verify_signatures_at_once(signatures: &[(&PublicKey, Hash256, &Signature)]) -> bool {
+    let signature_sets: Vec<SignatureSet> = signatures
+        .iter()
+        .map(|(public_key, digest, signature)| {
+            SignatureSet::single_pubkey(*signature, Cow::Borrowed(*public_key), *digest)
+        })
+        .collect();
+    verify_signature_sets(signature_sets.iter())
+}
+
+async fn save_price_interval_attestation(
+    state: &AppState,
+    connection: &mut PgConnection,
+    asset: &str,
+    message: &SignedIntervalInclusionMessage,
+    validator_public_key: &PublicKey,
+    signature_verified: bool,
+    origin: MessageOrigin,
+) -> Result<(), OracleMessageError> {
+    let allowed_interval_sizes = &state.config.allowed_interval_sizes;
+    if !allowed_interval_sizes.is_empty()
+        && !allowed_interval_sizes.contains(&message.message.interval_size)
+    {
+        return Err(OracleMessageError::DisallowedIntervalSize(
+            message.message.interval_size,
+        ));
+    }
+    if !signature_verified
+        && !verify_message_signature(
+            state,
+            asset,
+            validator_public_key,
+            &message.message,
+            &message.signature,
+        )
+    {
+        return Err(OracleMessageError::InvalidSignature(
+            "invalid signature of interval message".to_string(),
+        ));
+    }
+    if origin == MessageOrigin::Submitted
+        && !state.slot_clock.is_within_window(
+            message.message.slot_number,
+            state.config.interval_window_slots,
+        )
+    {
+        record_offence(
+            state,
+            connection,
+            validator_public_key,
+            Offence::OutOfBounds,
+            message.message.slot_number,
+        )
+        .await?;
+        return Err(OracleMessageError::OutsideWindow(format!(
+            "interval message slot {} is outside of the accepted window",
+            message.message.slot_number
+        )));
+    }
+    let value = message.message.value as i64;
+    let interval_size = message.message.interval_size as i64;
+    let slot_number = message.message.slot_number as i64;
+    let signature = &encode_signature(&message.signature);
+    let pk_string = encode_public_key(validator_public_key);
+    let aggregate_value = get_aggregate_value(
+        message.message.value,
+        state
+            .config
+            .interval_value_bands
+            .get(&message.message.interval_size)
+            .copied(),
+    ) as i64;
+
+    // Save price_value_message in DB
+    let insert_started_at = Instant::now();
+    // Skipping conflicts instead of failing on them keeps the transaction usable, see
+    // `save_price_value_attestation`.
+    let num_inserted = sqlx::query!(
+        "
+        INSERT INTO price_interval_attestations(
+            validator_public_key,
+            asset,
+            value,
+            interval_size,
+            slot_number,
+            signature,
+            aggregate_value,
+            signing_preimage
+        )
+        VALUES (
+            $1,
+            $2,
+            $3,
+            $4,
+            $5,
+            $6,
+            $7,
+            $8
+        )
+        ON CONFLICT DO NOTHING;
         ",
         pk_string,
+        asset,
         value,
         interval_size,
         slot_number,
         signature,
+        aggregate_value,
+        get_signing_preimage(state, &message.message),
     )
-    .execute(db_pool)
-    .await?;
+    .execute(&mut *connection)
+    .await?
+    .rows_affected();
+    if num_inserted == 0 {
+        return Err(OracleMessageError::Duplicate);
+    }
+    state
+        .metrics
+        .db_insert_seconds
+        .observe(insert_started_at.elapsed());
+    increment_asset_totals(connection, asset, 0, 1).await?;
 
     // TODO: Review if we really want to aggregate every time we receive a new message
-    extend_or_create_aggregate_interval_attestation(db_pool, message, validator_public_key).await?;
+    extend_or_create_aggregate_interval_attestation(
+        state,
+        connection,
+        asset,
+        message,
+        validator_public_key,
+        aggregate_value,
+    )
+    .await?;
     Ok(())
 }
 
+/// Whether the validator has stored interval attestations for the asset and slot.
+async fn has_interval_attestations(
+    connection: &mut PgConnection,
+    validator_public_key: &str,
+    asset: &str,
+    slot_number: i64,
+) -> sqlx::Result<bool> {
+    let has_interval_attestations = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT
+                1
+            FROM
+                price_interval_attestations
+            WHERE
+                validator_public_key = $1
+            AND
+                asset = $2
+            AND
+                slot_number = $3
+        ) AS "exists!";
+        "#,
+        validator_public_key,
+        asset,
+        slot_number,
+    )
+    .fetch_one(connection)
+    .await?
+    .exists;
+    Ok(has_interval_attestations)
+}
+
+/// Values within the same band collapse into a single aggregate keyed by the lower bound of the
+/// band. Without a band every value gets its own aggregate.
+///
+/// Note that the signatures in a banded aggregate are over different messages, so it can only be
+/// verified against the original values of the individual attestations.
+fn get_aggregate_value(value: u64, band: Option<u64>) -> u64 {
+    match band {
+        Some(band) if band > 1 => value - value % band,
+        _ => value,
+    }
+}
+
+/// The configured cap on validators per aggregate, or the allowlist size if there is one, as no
+/// more validators than that can legitimately contribute.
+fn get_max_validators_per_aggregate(state: &AppState) -> Option<i64> {
+    let allowlist_size =
+        Some(state.config.validator_allowlist.len() as i64).filter(|&size| size > 0);
+    state.config.max_validators_per_aggregate.or(allowlist_size)
+}
+
+/// Key of the advisory lock serializing the updates of an aggregate.
+pub fn get_aggregate_lock_key(
+    asset: &str,
+    interval_size: i64,
+    slot_number: i64,
+    value: i64,
+) -> String {
+    format!("aggregate_interval_attestations/{asset}/{interval_size}/{slot_number}/{value}")
+}
+
+/// Has to run in a transaction, which holds the lock of the aggregate until it ends.
 async fn extend_or_create_aggregate_interval_attestation(
-    db_pool: &DbPool,
+    state: &AppState,
+    connection: &mut PgConnection,
+    asset: &str,
     message: &SignedIntervalInclusionMessage,
     validator_public_key: &PublicKey,
+    value: i64,
 ) -> eyre::Result<()> {
     let interval_size = message.message.interval_size as i64;
     let slot_number = message.message.slot_number as i64;
-    let value = message.message.value as i64;
+    // Concurrent submissions for the same aggregate would otherwise both read the same aggregate
+    // and one of them overwrite the other's signature. A row lock can't cover the creation of the
+    // aggregate, so the aggregate's key is locked instead, until the transaction ends.
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended($1, 0));")
+        .bind(get_aggregate_lock_key(
+            asset,
+            interval_size,
+            slot_number,
+            value,
+        ))
+        .execute(&mut *connection)
+        .await?;
     let query_result = sqlx::query!(
         "
             SELECT
                 num_validators,
+                total_weight,
                 aggregate_signature,
-                aggregate_public_key
+                aggregate_public_key,
+                operators,
+                public_keys,
+                signatures
             FROM
                 aggregate_interval_attestations
             WHERE
-                interval_size = $1
+                asset = $1
+            AND
+                interval_size = $2
             AND
-                slot_number = $2
+                slot_number = $3
             AND
-                value = $3;
+                value = $4;
         ",
+        asset,
         interval_size,
         slot_number,
         value,
     )
-    .fetch_optional(db_pool)
+    .fetch_optional(&mut *connection)
     .await?;
 
-    let (new_num_validators, mut aggregate_signature, aggregate_public_key) =
-        if let Some(entry) = query_result {
-            // Aggregate new message into existing aggregates if it is not the first one
-            (
-                entry.num_validators + 1,
-                AggregateSignature::deserialize(&hex::decode(entry.aggregate_signature)?)
-                    .map_err(|_| eyre::eyre!("Invalid aggregate signature in DB"))?,
-                AggregatePublicKey::aggregate(&[
-                    PublicKey::deserialize(&hex::decode(entry.aggregate_public_key)?)
-                        .map_err(|_| eyre::eyre!("Invalid aggregate public key in DB"))?,
-                    validator_public_key.clone(),
-                ])
-                .map_err(|_| eyre::eyre!("Invalid aggregate public key in DB"))?,
-            )
-        } else {
-            // Starting values if this is the first message to be submitted
-            (
-                1,
-                AggregateSignature::infinity(),
-                AggregatePublicKey::aggregate(&[validator_public_key.clone()])
-                    .map_err(|_| eyre::eyre!("Invalid aggregate public key"))?,
-            )
-        };
+    let (
+        num_validators,
+        total_weight,
+        mut operators,
+        mut public_keys,
+        mut signatures,
+        mut aggregate_signature,
+        aggregate_public_key,
+    ) = if let Some(entry) = query_result {
+        // Aggregate new message into existing aggregates if it is not the first one
+        (
+            entry.num_validators,
+            entry.total_weight,
+            entry.operators,
+            entry.public_keys,
+            entry.signatures,
+            AggregateSignature::deserialize(&hex::decode(entry.aggregate_signature)?)
+                .map_err(|_| eyre::eyre!("Invalid aggregate signature in DB"))?,
+            AggregatePublicKey::aggregate(&[
+                PublicKey::deserialize(&hex::decode(entry.aggregate_public_key)?)
+                    .map_err(|_| eyre::eyre!("Invalid aggregate public key in DB"))?,
+                validator_public_key.clone(),
+            ])
+            .map_err(|_| eyre::eyre!("Invalid aggregate public key in DB"))?,
+        )
+    } else {
+        if let Some(max_aggregates_per_slot) = state.config.max_aggregates_per_slot {
+            if count_aggregates_in_slot(connection, asset, slot_number).await?
+                >= max_aggregates_per_slot
+            {
+                tracing::warn!(
+                    "Not aggregating value {} in slot {}, the slot already has {} aggregates",
+                    value,
+                    slot_number,
+                    max_aggregates_per_slot
+                );
+                return Ok(());
+            }
+        }
+        // Starting values if this is the first message to be submitted
+        (
+            0,
+            0,
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+            AggregateSignature::infinity(),
+            AggregatePublicKey::aggregate(std::slice::from_ref(validator_public_key))
+                .map_err(|_| eyre::eyre!("Invalid aggregate public key"))?,
+        )
+    };
 
+    // Adding the same signature twice would turn the aggregate into one nobody signed.
+    let signature_string = encode_signature(&message.signature);
+    if signatures.contains(&signature_string) {
+        tracing::warn!(
+            "Not aggregating signature {} into value {} in slot {} again",
+            signature_string,
+            value,
+            slot_number
+        );
+        return Ok(());
+    }
+
+    // Within a band a validator can attest to several values of the same aggregate, adding the
+    // same validator twice would inflate the count and break verification against the keys.
+    let pk_string = encode_public_key(validator_public_key);
+    if public_keys.contains(&pk_string) {
+        tracing::warn!(
+            "Not aggregating signature of {} into value {} in slot {} again",
+            pk_string,
+            value,
+            slot_number
+        );
+        return Ok(());
+    }
+
+    let reached_quorum_before = reaches_quorum(
+        state,
+        asset,
+        slot_number,
+        num_validators,
+        operators.len() as i64,
+    );
+    let new_num_validators = num_validators + 1;
+    if let Some(max_validators) = get_max_validators_per_aggregate(state) {
+        if new_num_validators > max_validators {
+            tracing::warn!(
+                "Not aggregating signature of {} into value {} in slot {}, the aggregate already has {} validators",
+                validator_public_key,
+                value,
+                slot_number,
+                num_validators
+            );
+            return Ok(());
+        }
+    }
+    let operator = get_operator(state, validator_public_key);
+    if !operators.contains(&operator) {
+        operators.push(operator);
+    }
+    let new_num_operators = operators.len() as i64;
+    let new_total_weight = total_weight + get_validator_weight(state, validator_public_key);
+    public_keys.push(pk_string);
+    signatures.push(signature_string);
     aggregate_signature.add_assign(&message.signature);
     let new_aggregate_signature = hex::encode(aggregate_signature.serialize());
     let new_aggregate_public_key = hex::encode(aggregate_public_key.to_public_key().serialize());
@@ -495,12 +2357,18 @@ async fn extend_or_create_aggregate_interval_attestation(
         sqlx::query!(
             "
             INSERT INTO aggregate_interval_attestations(
+                asset,
                 value,
                 interval_size,
                 slot_number,
                 num_validators,
                 aggregate_signature,
-                aggregate_public_key
+                aggregate_public_key,
+                operators,
+                num_operators,
+                public_keys,
+                signatures,
+                total_weight
             )
             VALUES (
                 $1,
@@ -508,17 +2376,29 @@ async fn extend_or_create_aggregate_interval_attestation(
                 $3,
                 $4,
                 $5,
-                $6
+                $6,
+                $7,
+                $8,
+                $9,
+                $10,
+                $11,
+                $12
             );
             ",
+            asset,
             value,
             interval_size,
             slot_number,
             new_num_validators,
             new_aggregate_signature,
             new_aggregate_public_key,
+            &operators,
+            new_num_operators,
+            &public_keys,
+            &signatures,
+            new_total_weight,
         )
-        .execute(db_pool)
+        .execute(&mut *connection)
         .await?;
     } else {
         // Update existing db entry
@@ -527,37 +2407,363 @@ async fn extend_or_create_aggregate_interval_attestation(
             UPDATE aggregate_interval_attestations
             SET
                 num_validators = $1,
-                aggregate_signature = $2
+                aggregate_signature = $2,
+                operators = $3,
+                num_operators = $4,
+                public_keys = $9,
+                signatures = $10,
+                total_weight = $11,
+                aggregate_public_key = $12,
+                updated_at = now()
             WHERE
-                interval_size = $3
+                asset = $5
+            AND
+                interval_size = $6
             AND
-                slot_number = $4
+                slot_number = $7
             AND
-                value = $5;
+                value = $8;
             ",
             new_num_validators,
             new_aggregate_signature,
+            &operators,
+            new_num_operators,
+            asset,
             interval_size,
             slot_number,
             value,
+            &public_keys,
+            &signatures,
+            new_total_weight,
+            new_aggregate_public_key,
         )
-        .execute(db_pool)
+        .execute(&mut *connection)
         .await?;
     }
 
+    if new_num_validators == 1 {
+        state.metrics.aggregates_created.inc();
+    } else {
+        state.metrics.aggregates_extended.inc();
+    }
+
+    let reaches_quorum_now = reaches_quorum(
+        state,
+        asset,
+        slot_number,
+        new_num_validators,
+        new_num_operators,
+    );
+    let flipped_from = if state.config.detect_value_flips && reaches_quorum_now {
+        track_quorum_value(
+            state,
+            asset,
+            slot_number,
+            interval_size,
+            value,
+            new_num_validators,
+        )
+    } else {
+        None
+    };
+    let aggregate = AggregatePriceIntervalEntry {
+        asset: asset.to_string(),
+        value,
+        slot_number,
+        aggregate_signature: new_aggregate_signature,
+        aggregate_public_key: new_aggregate_public_key,
+        interval_size,
+        num_validators: new_num_validators,
+        num_operators: new_num_operators,
+        public_keys,
+        finalized: None,
+    };
+    if let Some(previous_value) = flipped_from {
+        tracing::warn!(
+            "Quorum-reaching value of slot {} with interval size {} flipped from {} to {} with {} validators",
+            slot_number,
+            interval_size,
+            previous_value,
+            value,
+            new_num_validators
+        );
+        state.num_value_flips.fetch_add(1, Ordering::Relaxed);
+        enqueue_notification(state, connection, "value_flipped", aggregate.clone()).await?;
+    }
+    if !reached_quorum_before && reaches_quorum_now {
+        enqueue_quorum_notification(state, connection, aggregate).await?;
+    }
+
     Ok(())
 }
 
-fn validate_message<T: ssz::Encode>(
+/// Number of slots before the latest tracked one whose quorum values are kept.
+const QUORUM_VALUE_SLOTS: i64 = 64;
+
+/// Records a quorum-reaching aggregate, returning the previously best value if this aggregate
+/// now has more validators than it.
+fn track_quorum_value(
+    state: &AppState,
+    asset: &str,
+    slot_number: i64,
+    interval_size: i64,
+    value: i64,
+    num_validators: i64,
+) -> Option<i64> {
+    let mut quorum_values = state.quorum_values.lock().unwrap();
+    let key = (asset.to_string(), slot_number, interval_size);
+    match quorum_values.get_mut(&key) {
+        Some(best) if best.0 == value => {
+            best.1 = num_validators;
+            None
+        }
+        Some(best) if num_validators > best.1 => {
+            let previous_value = best.0;
+            *best = (value, num_validators);
+            Some(previous_value)
+        }
+        Some(_) => None,
+        None => {
+            quorum_values
+                .retain(|(_, tracked_slot, _), _| *tracked_slot > slot_number - QUORUM_VALUE_SLOTS);
+            quorum_values.insert(key, (value, num_validators));
+            None
+        }
+    }
+}
+
+/// Whether an aggregate reaches the quorum threshold of its asset and slot and the operator
+/// diversity.
+pub fn reaches_quorum(
+    state: &AppState,
+    asset: &str,
+    slot_number: i64,
+    num_validators: i64,
+    num_operators: i64,
+) -> bool {
+    match get_quorum_threshold(state, asset, slot_number) {
+        Some(quorum_threshold) => {
+            num_validators >= quorum_threshold
+                && num_operators >= state.config.quorum_min_operators.unwrap_or(0)
+        }
+        None => false,
+    }
+}
+
+/// The configured operator of a validator, validators without one count as their own operator.
+pub fn get_operator(state: &AppState, validator_public_key: &PublicKey) -> String {
+    let pk_string = encode_public_key(validator_public_key);
+    state
+        .config
+        .validator_operators
+        .get(&pk_string)
+        .cloned()
+        .unwrap_or(pk_string)
+}
+
+/// Stake weight of the validator, one unless configured.
+pub fn get_validator_weight(state: &AppState, validator_public_key: &PublicKey) -> i64 {
+    state
+        .config
+        .validator_weights
+        .get(&encode_public_key(validator_public_key))
+        .copied()
+        .unwrap_or(1)
+}
+
+async fn count_aggregates_in_slot(
+    connection: &mut PgConnection,
+    asset: &str,
+    slot_number: i64,
+) -> eyre::Result<i64> {
+    let count = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) AS "count!"
+        FROM
+            aggregate_interval_attestations
+        WHERE
+            asset = $1
+        AND
+            slot_number = $2;
+        "#,
+        asset,
+        slot_number,
+    )
+    .fetch_one(connection)
+    .await?
+    .count;
+    Ok(count)
+}
+
+pub fn validate_message<T: SignedMessage>(
     public_key: &PublicKey,
     message: &T,
     signature: &Signature,
+    digest_config: DigestConfig,
 ) -> bool {
-    let message_digest = get_message_digest(&message);
+    let message_digest = get_message_digest(&message, digest_config);
     signature.verify(public_key, message_digest)
 }
 
-pub fn get_message_digest<T: ssz::Encode>(message: &T) -> Hash256 {
-    let message_ssz = message.as_ssz_bytes();
-    Hash256::from_slice(&Sha3_256::digest(message_ssz))
+/// Verifies the signature of a message of the asset, serving the result from the cache if it was
+/// verified before.
+fn verify_message_signature<T: SignedMessage>(
+    state: &AppState,
+    asset: &str,
+    public_key: &PublicKey,
+    message: &T,
+    signature: &Signature,
+) -> bool {
+    let digest_config = state.config.digest_config().for_asset(asset);
+    let message_digest = get_message_digest(message, digest_config);
+    let cache_key = (message_digest, signature.serialize(), public_key.clone());
+    if let Some(signature_cache) = &state.signature_cache {
+        if let Some(is_valid) = signature_cache.lock().unwrap().get(&cache_key) {
+            return is_valid;
+        }
+    }
+    state
+        .num_signature_verifications
+        .fetch_add(1, Ordering::Relaxed);
+    let is_valid = signature.verify(public_key, message_digest);
+    if let Some(signature_cache) = &state.signature_cache {
+        signature_cache.lock().unwrap().insert(cache_key, is_valid);
+    }
+    is_valid
+}
+
+/// The hex encoded SSZ bytes of a message, if configured to be stored alongside attestations.
+fn get_signing_preimage<T: ssz::Encode>(state: &AppState, message: &T) -> Option<String> {
+    state
+        .config
+        .store_signing_preimages
+        .then(|| hex::encode(message.as_ssz_bytes()))
+}
+
+/// Separates the oracle's signatures from those over the same bytes in any other context.
+pub const DOMAIN: [u8; 32] = *b"ultra-sound-oracle-attestation\0\0";
+
+/// Kind of message a signature is over, like eth2's `DomainType`, so a signature over the bytes
+/// of one kind of message doesn't verify for another kind with the same bytes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DomainType {
+    Value,
+    Interval,
+    Admin,
+    Bundle,
+}
+
+impl DomainType {
+    fn to_bytes(self) -> [u8; 4] {
+        match self {
+            DomainType::Value => [0, 0, 0, 0],
+            DomainType::Interval => [1, 0, 0, 0],
+            DomainType::Admin => [2, 0, 0, 0],
+            DomainType::Bundle => [3, 0, 0, 0],
+        }
+    }
+}
+
+/// A message signed over its `get_message_digest`, in the domain of its type.
+pub trait SignedMessage: ssz::Encode {
+    const DOMAIN_TYPE: DomainType;
+}
+
+impl SignedMessage for PriceValueMessage {
+    const DOMAIN_TYPE: DomainType = DomainType::Value;
+}
+
+impl SignedMessage for IntervalInclusionMessage {
+    const DOMAIN_TYPE: DomainType = DomainType::Interval;
+}
+
+/// The domain of a message type, and of the network if there is one, so signatures of one type
+/// or network can't be replayed as another.
+pub fn compute_domain(
+    domain_type: DomainType,
+    network_id: Option<u64>,
+    hash_function: HashFunction,
+) -> Hash256 {
+    let domain_type = domain_type.to_bytes();
+    let network_id = network_id.map(u64::to_le_bytes);
+    let mut chunks: Vec<&[u8]> = vec![&DOMAIN, &domain_type];
+    if let Some(network_id) = &network_id {
+        chunks.push(network_id);
+    }
+    hash_function.hash(&chunks)
+}
+
+/// Hash function messages are digested with. Note sha3-256 and keccak256 differ in their
+/// padding, so they produce different digests.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum HashFunction {
+    #[default]
+    #[serde(rename = "sha3-256")]
+    Sha3_256,
+    #[serde(rename = "keccak256")]
+    Keccak256,
+}
+
+impl FromStr for HashFunction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sha3-256" => Ok(HashFunction::Sha3_256),
+            "keccak256" => Ok(HashFunction::Keccak256),
+            _ => Err(format!("unknown hash function {s}")),
+        }
+    }
+}
+
+impl HashFunction {
+    pub fn hash(&self, chunks: &[&[u8]]) -> Hash256 {
+        fn hash_with<D: Digest>(chunks: &[&[u8]]) -> Hash256 {
+            let mut hasher = D::new();
+            for chunk in chunks {
+                hasher.update(chunk);
+            }
+            Hash256::from_slice(&hasher.finalize())
+        }
+        match self {
+            HashFunction::Sha3_256 => hash_with::<Sha3_256>(chunks),
+            HashFunction::Keccak256 => hash_with::<Keccak256>(chunks),
+        }
+    }
+}
+
+/// What goes into the digest of a message besides the message itself, which signers and the
+/// server have to agree on.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DigestConfig {
+    pub network_id: Option<u64>,
+    pub hash_function: HashFunction,
+    /// Hash of the asset pair the message is for, set by `DigestConfig::for_asset`.
+    pub asset_hash: Option<Hash256>,
+}
+
+impl DigestConfig {
+    /// The config for messages of the asset pair. Other assets than the default one are mixed
+    /// into the digest, so a signature for one asset doesn't verify for another, while messages
+    /// of the default asset keep the digest they had before there were multiple assets.
+    pub fn for_asset(self, asset: &str) -> Self {
+        let asset_hash =
+            (asset != DEFAULT_ASSET).then(|| self.hash_function.hash(&[asset.as_bytes()]));
+        DigestConfig { asset_hash, ..self }
+    }
+}
+
+/// The digest a message is signed over. Like eth2's `compute_signing_root`, the hash of the
+/// message is mixed with the domain of its type and network. The hash of the asset, if any, is
+/// mixed into the message hash first.
+pub fn get_message_digest<T: SignedMessage>(message: &T, digest_config: DigestConfig) -> Hash256 {
+    let hash_function = digest_config.hash_function;
+    let message_root = hash_function.hash(&[&message.as_ssz_bytes()]);
+    let message_root = match digest_config.asset_hash {
+        Some(asset_hash) => hash_function.hash(&[message_root.as_bytes(), asset_hash.as_bytes()]),
+        None => message_root,
+    };
+    let domain = compute_domain(T::DOMAIN_TYPE, digest_config.network_id, hash_function);
+    hash_function.hash(&[message_root.as_bytes(), domain.as_bytes()])
 }
@@ -0,0 +1,224 @@
+//! Bloom filter of recently seen items, letting the duplicate check skip the DB for items that
+//! were definitely not seen before.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Bits per item at capacity, which together with the number of hashes gives a false positive
+/// rate of about 1%.
+const BITS_PER_ITEM: usize = 10;
+const NUM_HASHES: u64 = 7;
+
+struct BloomFilter {
+    bits: Vec<u64>,
+    num_items: usize,
+}
+
+impl BloomFilter {
+    fn new(capacity: usize) -> Self {
+        let num_words = (capacity * BITS_PER_ITEM).div_ceil(64).max(1);
+        BloomFilter {
+            bits: vec![0; num_words],
+            num_items: 0,
+        }
+    }
+
+    fn bit_indices<T: Hash>(&self, item: &T) -> impl Iterator<Item = usize> {
+        // Double hashing, deriving all hashes from two.
+        let hash_with_seed = |seed: u64| {
+            let mut hasher = DefaultHasher::new();
+            seed.hash(&mut hasher);
+            item.hash(&mut hasher);
+            hasher.finish()
+        };
+        let (hash_a, hash_b) = (hash_with_seed(0), hash_with_seed(1));
+        let num_bits = self.bits.len() as u64 * 64;
+        (0..NUM_HASHES)
+            .map(move |i| (hash_a.wrapping_add(i.wrapping_mul(hash_b)) % num_bits) as usize)
+    }
+
+    fn insert<T: Hash>(&mut self, item: &T) {
+        for index in self.bit_indices(item).collect::<Vec<_>>() {
+            self.bits[index / 64] |= 1 << (index % 64);
+        }
+        self.num_items += 1;
+    }
+
+    fn contains<T: Hash>(&self, item: &T) -> bool {
+        self.bit_indices(item)
+            .all(|index| self.bits[index / 64] & (1 << (index % 64)) != 0)
+    }
+}
+
+/// Remembers between `capacity` and twice `capacity` of the most recently inserted items, by
+/// starting a new filter once the current one is full and dropping the one before it.
+pub struct RecentBloomFilter {
+    current: BloomFilter,
+    previous: BloomFilter,
+    capacity: usize,
+}
+
+impl RecentBloomFilter {
+    pub fn new(capacity: usize) -> Self {
+        RecentBloomFilter {
+            current: BloomFilter::new(capacity),
+            previous: BloomFilter::new(capacity),
+            capacity,
+        }
+    }
+
+    pub fn insert<T: Hash>(&mut self, item: &T) {
+        if self.current.num_items >= self.capacity {
+            self.previous = std::mem::replace(&mut self.current, BloomFilter::new(self.capacity));
+        }
+        self.current.insert(item);
+    }
+
+    /// False means the item was definitely not inserted recently, true means it may have been.
+    pub fn may_contain<T: Hash>(&self, item: &T) -> bool {
+        self.current.contains(item) || self.previous.contains(item)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::attestations::{import_oracle_message, ingest_oracle_message, OracleMessageError};
+    use crate::config::Config;
+    use crate::db::DbPool;
+    use crate::test_utils::{state_at_slot, value_message, TestApp, TEST_MESSAGE_SLOT};
+    use axum::http::StatusCode;
+    use bls::SecretKey;
+    use std::sync::atomic::Ordering;
+
+    #[sqlx::test]
+    async fn catches_duplicates_with_fewer_lookups(db_pool: DbPool) {
+        let config = Config {
+            dedup_bloom_capacity: Some(1000),
+            quarantine_equivocation_threshold: Some(1),
+            ..Config::default()
+        };
+        let state = state_at_slot(db_pool.clone(), config.clone(), TEST_MESSAGE_SLOT);
+        let private_keys: Vec<SecretKey> = (0..5).map(|_| SecretKey::random()).collect();
+        for private_key in &private_keys {
+            let message = value_message(private_key, TEST_MESSAGE_SLOT, 1811093163);
+            ingest_oracle_message(&state, &message).await.unwrap();
+        }
+        // None of the first messages were seen before, so the DB was never asked
+        assert_eq!(state.num_dedup_lookups.load(Ordering::Relaxed), 0);
+
+        let duplicate = value_message(&private_keys[0], TEST_MESSAGE_SLOT, 1811093163);
+        assert_eq!(
+            ingest_oracle_message(&state, &duplicate)
+                .await
+                .map_err(|err| err.status_code()),
+            Err(StatusCode::CONFLICT)
+        );
+        let equivocation = value_message(&private_keys[1], TEST_MESSAGE_SLOT, 1);
+        assert_eq!(
+            ingest_oracle_message(&state, &equivocation)
+                .await
+                .map_err(|err| err.status_code()),
+            Err(StatusCode::CONFLICT)
+        );
+        assert_eq!(state.num_dedup_lookups.load(Ordering::Relaxed), 2);
+
+        // The equivocation was recognized as such, quarantining the validator
+        let test_app = TestApp::with_config(db_pool, config).await;
+        test_app
+            .post_oracle_message(
+                &value_message(&private_keys[1], TEST_MESSAGE_SLOT - 1, 1),
+                StatusCode::FORBIDDEN,
+            )
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn recognizes_equivocations_the_filter_has_not_seen(db_pool: DbPool) {
+        let config = Config {
+            dedup_bloom_capacity: Some(1000),
+            quarantine_equivocation_threshold: Some(1),
+            ..Config::default()
+        };
+        let state = state_at_slot(db_pool.clone(), config.clone(), TEST_MESSAGE_SLOT);
+        let private_key = SecretKey::random();
+        let message = value_message(&private_key, TEST_MESSAGE_SLOT, 1811093163);
+        ingest_oracle_message(&state, &message).await.unwrap();
+
+        // After a restart the filter is empty, so resubmissions skip the lookup
+        let restarted_state = state_at_slot(db_pool.clone(), config.clone(), TEST_MESSAGE_SLOT);
+        let equivocation = value_message(&private_key, TEST_MESSAGE_SLOT, 1);
+        for resubmitted_message in [&message, &equivocation] {
+            assert_eq!(
+                ingest_oracle_message(&restarted_state, resubmitted_message)
+                    .await
+                    .map_err(|err| err.status_code()),
+                Err(StatusCode::CONFLICT)
+            );
+        }
+        assert_eq!(restarted_state.num_dedup_lookups.load(Ordering::Relaxed), 0);
+
+        // Only the conflicting value was recorded as an equivocation, quarantining the validator
+        let num_equivocations: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM equivocations;")
+            .fetch_one(&db_pool)
+            .await
+            .unwrap();
+        assert_eq!(num_equivocations, 1);
+        let test_app = TestApp::with_config(db_pool, config).await;
+        test_app
+            .post_oracle_message(
+                &value_message(&private_key, TEST_MESSAGE_SLOT - 1, 1),
+                StatusCode::FORBIDDEN,
+            )
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn only_looks_up_duplicates_within_window(db_pool: DbPool) {
+        let config = Config {
+            value_window_slots: 100,
+            dedup_window_slots: Some(10),
+            ..Config::default()
+        };
+        let state = state_at_slot(db_pool, config, TEST_MESSAGE_SLOT);
+        let private_key = SecretKey::random();
+        let message = value_message(&private_key, TEST_MESSAGE_SLOT - 5, 1811093163);
+        ingest_oracle_message(&state, &message).await.unwrap();
+        assert_eq!(
+            ingest_oracle_message(&state, &message)
+                .await
+                .map_err(|err| err.status_code()),
+            Err(StatusCode::CONFLICT)
+        );
+        assert_eq!(state.num_dedup_lookups.load(Ordering::Relaxed), 2);
+
+        // The primary key still rejects duplicates of older slots, without a lookup
+        let old_message = value_message(&private_key, TEST_MESSAGE_SLOT - 20, 1811093163);
+        ingest_oracle_message(&state, &old_message).await.unwrap();
+        assert_eq!(
+            ingest_oracle_message(&state, &old_message)
+                .await
+                .map_err(|err| err.status_code()),
+            Err(StatusCode::CONFLICT)
+        );
+        assert_eq!(state.num_dedup_lookups.load(Ordering::Relaxed), 2);
+
+        // and still recognize equivocations on them
+        let equivocation = value_message(&private_key, TEST_MESSAGE_SLOT - 20, 1);
+        assert!(matches!(
+            ingest_oracle_message(&state, &equivocation).await,
+            Err(OracleMessageError::Equivocation(slot_number)) if slot_number == TEST_MESSAGE_SLOT - 20
+        ));
+    }
+
+    #[sqlx::test]
+    async fn window_does_not_overflow_for_imported_far_future_slots(db_pool: DbPool) {
+        let config = Config {
+            dedup_window_slots: Some(10),
+            ..Config::default()
+        };
+        let state = state_at_slot(db_pool, config, TEST_MESSAGE_SLOT);
+        // Imports skip the slot window, so any slot gets as far as the duplicate check
+        let message = value_message(&SecretKey::random(), u64::MAX, 1811093163);
+        let _ = import_oracle_message(&state, &message).await;
+    }
+}
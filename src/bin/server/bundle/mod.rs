@@ -0,0 +1,234 @@
+//! Archival bundles of everything stored for a slot, signed by the server so a consumer holding
+//! the server's public key can tell the bundle wasn't tampered with after it was exported.
+
+use crate::attestations::{
+    compute_domain, AggregatePriceIntervalEntry, DomainType, HashFunction, PriceIntervalEntry,
+    PriceValueEntry,
+};
+use crate::db::DbPool;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use bls::{Hash256, SecretKey};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SlotBundle {
+    pub slot_number: i64,
+    pub value_attestations: Vec<PriceValueEntry>,
+    pub interval_attestations: Vec<PriceIntervalEntry>,
+    pub aggregates: Vec<AggregatePriceIntervalEntry>,
+}
+
+impl SlotBundle {
+    /// Sha3 hash of the JSON encoding of the bundle mixed with the bundle domain, which is what
+    /// the server signs.
+    pub fn digest(&self) -> Hash256 {
+        let bytes = serde_json::to_vec(self).expect("expect bundle to serialize");
+        let hash_function = HashFunction::Sha3_256;
+        let bundle_root = hash_function.hash(&[&bytes]);
+        let domain = compute_domain(DomainType::Bundle, None, hash_function);
+        hash_function.hash(&[bundle_root.as_bytes(), domain.as_bytes()])
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SignedSlotBundle {
+    pub bundle: SlotBundle,
+    /// Hex encoded digest of the bundle, see `SlotBundle::digest`.
+    pub digest: String,
+    pub server_public_key: String,
+    /// Signature of the server key over the digest.
+    pub signature: String,
+}
+
+/// Parses the hex encoded signing key of the server, panicking on an invalid key so a
+/// misconfigured server doesn't start.
+pub fn parse_bundle_signing_key(hex_key: &str) -> SecretKey {
+    let bytes = hex::decode(hex_key.strip_prefix("0x").unwrap_or(hex_key))
+        .expect("expect bundle signing key to be hex encoded");
+    SecretKey::deserialize(&bytes).expect("expect bundle signing key to be a valid secret key")
+}
+
+async fn get_slot_bundle(db_pool: &DbPool, slot_number: i64) -> sqlx::Result<SlotBundle> {
+    let value_attestations = sqlx::query_as(
+        "
+        SELECT
+            validator_public_key,
+            asset,
+            value,
+            slot_number,
+            signature,
+            signing_preimage,
+            price_timestamp,
+            (EXTRACT(EPOCH FROM received_at) * 1000)::BIGINT AS received_at
+        FROM
+            price_value_attestations
+        WHERE
+            slot_number = $1
+        ORDER BY
+            validator_public_key,
+            asset;
+        ",
+    )
+    .bind(slot_number)
+    .fetch_all(db_pool)
+    .await?;
+    let interval_attestations = sqlx::query_as(
+        "
+        SELECT
+            validator_public_key,
+            asset,
+            value,
+            slot_number,
+            signature,
+            interval_size,
+            aggregate_value,
+            signing_preimage,
+            (EXTRACT(EPOCH FROM received_at) * 1000)::BIGINT AS received_at
+        FROM
+            price_interval_attestations
+        WHERE
+            slot_number = $1
+        ORDER BY
+            validator_public_key,
+            asset,
+            interval_size,
+            value;
+        ",
+    )
+    .bind(slot_number)
+    .fetch_all(db_pool)
+    .await?;
+    let aggregates = sqlx::query_as(
+        "
+        SELECT
+            asset,
+            value,
+            slot_number,
+            aggregate_signature,
+            aggregate_public_key,
+            public_keys,
+            interval_size,
+            num_validators,
+            num_operators
+        FROM
+            aggregate_interval_attestations
+        WHERE
+            slot_number = $1
+        ORDER BY
+            asset,
+            interval_size,
+            value;
+        ",
+    )
+    .bind(slot_number)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(SlotBundle {
+        slot_number,
+        value_attestations,
+        interval_attestations,
+        aggregates,
+    })
+}
+
+/// All attestations and aggregates of the slot, signed by the server. Responds with 501 if no
+/// signing key is configured and 404 if nothing was stored for the slot.
+pub async fn get_signed_slot_bundle(
+    Path(slot_number): Path<i64>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<SignedSlotBundle>, StatusCode> {
+    let signing_key = state
+        .bundle_signing_key
+        .as_ref()
+        .ok_or(StatusCode::NOT_IMPLEMENTED)?;
+    let bundle = get_slot_bundle(&state.db_pool, slot_number)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting slot bundle: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if bundle.value_attestations.is_empty()
+        && bundle.interval_attestations.is_empty()
+        && bundle.aggregates.is_empty()
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let digest = bundle.digest();
+    Ok(Json(SignedSlotBundle {
+        digest: format!("0x{}", hex::encode(digest.as_bytes())),
+        server_public_key: signing_key.public_key().to_string(),
+        signature: signing_key.sign(digest).to_string(),
+        bundle,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_utils::{get_test_message, TestApp, TEST_MESSAGE_SLOT};
+    use bls::Signature;
+
+    #[sqlx::test]
+    async fn signs_verifiable_slot_bundles(db_pool: DbPool) {
+        let signing_key = SecretKey::random();
+        let config = Config {
+            bundle_signing_key: Some(hex::encode(signing_key.serialize())),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(3);
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+
+        let response = test_app
+            .get_expect(&format!("/bundle/{TEST_MESSAGE_SLOT}"), StatusCode::OK)
+            .await;
+        let signed_bundle: SignedSlotBundle = serde_json::from_slice(&response).unwrap();
+        assert_eq!(signed_bundle.bundle.value_attestations.len(), 1);
+        assert_eq!(signed_bundle.bundle.interval_attestations.len(), 3);
+        assert_eq!(signed_bundle.bundle.aggregates.len(), 3);
+        assert_eq!(
+            signed_bundle.server_public_key,
+            signing_key.public_key().to_string()
+        );
+
+        let signature: Signature = signed_bundle.signature.parse().unwrap();
+        let digest = signed_bundle.bundle.digest();
+        assert!(signature.verify(&signing_key.public_key(), digest));
+
+        // Tampering with the bundle invalidates the signature
+        let mut tampered_bundle = signed_bundle.bundle;
+        tampered_bundle.value_attestations[0].value += 1;
+        assert!(!signature.verify(&signing_key.public_key(), tampered_bundle.digest()));
+
+        test_app
+            .get_expect(
+                &format!("/bundle/{}", TEST_MESSAGE_SLOT - 1),
+                StatusCode::NOT_FOUND,
+            )
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn requires_a_signing_key_for_bundles(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        test_app
+            .post_oracle_message(&get_test_message(), StatusCode::OK)
+            .await;
+        test_app
+            .get_expect(
+                &format!("/bundle/{TEST_MESSAGE_SLOT}"),
+                StatusCode::NOT_IMPLEMENTED,
+            )
+            .await;
+    }
+}
@@ -0,0 +1,91 @@
+//! Public keys and signatures are stored as hex strings and looked up by string equality, so
+//! everything going into or looked up in the database is encoded the one way defined here:
+//! lowercase hex with a `0x` prefix.
+
+use bls::{PublicKey, Signature};
+use serde::{de::Error as _, Deserialize, Deserializer};
+
+/// The stored encoding of a public key.
+pub fn encode_public_key(public_key: &PublicKey) -> String {
+    format!("0x{}", hex::encode(public_key.serialize()))
+}
+
+/// The stored encoding of a signature.
+pub fn encode_signature(signature: &Signature) -> String {
+    format!("0x{}", hex::encode(signature.serialize()))
+}
+
+/// Brings hex from outside, in any casing and with or without `0x` prefix, into the stored
+/// encoding.
+pub fn normalize_hex(hex_string: &str) -> String {
+    let digits = hex_string
+        .strip_prefix("0x")
+        .or_else(|| hex_string.strip_prefix("0X"))
+        .unwrap_or(hex_string);
+    format!("0x{}", digits.to_ascii_lowercase())
+}
+
+/// A public key in a path or query, accepted in any casing and with or without `0x` prefix.
+#[derive(Debug)]
+pub struct PublicKeyParam(pub PublicKey);
+
+impl<'de> Deserialize<'de> for PublicKeyParam {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex_string = String::deserialize(deserializer)?;
+        normalize_hex(&hex_string)
+            .parse()
+            .map(PublicKeyParam)
+            .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::DbPool;
+    use crate::test_utils::{value_message, TestApp, TEST_MESSAGE_SLOT};
+    use crate::validators::ValidatorStreak;
+    use axum::http::StatusCode;
+    use bls::SecretKey;
+
+    #[test]
+    fn normalizes_hex_of_any_format() {
+        let public_key = SecretKey::random().public_key();
+        let stored = encode_public_key(&public_key);
+        assert_eq!(stored, public_key.to_string());
+        let digits = stored.strip_prefix("0x").unwrap();
+        for hex_string in [
+            stored.clone(),
+            digits.to_string(),
+            digits.to_uppercase(),
+            format!("0X{}", digits.to_uppercase()),
+        ] {
+            assert_eq!(normalize_hex(&hex_string), stored);
+            let PublicKeyParam(parsed) = serde_json::from_value(hex_string.into()).unwrap();
+            assert_eq!(encode_public_key(&parsed), stored);
+        }
+    }
+
+    #[sqlx::test]
+    async fn looks_up_validators_by_any_key_format(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let private_key = SecretKey::random();
+        let message = value_message(&private_key, TEST_MESSAGE_SLOT, 1811093163);
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
+
+        let stored = encode_public_key(&private_key.public_key());
+        let digits = stored.strip_prefix("0x").unwrap();
+        for public_key in [
+            stored.clone(),
+            digits.to_uppercase(),
+            format!("0x{}", digits.to_uppercase()),
+        ] {
+            let response = test_app
+                .get_expect(&format!("/validators/{public_key}/streak"), StatusCode::OK)
+                .await;
+            let streak: ValidatorStreak = serde_json::from_slice(&response).unwrap();
+            assert_eq!(streak.validator_public_key, stored);
+            assert_eq!(streak.last_slot_number, Some(TEST_MESSAGE_SLOT as i64));
+        }
+    }
+}
@@ -0,0 +1,563 @@
+//! Server configuration, read from the environment once at startup.
+
+use crate::attestations::{DigestConfig, HashFunction, OrphanIntervalPolicy};
+use crate::consensus::ConsensusMethod;
+use crate::env;
+use crate::reconciliation::ReconciliationMode;
+use crate::state::AppState;
+use axum::{
+    extract::State,
+    http::{HeaderName, HeaderValue, Method},
+    Json,
+};
+use bls::PublicKey;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::num::NonZeroU64;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Unix timestamp of the first slot on Ethereum mainnet.
+const MAINNET_GENESIS_TIME: u64 = 1606824023;
+const MAINNET_SECONDS_PER_SLOT: u64 = 12;
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Unix timestamp (in seconds) at which slot 0 started.
+    pub genesis_time: u64,
+    pub seconds_per_slot: u64,
+    /// Maximum distance in slots between the current slot and the slot of an accepted value
+    /// message.
+    pub value_window_slots: u64,
+    /// Number of slots beyond the value window for which validly signed messages are deferred
+    /// rather than rejected, ingesting them once the slot clock catches up.
+    pub defer_window_slots: Option<u64>,
+    /// Maximum distance in slots between the current slot and the slot of an accepted interval
+    /// message.
+    pub interval_window_slots: u64,
+    /// Width of the value band, per interval size, within which interval messages are aggregated
+    /// together.
+    pub interval_value_bands: HashMap<u64, u64>,
+    /// Interval sizes validators may submit interval messages for, so all of them bucket on the
+    /// same grid. Without any, every size is accepted.
+    pub allowed_interval_sizes: HashSet<u64>,
+    /// Minimum number of validators an aggregate needs before it is considered to have reached
+    /// quorum.
+    pub quorum_threshold: Option<i64>,
+    /// Percentage of the validator allowlist an aggregate needs before it is considered to have
+    /// reached quorum, taking precedence over the absolute threshold once an allowlist is set.
+    pub quorum_threshold_percent: Option<u64>,
+    /// Minimum number of validators per asset, taking precedence over the other thresholds for
+    /// the assets it lists.
+    pub asset_quorum_thresholds: HashMap<String, i64>,
+    /// Age in slots after which the latest price is flagged as stale.
+    pub max_staleness_slots: u64,
+    pub admin_tokens: Vec<AdminToken>,
+    /// BLS keys admins can sign requests with instead of using a bearer token.
+    pub admin_keys: Vec<AdminKey>,
+    /// API keys accepted as bearer token by the submission endpoints. Without any, anyone can
+    /// submit.
+    pub submission_api_keys: Vec<String>,
+    /// Seconds for which the response to a submission with an idempotency key is replayed to
+    /// retries with the same key.
+    pub idempotency_key_ttl_seconds: u64,
+    /// Number of out-of-bounds messages after which a validator is quarantined.
+    pub quarantine_out_of_bounds_threshold: Option<i64>,
+    /// Number of equivocating messages after which a validator is quarantined.
+    pub quarantine_equivocation_threshold: Option<i64>,
+    /// Reject oracle messages that don't include any interval inclusion messages.
+    pub require_interval_messages: bool,
+    /// Reject oracle messages with interval messages for another slot than the value message, or
+    /// whose interval doesn't contain the attested value.
+    pub reject_inconsistent_intervals: bool,
+    /// What to do with interval messages for a slot the validator has no value message for.
+    pub orphan_interval_policy: OrphanIntervalPolicy,
+    /// Reject value messages arriving after interval messages of the validator for the same slot,
+    /// as the interval messages of a slot are expected no earlier than its value message.
+    pub enforce_arrival_order: bool,
+    /// Reject oracle messages with unknown top-level fields, to catch client bugs.
+    pub reject_unknown_fields: bool,
+    /// Network mixed into the digest of signed messages together with the oracle's domain, so
+    /// signatures can't be replayed across networks. Without one messages are signed undomained.
+    pub signing_network_id: Option<u64>,
+    /// Hash function signed messages are digested with.
+    pub hash_function: HashFunction,
+    /// Reject oracle messages whose keys or signatures are encoded for the min-sig BLS variant
+    /// with an error naming the field, rather than failing deserialization.
+    pub enforce_signature_scheme: bool,
+    /// Verify the interval message signatures of an oracle message all at once against a random
+    /// linear combination, rather than one by one.
+    pub batch_verify_intervals: bool,
+    /// Verify the signatures of all messages of a batch submission at once against a random
+    /// linear combination, before storing any.
+    pub batch_verify_submissions: bool,
+    /// Maximum number of distinct aggregates per slot, beyond which new values are no longer
+    /// aggregated.
+    pub max_aggregates_per_slot: Option<i64>,
+    /// Maximum number of validators in a single aggregate, defaulting to the allowlist size when
+    /// the allowlist is set. Signatures that would push an aggregate past it are not aggregated.
+    pub max_validators_per_aggregate: Option<i64>,
+    /// Maximum size in bytes of a request body, larger requests are rejected before they are
+    /// read in full.
+    pub max_body_bytes: usize,
+    /// Maximum number of interval messages in a single oracle message.
+    pub max_interval_messages: Option<usize>,
+    /// Number of recent value attestations remembered in a bloom filter, skipping the DB lookup
+    /// for earlier attestations of the same validator and slot when definitely not seen.
+    pub dedup_bloom_capacity: Option<usize>,
+    /// Number of slots before the current slot covered by the duplicate check. Older slots are
+    /// assumed finalized and skip the lookup, a resubmission is then caught by the primary key and
+    /// only looked up to tell a duplicate from an equivocation.
+    pub dedup_window_slots: Option<u64>,
+    /// Number of signature verification results cached, sparing retries of a message the
+    /// verification.
+    pub signature_cache_capacity: Option<usize>,
+    /// Number of messages a validator may submit per slot, with unused budget carrying over for
+    /// at most one slot.
+    pub rate_limit_messages_per_slot: Option<u64>,
+    /// Max number of clients subscribed to the attestation stream at the same time.
+    pub max_stream_connections: Option<usize>,
+    /// URL notified whenever an aggregate reaches quorum.
+    pub webhook_url: Option<String>,
+    /// Number of failed deliveries after which a notification is dead-lettered.
+    pub webhook_max_attempts: i32,
+    /// Alert when the quorum-reaching value with the most validators for a slot and interval
+    /// size changes after quorum was first reached.
+    pub detect_value_flips: bool,
+    /// Delay before the first retry of a failed notification, doubling with every attempt.
+    pub webhook_retry_delay_seconds: u64,
+    /// Store the SSZ bytes each attestation's signature was computed over, so auditors can
+    /// verify signatures without reconstructing the messages.
+    pub store_signing_preimages: bool,
+    /// Operator running each validator, keyed by validator public key. Validators run by the same
+    /// operator count only once towards operator diversity.
+    pub validator_operators: HashMap<String, String>,
+    /// Stake weight of each validator, keyed by validator public key, for weighted consensus.
+    /// Validators not listed weigh one.
+    pub validator_weights: HashMap<String, i64>,
+    /// Validators expected to submit every slot.
+    pub validator_allowlist: Vec<PublicKey>,
+    /// File listing the public keys of the validators allowed to submit, one per line. Without
+    /// one any validator may submit.
+    pub registered_validators_file: Option<String>,
+    /// Minimum number of distinct operators an aggregate needs, in addition to the quorum
+    /// threshold, before it is considered to have reached quorum.
+    pub quorum_min_operators: Option<i64>,
+    /// Default method combining the value attestations of a slot into a consensus value.
+    pub consensus_method: ConsensusMethod,
+    /// Percentage of values dropped at both ends by the trimmed mean consensus method.
+    pub consensus_trim_percent: u64,
+    /// Maximum distance from the slot median, in basis points of the median, for a value to count
+    /// as agreeing.
+    pub agreement_tolerance_basis_points: u64,
+    /// Whether to periodically check aggregates against their stored attestations, and whether
+    /// to repair the ones that drifted.
+    pub reconciliation_mode: ReconciliationMode,
+    pub reconciliation_interval_seconds: u64,
+    /// Number of most recent slots whose aggregates are recomputed from their stored attestations
+    /// on startup, fixing aggregates left stale by a crash.
+    pub startup_recompute_slots: Option<u64>,
+    /// Age in slots after which a slot is finalized, snapshotting its best quorum aggregate.
+    pub finalize_after_slots: Option<u64>,
+    /// Minimum number of validators an aggregate needs to be flagged final and returned by
+    /// `/aggregate/final`.
+    pub finality_threshold: i64,
+    /// Number of most recent slots whose attestations are retained, older ones may be pruned.
+    pub retention_slots: Option<u64>,
+    /// Seconds between passes deleting the attestations outside the retention window, pruning is
+    /// off if unset.
+    pub prune_interval_seconds: Option<u64>,
+    /// Whether pruning also deletes aggregates, which are kept by default.
+    pub prune_aggregates: bool,
+    /// Hex encoded BLS secret key the server signs slot bundles with.
+    pub bundle_signing_key: Option<String>,
+    /// Gas of verifying an aggregate on-chain regardless of its size, the pairing check mostly.
+    pub cost_estimate_base_gas: u64,
+    /// Gas each validator of an aggregate adds on-chain, for their public key's calldata and
+    /// aggregation.
+    pub cost_estimate_gas_per_validator: u64,
+    /// Origins browsers may call the API from. Without any, no CORS headers are sent and browsers
+    /// keep blocking cross-origin requests.
+    pub cors_allowed_origins: Vec<HeaderValue>,
+    pub cors_allowed_methods: Vec<Method>,
+    /// Request headers cross-origin requests may set, beyond those CORS always allows.
+    pub cors_allowed_headers: Vec<HeaderName>,
+    /// Maximum number of connections the DB pool opens.
+    pub db_max_connections: u32,
+    /// Seconds a request waits for a free DB connection before it is rejected as unavailable.
+    pub db_acquire_timeout_seconds: u64,
+    /// Milliseconds a statement waits for a lock held by a concurrent write before it fails,
+    /// rather than waiting indefinitely.
+    pub db_lock_timeout_ms: Option<u64>,
+    /// Number of times a write failing on contention is retried before giving up.
+    pub db_max_retries: u32,
+    /// Delay before the first retry of a write failing on contention, doubling with every retry.
+    pub db_retry_base_delay_ms: u64,
+}
+
+/// Bearer token granting access to the admin endpoints, with an id identifying its holder.
+#[derive(Clone, Debug)]
+pub struct AdminToken {
+    pub id: String,
+    pub token: String,
+}
+
+/// BLS public key granting access to the admin endpoints, with an id identifying its holder.
+#[derive(Clone, Debug)]
+pub struct AdminKey {
+    pub id: String,
+    pub public_key: PublicKey,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            genesis_time: MAINNET_GENESIS_TIME,
+            seconds_per_slot: MAINNET_SECONDS_PER_SLOT,
+            value_window_slots: 5,
+            defer_window_slots: None,
+            interval_window_slots: 5,
+            interval_value_bands: HashMap::new(),
+            allowed_interval_sizes: HashSet::new(),
+            quorum_threshold: None,
+            quorum_threshold_percent: None,
+            asset_quorum_thresholds: HashMap::new(),
+            max_staleness_slots: 10,
+            admin_tokens: Vec::new(),
+            admin_keys: Vec::new(),
+            submission_api_keys: Vec::new(),
+            idempotency_key_ttl_seconds: 24 * 60 * 60,
+            quarantine_out_of_bounds_threshold: None,
+            quarantine_equivocation_threshold: None,
+            require_interval_messages: false,
+            reject_inconsistent_intervals: false,
+            orphan_interval_policy: OrphanIntervalPolicy::Accept,
+            enforce_arrival_order: false,
+            reject_unknown_fields: false,
+            signing_network_id: None,
+            hash_function: HashFunction::default(),
+            enforce_signature_scheme: false,
+            batch_verify_intervals: false,
+            batch_verify_submissions: false,
+            max_aggregates_per_slot: None,
+            max_validators_per_aggregate: None,
+            max_body_bytes: 2 * 1024 * 1024,
+            max_interval_messages: None,
+            dedup_bloom_capacity: None,
+            dedup_window_slots: None,
+            signature_cache_capacity: None,
+            rate_limit_messages_per_slot: None,
+            max_stream_connections: None,
+            webhook_url: None,
+            webhook_max_attempts: 5,
+            detect_value_flips: false,
+            webhook_retry_delay_seconds: 10,
+            store_signing_preimages: false,
+            validator_operators: HashMap::new(),
+            validator_weights: HashMap::new(),
+            validator_allowlist: Vec::new(),
+            registered_validators_file: None,
+            quorum_min_operators: None,
+            consensus_method: ConsensusMethod::Median,
+            consensus_trim_percent: 10,
+            agreement_tolerance_basis_points: 50,
+            reconciliation_mode: ReconciliationMode::Off,
+            reconciliation_interval_seconds: 60,
+            startup_recompute_slots: None,
+            finalize_after_slots: None,
+            finality_threshold: 1,
+            retention_slots: None,
+            prune_interval_seconds: None,
+            prune_aggregates: false,
+            bundle_signing_key: None,
+            cost_estimate_base_gas: 150_000,
+            cost_estimate_gas_per_validator: 1_500,
+            cors_allowed_origins: Vec::new(),
+            cors_allowed_methods: vec![Method::GET],
+            cors_allowed_headers: Vec::new(),
+            db_max_connections: 10,
+            db_acquire_timeout_seconds: 30,
+            db_lock_timeout_ms: None,
+            db_max_retries: 3,
+            db_retry_base_delay_ms: 50,
+        }
+    }
+}
+
+impl Config {
+    pub fn digest_config(&self) -> DigestConfig {
+        DigestConfig {
+            network_id: self.signing_network_id,
+            hash_function: self.hash_function,
+            asset_hash: None,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let default = Config::default();
+        Config {
+            genesis_time: get_env_var_or("GENESIS_TIME", default.genesis_time),
+            // Slots are counted by dividing by it.
+            seconds_per_slot: get_env_var_parsed("SECONDS_PER_SLOT")
+                .map_or(default.seconds_per_slot, NonZeroU64::get),
+            value_window_slots: get_env_var_or("VALUE_WINDOW_SLOTS", default.value_window_slots),
+            defer_window_slots: get_env_var_parsed("DEFER_WINDOW_SLOTS"),
+            interval_window_slots: get_env_var_or(
+                "INTERVAL_WINDOW_SLOTS",
+                default.interval_window_slots,
+            ),
+            interval_value_bands: get_env_var_pairs("INTERVAL_VALUE_BANDS")
+                .map(|pairs| pairs.into_iter().collect())
+                .unwrap_or(default.interval_value_bands),
+            allowed_interval_sizes: get_env_var_list("ALLOWED_INTERVAL_SIZES")
+                .map(|sizes| sizes.into_iter().collect())
+                .unwrap_or(default.allowed_interval_sizes),
+            quorum_threshold: get_env_var_parsed("QUORUM_THRESHOLD"),
+            quorum_threshold_percent: get_env_var_parsed("QUORUM_THRESHOLD_PERCENT"),
+            asset_quorum_thresholds: get_env_var_pairs("ASSET_QUORUM_THRESHOLDS")
+                .map(|pairs| pairs.into_iter().collect())
+                .unwrap_or(default.asset_quorum_thresholds),
+            max_staleness_slots: get_env_var_or("MAX_STALENESS_SLOTS", default.max_staleness_slots),
+            admin_tokens: get_env_var_pairs("ADMIN_TOKENS")
+                .map(|pairs| {
+                    pairs
+                        .into_iter()
+                        .map(|(id, token)| AdminToken { id, token })
+                        .collect()
+                })
+                .unwrap_or(default.admin_tokens),
+            admin_keys: get_env_var_pairs("ADMIN_KEYS")
+                .map(|pairs| {
+                    pairs
+                        .into_iter()
+                        .map(|(id, public_key)| AdminKey { id, public_key })
+                        .collect()
+                })
+                .unwrap_or(default.admin_keys),
+            submission_api_keys: get_env_var_list("SUBMISSION_API_KEYS")
+                .unwrap_or(default.submission_api_keys),
+            idempotency_key_ttl_seconds: get_env_var_or(
+                "IDEMPOTENCY_KEY_TTL_SECONDS",
+                default.idempotency_key_ttl_seconds,
+            ),
+            quarantine_out_of_bounds_threshold: get_env_var_parsed(
+                "QUARANTINE_OUT_OF_BOUNDS_THRESHOLD",
+            ),
+            quarantine_equivocation_threshold: get_env_var_parsed(
+                "QUARANTINE_EQUIVOCATION_THRESHOLD",
+            ),
+            require_interval_messages: get_env_var_or(
+                "REQUIRE_INTERVAL_MESSAGES",
+                default.require_interval_messages,
+            ),
+            reject_inconsistent_intervals: get_env_var_or(
+                "REJECT_INCONSISTENT_INTERVALS",
+                default.reject_inconsistent_intervals,
+            ),
+            orphan_interval_policy: get_env_var_or(
+                "ORPHAN_INTERVAL_POLICY",
+                default.orphan_interval_policy,
+            ),
+            enforce_arrival_order: get_env_var_or(
+                "ENFORCE_ARRIVAL_ORDER",
+                default.enforce_arrival_order,
+            ),
+            reject_unknown_fields: get_env_var_or(
+                "REJECT_UNKNOWN_FIELDS",
+                default.reject_unknown_fields,
+            ),
+            signing_network_id: get_env_var_parsed("SIGNING_NETWORK_ID"),
+            hash_function: get_env_var_or("HASH_FUNCTION", default.hash_function),
+            enforce_signature_scheme: get_env_var_or(
+                "ENFORCE_SIGNATURE_SCHEME",
+                default.enforce_signature_scheme,
+            ),
+            batch_verify_intervals: get_env_var_or(
+                "BATCH_VERIFY_INTERVALS",
+                default.batch_verify_intervals,
+            ),
+            batch_verify_submissions: get_env_var_or(
+                "BATCH_VERIFY_SUBMISSIONS",
+                default.batch_verify_submissions,
+            ),
+            max_aggregates_per_slot: get_env_var_parsed("MAX_AGGREGATES_PER_SLOT"),
+            max_validators_per_aggregate: get_env_var_parsed("MAX_VALIDATORS_PER_AGGREGATE"),
+            max_body_bytes: get_env_var_or("MAX_BODY_BYTES", default.max_body_bytes),
+            max_interval_messages: get_env_var_parsed("MAX_INTERVAL_MESSAGES"),
+            dedup_bloom_capacity: get_env_var_parsed("DEDUP_BLOOM_CAPACITY"),
+            dedup_window_slots: get_env_var_parsed("DEDUP_WINDOW_SLOTS"),
+            signature_cache_capacity: get_env_var_parsed("SIGNATURE_CACHE_CAPACITY"),
+            rate_limit_messages_per_slot: get_env_var_parsed("RATE_LIMIT_MESSAGES_PER_SLOT"),
+            max_stream_connections: get_env_var_parsed("MAX_STREAM_CONNECTIONS"),
+            webhook_url: env::get_env_var("WEBHOOK_URL"),
+            webhook_max_attempts: get_env_var_or(
+                "WEBHOOK_MAX_ATTEMPTS",
+                default.webhook_max_attempts,
+            ),
+            detect_value_flips: get_env_var_or("DETECT_VALUE_FLIPS", default.detect_value_flips),
+            webhook_retry_delay_seconds: get_env_var_or(
+                "WEBHOOK_RETRY_DELAY_SECONDS",
+                default.webhook_retry_delay_seconds,
+            ),
+            store_signing_preimages: get_env_var_or(
+                "STORE_SIGNING_PREIMAGES",
+                default.store_signing_preimages,
+            ),
+            validator_operators: get_env_var_pairs("VALIDATOR_OPERATORS")
+                .map(|pairs| pairs.into_iter().collect())
+                .unwrap_or(default.validator_operators),
+            validator_weights: get_env_var_pairs("VALIDATOR_WEIGHTS")
+                .map(|pairs| pairs.into_iter().collect())
+                .unwrap_or(default.validator_weights),
+            validator_allowlist: get_env_var_list("VALIDATOR_ALLOWLIST")
+                .unwrap_or(default.validator_allowlist),
+            registered_validators_file: env::get_env_var("REGISTERED_VALIDATORS_FILE"),
+            quorum_min_operators: get_env_var_parsed("QUORUM_MIN_OPERATORS"),
+            consensus_method: get_env_var_or("CONSENSUS_METHOD", default.consensus_method),
+            consensus_trim_percent: get_env_var_or(
+                "CONSENSUS_TRIM_PERCENT",
+                default.consensus_trim_percent,
+            ),
+            agreement_tolerance_basis_points: get_env_var_or(
+                "AGREEMENT_TOLERANCE_BASIS_POINTS",
+                default.agreement_tolerance_basis_points,
+            ),
+            reconciliation_mode: get_env_var_or("RECONCILIATION_MODE", default.reconciliation_mode),
+            reconciliation_interval_seconds: get_env_var_or(
+                "RECONCILIATION_INTERVAL_SECONDS",
+                default.reconciliation_interval_seconds,
+            ),
+            startup_recompute_slots: get_env_var_parsed("STARTUP_RECOMPUTE_SLOTS"),
+            finalize_after_slots: get_env_var_parsed("FINALIZE_AFTER_SLOTS"),
+            finality_threshold: get_env_var_or("FINALITY_THRESHOLD", default.finality_threshold),
+            retention_slots: get_env_var_parsed("RETENTION_SLOTS"),
+            // A timer can't tick every 0 seconds.
+            prune_interval_seconds: get_env_var_parsed("PRUNE_INTERVAL_SECONDS")
+                .map(NonZeroU64::get),
+            prune_aggregates: get_env_var_or("PRUNE_AGGREGATES", default.prune_aggregates),
+            bundle_signing_key: env::get_env_var("BUNDLE_SIGNING_KEY"),
+            cost_estimate_base_gas: get_env_var_or(
+                "COST_ESTIMATE_BASE_GAS",
+                default.cost_estimate_base_gas,
+            ),
+            cost_estimate_gas_per_validator: get_env_var_or(
+                "COST_ESTIMATE_GAS_PER_VALIDATOR",
+                default.cost_estimate_gas_per_validator,
+            ),
+            cors_allowed_origins: get_env_var_list("CORS_ALLOWED_ORIGINS")
+                .unwrap_or(default.cors_allowed_origins),
+            cors_allowed_methods: get_env_var_list("CORS_ALLOWED_METHODS")
+                .unwrap_or(default.cors_allowed_methods),
+            cors_allowed_headers: get_env_var_list("CORS_ALLOWED_HEADERS")
+                .unwrap_or(default.cors_allowed_headers),
+            db_max_connections: get_env_var_or("DB_MAX_CONNECTIONS", default.db_max_connections),
+            db_acquire_timeout_seconds: get_env_var_or(
+                "DB_ACQUIRE_TIMEOUT_SECONDS",
+                default.db_acquire_timeout_seconds,
+            ),
+            db_lock_timeout_ms: get_env_var_parsed("DB_LOCK_TIMEOUT_MS"),
+            db_max_retries: get_env_var_or("DB_MAX_RETRIES", default.db_max_retries),
+            db_retry_base_delay_ms: get_env_var_or(
+                "DB_RETRY_BASE_DELAY_MS",
+                default.db_retry_base_delay_ms,
+            ),
+        }
+    }
+}
+
+/// Parse an environment variable if present, panicking if it can't be parsed.
+/// The parts of the configuration clients need to agree on with the server, such as how to sign
+/// their messages.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PublicConfig {
+    pub genesis_time: u64,
+    pub seconds_per_slot: u64,
+    pub value_window_slots: u64,
+    pub interval_window_slots: u64,
+    pub signing_network_id: Option<u64>,
+    pub hash_function: HashFunction,
+    pub quorum_threshold: Option<i64>,
+    pub quorum_threshold_percent: Option<u64>,
+}
+
+pub async fn get_config(State(state): State<Arc<AppState>>) -> Json<PublicConfig> {
+    let config = &state.config;
+    Json(PublicConfig {
+        genesis_time: state.slot_clock.genesis_time(),
+        seconds_per_slot: config.seconds_per_slot,
+        value_window_slots: config.value_window_slots,
+        interval_window_slots: config.interval_window_slots,
+        signing_network_id: config.signing_network_id,
+        hash_function: config.hash_function,
+        quorum_threshold: config.quorum_threshold,
+        quorum_threshold_percent: config.quorum_threshold_percent,
+    })
+}
+
+fn get_env_var_parsed<T>(key: &str) -> Option<T>
+where
+    T: FromStr,
+    T::Err: Debug,
+{
+    env::get_env_var(key).map(|var| {
+        var.parse()
+            .unwrap_or_else(|err| panic!("{key} should be a valid value: {err:?}"))
+    })
+}
+
+fn get_env_var_or<T>(key: &str, default: T) -> T
+where
+    T: FromStr,
+    T::Err: Debug,
+{
+    get_env_var_parsed(key).unwrap_or(default)
+}
+
+/// Parse an environment variable formatted as comma separated values.
+fn get_env_var_list<T>(key: &str) -> Option<Vec<T>>
+where
+    T: FromStr,
+    T::Err: Debug,
+{
+    env::get_env_var(key).map(|var| {
+        var.split(',')
+            .filter(|item| !item.is_empty())
+            .map(|item| {
+                item.trim()
+                    .parse()
+                    .unwrap_or_else(|err| panic!("{key} should have valid values: {err:?}"))
+            })
+            .collect()
+    })
+}
+
+/// Parse an environment variable formatted as comma separated `key:value` pairs, e.g. `20:5,50:10`.
+fn get_env_var_pairs<K, V>(key: &str) -> Option<Vec<(K, V)>>
+where
+    K: FromStr,
+    K::Err: Debug,
+    V: FromStr,
+    V::Err: Debug,
+{
+    env::get_env_var(key).map(|var| {
+        var.split(',')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (pair_key, pair_value) = pair
+                    .split_once(':')
+                    .unwrap_or_else(|| panic!("{key} entries should be formatted as key:value"));
+                (
+                    pair_key
+                        .trim()
+                        .parse()
+                        .unwrap_or_else(|err| panic!("{key} should have valid keys: {err:?}")),
+                    pair_value
+                        .trim()
+                        .parse()
+                        .unwrap_or_else(|err| panic!("{key} should have valid values: {err:?}")),
+                )
+            })
+            .collect()
+    })
+}
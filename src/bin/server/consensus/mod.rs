@@ -0,0 +1,865 @@
+//! Cross-checks the two attestation types against each other: the consensus value of the value
+//! attestations should fall within the best interval aggregate of the same slot.
+
+use crate::attestations::{AggregatePriceIntervalEntry, PriceIntervalEntry, DEFAULT_ASSET};
+use crate::db::DbPool;
+use crate::quorum::get_quorum_threshold;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Value messages carry the price with six decimals, interval messages in cents.
+const PRICE_VALUE_PER_INTERVAL_VALUE: i64 = 10_000;
+/// Interval sizes are expressed in basis points of the interval value.
+const BASIS_POINTS: i64 = 10_000;
+
+/// How the value attestations of a slot are combined into a single consensus value.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsensusMethod {
+    #[default]
+    Median,
+    /// The most common value, the lowest one of those if there are several.
+    Mode,
+    /// The mean after dropping the configured percentage of values at both ends.
+    TrimmedMean,
+}
+
+impl FromStr for ConsensusMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "median" => Ok(ConsensusMethod::Median),
+            "mode" => Ok(ConsensusMethod::Mode),
+            "trimmed_mean" => Ok(ConsensusMethod::TrimmedMean),
+            _ => Err(format!("unknown consensus method {s}")),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConsensusQuery {
+    /// Overrides the configured consensus method.
+    pub method: Option<ConsensusMethod>,
+}
+
+impl ConsensusQuery {
+    pub fn method(&self, state: &AppState) -> ConsensusMethod {
+        self.method.unwrap_or(state.config.consensus_method)
+    }
+}
+
+/// Combines ascending values into a single value using the given method.
+pub fn get_consensus_value(
+    values: &[i64],
+    method: ConsensusMethod,
+    trim_percent: u64,
+) -> Option<i64> {
+    let median = values.get(values.len() / 2).copied();
+    match method {
+        ConsensusMethod::Median => median,
+        ConsensusMethod::Mode => values
+            .iter()
+            .dedup_with_count()
+            .max_by(|(count_a, value_a), (count_b, value_b)| {
+                count_a.cmp(count_b).then(value_b.cmp(value_a))
+            })
+            .map(|(_, value)| *value),
+        ConsensusMethod::TrimmedMean => {
+            let num_trimmed = values.len() * trim_percent as usize / 100;
+            let trimmed_values = &values[num_trimmed.min(values.len())..]
+                [..values.len().saturating_sub(2 * num_trimmed)];
+            if trimmed_values.is_empty() {
+                return median;
+            }
+            let sum: i128 = trimmed_values.iter().map(|value| *value as i128).sum();
+            Some((sum / trimmed_values.len() as i128) as i64)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConsensusValue {
+    pub slot_number: i64,
+    pub method: ConsensusMethod,
+    pub consensus_value: Option<i64>,
+    pub num_value_attestations: usize,
+    /// Interval aggregate with the most validators among those reaching quorum.
+    pub interval_aggregate: Option<AggregatePriceIntervalEntry>,
+    /// Whether the consensus value falls within the interval of the aggregate.
+    pub agree: bool,
+}
+
+pub async fn get_consensus(
+    Path(slot_number): Path<i64>,
+    Query(query): Query<ConsensusQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ConsensusValue>, StatusCode> {
+    let db_pool = &state.db_pool;
+    let values = get_values(db_pool, slot_number).await.map_err(|e| {
+        tracing::error!("Error getting value attestations: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    // Consensus doesn't distinguish assets yet, the default asset's threshold applies.
+    let quorum_threshold = get_quorum_threshold(&state, DEFAULT_ASSET, slot_number).unwrap_or(1);
+    let min_operators = state.config.quorum_min_operators.unwrap_or(0);
+    let interval_aggregate =
+        get_best_quorum_aggregate(db_pool, slot_number, quorum_threshold, min_operators)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting quorum aggregate: {:?}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+    if values.is_empty() && interval_aggregate.is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let method = query.method(&state);
+    let consensus_value = get_consensus_value(&values, method, state.config.consensus_trim_percent);
+    let agree = match (consensus_value, &interval_aggregate) {
+        (Some(consensus_value), Some(aggregate)) => interval_contains(aggregate, consensus_value),
+        _ => false,
+    };
+    Ok(Json(ConsensusValue {
+        slot_number,
+        method,
+        consensus_value,
+        num_value_attestations: values.len(),
+        interval_aggregate,
+        agree,
+    }))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WeightedConsensus {
+    pub slot_number: i64,
+    pub aggregate: AggregatePriceIntervalEntry,
+    /// Summed stake weight of the validators in the aggregate.
+    pub total_weight: i64,
+}
+
+/// The aggregate of the slot backed by the most stake rather than the most validators, the
+/// tightest interval of those if there are several, and the median by value of those.
+pub async fn get_weighted_consensus(
+    Path(slot_number): Path<i64>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<WeightedConsensus>, StatusCode> {
+    let entries: Vec<(i64, AggregatePriceIntervalEntry)> = sqlx::query!(
+        "
+        SELECT
+            asset,
+            value,
+            slot_number,
+            aggregate_signature,
+            aggregate_public_key,
+            public_keys,
+            interval_size,
+            num_validators,
+            num_operators,
+            total_weight
+        FROM
+            aggregate_interval_attestations
+        WHERE
+            slot_number = $1
+        AND
+            asset = $2;
+        ",
+        slot_number,
+        DEFAULT_ASSET,
+    )
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error getting aggregates: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .into_iter()
+    .map(|row| {
+        (
+            row.total_weight,
+            AggregatePriceIntervalEntry {
+                asset: row.asset,
+                value: row.value,
+                slot_number: row.slot_number,
+                aggregate_signature: row.aggregate_signature,
+                aggregate_public_key: row.aggregate_public_key,
+                public_keys: row.public_keys,
+                interval_size: row.interval_size,
+                num_validators: row.num_validators,
+                num_operators: row.num_operators,
+                finalized: None,
+            },
+        )
+    })
+    .collect();
+    let best_entries: Vec<(i64, AggregatePriceIntervalEntry)> = entries
+        .into_iter()
+        .max_set_by_key(|(total_weight, entry)| (*total_weight, -entry.interval_size))
+        .into_iter()
+        .sorted_by_key(|(_, entry)| entry.value)
+        .collect();
+    let (total_weight, aggregate) = best_entries
+        .get(best_entries.len() / 2)
+        .cloned()
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(WeightedConsensus {
+        slot_number,
+        aggregate,
+        total_weight,
+    }))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Agreement {
+    pub slot_number: i64,
+    pub median_value: i64,
+    pub tolerance_basis_points: u64,
+    pub num_validators: usize,
+    pub num_agreeing: usize,
+    /// Fraction of validators whose value is within the tolerance of the median.
+    pub agreement: f64,
+}
+
+pub async fn get_agreement(
+    Path(slot_number): Path<i64>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Agreement>, StatusCode> {
+    let values = get_values(&state.db_pool, slot_number).await.map_err(|e| {
+        tracing::error!("Error getting value attestations: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let median_value =
+        get_consensus_value(&values, ConsensusMethod::Median, 0).ok_or(StatusCode::NOT_FOUND)?;
+
+    let tolerance_basis_points = state.config.agreement_tolerance_basis_points;
+    let tolerance =
+        median_value.unsigned_abs() as u128 * tolerance_basis_points as u128 / BASIS_POINTS as u128;
+    let num_agreeing = values
+        .iter()
+        .filter(|value| value.abs_diff(median_value) as u128 <= tolerance)
+        .count();
+    Ok(Json(Agreement {
+        slot_number,
+        median_value,
+        tolerance_basis_points,
+        num_validators: values.len(),
+        num_agreeing,
+        agreement: num_agreeing as f64 / values.len() as f64,
+    }))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MedianClaim {
+    pub median_value: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MedianVerification {
+    pub slot_number: i64,
+    pub claimed_median_value: i64,
+    pub median_value: i64,
+    pub matches: bool,
+}
+
+/// Checks a median computed elsewhere against the median of the slot's value attestations.
+pub async fn post_verify_median(
+    Path(slot_number): Path<i64>,
+    State(state): State<Arc<AppState>>,
+    Json(claim): Json<MedianClaim>,
+) -> Result<Json<MedianVerification>, StatusCode> {
+    let values = get_values(&state.db_pool, slot_number).await.map_err(|e| {
+        tracing::error!("Error getting value attestations: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let median_value =
+        get_consensus_value(&values, ConsensusMethod::Median, 0).ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(MedianVerification {
+        slot_number,
+        claimed_median_value: claim.median_value,
+        median_value,
+        matches: claim.median_value == median_value,
+    }))
+}
+
+pub fn interval_contains(aggregate: &AggregatePriceIntervalEntry, value: i64) -> bool {
+    interval_message_contains(aggregate.value, aggregate.interval_size, value)
+}
+
+/// Lowest and highest price value of the interval of the given interval value and size.
+pub fn interval_bounds(interval_value: i64, interval_size: i64) -> (i64, i64) {
+    let center = interval_value * PRICE_VALUE_PER_INTERVAL_VALUE;
+    let half_width = center * interval_size / BASIS_POINTS;
+    (center - half_width, center + half_width)
+}
+
+/// Whether the interval of the given interval value and size contains the price value.
+pub fn interval_message_contains(interval_value: i64, interval_size: i64, value: i64) -> bool {
+    let (lower, upper) = interval_bounds(interval_value, interval_size);
+    (lower..=upper).contains(&value)
+}
+
+/// Like `interval_message_contains`, but allowing for the price having been rounded to the
+/// interval value, as clients do when picking the intervals to attest to.
+pub fn interval_message_admits(interval_value: i64, interval_size: i64, value: i64) -> bool {
+    let center = interval_value * PRICE_VALUE_PER_INTERVAL_VALUE;
+    let half_width = center * interval_size / BASIS_POINTS + PRICE_VALUE_PER_INTERVAL_VALUE;
+    (center - half_width..=center + half_width).contains(&value)
+}
+
+/// A validator whose value attestation falls outside all of their own interval attestations of
+/// the slot.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InconsistentValidator {
+    pub validator_public_key: String,
+    pub asset: String,
+    pub value: i64,
+    pub num_interval_attestations: usize,
+}
+
+/// Validators contradicting themselves in the slot, which points at a buggy or malicious client.
+/// Validators without interval attestations have nothing to contradict.
+pub async fn get_consistency(
+    Path(slot_number): Path<i64>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<InconsistentValidator>>, StatusCode> {
+    let rows = sqlx::query!(
+        "
+        SELECT
+            value_attestation.validator_public_key,
+            value_attestation.asset,
+            value_attestation.value,
+            interval_attestation.value AS interval_value,
+            interval_attestation.interval_size
+        FROM
+            price_value_attestations value_attestation
+        JOIN
+            price_interval_attestations interval_attestation
+        ON
+            interval_attestation.validator_public_key = value_attestation.validator_public_key
+        AND
+            interval_attestation.asset = value_attestation.asset
+        AND
+            interval_attestation.slot_number = value_attestation.slot_number
+        WHERE
+            value_attestation.slot_number = $1
+        ORDER BY
+            value_attestation.validator_public_key,
+            value_attestation.asset;
+        ",
+        slot_number
+    )
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error getting attestations: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let inconsistent_validators = rows
+        .into_iter()
+        .group_by(|row| {
+            (
+                row.validator_public_key.clone(),
+                row.asset.clone(),
+                row.value,
+            )
+        })
+        .into_iter()
+        .filter_map(|((validator_public_key, asset, value), rows)| {
+            let intervals: Vec<(i64, i64)> = rows
+                .map(|row| (row.interval_value, row.interval_size))
+                .collect();
+            let is_consistent = intervals.iter().any(|(interval_value, interval_size)| {
+                interval_message_contains(*interval_value, *interval_size, value)
+            });
+            (!is_consistent).then_some(InconsistentValidator {
+                validator_public_key,
+                asset,
+                value,
+                num_interval_attestations: intervals.len(),
+            })
+        })
+        .collect();
+    Ok(Json(inconsistent_validators))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IntervalsContainingQuery {
+    pub slot: i64,
+    /// Price value, with six decimals like value messages.
+    pub value: i64,
+}
+
+/// Interval attestations of the slot whose interval contains the target price value, showing
+/// which validators' attestations bracket it.
+pub async fn get_intervals_containing(
+    Query(query): Query<IntervalsContainingQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<PriceIntervalEntry>>, StatusCode> {
+    let entries: Vec<PriceIntervalEntry> = sqlx::query_as(
+        "
+        SELECT
+            validator_public_key,
+            asset,
+            value,
+            slot_number,
+            signature,
+            interval_size,
+            aggregate_value,
+            signing_preimage,
+            (EXTRACT(EPOCH FROM received_at) * 1000)::BIGINT AS received_at
+        FROM
+            price_interval_attestations
+        WHERE
+            slot_number = $1
+        ORDER BY
+            validator_public_key,
+            asset,
+            interval_size,
+            value;
+        ",
+    )
+    .bind(query.slot)
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error getting interval attestations: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let entries = entries
+        .into_iter()
+        .filter(|entry| interval_message_contains(entry.value, entry.interval_size, query.value))
+        .collect();
+    Ok(Json(entries))
+}
+
+/// Values attested to in the slot, in ascending order.
+pub async fn get_values(db_pool: &DbPool, slot_number: i64) -> eyre::Result<Vec<i64>> {
+    let values = sqlx::query!(
+        "
+        SELECT
+            value
+        FROM
+            price_value_attestations
+        WHERE
+            slot_number = $1
+        ORDER BY
+            value;
+        ",
+        slot_number
+    )
+    .fetch_all(db_pool)
+    .await?
+    .into_iter()
+    .map(|row| row.value)
+    .collect();
+    Ok(values)
+}
+
+/// Among the aggregates reaching quorum, picks those with the most validators and the tightest
+/// interval, returning the median by value.
+pub async fn get_best_quorum_aggregate(
+    db_pool: &DbPool,
+    slot_number: i64,
+    quorum_threshold: i64,
+    min_operators: i64,
+) -> eyre::Result<Option<AggregatePriceIntervalEntry>> {
+    let entries =
+        get_quorum_aggregates(db_pool, slot_number, quorum_threshold, min_operators).await?;
+    Ok(pick_best_aggregate(entries))
+}
+
+/// The aggregates of the slot with enough validators and operators to reach quorum.
+pub async fn get_quorum_aggregates(
+    db_pool: &DbPool,
+    slot_number: i64,
+    quorum_threshold: i64,
+    min_operators: i64,
+) -> eyre::Result<Vec<AggregatePriceIntervalEntry>> {
+    let entries = sqlx::query!(
+        "
+        SELECT
+            asset,
+            value,
+            slot_number,
+            aggregate_signature,
+            aggregate_public_key,
+            public_keys,
+            interval_size,
+            num_validators,
+            num_operators
+        FROM
+            aggregate_interval_attestations
+        WHERE
+            slot_number = $1
+        AND
+            num_validators >= $2
+        AND
+            num_operators >= $3;
+        ",
+        slot_number,
+        quorum_threshold,
+        min_operators,
+    )
+    .fetch_all(db_pool)
+    .await?
+    .into_iter()
+    .map(|row| AggregatePriceIntervalEntry {
+        asset: row.asset,
+        value: row.value,
+        slot_number: row.slot_number,
+        aggregate_signature: row.aggregate_signature,
+        aggregate_public_key: row.aggregate_public_key,
+        public_keys: row.public_keys,
+        interval_size: row.interval_size,
+        num_validators: row.num_validators,
+        num_operators: row.num_operators,
+        finalized: None,
+    })
+    .collect();
+    Ok(entries)
+}
+
+/// Picks the aggregates with the most validators and the tightest interval, returning the median
+/// by value.
+pub fn pick_best_aggregate(
+    entries: Vec<AggregatePriceIntervalEntry>,
+) -> Option<AggregatePriceIntervalEntry> {
+    let best_entries: Vec<AggregatePriceIntervalEntry> = entries
+        .into_iter()
+        .max_set_by_key(|entry| (entry.num_validators, -entry.interval_size))
+        .into_iter()
+        .sorted_by_key(|entry| entry.value)
+        .collect();
+    best_entries.get(best_entries.len() / 2).cloned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_utils::{
+        get_test_message, sign_oracle_message_with_new_key, value_message_from_new_validator,
+        TestApp, TEST_MESSAGE_SLOT,
+    };
+    use axum::body::Body;
+    use bls::SecretKey;
+
+    #[sqlx::test]
+    async fn lists_intervals_containing_target(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let mut test_message = get_test_message();
+        // The first interval ends just below the target, the second contains it
+        test_message.interval_inclusion_messages.truncate(2);
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+
+        let response = test_app
+            .get_expect(
+                &format!("/intervals/containing?slot={TEST_MESSAGE_SLOT}&value=1811093163"),
+                StatusCode::OK,
+            )
+            .await;
+        let entries: Vec<PriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            entries[0].value,
+            test_message.interval_inclusion_messages[1].message.value as i64
+        );
+        assert_eq!(
+            entries[0].validator_public_key,
+            test_message.validator_public_key.to_string()
+        );
+
+        let response = test_app
+            .get_expect(
+                &format!("/intervals/containing?slot={TEST_MESSAGE_SLOT}&value=1000000000"),
+                StatusCode::OK,
+            )
+            .await;
+        let entries: Vec<PriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert!(entries.is_empty());
+    }
+
+    #[sqlx::test]
+    async fn weighs_aggregates_by_stake(db_pool: DbPool) {
+        let heavy_key = SecretKey::random();
+        let config = Config {
+            validator_weights: [(heavy_key.public_key().to_string(), 32)].into(),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        let test_message = get_test_message();
+        let heavy_interval = test_message.interval_inclusion_messages[362].clone();
+        let light_interval = test_message.interval_inclusion_messages[363].clone();
+        let mut message = test_message.clone();
+        message.interval_inclusion_messages = vec![heavy_interval.clone()];
+        let message = sign_oracle_message_with_new_key(message, &heavy_key);
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
+        for _ in 0..3 {
+            let mut message = test_message.clone();
+            message.interval_inclusion_messages = vec![light_interval.clone()];
+            let message = sign_oracle_message_with_new_key(message, &SecretKey::random());
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+        }
+
+        // By count the light validators win
+        let response = test_app
+            .get_expect(&format!("/consensus/{TEST_MESSAGE_SLOT}"), StatusCode::OK)
+            .await;
+        let consensus: ConsensusValue = serde_json::from_slice(&response).unwrap();
+        assert_eq!(
+            consensus.interval_aggregate.unwrap().value,
+            light_interval.message.value as i64
+        );
+
+        // By weight the single heavy validator does
+        let response = test_app
+            .get_expect(
+                &format!("/consensus/{TEST_MESSAGE_SLOT}/weighted"),
+                StatusCode::OK,
+            )
+            .await;
+        let weighted: WeightedConsensus = serde_json::from_slice(&response).unwrap();
+        assert_eq!(
+            weighted.aggregate.value,
+            heavy_interval.message.value as i64
+        );
+        assert_eq!(weighted.aggregate.num_validators, 1);
+        assert_eq!(weighted.total_weight, 32);
+
+        test_app
+            .get_expect(
+                &format!("/consensus/{}/weighted", TEST_MESSAGE_SLOT + 1),
+                StatusCode::NOT_FOUND,
+            )
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn median_and_interval_aggregate_agree(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        test_app
+            .post_oracle_message(&get_test_message(), StatusCode::OK)
+            .await;
+
+        let response = test_app
+            .get_expect(&format!("/consensus/{TEST_MESSAGE_SLOT}"), StatusCode::OK)
+            .await;
+        let consensus: ConsensusValue = serde_json::from_slice(&response).unwrap();
+        assert_eq!(consensus.method, ConsensusMethod::Median);
+        assert_eq!(consensus.consensus_value, Some(1811093163));
+        assert_eq!(consensus.num_value_attestations, 1);
+        assert!(consensus.interval_aggregate.is_some());
+        assert!(consensus.agree);
+    }
+
+    #[sqlx::test]
+    async fn median_and_interval_aggregate_conflict(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        test_app
+            .post_oracle_message(&get_test_message(), StatusCode::OK)
+            .await;
+        for _ in 0..2 {
+            test_app
+                .post_oracle_message(
+                    &value_message_from_new_validator(1000000000),
+                    StatusCode::OK,
+                )
+                .await;
+        }
+
+        let response = test_app
+            .get_expect(&format!("/consensus/{TEST_MESSAGE_SLOT}"), StatusCode::OK)
+            .await;
+        let consensus: ConsensusValue = serde_json::from_slice(&response).unwrap();
+        assert_eq!(consensus.consensus_value, Some(1000000000));
+        assert!(consensus.interval_aggregate.is_some());
+        assert!(!consensus.agree);
+
+        test_app
+            .get_expect(
+                &format!("/consensus/{}", TEST_MESSAGE_SLOT + 1),
+                StatusCode::NOT_FOUND,
+            )
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn finds_validators_contradicting_themselves(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let test_message = get_test_message();
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+        // Far below all of the intervals
+        let mut inconsistent_message = test_message.clone();
+        inconsistent_message.value_message.message.price.value = 1000000000;
+        let inconsistent_message =
+            sign_oracle_message_with_new_key(inconsistent_message, &SecretKey::random());
+        test_app
+            .post_oracle_message(&inconsistent_message, StatusCode::OK)
+            .await;
+
+        let response = test_app
+            .get_expect(&format!("/consistency/{TEST_MESSAGE_SLOT}"), StatusCode::OK)
+            .await;
+        let inconsistent_validators: Vec<InconsistentValidator> =
+            serde_json::from_slice(&response).unwrap();
+        assert_eq!(inconsistent_validators.len(), 1);
+        assert_eq!(
+            inconsistent_validators[0].validator_public_key,
+            inconsistent_message.validator_public_key.to_string()
+        );
+        assert_eq!(inconsistent_validators[0].value, 1000000000);
+        assert_eq!(
+            inconsistent_validators[0].num_interval_attestations,
+            test_message.interval_inclusion_messages.len()
+        );
+    }
+
+    async fn get_agreement_at(test_app: &TestApp) -> Agreement {
+        let response = test_app
+            .get_expect(&format!("/agreement/{TEST_MESSAGE_SLOT}"), StatusCode::OK)
+            .await;
+        serde_json::from_slice(&response).unwrap()
+    }
+
+    #[sqlx::test]
+    async fn scores_high_and_low_agreement(db_pool: DbPool) {
+        let config = Config {
+            agreement_tolerance_basis_points: 100,
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        test_app
+            .get_expect(
+                &format!("/agreement/{TEST_MESSAGE_SLOT}"),
+                StatusCode::NOT_FOUND,
+            )
+            .await;
+
+        // All within 1% of the median of 10000
+        for value in [9950, 10000, 10000, 10050] {
+            test_app
+                .post_oracle_message(&value_message_from_new_validator(value), StatusCode::OK)
+                .await;
+        }
+        let agreement = get_agreement_at(&test_app).await;
+        assert_eq!(agreement.median_value, 10000);
+        assert_eq!(agreement.num_validators, 4);
+        assert_eq!(agreement.num_agreeing, 4);
+        assert_eq!(agreement.agreement, 1.0);
+
+        // Outliers on both ends
+        for value in [5000, 8000, 12000, 20000] {
+            test_app
+                .post_oracle_message(&value_message_from_new_validator(value), StatusCode::OK)
+                .await;
+        }
+        let agreement = get_agreement_at(&test_app).await;
+        assert_eq!(agreement.median_value, 10000);
+        assert_eq!(agreement.num_validators, 8);
+        assert_eq!(agreement.num_agreeing, 4);
+        assert_eq!(agreement.agreement, 0.5);
+    }
+
+    async fn verify_median(test_app: &TestApp, median_value: i64) -> MedianVerification {
+        let response = test_app
+            .post_expect(
+                &format!("/verify/median/{TEST_MESSAGE_SLOT}"),
+                Body::from(serde_json::to_string(&MedianClaim { median_value }).unwrap()),
+                StatusCode::OK,
+            )
+            .await;
+        serde_json::from_slice(&response).unwrap()
+    }
+
+    #[sqlx::test]
+    async fn verifies_claimed_median(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        for value in [9000, 10000, 11000] {
+            test_app
+                .post_oracle_message(&value_message_from_new_validator(value), StatusCode::OK)
+                .await;
+        }
+
+        let verification = verify_median(&test_app, 10000).await;
+        assert!(verification.matches);
+        assert_eq!(verification.median_value, 10000);
+
+        let verification = verify_median(&test_app, 10001).await;
+        assert!(!verification.matches);
+        assert_eq!(verification.claimed_median_value, 10001);
+        assert_eq!(verification.median_value, 10000);
+
+        test_app
+            .post_expect(
+                &format!("/verify/median/{}", TEST_MESSAGE_SLOT + 1),
+                Body::from(
+                    serde_json::to_string(&MedianClaim {
+                        median_value: 10000,
+                    })
+                    .unwrap(),
+                ),
+                StatusCode::NOT_FOUND,
+            )
+            .await;
+    }
+
+    async fn get_consensus_value_with(test_app: &TestApp, query: &str) -> Option<i64> {
+        let response = test_app
+            .get_expect(
+                &format!("/consensus/{TEST_MESSAGE_SLOT}{query}"),
+                StatusCode::OK,
+            )
+            .await;
+        let consensus: ConsensusValue = serde_json::from_slice(&response).unwrap();
+        consensus.consensus_value
+    }
+
+    #[sqlx::test]
+    async fn combines_values_with_each_method(db_pool: DbPool) {
+        let config = Config {
+            consensus_method: ConsensusMethod::Mode,
+            consensus_trim_percent: 20,
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        for value in [100, 100, 200, 400, 2000] {
+            test_app
+                .post_oracle_message(&value_message_from_new_validator(value), StatusCode::OK)
+                .await;
+        }
+
+        assert_eq!(get_consensus_value_with(&test_app, "").await, Some(100));
+        assert_eq!(
+            get_consensus_value_with(&test_app, "?method=median").await,
+            Some(200)
+        );
+        assert_eq!(
+            get_consensus_value_with(&test_app, "?method=mode").await,
+            Some(100)
+        );
+        // Drops 100 and 2000, averaging 100, 200 and 400
+        assert_eq!(
+            get_consensus_value_with(&test_app, "?method=trimmed_mean").await,
+            Some(233)
+        );
+        test_app
+            .get_expect(
+                &format!("/consensus/{TEST_MESSAGE_SLOT}?method=mean"),
+                StatusCode::BAD_REQUEST,
+            )
+            .await;
+    }
+}
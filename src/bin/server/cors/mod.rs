@@ -0,0 +1,85 @@
+//! CORS headers for browser dashboards calling the API from another origin. Only the configured
+//! origins are allowed, by default none.
+
+use crate::config::Config;
+use axum::http::HeaderValue;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+const ANY_ORIGIN: HeaderValue = HeaderValue::from_static("*");
+
+pub fn cors_layer(config: &Config) -> CorsLayer {
+    let allow_origin = if config.cors_allowed_origins.contains(&ANY_ORIGIN) {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(config.cors_allowed_origins.clone())
+    };
+    CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods(config.cors_allowed_methods.clone())
+        .allow_headers(config.cors_allowed_headers.clone())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::DbPool;
+    use crate::test_utils::TestApp;
+    use axum::http::{header, StatusCode};
+
+    const DASHBOARD_ORIGIN: &str = "https://dashboard.example";
+
+    #[sqlx::test]
+    async fn allows_configured_origins_only(db_pool: DbPool) {
+        let config = Config {
+            cors_allowed_origins: vec![HeaderValue::from_static(DASHBOARD_ORIGIN)],
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+
+        let response = test_app
+            .get_response("/slot", &[("Origin", DASHBOARD_ORIGIN)])
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            DASHBOARD_ORIGIN
+        );
+
+        let response = test_app
+            .get_response("/slot", &[("Origin", "https://elsewhere.example")])
+            .await;
+        assert!(!response
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+
+        let response = test_app
+            .options_response(
+                "/slot",
+                &[
+                    ("Origin", DASHBOARD_ORIGIN),
+                    ("Access-Control-Request-Method", "GET"),
+                ],
+            )
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            DASHBOARD_ORIGIN
+        );
+        assert_eq!(
+            response.headers()[header::ACCESS_CONTROL_ALLOW_METHODS],
+            "GET"
+        );
+    }
+
+    #[sqlx::test]
+    async fn sends_no_cors_headers_by_default(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let response = test_app
+            .get_response("/slot", &[("Origin", DASHBOARD_ORIGIN)])
+            .await;
+        assert!(!response
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+}
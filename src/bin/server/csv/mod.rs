@@ -0,0 +1,355 @@
+//! Attestations as CSV, for loading into spreadsheets and notebooks. Rows are streamed from a
+//! database cursor like the JSON lines export, so large tables are never held in memory.
+
+use crate::attestations::{
+    AggregatePriceIntervalEntry, AttestationStreamQuery, PriceIntervalEntry, PriceValueEntry,
+};
+use crate::state::AppState;
+use axum::{
+    body::{boxed, Body, Bytes},
+    extract::{Query, State},
+    http::header::CONTENT_TYPE,
+    response::IntoResponse,
+};
+use futures_util::TryStreamExt;
+use sqlx::postgres::PgRow;
+use std::sync::Arc;
+
+/// A type written as one CSV row, with a column per field.
+trait CsvRecord {
+    const HEADER: &'static [&'static str];
+
+    fn fields(&self) -> Vec<String>;
+}
+
+fn optional_field<T: ToString>(field: &Option<T>) -> String {
+    field.as_ref().map(T::to_string).unwrap_or_default()
+}
+
+impl CsvRecord for PriceValueEntry {
+    const HEADER: &'static [&'static str] = &[
+        "validator_public_key",
+        "asset",
+        "value",
+        "slot_number",
+        "signature",
+        "signing_preimage",
+        "price_timestamp",
+        "received_at",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.validator_public_key.clone(),
+            self.asset.clone(),
+            self.value.to_string(),
+            self.slot_number.to_string(),
+            self.signature.clone(),
+            optional_field(&self.signing_preimage),
+            optional_field(&self.price_timestamp),
+            self.received_at.to_string(),
+        ]
+    }
+}
+
+impl CsvRecord for PriceIntervalEntry {
+    const HEADER: &'static [&'static str] = &[
+        "validator_public_key",
+        "asset",
+        "value",
+        "slot_number",
+        "signature",
+        "interval_size",
+        "aggregate_value",
+        "signing_preimage",
+        "received_at",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.validator_public_key.clone(),
+            self.asset.clone(),
+            self.value.to_string(),
+            self.slot_number.to_string(),
+            self.signature.clone(),
+            self.interval_size.to_string(),
+            self.aggregate_value.to_string(),
+            optional_field(&self.signing_preimage),
+            self.received_at.to_string(),
+        ]
+    }
+}
+
+impl CsvRecord for AggregatePriceIntervalEntry {
+    const HEADER: &'static [&'static str] = &[
+        "asset",
+        "value",
+        "slot_number",
+        "aggregate_signature",
+        "aggregate_public_key",
+        "interval_size",
+        "num_validators",
+        "num_operators",
+        "public_keys",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.asset.clone(),
+            self.value.to_string(),
+            self.slot_number.to_string(),
+            self.aggregate_signature.clone(),
+            self.aggregate_public_key.clone(),
+            self.interval_size.to_string(),
+            self.num_validators.to_string(),
+            self.num_operators.to_string(),
+            // Space separated, keeping the column a single field.
+            self.public_keys.join(" "),
+        ]
+    }
+}
+
+/// Quotes the field if it would otherwise be misread, doubling any quotes in it.
+fn escape_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn csv_line<S: AsRef<str>>(fields: &[S]) -> Bytes {
+    let mut line = fields
+        .iter()
+        .map(|field| escape_field(field.as_ref()))
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push_str("\r\n");
+    Bytes::from(line)
+}
+
+/// Streams the rows of the query, which takes the first and last slot as its parameters, as CSV
+/// with a header row.
+fn csv_response<T>(
+    state: Arc<AppState>,
+    sql: &'static str,
+    query: AttestationStreamQuery,
+) -> impl IntoResponse
+where
+    T: CsvRecord + for<'r> sqlx::FromRow<'r, PgRow> + Send + Unpin + 'static,
+{
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        if sender.send_data(csv_line(T::HEADER)).await.is_err() {
+            return;
+        }
+        let mut rows = sqlx::query_as::<_, T>(sql)
+            .bind(query.from_slot)
+            .bind(query.to_slot)
+            .fetch(&state.db_pool);
+        loop {
+            let row = match rows.try_next().await {
+                Ok(Some(row)) => row,
+                Ok(None) => break,
+                Err(e) => {
+                    tracing::error!("Error streaming CSV rows: {:?}", e);
+                    sender.abort();
+                    break;
+                }
+            };
+            if sender.send_data(csv_line(&row.fields())).await.is_err() {
+                // The client went away, no point in reading further.
+                break;
+            }
+        }
+    });
+    ([(CONTENT_TYPE, "text/csv")], boxed(body))
+}
+
+pub async fn get_price_value_attestations_csv(
+    Query(query): Query<AttestationStreamQuery>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    csv_response::<PriceValueEntry>(
+        state,
+        "
+        SELECT
+            validator_public_key,
+            asset,
+            value,
+            slot_number,
+            signature,
+            signing_preimage,
+            price_timestamp,
+            (EXTRACT(EPOCH FROM received_at) * 1000)::BIGINT AS received_at
+        FROM
+            price_value_attestations
+        WHERE
+            ($1::BIGINT IS NULL OR slot_number >= $1)
+        AND
+            ($2::BIGINT IS NULL OR slot_number <= $2)
+        ORDER BY
+            slot_number,
+            validator_public_key;
+        ",
+        query,
+    )
+}
+
+pub async fn get_price_interval_attestations_csv(
+    Query(query): Query<AttestationStreamQuery>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    csv_response::<PriceIntervalEntry>(
+        state,
+        "
+        SELECT
+            validator_public_key,
+            asset,
+            value,
+            slot_number,
+            signature,
+            interval_size,
+            aggregate_value,
+            signing_preimage,
+            (EXTRACT(EPOCH FROM received_at) * 1000)::BIGINT AS received_at
+        FROM
+            price_interval_attestations
+        WHERE
+            ($1::BIGINT IS NULL OR slot_number >= $1)
+        AND
+            ($2::BIGINT IS NULL OR slot_number <= $2)
+        ORDER BY
+            slot_number,
+            validator_public_key,
+            interval_size,
+            value;
+        ",
+        query,
+    )
+}
+
+pub async fn get_aggregate_attestations_csv(
+    Query(query): Query<AttestationStreamQuery>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    csv_response::<AggregatePriceIntervalEntry>(
+        state,
+        "
+        SELECT
+            asset,
+            value,
+            slot_number,
+            aggregate_signature,
+            aggregate_public_key,
+            interval_size,
+            num_validators,
+            num_operators,
+            public_keys
+        FROM
+            aggregate_interval_attestations
+        WHERE
+            ($1::BIGINT IS NULL OR slot_number >= $1)
+        AND
+            ($2::BIGINT IS NULL OR slot_number <= $2)
+        ORDER BY
+            slot_number,
+            asset,
+            interval_size,
+            value;
+        ",
+        query,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::DbPool;
+    use crate::test_utils::{get_test_message, value_message, TestApp, TEST_MESSAGE_SLOT};
+    use axum::http::StatusCode;
+    use bls::SecretKey;
+
+    #[test]
+    fn quotes_fields_that_need_it() {
+        assert_eq!(
+            csv_line(&["plain", "with,comma", "with \"quotes\""]),
+            "plain,\"with,comma\",\"with \"\"quotes\"\"\"\r\n"
+        );
+    }
+
+    #[sqlx::test]
+    async fn exports_attestations_as_csv(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let private_keys = [SecretKey::random(), SecretKey::random()];
+        for (slot_number, private_key) in (TEST_MESSAGE_SLOT - 1..).zip(&private_keys) {
+            let message = value_message(private_key, slot_number, 1811093163);
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+        }
+
+        let response = test_app
+            .get_response("/attestations/price_value.csv", &[])
+            .await;
+        assert_eq!(response.headers()[CONTENT_TYPE], "text/csv");
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let csv = String::from_utf8(body.to_vec()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "validator_public_key,asset,value,slot_number,signature,signing_preimage,price_timestamp,received_at"
+        );
+        assert_eq!(lines.len(), 3);
+        for (line, (slot_number, private_key)) in lines[1..]
+            .iter()
+            .zip((TEST_MESSAGE_SLOT - 1..).zip(&private_keys))
+        {
+            let fields: Vec<&str> = line.split(',').collect();
+            assert_eq!(fields[0], private_key.public_key().to_string());
+            assert_eq!(
+                fields[1..4],
+                ["ETHUSD", "1811093163", &slot_number.to_string()]
+            );
+            // Neither a preimage nor timestamp
+            assert_eq!(fields[5..7], ["", ""]);
+        }
+
+        // Filtered like the JSON endpoints
+        let body = test_app
+            .get_expect(
+                &format!("/attestations/price_value.csv?from_slot={TEST_MESSAGE_SLOT}"),
+                StatusCode::OK,
+            )
+            .await;
+        assert_eq!(String::from_utf8(body.to_vec()).unwrap().lines().count(), 2);
+    }
+
+    #[sqlx::test]
+    async fn exports_intervals_and_aggregates_as_csv(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let test_message = get_test_message();
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+        let num_intervals = test_message.interval_inclusion_messages.len();
+
+        let body = test_app
+            .get_expect("/attestations/price_interval.csv", StatusCode::OK)
+            .await;
+        let csv = String::from_utf8(body.to_vec()).unwrap();
+        assert!(csv
+            .starts_with("validator_public_key,asset,value,slot_number,signature,interval_size,"));
+        assert_eq!(csv.lines().count(), num_intervals + 1);
+
+        let body = test_app
+            .get_expect("/attestations/aggregate.csv", StatusCode::OK)
+            .await;
+        let csv = String::from_utf8(body.to_vec()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), num_intervals + 1);
+        let first_interval = &test_message.interval_inclusion_messages[0].message;
+        let fields: Vec<&str> = lines[1].split(',').collect();
+        assert_eq!(fields[1], first_interval.value.to_string());
+        assert_eq!(fields[6], "1");
+        assert_eq!(fields[8], test_message.validator_public_key.to_string());
+    }
+}
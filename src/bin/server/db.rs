@@ -1,4 +1,8 @@
+use crate::config::Config;
 use crate::env;
+use sqlx::migrate::Migrator;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use std::time::Duration;
 
 pub fn get_db_url() -> String {
     env::get_env_var_unsafe("DATABASE_URL")
@@ -6,9 +10,272 @@ pub fn get_db_url() -> String {
 
 pub type DbPool = sqlx::PgPool;
 
-pub async fn get_db_pool() -> DbPool {
-    let db_url = get_db_url();
-    sqlx::PgPool::connect(&db_url)
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// Postgres error code of a statement giving up on a lock, see `Config::db_lock_timeout_ms`.
+const LOCK_NOT_AVAILABLE: &str = "55P03";
+/// Postgres error code of a transaction aborted to resolve a deadlock.
+const DEADLOCK_DETECTED: &str = "40P01";
+/// Postgres error code of a transaction aborted as it conflicts with a concurrent one.
+const SERIALIZATION_FAILURE: &str = "40001";
+
+pub fn get_pool_options(config: &Config) -> PgPoolOptions {
+    PgPoolOptions::new()
+        .max_connections(config.db_max_connections)
+        .acquire_timeout(Duration::from_secs(config.db_acquire_timeout_seconds))
+}
+
+/// Applies the configured session settings to the options connections are opened with.
+pub fn configure_connect_options(
+    connect_options: PgConnectOptions,
+    config: &Config,
+) -> PgConnectOptions {
+    match config.db_lock_timeout_ms {
+        Some(lock_timeout_ms) => connect_options.options([("lock_timeout", lock_timeout_ms)]),
+        None => connect_options,
+    }
+}
+
+pub async fn get_db_pool(config: &Config) -> DbPool {
+    let connect_options: PgConnectOptions = get_db_url()
+        .parse()
+        .expect("expect DATABASE_URL to be a valid Postgres URL");
+    let connect_options = configure_connect_options(connect_options, config);
+    get_pool_options(config)
+        .connect_with(connect_options)
         .await
         .expect("expect Postgres DB to be available to connect")
 }
+
+/// Whether the error is the DB being too busy to serve the request right now, rather than the
+/// request or the DB being broken, so the request is worth retrying later.
+pub fn is_db_unavailable(err: &sqlx::Error) -> bool {
+    matches!(err, sqlx::Error::PoolTimedOut) || is_db_busy(err)
+}
+
+/// Whether the error is an operation losing out to a concurrent one, which may well succeed when
+/// tried again right away.
+pub fn is_db_busy(err: &sqlx::Error) -> bool {
+    match err {
+        sqlx::Error::Database(db_err) => matches!(
+            db_err.code().as_deref(),
+            Some(LOCK_NOT_AVAILABLE | DEADLOCK_DETECTED | SERIALIZATION_FAILURE)
+        ),
+        _ => false,
+    }
+}
+
+/// Exponential backoff between the attempts of a DB operation failing on contention, see
+/// `is_db_busy`, allowing at most `Config::db_max_retries` retries. The operation has to be safe
+/// to run again after failing.
+pub struct Backoff {
+    delay: Duration,
+    num_retries: u32,
+    max_retries: u32,
+}
+
+impl Backoff {
+    pub fn new(config: &Config) -> Self {
+        Backoff {
+            delay: Duration::from_millis(config.db_retry_base_delay_ms),
+            num_retries: 0,
+            max_retries: config.db_max_retries,
+        }
+    }
+
+    /// Waits before the next attempt, returning false without waiting once the retries are used
+    /// up.
+    pub async fn wait(&mut self) -> bool {
+        if self.num_retries >= self.max_retries {
+            return false;
+        }
+        tracing::warn!("Retrying DB operation in {:?}", self.delay);
+        tokio::time::sleep(self.delay).await;
+        self.delay *= 2;
+        self.num_retries += 1;
+        true
+    }
+}
+
+/// Applies the migrations the DB doesn't have yet, creating the schema on a fresh DB.
+pub async fn run_migrations(db_pool: &DbPool) -> eyre::Result<()> {
+    MIGRATOR.run(db_pool).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::attestations::{
+        get_aggregate_lock_key, AggregatePriceIntervalEntry, BatchMessageResult, OracleMessage,
+        PriceValueEntry,
+    };
+    use crate::canonical::encode_public_key;
+    use crate::test_utils::{get_test_message, value_message_from_new_validator, TestApp};
+    use axum::{body::Body, http::StatusCode};
+    use sqlx::{Postgres, Transaction};
+
+    #[sqlx::test(migrations = false)]
+    async fn migrates_empty_db(db_pool: DbPool) {
+        run_migrations(&db_pool).await.unwrap();
+        // Already applied migrations are skipped
+        run_migrations(&db_pool).await.unwrap();
+
+        let test_app = TestApp::new(db_pool).await;
+        test_app
+            .post_oracle_message(&get_test_message(), StatusCode::OK)
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn rejects_submissions_while_pool_is_exhausted(db_pool: DbPool) {
+        let config = Config {
+            db_max_connections: 1,
+            db_acquire_timeout_seconds: 1,
+            ..Config::default()
+        };
+        let small_pool = get_pool_options(&config)
+            .connect_with(db_pool.connect_options().clone())
+            .await
+            .unwrap();
+        let test_app = TestApp::with_config(small_pool.clone(), config).await;
+
+        let connection = small_pool.acquire().await.unwrap();
+        let response = tokio::time::timeout(
+            Duration::from_secs(10),
+            test_app.post_oracle_message(&get_test_message(), StatusCode::SERVICE_UNAVAILABLE),
+        )
+        .await
+        .expect("expect submission to give up waiting for a connection");
+        assert_eq!(&response[..], b"database is busy, retry later");
+
+        // Once a connection is free again the message goes through
+        drop(connection);
+        test_app
+            .post_oracle_message(&get_test_message(), StatusCode::OK)
+            .await;
+    }
+
+    /// A pool with the retry and lock timeout settings of the config, on the test DB.
+    async fn get_contended_pool(db_pool: &DbPool, config: &Config) -> DbPool {
+        let connect_options = configure_connect_options(db_pool.connect_options().clone(), config);
+        get_pool_options(config)
+            .connect_with(connect_options)
+            .await
+            .unwrap()
+    }
+
+    /// Holds the lock of the aggregate the message's first interval message goes into, as a
+    /// concurrent submission for it would, until the returned transaction ends.
+    async fn lock_aggregate(
+        db_pool: &DbPool,
+        message: &OracleMessage,
+    ) -> Transaction<'static, Postgres> {
+        let interval_message = &message.interval_inclusion_messages[0].message;
+        let mut transaction = db_pool.begin().await.unwrap();
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended($1, 0));")
+            .bind(get_aggregate_lock_key(
+                &message.asset,
+                interval_message.interval_size as i64,
+                interval_message.slot_number as i64,
+                interval_message.value as i64,
+            ))
+            .execute(&mut transaction)
+            .await
+            .unwrap();
+        transaction
+    }
+
+    #[sqlx::test]
+    async fn retries_writes_blocked_by_concurrent_ones(db_pool: DbPool) {
+        let config = Config {
+            db_lock_timeout_ms: Some(100),
+            db_max_retries: 5,
+            db_retry_base_delay_ms: 50,
+            ..Config::default()
+        };
+        let contended_pool = get_contended_pool(&db_pool, &config).await;
+        let test_app = TestApp::with_config(contended_pool, config).await;
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(1);
+
+        let transaction = lock_aggregate(&db_pool, &test_message).await;
+        // The concurrent write finishes while the submission is backing off
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            transaction.rollback().await.unwrap();
+        });
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+
+        let response = test_app
+            .get_expect("/aggregate_price_interval_attestations", StatusCode::OK)
+            .await;
+        let aggregates: Vec<AggregatePriceIntervalEntry> =
+            serde_json::from_slice(&response).unwrap();
+        assert_eq!(aggregates.len(), 1);
+        assert_eq!(aggregates[0].num_validators, 1);
+    }
+
+    #[sqlx::test]
+    async fn rejects_submissions_blocked_beyond_retries(db_pool: DbPool) {
+        let config = Config {
+            db_lock_timeout_ms: Some(50),
+            db_max_retries: 2,
+            db_retry_base_delay_ms: 10,
+            ..Config::default()
+        };
+        let contended_pool = get_contended_pool(&db_pool, &config).await;
+        let test_app = TestApp::with_config(contended_pool, config).await;
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(1);
+
+        let _transaction = lock_aggregate(&db_pool, &test_message).await;
+        let response = test_app
+            .post_oracle_message(&test_message, StatusCode::SERVICE_UNAVAILABLE)
+            .await;
+        assert_eq!(&response[..], b"database is busy, retry later");
+    }
+
+    #[sqlx::test]
+    async fn rolls_back_only_the_blocked_message_of_a_batch(db_pool: DbPool) {
+        let config = Config {
+            db_lock_timeout_ms: Some(50),
+            db_max_retries: 0,
+            ..Config::default()
+        };
+        let contended_pool = get_contended_pool(&db_pool, &config).await;
+        let test_app = TestApp::with_config(contended_pool, config).await;
+        let mut blocked_message = get_test_message();
+        blocked_message.interval_inclusion_messages.truncate(1);
+        let messages = vec![
+            value_message_from_new_validator(1811093163),
+            blocked_message.clone(),
+            value_message_from_new_validator(1811093164),
+        ];
+
+        let _transaction = lock_aggregate(&db_pool, &blocked_message).await;
+        let response = test_app
+            .post_expect(
+                "/oracle_messages/batch",
+                Body::from(serde_json::to_string(&messages).unwrap()),
+                StatusCode::OK,
+            )
+            .await;
+        let results: Vec<BatchMessageResult> = serde_json::from_slice(&response).unwrap();
+        let statuses: Vec<u16> = results.iter().map(|result| result.status).collect();
+        assert_eq!(statuses, vec![200, 503, 200]);
+
+        // The value attestation of the blocked message is rolled back with its interval one
+        let response = test_app
+            .get_expect("/price_value_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceValueEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 2);
+        let blocked_public_key = encode_public_key(&blocked_message.validator_public_key);
+        assert!(entries
+            .iter()
+            .all(|entry| entry.validator_public_key != blocked_public_key));
+    }
+}
@@ -0,0 +1,229 @@
+//! Messages for slots slightly ahead of the value window, e.g. from validators with a fast clock,
+//! are quarantined instead of rejected and ingested once the slot clock catches up with them.
+
+use crate::attestations::{ingest_oracle_message, validate_message, OracleMessage};
+use crate::canonical::encode_public_key;
+use crate::state::AppState;
+use sqlx::PgConnection;
+use std::sync::Arc;
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Whether the message is validly signed and its value slot is ahead of the value window, but by
+/// no more than the configured number of slots.
+pub fn is_deferrable(state: &AppState, message: &OracleMessage) -> bool {
+    let defer_window_slots = match state.config.defer_window_slots {
+        Some(defer_window_slots) => defer_window_slots,
+        None => return false,
+    };
+    let window_end = state.slot_clock.current_slot() + state.config.value_window_slots;
+    let slot_number = message.value_message.message.slot_number;
+    slot_number > window_end
+        && slot_number <= window_end + defer_window_slots
+        && validate_message(
+            &message.validator_public_key,
+            &message.value_message.message,
+            &message.value_message.signature,
+            state.config.digest_config().for_asset(&message.asset),
+        )
+}
+
+/// Defers the message, returning false if the validator already has one deferred for the asset
+/// and slot.
+pub async fn defer_message(
+    connection: &mut PgConnection,
+    message: &OracleMessage,
+) -> eyre::Result<bool> {
+    let pk_string = encode_public_key(&message.validator_public_key);
+    let slot_number = message.value_message.message.slot_number as i64;
+    let message_json = serde_json::to_string(message)?;
+    let result = sqlx::query!(
+        "
+        INSERT INTO deferred_messages(
+            validator_public_key,
+            asset,
+            slot_number,
+            message
+        )
+        VALUES (
+            $1,
+            $2,
+            $3,
+            $4
+        )
+        ON CONFLICT (validator_public_key, asset, slot_number) DO NOTHING;
+        ",
+        pk_string,
+        message.asset,
+        slot_number,
+        message_json,
+    )
+    .execute(connection)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn run_deferred_message_worker(state: Arc<AppState>) {
+    if state.config.defer_window_slots.is_none() {
+        return;
+    }
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = process_deferred_messages(&state).await {
+            tracing::error!("Error processing deferred messages: {:?}", e);
+        }
+    }
+}
+
+/// Ingests the deferred messages whose slot has entered the value window, returning how many were
+/// ingested successfully. Deferred messages are only tried once, those that fail are dropped the
+/// same way they would have been rejected had they arrived in time.
+pub async fn process_deferred_messages(state: &AppState) -> eyre::Result<usize> {
+    let window_end = (state.slot_clock.current_slot() + state.config.value_window_slots) as i64;
+    let rows = sqlx::query!(
+        "
+        DELETE FROM deferred_messages
+        WHERE
+            slot_number <= $1
+        RETURNING
+            id,
+            message;
+        ",
+        window_end
+    )
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    let mut num_ingested = 0;
+    for row in rows {
+        let message: OracleMessage = match serde_json::from_str(&row.message) {
+            Ok(message) => message,
+            Err(e) => {
+                tracing::error!("Error parsing deferred message {}: {:?}", row.id, e);
+                continue;
+            }
+        };
+        match ingest_oracle_message(state, &message).await {
+            Ok(()) => num_ingested += 1,
+            Err(err) => tracing::warn!(
+                "Dropping deferred message {} for slot {}: {}",
+                row.id,
+                message.value_message.message.slot_number,
+                err
+            ),
+        }
+    }
+    if num_ingested > 0 {
+        tracing::info!("Ingested {} deferred messages", num_ingested);
+    }
+    Ok(num_ingested)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Config;
+    use crate::db::DbPool;
+    use crate::test_utils::{
+        get_test_message, state_at_slot, value_message, TestApp, TEST_MESSAGE_SLOT,
+    };
+    use axum::http::StatusCode;
+    use bls::SecretKey;
+
+    #[sqlx::test]
+    async fn ingests_deferred_message_once_valid(db_pool: DbPool) {
+        let config = Config {
+            defer_window_slots: Some(5),
+            ..Config::default()
+        };
+        // The message is 7 slots ahead, 2 beyond the value window
+        let early_app =
+            TestApp::with_config_at_slot(db_pool.clone(), config.clone(), TEST_MESSAGE_SLOT - 7)
+                .await;
+        early_app
+            .post_oracle_message(&get_test_message(), StatusCode::ACCEPTED)
+            .await;
+        let response = early_app
+            .get_expect("/price_value_attestations", StatusCode::OK)
+            .await;
+        assert_eq!(&response[..], b"[]");
+
+        // Still too early
+        let state = state_at_slot(db_pool.clone(), config.clone(), TEST_MESSAGE_SLOT - 6);
+        assert_eq!(process_deferred_messages(&state).await.unwrap(), 0);
+
+        let state = state_at_slot(db_pool.clone(), config.clone(), TEST_MESSAGE_SLOT);
+        assert_eq!(process_deferred_messages(&state).await.unwrap(), 1);
+        // Only ingested once
+        assert_eq!(process_deferred_messages(&state).await.unwrap(), 0);
+
+        let test_app = TestApp::with_config(db_pool, config).await;
+        let response = test_app
+            .get_expect("/price_value_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<serde_json::Value> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0]["value"], 1811093163);
+    }
+
+    #[sqlx::test]
+    async fn defers_a_message_only_once(db_pool: DbPool) {
+        let config = Config {
+            defer_window_slots: Some(5),
+            ..Config::default()
+        };
+        let early_app =
+            TestApp::with_config_at_slot(db_pool.clone(), config.clone(), TEST_MESSAGE_SLOT - 7)
+                .await;
+        let private_key = SecretKey::random();
+        early_app
+            .post_oracle_message(
+                &value_message(&private_key, TEST_MESSAGE_SLOT, 1),
+                StatusCode::ACCEPTED,
+            )
+            .await;
+        for value in [1, 2] {
+            early_app
+                .post_oracle_message(
+                    &value_message(&private_key, TEST_MESSAGE_SLOT, value),
+                    StatusCode::CONFLICT,
+                )
+                .await;
+        }
+
+        let state = state_at_slot(db_pool, config, TEST_MESSAGE_SLOT);
+        assert_eq!(process_deferred_messages(&state).await.unwrap(), 1);
+    }
+
+    #[sqlx::test]
+    async fn does_not_defer_messages_of_quarantined_validators(db_pool: DbPool) {
+        let config = Config {
+            defer_window_slots: Some(5),
+            quarantine_equivocation_threshold: Some(1),
+            ..Config::default()
+        };
+        let early_app =
+            TestApp::with_config_at_slot(db_pool.clone(), config.clone(), TEST_MESSAGE_SLOT - 7)
+                .await;
+        let private_key = SecretKey::random();
+        for (value, status_code) in [(1, StatusCode::OK), (2, StatusCode::CONFLICT)] {
+            early_app
+                .post_oracle_message(
+                    &value_message(&private_key, TEST_MESSAGE_SLOT - 7, value),
+                    status_code,
+                )
+                .await;
+        }
+
+        early_app
+            .post_oracle_message(
+                &value_message(&private_key, TEST_MESSAGE_SLOT, 1),
+                StatusCode::FORBIDDEN,
+            )
+            .await;
+        let state = state_at_slot(db_pool, config, TEST_MESSAGE_SLOT);
+        assert_eq!(process_deferred_messages(&state).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,713 @@
+//! SSZ encodings of oracle messages and stored attestations, for clients with an SSZ-native
+//! pipeline. Keys and signatures are carried as their raw bytes rather than the hex strings of
+//! the JSON encoding.
+
+use crate::attestations::{
+    IntervalInclusionMessage, OracleMessage, Price, PriceIntervalEntry, PriceValueEntry,
+    PriceValueMessage, SignedIntervalInclusionMessage, SignedPriceValueMessage,
+    UntimedPriceValueMessage,
+};
+use axum::{
+    async_trait,
+    body::{Body, Bytes},
+    extract::FromRequest,
+    http::{header::ACCEPT, header::CONTENT_TYPE, HeaderMap, Request, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use bls::{PublicKey, Signature};
+use serde::de::DeserializeOwned;
+use ssz::{Decode, DecodeError, Encode};
+use ssz_derive::{Decode, Encode};
+
+pub const SSZ_CONTENT_TYPE: &str = "application/octet-stream";
+
+fn is_ssz_media_type(media_type: &str) -> bool {
+    media_type.split(';').next().unwrap_or_default().trim() == SSZ_CONTENT_TYPE
+}
+
+/// Whether the request asks for SSZ rather than JSON.
+pub fn accepts_ssz(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(is_ssz_media_type)
+}
+
+/// A request body that is SSZ decoded when sent as `application/octet-stream`, and JSON decoded
+/// otherwise.
+pub struct JsonOrSsz<T>(pub T);
+
+#[async_trait]
+impl<S, T> FromRequest<S, Body> for JsonOrSsz<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned + Decode,
+{
+    type Rejection = Response;
+
+    async fn from_request(request: Request<Body>, state: &S) -> Result<Self, Self::Rejection> {
+        let is_ssz = request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(is_ssz_media_type);
+        if !is_ssz {
+            let Json(value) = Json::<T>::from_request(request, state)
+                .await
+                .map_err(IntoResponse::into_response)?;
+            return Ok(JsonOrSsz(value));
+        }
+        let body = Bytes::from_request(request, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        let value = T::from_ssz_bytes(&body).map_err(|err| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("invalid SSZ encoding: {err:?}"),
+            )
+                .into_response()
+        })?;
+        Ok(JsonOrSsz(value))
+    }
+}
+
+/// A fixed number of bytes, encoded as is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FixedBytes<const N: usize>(pub [u8; N]);
+
+pub type PublicKeyBytes = FixedBytes<48>;
+pub type SignatureBytes = FixedBytes<96>;
+
+impl<const N: usize> FixedBytes<N> {
+    /// Parses the bytes from hex, with or without a 0x prefix.
+    pub fn from_hex(hex_string: &str) -> eyre::Result<Self> {
+        let bytes = hex::decode(hex_string.strip_prefix("0x").unwrap_or(hex_string))?;
+        let bytes = bytes
+            .try_into()
+            .map_err(|bytes: Vec<u8>| eyre::eyre!("expected {} bytes, got {}", N, bytes.len()))?;
+        Ok(FixedBytes(bytes))
+    }
+}
+
+impl<const N: usize> Encode for FixedBytes<N> {
+    fn is_ssz_fixed_len() -> bool {
+        true
+    }
+
+    fn ssz_fixed_len() -> usize {
+        N
+    }
+
+    fn ssz_bytes_len(&self) -> usize {
+        N
+    }
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.0);
+    }
+}
+
+impl<const N: usize> Decode for FixedBytes<N> {
+    fn is_ssz_fixed_len() -> bool {
+        true
+    }
+
+    fn ssz_fixed_len() -> usize {
+        N
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let bytes = bytes
+            .try_into()
+            .map_err(|_| DecodeError::InvalidByteLength {
+                len: bytes.len(),
+                expected: N,
+            })?;
+        Ok(FixedBytes(bytes))
+    }
+}
+
+/// Implements the SSZ encoding of a type through that of its wire representation.
+macro_rules! impl_ssz_via {
+    ($type:ty, $wire:ty) => {
+        impl Encode for $type {
+            fn is_ssz_fixed_len() -> bool {
+                <$wire as Encode>::is_ssz_fixed_len()
+            }
+
+            fn ssz_fixed_len() -> usize {
+                <$wire as Encode>::ssz_fixed_len()
+            }
+
+            fn ssz_bytes_len(&self) -> usize {
+                <$wire>::from(self).ssz_bytes_len()
+            }
+
+            fn ssz_append(&self, buf: &mut Vec<u8>) {
+                <$wire>::from(self).ssz_append(buf)
+            }
+        }
+
+        impl Decode for $type {
+            fn is_ssz_fixed_len() -> bool {
+                <$wire as Decode>::is_ssz_fixed_len()
+            }
+
+            fn ssz_fixed_len() -> usize {
+                <$wire as Decode>::ssz_fixed_len()
+            }
+
+            fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+                <$wire>::from_ssz_bytes(bytes)?.try_into()
+            }
+        }
+    };
+}
+
+fn decode_public_key(bytes: &PublicKeyBytes) -> Result<PublicKey, DecodeError> {
+    PublicKey::deserialize(&bytes.0)
+        .map_err(|err| DecodeError::BytesInvalid(format!("invalid public key: {err:?}")))
+}
+
+fn decode_signature(bytes: &SignatureBytes) -> Result<Signature, DecodeError> {
+    Signature::deserialize(&bytes.0)
+        .map_err(|err| DecodeError::BytesInvalid(format!("invalid signature: {err:?}")))
+}
+
+/// The value message is inlined without its timestamp, which goes in a trailing field of the
+/// container, so the layout of untimed messages is the same as before timestamps were added.
+#[derive(Encode, Decode)]
+struct SszSignedPriceValueMessage {
+    message: UntimedPriceValueMessage,
+    signature: SignatureBytes,
+}
+
+impl From<&SignedPriceValueMessage> for SszSignedPriceValueMessage {
+    fn from(signed_message: &SignedPriceValueMessage) -> Self {
+        SszSignedPriceValueMessage {
+            message: UntimedPriceValueMessage {
+                value: signed_message.message.price.value,
+                slot_number: signed_message.message.slot_number,
+            },
+            signature: FixedBytes(signed_message.signature.serialize()),
+        }
+    }
+}
+
+impl SszSignedPriceValueMessage {
+    fn with_timestamp(
+        self,
+        timestamp: Option<u64>,
+    ) -> Result<SignedPriceValueMessage, DecodeError> {
+        Ok(SignedPriceValueMessage {
+            message: PriceValueMessage {
+                price: Price {
+                    value: self.message.value,
+                    timestamp,
+                },
+                slot_number: self.message.slot_number,
+            },
+            signature: decode_signature(&self.signature)?,
+        })
+    }
+}
+
+#[derive(Encode, Decode)]
+struct SszSignedIntervalInclusionMessage {
+    message: IntervalInclusionMessage,
+    signature: SignatureBytes,
+}
+
+impl From<&SignedIntervalInclusionMessage> for SszSignedIntervalInclusionMessage {
+    fn from(signed_message: &SignedIntervalInclusionMessage) -> Self {
+        SszSignedIntervalInclusionMessage {
+            message: signed_message.message.clone(),
+            signature: FixedBytes(signed_message.signature.serialize()),
+        }
+    }
+}
+
+impl TryFrom<SszSignedIntervalInclusionMessage> for SignedIntervalInclusionMessage {
+    type Error = DecodeError;
+
+    fn try_from(signed_message: SszSignedIntervalInclusionMessage) -> Result<Self, DecodeError> {
+        Ok(SignedIntervalInclusionMessage {
+            signature: decode_signature(&signed_message.signature)?,
+            message: signed_message.message,
+        })
+    }
+}
+
+impl_ssz_via!(
+    SignedIntervalInclusionMessage,
+    SszSignedIntervalInclusionMessage
+);
+
+/// The asset is encoded as its UTF-8 bytes. Unknown fields are a JSON concept and have no
+/// encoding. Messages with a timestamp are encoded as `SszTimedOracleMessage` instead.
+#[derive(Encode, Decode)]
+struct SszOracleMessage {
+    asset: Vec<u8>,
+    value_message: SszSignedPriceValueMessage,
+    interval_inclusion_messages: Vec<SignedIntervalInclusionMessage>,
+    validator_public_key: PublicKeyBytes,
+}
+
+/// `SszOracleMessage` with the timestamp of the value message as trailing field.
+#[derive(Encode, Decode)]
+struct SszTimedOracleMessage {
+    asset: Vec<u8>,
+    value_message: SszSignedPriceValueMessage,
+    interval_inclusion_messages: Vec<SignedIntervalInclusionMessage>,
+    validator_public_key: PublicKeyBytes,
+    value_timestamp: u64,
+}
+
+impl From<&OracleMessage> for SszOracleMessage {
+    fn from(message: &OracleMessage) -> Self {
+        SszOracleMessage {
+            asset: message.asset.as_bytes().to_vec(),
+            value_message: SszSignedPriceValueMessage::from(&message.value_message),
+            interval_inclusion_messages: message.interval_inclusion_messages.clone(),
+            validator_public_key: FixedBytes(message.validator_public_key.serialize()),
+        }
+    }
+}
+
+impl SszOracleMessage {
+    fn with_timestamp(self, timestamp: u64) -> SszTimedOracleMessage {
+        SszTimedOracleMessage {
+            asset: self.asset,
+            value_message: self.value_message,
+            interval_inclusion_messages: self.interval_inclusion_messages,
+            validator_public_key: self.validator_public_key,
+            value_timestamp: timestamp,
+        }
+    }
+
+    fn into_oracle_message(self, timestamp: Option<u64>) -> Result<OracleMessage, DecodeError> {
+        Ok(OracleMessage {
+            asset: String::from_utf8(self.asset)
+                .map_err(|err| DecodeError::BytesInvalid(format!("invalid asset: {err}")))?,
+            validator_public_key: decode_public_key(&self.validator_public_key)?,
+            value_message: self.value_message.with_timestamp(timestamp)?,
+            interval_inclusion_messages: self.interval_inclusion_messages,
+            unknown_fields: Default::default(),
+        })
+    }
+}
+
+impl Encode for OracleMessage {
+    fn is_ssz_fixed_len() -> bool {
+        false
+    }
+
+    fn ssz_bytes_len(&self) -> usize {
+        let untimed_len = SszOracleMessage::from(self).ssz_bytes_len();
+        match self.value_message.message.price.timestamp {
+            Some(_) => untimed_len + <u64 as Encode>::ssz_fixed_len(),
+            None => untimed_len,
+        }
+    }
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        let message = SszOracleMessage::from(self);
+        match self.value_message.message.price.timestamp {
+            Some(timestamp) => message.with_timestamp(timestamp).ssz_append(buf),
+            None => message.ssz_append(buf),
+        }
+    }
+}
+
+impl Decode for OracleMessage {
+    fn is_ssz_fixed_len() -> bool {
+        false
+    }
+
+    /// The offset of the asset, which comes first, is where the fixed size fields end, so an
+    /// encoding only decodes as the container it was encoded as.
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        match SszOracleMessage::from_ssz_bytes(bytes) {
+            Ok(message) => message.into_oracle_message(None),
+            Err(_) => {
+                let message = SszTimedOracleMessage::from_ssz_bytes(bytes)?;
+                let timestamp = message.value_timestamp;
+                SszOracleMessage {
+                    asset: message.asset,
+                    value_message: message.value_message,
+                    interval_inclusion_messages: message.interval_inclusion_messages,
+                    validator_public_key: message.validator_public_key,
+                }
+                .into_oracle_message(Some(timestamp))
+            }
+        }
+    }
+}
+
+/// Laid out like the value attestations from before timestamps were added, with the timestamp
+/// appended when there is one.
+#[derive(Debug)]
+pub struct SszValueAttestation {
+    pub validator_public_key: PublicKeyBytes,
+    pub message: UntimedPriceValueMessage,
+    pub signature: SignatureBytes,
+    pub timestamp: Option<u64>,
+}
+
+impl SszValueAttestation {
+    /// Length of the encoding without a timestamp.
+    fn untimed_len() -> usize {
+        <PublicKeyBytes as Encode>::ssz_fixed_len()
+            + <UntimedPriceValueMessage as Encode>::ssz_fixed_len()
+            + <SignatureBytes as Encode>::ssz_fixed_len()
+    }
+
+    /// The message the signature is over.
+    pub fn price_value_message(&self) -> PriceValueMessage {
+        PriceValueMessage {
+            price: Price {
+                value: self.message.value,
+                timestamp: self.timestamp,
+            },
+            slot_number: self.message.slot_number,
+        }
+    }
+}
+
+impl Encode for SszValueAttestation {
+    fn is_ssz_fixed_len() -> bool {
+        false
+    }
+
+    fn ssz_bytes_len(&self) -> usize {
+        match self.timestamp {
+            Some(_) => Self::untimed_len() + <u64 as Encode>::ssz_fixed_len(),
+            None => Self::untimed_len(),
+        }
+    }
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        self.validator_public_key.ssz_append(buf);
+        self.message.ssz_append(buf);
+        self.signature.ssz_append(buf);
+        if let Some(timestamp) = self.timestamp {
+            timestamp.ssz_append(buf);
+        }
+    }
+}
+
+impl Decode for SszValueAttestation {
+    fn is_ssz_fixed_len() -> bool {
+        false
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let untimed_len = Self::untimed_len();
+        let timestamp = if bytes.len() == untimed_len {
+            None
+        } else if bytes.len() == untimed_len + <u64 as Decode>::ssz_fixed_len() {
+            Some(u64::from_ssz_bytes(&bytes[untimed_len..])?)
+        } else {
+            return Err(DecodeError::InvalidByteLength {
+                len: bytes.len(),
+                expected: untimed_len,
+            });
+        };
+        let (public_key_bytes, rest) = bytes.split_at(<PublicKeyBytes as Decode>::ssz_fixed_len());
+        let (message_bytes, rest) =
+            rest.split_at(<UntimedPriceValueMessage as Decode>::ssz_fixed_len());
+        let signature_bytes = &rest[..<SignatureBytes as Decode>::ssz_fixed_len()];
+        Ok(SszValueAttestation {
+            validator_public_key: PublicKeyBytes::from_ssz_bytes(public_key_bytes)?,
+            message: UntimedPriceValueMessage::from_ssz_bytes(message_bytes)?,
+            signature: SignatureBytes::from_ssz_bytes(signature_bytes)?,
+            timestamp,
+        })
+    }
+}
+
+impl TryFrom<&PriceValueEntry> for SszValueAttestation {
+    type Error = eyre::Report;
+
+    fn try_from(entry: &PriceValueEntry) -> eyre::Result<Self> {
+        Ok(SszValueAttestation {
+            validator_public_key: FixedBytes::from_hex(&entry.validator_public_key)?,
+            message: UntimedPriceValueMessage {
+                value: entry.value as u64,
+                slot_number: entry.slot_number as u64,
+            },
+            signature: FixedBytes::from_hex(&entry.signature)?,
+            timestamp: entry.price_timestamp.map(|timestamp| timestamp as u64),
+        })
+    }
+}
+
+#[derive(Debug, Encode, Decode)]
+pub struct SszIntervalAttestation {
+    pub validator_public_key: PublicKeyBytes,
+    pub message: IntervalInclusionMessage,
+    pub signature: SignatureBytes,
+}
+
+impl TryFrom<&PriceIntervalEntry> for SszIntervalAttestation {
+    type Error = eyre::Report;
+
+    fn try_from(entry: &PriceIntervalEntry) -> eyre::Result<Self> {
+        Ok(SszIntervalAttestation {
+            validator_public_key: FixedBytes::from_hex(&entry.validator_public_key)?,
+            message: IntervalInclusionMessage {
+                value: entry.value as u64,
+                interval_size: entry.interval_size as u64,
+                slot_number: entry.slot_number as u64,
+            },
+            signature: FixedBytes::from_hex(&entry.signature)?,
+        })
+    }
+}
+
+/// The entries as an SSZ list of their encodings.
+pub fn ssz_list_response<'a, E, T>(entries: &'a [E]) -> eyre::Result<Response>
+where
+    T: Encode + TryFrom<&'a E, Error = eyre::Report>,
+{
+    let items = entries
+        .iter()
+        .map(T::try_from)
+        .collect::<eyre::Result<Vec<T>>>()?;
+    Ok(([(CONTENT_TYPE, SSZ_CONTENT_TYPE)], items.as_ssz_bytes()).into_response())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::attestations::{get_message_digest, DigestConfig};
+    use crate::db::DbPool;
+    use crate::test_utils::{get_test_message, TestApp};
+    use axum::{body::Body, http::StatusCode};
+
+    async fn get_ssz(test_app: &TestApp, uri: &str) -> Vec<u8> {
+        let response = test_app
+            .get_expect_with_headers(uri, &[("Accept", SSZ_CONTENT_TYPE)], StatusCode::OK)
+            .await;
+        response.to_vec()
+    }
+
+    #[sqlx::test]
+    async fn serves_attestations_as_ssz(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let test_message = get_test_message();
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+        let public_key_bytes = test_message.validator_public_key.serialize();
+
+        let bytes = get_ssz(&test_app, "/price_value_attestations").await;
+        let value_attestations = Vec::<SszValueAttestation>::from_ssz_bytes(&bytes).unwrap();
+        assert_eq!(value_attestations.len(), 1);
+        let value_attestation = &value_attestations[0];
+        assert_eq!(value_attestation.validator_public_key.0, public_key_bytes);
+        assert_eq!(
+            value_attestation.price_value_message().as_ssz_bytes(),
+            test_message.value_message.message.as_ssz_bytes()
+        );
+        assert_eq!(
+            value_attestation.signature.0,
+            test_message.value_message.signature.serialize()
+        );
+        // The raw bytes are all it takes to verify the signature
+        let public_key = PublicKey::deserialize(&value_attestation.validator_public_key.0).unwrap();
+        let signature = Signature::deserialize(&value_attestation.signature.0).unwrap();
+        assert!(signature.verify(
+            &public_key,
+            get_message_digest(
+                &value_attestation.price_value_message(),
+                DigestConfig::default()
+            )
+        ));
+
+        let bytes = get_ssz(&test_app, "/price_interval_attestations").await;
+        let interval_attestations = Vec::<SszIntervalAttestation>::from_ssz_bytes(&bytes).unwrap();
+        assert_eq!(
+            interval_attestations.len(),
+            test_message.interval_inclusion_messages.len()
+        );
+        for interval_attestation in &interval_attestations {
+            let interval_message = test_message
+                .interval_inclusion_messages
+                .iter()
+                .find(|interval_message| {
+                    interval_message.message.value == interval_attestation.message.value
+                })
+                .unwrap();
+            assert_eq!(
+                interval_attestation.validator_public_key.0,
+                public_key_bytes
+            );
+            assert_eq!(
+                interval_attestation.message.as_ssz_bytes(),
+                interval_message.message.as_ssz_bytes()
+            );
+            assert_eq!(
+                interval_attestation.signature.0,
+                interval_message.signature.serialize()
+            );
+        }
+
+        // JSON stays the default
+        let response = test_app
+            .get_expect("/price_value_attestations", StatusCode::OK)
+            .await;
+        assert_eq!(response[0], b'[');
+    }
+
+    #[sqlx::test]
+    async fn accepts_ssz_submissions(db_pool: DbPool) {
+        let test_message = get_test_message();
+        let bytes = test_message.as_ssz_bytes();
+        let decoded_message = OracleMessage::from_ssz_bytes(&bytes).unwrap();
+        assert_eq!(
+            serde_json::to_value(&decoded_message).unwrap(),
+            serde_json::to_value(&test_message).unwrap()
+        );
+
+        let test_app = TestApp::new(db_pool).await;
+        test_app
+            .post_expect_with_headers(
+                "/post_oracle_message",
+                Body::from(bytes),
+                &[("Content-Type", SSZ_CONTENT_TYPE)],
+                StatusCode::OK,
+            )
+            .await;
+        // Stored as if it had been submitted as JSON
+        let response = test_app
+            .get_expect("/price_value_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceValueEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            entries[0].validator_public_key,
+            test_message.validator_public_key.to_string()
+        );
+        assert_eq!(
+            entries[0].signature,
+            test_message.value_message.signature.to_string()
+        );
+        assert_eq!(
+            entries[0].value,
+            test_message.value_message.message.price.value as i64
+        );
+        let response = test_app
+            .get_expect("/price_interval_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(
+            entries.len(),
+            test_message.interval_inclusion_messages.len()
+        );
+
+        test_app
+            .post_expect_with_headers(
+                "/post_oracle_message",
+                Body::from(vec![0u8; 10]),
+                &[("Content-Type", SSZ_CONTENT_TYPE)],
+                StatusCode::UNPROCESSABLE_ENTITY,
+            )
+            .await;
+    }
+
+    /// Value, slot, signature, interval messages offset and public key, then the variable length
+    /// fields.
+    const UNTIMED_FIXED_LEN: usize = 4 + 8 + 8 + 96 + 4 + 48;
+
+    #[test]
+    fn lays_out_untimed_messages_as_before_timestamps() {
+        let test_message = get_test_message();
+        assert_eq!(test_message.value_message.message.price.timestamp, None);
+        let bytes = test_message.as_ssz_bytes();
+        let asset_len = test_message.asset.len();
+
+        let offset = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as usize;
+        assert_eq!(offset(0), UNTIMED_FIXED_LEN);
+        assert_eq!(
+            bytes[4..12],
+            test_message.value_message.message.price.value.to_le_bytes()
+        );
+        assert_eq!(
+            bytes[12..20],
+            test_message.value_message.message.slot_number.to_le_bytes()
+        );
+        assert_eq!(
+            bytes[20..116],
+            test_message.value_message.signature.serialize()
+        );
+        assert_eq!(offset(116), UNTIMED_FIXED_LEN + asset_len);
+        assert_eq!(
+            bytes[120..168],
+            test_message.validator_public_key.serialize()
+        );
+        assert_eq!(
+            &bytes[UNTIMED_FIXED_LEN..UNTIMED_FIXED_LEN + asset_len],
+            test_message.asset.as_bytes()
+        );
+        assert_eq!(
+            bytes[UNTIMED_FIXED_LEN + asset_len..],
+            test_message.interval_inclusion_messages.as_ssz_bytes()
+        );
+
+        let value_attestation = SszValueAttestation {
+            validator_public_key: FixedBytes(test_message.validator_public_key.serialize()),
+            message: UntimedPriceValueMessage {
+                value: test_message.value_message.message.price.value,
+                slot_number: test_message.value_message.message.slot_number,
+            },
+            signature: FixedBytes(test_message.value_message.signature.serialize()),
+            timestamp: None,
+        };
+        let bytes = value_attestation.as_ssz_bytes();
+        assert_eq!(bytes.len(), 48 + 16 + 96);
+        assert_eq!(bytes[..48], test_message.validator_public_key.serialize());
+        assert_eq!(
+            bytes[48..64],
+            test_message.value_message.message.as_ssz_bytes()
+        );
+        assert_eq!(
+            bytes[64..],
+            test_message.value_message.signature.serialize()
+        );
+    }
+
+    #[test]
+    fn appends_the_timestamp_of_timed_messages() {
+        let mut test_message = get_test_message();
+        test_message.value_message.message.price.timestamp = Some(1685000000);
+        let untimed_bytes = get_test_message().as_ssz_bytes();
+        let bytes = test_message.as_ssz_bytes();
+        assert_eq!(bytes.len(), untimed_bytes.len() + 8);
+
+        let offset = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as usize;
+        assert_eq!(offset(0), UNTIMED_FIXED_LEN + 8);
+        // Only the offsets move, the fixed size fields are the same
+        assert_eq!(bytes[4..116], untimed_bytes[4..116]);
+        assert_eq!(bytes[120..168], untimed_bytes[120..168]);
+        assert_eq!(
+            bytes[UNTIMED_FIXED_LEN..UNTIMED_FIXED_LEN + 8],
+            1685000000u64.to_le_bytes()
+        );
+        assert_eq!(
+            bytes[UNTIMED_FIXED_LEN + 8..],
+            untimed_bytes[UNTIMED_FIXED_LEN..]
+        );
+
+        let decoded_message = OracleMessage::from_ssz_bytes(&bytes).unwrap();
+        assert_eq!(
+            decoded_message.value_message.message.price.timestamp,
+            Some(1685000000)
+        );
+        let decoded_message = OracleMessage::from_ssz_bytes(&untimed_bytes).unwrap();
+        assert_eq!(decoded_message.value_message.message.price.timestamp, None);
+    }
+}
@@ -5,7 +5,14 @@ use std::env;
 
 use tracing::debug;
 
-const SECRET_LOG_BLACKLIST: [&str; 3] = ["DATABASE_URL", "OPSGENIE_API_KEY", "ETHERSCAN_API_KEY"];
+const SECRET_LOG_BLACKLIST: [&str; 6] = [
+    "DATABASE_URL",
+    "OPSGENIE_API_KEY",
+    "ETHERSCAN_API_KEY",
+    "ADMIN_TOKENS",
+    "BUNDLE_SIGNING_KEY",
+    "SUBMISSION_API_KEYS",
+];
 
 /// Get an environment variable, encoding found or missing as Option, and panic otherwise.
 pub fn get_env_var(key: &str) -> Option<String> {
@@ -0,0 +1,257 @@
+//! Resumable bulk export. Pages are keyed on the insertion id rather than an offset, so rows
+//! inserted while a client is paging through only ever show up on later pages.
+
+use crate::attestations::PriceValueEntry;
+use crate::state::AppState;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+const DEFAULT_PAGE_SIZE: i64 = 1000;
+const MAX_PAGE_SIZE: i64 = 10_000;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExportQuery {
+    /// The `next_cursor` of the previous page, omitted for the first page.
+    pub cursor: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExportPage<T> {
+    pub items: Vec<T>,
+    /// Cursor of the next page, null once the export is complete.
+    pub next_cursor: Option<i64>,
+}
+
+pub async fn get_price_values_export(
+    Query(query): Query<ExportQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ExportPage<PriceValueEntry>>, StatusCode> {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            id,
+            validator_public_key,
+            asset,
+            value,
+            slot_number,
+            signature,
+            signing_preimage,
+            price_timestamp,
+            (EXTRACT(EPOCH FROM received_at) * 1000)::BIGINT AS "received_at!"
+        FROM
+            price_value_attestations
+        WHERE
+            id > $1
+        ORDER BY
+            id
+        LIMIT $2;
+        "#,
+        query.cursor.unwrap_or(0),
+        limit,
+    )
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error exporting value attestations: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    // A short page means there is nothing left to export.
+    let next_cursor = match rows.last() {
+        Some(row) if rows.len() as i64 == limit => Some(row.id),
+        _ => None,
+    };
+    let items = rows
+        .into_iter()
+        .map(|row| PriceValueEntry {
+            validator_public_key: row.validator_public_key,
+            asset: row.asset,
+            value: row.value,
+            slot_number: row.slot_number,
+            signature: row.signature,
+            signing_preimage: row.signing_preimage,
+            price_timestamp: row.price_timestamp,
+            received_at: row.received_at,
+        })
+        .collect();
+    Ok(Json(ExportPage { items, next_cursor }))
+}
+
+/// Widest slot range a single contributions request may span, about a day.
+const MAX_CONTRIBUTIONS_SLOT_RANGE: i64 = 7200;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ContributionsQuery {
+    pub from_slot: i64,
+    /// Inclusive.
+    pub to_slot: i64,
+}
+
+/// A validator attesting to an interval in a slot.
+pub type Contribution = (String, i64, i64, i64);
+
+/// The validator by slot contribution matrix as a sparse list of (validator, slot, interval size,
+/// interval value) tuples, to load into a dataframe.
+pub async fn get_contributions(
+    Query(query): Query<ContributionsQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<Contribution>>, (StatusCode, String)> {
+    let num_slots = query.to_slot - query.from_slot + 1;
+    if !(1..=MAX_CONTRIBUTIONS_SLOT_RANGE).contains(&num_slots) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("slot range must span 1 to {MAX_CONTRIBUTIONS_SLOT_RANGE} slots"),
+        ));
+    }
+    let contributions = sqlx::query!(
+        "
+        SELECT
+            validator_public_key,
+            slot_number,
+            interval_size,
+            value
+        FROM
+            price_interval_attestations
+        WHERE
+            slot_number BETWEEN $1 AND $2
+        ORDER BY
+            slot_number,
+            validator_public_key,
+            interval_size,
+            value;
+        ",
+        query.from_slot,
+        query.to_slot,
+    )
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error getting contributions: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "error getting contributions".to_string(),
+        )
+    })?
+    .into_iter()
+    .map(|row| {
+        (
+            row.validator_public_key,
+            row.slot_number,
+            row.interval_size,
+            row.value,
+        )
+    })
+    .collect();
+    Ok(Json(contributions))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::DbPool;
+    use crate::test_utils::{
+        get_test_message, sign_oracle_message_with_new_key, value_message_from_new_validator,
+        TestApp, TEST_MESSAGE_SLOT,
+    };
+    use bls::SecretKey;
+    use std::collections::HashSet;
+
+    #[sqlx::test]
+    async fn exports_all_rows_in_chunks(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        for value in 1..=5 {
+            test_app
+                .post_oracle_message(&value_message_from_new_validator(value), StatusCode::OK)
+                .await;
+        }
+
+        let mut exported = Vec::new();
+        let mut cursor: Option<i64> = None;
+        let mut num_pages = 0;
+        loop {
+            let uri = match cursor {
+                Some(cursor) => format!("/export/price-values?limit=2&cursor={cursor}"),
+                None => "/export/price-values?limit=2".to_string(),
+            };
+            let response = test_app.get_expect(&uri, StatusCode::OK).await;
+            let page: ExportPage<PriceValueEntry> = serde_json::from_slice(&response).unwrap();
+            exported.extend(page.items);
+            num_pages += 1;
+            // Inserted mid-export, ends up on a later page
+            if num_pages == 1 {
+                test_app
+                    .post_oracle_message(&value_message_from_new_validator(6), StatusCode::OK)
+                    .await;
+            }
+            match page.next_cursor {
+                Some(next_cursor) => cursor = Some(next_cursor),
+                None => break,
+            }
+        }
+
+        assert_eq!(num_pages, 4);
+        let values: HashSet<i64> = exported.iter().map(|entry| entry.value).collect();
+        assert_eq!(exported.len(), 6);
+        assert_eq!(values, (1..=6).collect());
+
+        test_app
+            .get_expect("/export/price-values?limit=0", StatusCode::BAD_REQUEST)
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn lists_contributions_in_range(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let mut expected = Vec::new();
+        for slot_number in [TEST_MESSAGE_SLOT - 1, TEST_MESSAGE_SLOT] {
+            let mut message = get_test_message();
+            message.value_message.message.slot_number = slot_number;
+            message.interval_inclusion_messages.truncate(2);
+            for interval_message in &mut message.interval_inclusion_messages {
+                interval_message.message.slot_number = slot_number;
+            }
+            let message = sign_oracle_message_with_new_key(message, &SecretKey::random());
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+            if slot_number == TEST_MESSAGE_SLOT {
+                expected.extend(message.interval_inclusion_messages.iter().map(
+                    |interval_message| {
+                        (
+                            message.validator_public_key.to_string(),
+                            slot_number as i64,
+                            interval_message.message.interval_size as i64,
+                            interval_message.message.value as i64,
+                        )
+                    },
+                ));
+            }
+        }
+
+        let response = test_app
+            .get_expect(
+                &format!(
+                    "/contributions?from_slot={TEST_MESSAGE_SLOT}&to_slot={TEST_MESSAGE_SLOT}"
+                ),
+                StatusCode::OK,
+            )
+            .await;
+        let contributions: Vec<Contribution> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(contributions, expected);
+
+        test_app
+            .get_expect(
+                "/contributions?from_slot=0&to_slot=100000",
+                StatusCode::BAD_REQUEST,
+            )
+            .await;
+    }
+}
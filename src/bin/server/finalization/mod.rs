@@ -0,0 +1,238 @@
+//! Slots older than the configured age are finalized: their best quorum aggregate is snapshotted
+//! so the outcome of the slot stays fixed, whatever is stored for it later.
+
+use crate::attestations::{AggregatePriceIntervalEntry, DEFAULT_ASSET};
+use crate::consensus::get_best_quorum_aggregate;
+use crate::quorum::get_quorum_threshold;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+const FINALIZATION_INTERVAL: Duration = Duration::from_secs(12);
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FinalizedSlot {
+    pub slot_number: i64,
+    /// Milliseconds since the epoch.
+    pub finalized_at: i64,
+    /// Best aggregate reaching quorum when the slot was finalized, null if none did.
+    pub aggregate: Option<AggregatePriceIntervalEntry>,
+}
+
+pub async fn run_finalization_worker(state: Arc<AppState>) {
+    if state.config.finalize_after_slots.is_none() {
+        return;
+    }
+    let mut interval = tokio::time::interval(FINALIZATION_INTERVAL);
+    loop {
+        interval.tick().await;
+        match finalize_old_slots(&state).await {
+            Ok(0) => {}
+            Ok(num_finalized) => tracing::info!("Finalized {} slots", num_finalized),
+            Err(e) => tracing::error!("Error finalizing slots: {:?}", e),
+        }
+    }
+}
+
+/// Finalizes the slots with attestations that are at least the configured number of slots old
+/// and weren't finalized yet, returning how many.
+pub async fn finalize_old_slots(state: &AppState) -> eyre::Result<usize> {
+    let finalize_after_slots = match state.config.finalize_after_slots {
+        Some(finalize_after_slots) => finalize_after_slots,
+        None => return Ok(0),
+    };
+    let db_pool = &state.db_pool;
+    let to_slot = state.slot_clock.current_slot() as i64 - finalize_after_slots as i64;
+    let slot_numbers = sqlx::query!(
+        r#"
+        SELECT
+            slot_number AS "slot_number!"
+        FROM (
+            SELECT slot_number FROM price_value_attestations
+            UNION
+            SELECT slot_number FROM aggregate_interval_attestations
+        ) AS attested_slots
+        WHERE
+            slot_number <= $1
+        AND
+            slot_number NOT IN (SELECT slot_number FROM finalized_slots)
+        ORDER BY
+            slot_number;
+        "#,
+        to_slot
+    )
+    .fetch_all(db_pool)
+    .await?;
+
+    let min_operators = state.config.quorum_min_operators.unwrap_or(0);
+    for row in &slot_numbers {
+        let slot_number = row.slot_number;
+        // Consensus doesn't distinguish assets yet, the default asset's threshold applies.
+        let quorum_threshold = get_quorum_threshold(state, DEFAULT_ASSET, slot_number).unwrap_or(1);
+        let aggregate =
+            get_best_quorum_aggregate(db_pool, slot_number, quorum_threshold, min_operators)
+                .await?;
+        sqlx::query!(
+            "
+            INSERT INTO finalized_slots(
+                slot_number,
+                asset,
+                value,
+                interval_size,
+                aggregate_signature,
+                aggregate_public_key,
+                public_keys,
+                num_validators,
+                num_operators
+            )
+            VALUES (
+                $1,
+                $2,
+                $3,
+                $4,
+                $5,
+                $6,
+                $7,
+                $8,
+                $9
+            )
+            ON CONFLICT (slot_number) DO NOTHING;
+            ",
+            slot_number,
+            aggregate.as_ref().map(|entry| entry.asset.clone()),
+            aggregate.as_ref().map(|entry| entry.value),
+            aggregate.as_ref().map(|entry| entry.interval_size),
+            aggregate
+                .as_ref()
+                .map(|entry| entry.aggregate_signature.clone()),
+            aggregate
+                .as_ref()
+                .map(|entry| entry.aggregate_public_key.clone()),
+            aggregate.as_ref().map(|entry| entry.public_keys.as_slice()),
+            aggregate.as_ref().map(|entry| entry.num_validators),
+            aggregate.as_ref().map(|entry| entry.num_operators),
+        )
+        .execute(db_pool)
+        .await?;
+    }
+    Ok(slot_numbers.len())
+}
+
+pub async fn get_finalized_slot(
+    Path(slot_number): Path<i64>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<FinalizedSlot>, StatusCode> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            slot_number,
+            (EXTRACT(EPOCH FROM finalized_at) * 1000)::BIGINT AS "finalized_at!",
+            asset,
+            value,
+            interval_size,
+            aggregate_signature,
+            aggregate_public_key,
+            public_keys,
+            num_validators,
+            num_operators
+        FROM
+            finalized_slots
+        WHERE
+            slot_number = $1;
+        "#,
+        slot_number
+    )
+    .fetch_optional(&state.db_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error getting finalized slot: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let aggregate = match (row.asset, row.value, row.interval_size) {
+        (Some(asset), Some(value), Some(interval_size)) => Some(AggregatePriceIntervalEntry {
+            asset,
+            value,
+            slot_number: row.slot_number,
+            aggregate_signature: row.aggregate_signature.unwrap_or_default(),
+            aggregate_public_key: row.aggregate_public_key.unwrap_or_default(),
+            interval_size,
+            num_validators: row.num_validators.unwrap_or_default(),
+            num_operators: row.num_operators.unwrap_or_default(),
+            public_keys: row.public_keys.unwrap_or_default(),
+            finalized: None,
+        }),
+        _ => None,
+    };
+    Ok(Json(FinalizedSlot {
+        slot_number: row.slot_number,
+        finalized_at: row.finalized_at,
+        aggregate,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Config;
+    use crate::db::DbPool;
+    use crate::test_utils::{
+        get_test_message, state_at_slot, value_message_from_new_validator, TestApp,
+        TEST_MESSAGE_SLOT,
+    };
+
+    #[sqlx::test]
+    async fn finalizes_slots_once_old_enough(db_pool: DbPool) {
+        let config = Config {
+            quorum_threshold: Some(1),
+            finalize_after_slots: Some(4),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool.clone(), config.clone()).await;
+        let mut message = get_test_message();
+        message.interval_inclusion_messages.truncate(1);
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
+
+        // Too recent at first
+        let state = state_at_slot(db_pool.clone(), config.clone(), TEST_MESSAGE_SLOT + 3);
+        assert_eq!(finalize_old_slots(&state).await.unwrap(), 0);
+        test_app
+            .get_expect(
+                &format!("/finalized/{TEST_MESSAGE_SLOT}"),
+                StatusCode::NOT_FOUND,
+            )
+            .await;
+
+        let state = state_at_slot(db_pool.clone(), config.clone(), TEST_MESSAGE_SLOT + 4);
+        assert_eq!(finalize_old_slots(&state).await.unwrap(), 1);
+        // Only once
+        assert_eq!(finalize_old_slots(&state).await.unwrap(), 0);
+        let response = test_app
+            .get_expect(&format!("/finalized/{TEST_MESSAGE_SLOT}"), StatusCode::OK)
+            .await;
+        let finalized_slot: FinalizedSlot = serde_json::from_slice(&response).unwrap();
+        let aggregate = finalized_slot.aggregate.unwrap();
+        assert_eq!(
+            aggregate.value,
+            message.interval_inclusion_messages[0].message.value as i64
+        );
+        assert_eq!(aggregate.num_validators, 1);
+
+        // Later attestations don't change the snapshot
+        test_app
+            .post_oracle_message(&value_message_from_new_validator(1), StatusCode::OK)
+            .await;
+        let response = test_app
+            .get_expect(&format!("/finalized/{TEST_MESSAGE_SLOT}"), StatusCode::OK)
+            .await;
+        let snapshot: FinalizedSlot = serde_json::from_slice(&response).unwrap();
+        assert_eq!(snapshot.finalized_at, finalized_slot.finalized_at);
+    }
+}
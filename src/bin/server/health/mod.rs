@@ -0,0 +1,401 @@
+use crate::attestations::DEFAULT_ASSET;
+use crate::consensus::get_best_quorum_aggregate;
+use crate::db::{DbPool, MIGRATOR};
+use crate::quorum::get_quorum_threshold;
+use crate::state::AppState;
+use axum::{
+    extract::State,
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long the database gets to answer the readiness probe.
+const READY_TIMEOUT: Duration = Duration::from_secs(2);
+
+const EXPECTED_TABLES: [&str; 14] = [
+    "price_value_attestations",
+    "price_interval_attestations",
+    "aggregate_interval_attestations",
+    "settings",
+    "validator_offences",
+    "quarantined_validators",
+    "admin_audit",
+    "pending_notifications",
+    "asset_totals",
+    "deferred_messages",
+    "validator_set_sizes",
+    "finalized_slots",
+    "equivocations",
+    "idempotency_keys",
+];
+
+const EXPECTED_INDEXES: [&str; 18] = [
+    "price_value_attestations_pkey",
+    "price_interval_attestations_pkey",
+    "aggregate_interval_attestations_pkey",
+    "aggregate_interval_attestations_asset_key_idx",
+    "settings_pkey",
+    "validator_offences_validator_public_key_offence_idx",
+    "quarantined_validators_pkey",
+    "admin_audit_pkey",
+    "pending_notifications_pkey",
+    "asset_totals_pkey",
+    "deferred_messages_pkey",
+    "deferred_messages_validator_public_key_asset_slot_number_key",
+    "price_value_attestations_id_idx",
+    "validator_set_sizes_pkey",
+    "finalized_slots_pkey",
+    "equivocations_pkey",
+    "idempotency_keys_pkey",
+    "idempotency_keys_created_at_idx",
+];
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReadinessCheck {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeepReadiness {
+    pub ok: bool,
+    pub checks: Vec<ReadinessCheck>,
+}
+
+/// Liveness probe, succeeds as long as the process serves requests.
+pub async fn get_health_live() -> StatusCode {
+    StatusCode::OK
+}
+
+/// Readiness probe, succeeds only if the database answers a trivial query in time. Unlike
+/// `/ready/deep` it doesn't touch the schema, so it is cheap enough to poll.
+pub async fn get_health_ready(State(state): State<Arc<AppState>>) -> StatusCode {
+    let query = sqlx::query("SELECT 1").execute(&state.db_pool);
+    match tokio::time::timeout(READY_TIMEOUT, query).await {
+        Ok(Ok(_)) => StatusCode::OK,
+        Ok(Err(e)) => {
+            tracing::warn!("Database not ready: {:?}", e);
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+        Err(_) => {
+            tracing::warn!("Database didn't answer within {:?}", READY_TIMEOUT);
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+    }
+}
+
+/// Checks the schema, migration version and required config, returning 503 if any check fails.
+pub async fn get_ready_deep(
+    State(state): State<Arc<AppState>>,
+) -> (StatusCode, Json<DeepReadiness>) {
+    let db_pool = &state.db_pool;
+    let mut checks = Vec::new();
+
+    for table in EXPECTED_TABLES {
+        let name = format!("table:{table}");
+        checks.push(match table_exists(db_pool, table).await {
+            Ok(true) => ReadinessCheck::passed(name, "exists".to_string()),
+            Ok(false) => ReadinessCheck::failed(name, "missing".to_string()),
+            Err(err) => ReadinessCheck::failed(name, err.to_string()),
+        });
+    }
+
+    for index in EXPECTED_INDEXES {
+        let name = format!("index:{index}");
+        checks.push(match index_exists(db_pool, index).await {
+            Ok(true) => ReadinessCheck::passed(name, "exists".to_string()),
+            Ok(false) => ReadinessCheck::failed(name, "missing".to_string()),
+            Err(err) => ReadinessCheck::failed(name, err.to_string()),
+        });
+    }
+
+    checks.push(check_migration_version(db_pool).await);
+
+    let genesis_time = state.slot_clock.genesis_time();
+    let name = "config:genesis_time".to_string();
+    checks.push(if genesis_time > 0 {
+        ReadinessCheck::passed(name, genesis_time.to_string())
+    } else {
+        ReadinessCheck::failed(name, "not set".to_string())
+    });
+    let name = "config:quorum_threshold".to_string();
+    let current_slot = state.slot_clock.current_slot() as i64;
+    checks.push(
+        match get_quorum_threshold(&state, DEFAULT_ASSET, current_slot) {
+            Some(quorum_threshold) if quorum_threshold > 0 => {
+                ReadinessCheck::passed(name, quorum_threshold.to_string())
+            }
+            _ => ReadinessCheck::failed(name, "not set".to_string()),
+        },
+    );
+
+    let ok = checks.iter().all(|check| check.ok);
+    let status_code = if ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status_code, Json(DeepReadiness { ok, checks }))
+}
+
+/// Single line summary for terminal checks, e.g.
+/// `slot=6556020 latest_slot=6556020 validators=3 quorum=yes`.
+pub async fn get_status(
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let db_pool = &state.db_pool;
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            MAX(slot_number) AS latest_slot,
+            COUNT(DISTINCT validator_public_key) AS "num_validators!"
+        FROM
+            price_value_attestations;
+        "#
+    )
+    .fetch_one(db_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error getting status: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let reached_quorum = match row.latest_slot {
+        Some(latest_slot) => get_best_quorum_aggregate(
+            db_pool,
+            latest_slot,
+            get_quorum_threshold(&state, DEFAULT_ASSET, latest_slot).unwrap_or(1),
+            state.config.quorum_min_operators.unwrap_or(0),
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting quorum aggregate: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .is_some(),
+        None => false,
+    };
+    let latest_slot = row
+        .latest_slot
+        .map(|latest_slot| latest_slot.to_string())
+        .unwrap_or_else(|| "none".to_string());
+    let line = format!(
+        "slot={} latest_slot={} validators={} quorum={}\n",
+        state.slot_clock.current_slot(),
+        latest_slot,
+        row.num_validators,
+        if reached_quorum { "yes" } else { "no" }
+    );
+    Ok(([(CONTENT_TYPE, "text/plain")], line))
+}
+
+impl ReadinessCheck {
+    fn passed(name: String, detail: String) -> Self {
+        ReadinessCheck {
+            name,
+            ok: true,
+            detail,
+        }
+    }
+
+    fn failed(name: String, detail: String) -> Self {
+        ReadinessCheck {
+            name,
+            ok: false,
+            detail,
+        }
+    }
+}
+
+async fn table_exists(db_pool: &DbPool, table: &str) -> eyre::Result<bool> {
+    let exists = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT
+                1
+            FROM
+                information_schema.tables
+            WHERE
+                table_schema = current_schema()
+            AND
+                table_name = $1
+        ) AS "exists!";
+        "#,
+        table
+    )
+    .fetch_one(db_pool)
+    .await?
+    .exists;
+    Ok(exists)
+}
+
+async fn index_exists(db_pool: &DbPool, index: &str) -> eyre::Result<bool> {
+    let exists = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT
+                1
+            FROM
+                pg_indexes
+            WHERE
+                schemaname = current_schema()
+            AND
+                indexname = $1
+        ) AS "exists!";
+        "#,
+        index
+    )
+    .fetch_one(db_pool)
+    .await?
+    .exists;
+    Ok(exists)
+}
+
+async fn check_migration_version(db_pool: &DbPool) -> ReadinessCheck {
+    let name = "migrations:version".to_string();
+    let expected_version = MIGRATOR.iter().map(|migration| migration.version).max();
+    // The migrations table is created by sqlx itself, so its schema isn't checked at compile time.
+    let applied_version: Result<Option<i64>, sqlx::Error> =
+        sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations WHERE success;")
+            .fetch_one(db_pool)
+            .await;
+    match applied_version {
+        Ok(applied_version) => {
+            let detail = format!("applied {applied_version:?}, expected {expected_version:?}");
+            if applied_version == expected_version {
+                ReadinessCheck::passed(name, detail)
+            } else {
+                ReadinessCheck::failed(name, detail)
+            }
+        }
+        Err(err) => ReadinessCheck::failed(name, err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_utils::{
+        get_test_message, sign_oracle_message_with_new_key, TestApp, TEST_MESSAGE_SLOT,
+    };
+    use bls::SecretKey;
+    use hyper::http::StatusCode;
+
+    #[sqlx::test]
+    async fn readiness_fails_without_database(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool.clone()).await;
+        test_app.get_expect("/health/ready", StatusCode::OK).await;
+
+        db_pool.close().await;
+        test_app
+            .get_expect("/health/ready", StatusCode::SERVICE_UNAVAILABLE)
+            .await;
+        test_app.get_expect("/health/live", StatusCode::OK).await;
+    }
+
+    #[sqlx::test]
+    async fn deep_readiness_passes_with_full_schema_and_config(db_pool: DbPool) {
+        let config = Config {
+            quorum_threshold: Some(3),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+
+        let response = test_app.get_expect("/ready/deep", StatusCode::OK).await;
+        let readiness: DeepReadiness = serde_json::from_slice(&response).unwrap();
+        assert!(readiness.ok);
+        assert!(readiness.checks.iter().all(|check| check.ok));
+    }
+
+    #[sqlx::test]
+    async fn deep_readiness_reports_missing_table(db_pool: DbPool) {
+        sqlx::query("DROP TABLE aggregate_interval_attestations;")
+            .execute(&db_pool)
+            .await
+            .unwrap();
+        let config = Config {
+            quorum_threshold: Some(3),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+
+        let response = test_app
+            .get_expect("/ready/deep", StatusCode::SERVICE_UNAVAILABLE)
+            .await;
+        let readiness: DeepReadiness = serde_json::from_slice(&response).unwrap();
+        assert!(!readiness.ok);
+        let failed_checks: Vec<&str> = readiness
+            .checks
+            .iter()
+            .filter(|check| !check.ok)
+            .map(|check| check.name.as_str())
+            .collect();
+        assert_eq!(
+            failed_checks,
+            vec![
+                "table:aggregate_interval_attestations",
+                "index:aggregate_interval_attestations_pkey",
+                "index:aggregate_interval_attestations_asset_key_idx"
+            ]
+        );
+    }
+
+    #[sqlx::test]
+    async fn deep_readiness_reports_missing_quorum_threshold(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+
+        let response = test_app
+            .get_expect("/ready/deep", StatusCode::SERVICE_UNAVAILABLE)
+            .await;
+        let readiness: DeepReadiness = serde_json::from_slice(&response).unwrap();
+        let quorum_check = readiness
+            .checks
+            .iter()
+            .find(|check| check.name == "config:quorum_threshold")
+            .unwrap();
+        assert!(!quorum_check.ok);
+    }
+
+    async fn get_status_line(test_app: &TestApp) -> String {
+        let response = test_app.get_expect("/status", StatusCode::OK).await;
+        String::from_utf8(response.to_vec()).unwrap()
+    }
+
+    #[sqlx::test]
+    async fn summarizes_status_in_one_line(db_pool: DbPool) {
+        let config = Config {
+            quorum_threshold: Some(2),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        assert_eq!(
+            get_status_line(&test_app).await,
+            format!("slot={TEST_MESSAGE_SLOT} latest_slot=none validators=0 quorum=no\n")
+        );
+
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(1);
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+        assert_eq!(
+            get_status_line(&test_app).await,
+            format!(
+                "slot={TEST_MESSAGE_SLOT} latest_slot={TEST_MESSAGE_SLOT} validators=1 quorum=no\n"
+            )
+        );
+
+        let message = sign_oracle_message_with_new_key(test_message, &SecretKey::random());
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
+        assert_eq!(
+            get_status_line(&test_app).await,
+            format!("slot={TEST_MESSAGE_SLOT} latest_slot={TEST_MESSAGE_SLOT} validators=2 quorum=yes\n")
+        );
+    }
+}
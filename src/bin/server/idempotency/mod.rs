@@ -0,0 +1,267 @@
+//! Clients that time out on a submission can't tell whether it was stored. Sending an
+//! `Idempotency-Key` header makes retrying it safe: the outcome of the first submission with the
+//! key is stored and returned to retries, instead of processing the message again.
+//!
+//! Keys are scoped to the validator the message claims to be from, which isn't authenticated
+//! until its signatures are verified. Responses are therefore only replayed to retries of the
+//! same message, and rejections anyone could have caused aren't stored at all.
+
+use crate::db::DbPool;
+use crate::state::AppState;
+use axum::http::{HeaderMap, StatusCode};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// How often responses older than the TTL are deleted.
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// The response a submission got, replayed to retries.
+#[derive(Debug, PartialEq)]
+pub struct StoredResponse {
+    pub status_code: StatusCode,
+    pub body: String,
+    /// Digest of the message the response is to, retries with another message are rejected.
+    pub body_digest: String,
+}
+
+pub fn get_idempotency_key(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|idempotency_key| !idempotency_key.is_empty())
+}
+
+/// Whether retries should get the response as is. Failures that may pass on a later attempt
+/// are processed again instead.
+pub fn is_replayable(status_code: StatusCode) -> bool {
+    !status_code.is_server_error() && status_code != StatusCode::TOO_MANY_REQUESTS
+}
+
+/// The response stored for the key of the validator, unless older than `ttl_seconds`.
+pub async fn get_stored_response(
+    db_pool: &DbPool,
+    validator_public_key: &str,
+    idempotency_key: &str,
+    ttl_seconds: u64,
+) -> eyre::Result<Option<StoredResponse>> {
+    let row = sqlx::query!(
+        "
+        SELECT
+            status_code,
+            body,
+            body_digest
+        FROM
+            idempotency_keys
+        WHERE
+            validator_public_key = $1
+        AND
+            idempotency_key = $2
+        AND
+            created_at > NOW() - make_interval(secs => $3);
+        ",
+        validator_public_key,
+        idempotency_key,
+        ttl_seconds as f64,
+    )
+    .fetch_optional(db_pool)
+    .await?;
+    row.map(|row| {
+        Ok(StoredResponse {
+            status_code: StatusCode::from_u16(row.status_code as u16)?,
+            body: row.body,
+            body_digest: row.body_digest,
+        })
+    })
+    .transpose()
+}
+
+/// Stores the response for the key of the validator, replacing a stored response only once it
+/// is older than `ttl_seconds`.
+pub async fn store_response(
+    db_pool: &DbPool,
+    validator_public_key: &str,
+    idempotency_key: &str,
+    ttl_seconds: u64,
+    response: &StoredResponse,
+) -> sqlx::Result<()> {
+    sqlx::query!(
+        "
+        INSERT INTO idempotency_keys(
+            validator_public_key,
+            idempotency_key,
+            status_code,
+            body,
+            body_digest
+        )
+        VALUES (
+            $1,
+            $2,
+            $3,
+            $4,
+            $5
+        )
+        ON CONFLICT (validator_public_key, idempotency_key) DO UPDATE SET
+            status_code = EXCLUDED.status_code,
+            body = EXCLUDED.body,
+            body_digest = EXCLUDED.body_digest,
+            created_at = NOW()
+        WHERE
+            idempotency_keys.created_at <= NOW() - make_interval(secs => $6);
+        ",
+        validator_public_key,
+        idempotency_key,
+        response.status_code.as_u16() as i32,
+        response.body,
+        response.body_digest,
+        ttl_seconds as f64,
+    )
+    .execute(db_pool)
+    .await?;
+    Ok(())
+}
+
+/// Deletes the responses older than `ttl_seconds`, returning how many were deleted.
+pub async fn delete_expired_responses(db_pool: &DbPool, ttl_seconds: u64) -> sqlx::Result<u64> {
+    let result = sqlx::query!(
+        "
+        DELETE FROM idempotency_keys
+        WHERE
+            created_at <= NOW() - make_interval(secs => $1);
+        ",
+        ttl_seconds as f64,
+    )
+    .execute(db_pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+pub async fn run_idempotency_key_expiry_worker(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(EXPIRY_INTERVAL);
+    loop {
+        interval.tick().await;
+        match delete_expired_responses(&state.db_pool, state.config.idempotency_key_ttl_seconds)
+            .await
+        {
+            Ok(num_deleted) if num_deleted > 0 => {
+                tracing::info!("Deleted {} expired idempotency keys", num_deleted)
+            }
+            Ok(_) => {}
+            Err(e) => tracing::error!("Error deleting expired idempotency keys: {:?}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::delete_expired_responses;
+    use crate::attestations::PriceValueEntry;
+    use crate::db::DbPool;
+    use crate::test_utils::{
+        signature_from_random_signer, value_message, value_message_from_new_validator, TestApp,
+        TEST_MESSAGE_SLOT,
+    };
+    use axum::{body::Body, http::StatusCode};
+    use bls::SecretKey;
+
+    async fn post_with_key(
+        test_app: &TestApp,
+        body: &str,
+        idempotency_key: &str,
+        expected_code: StatusCode,
+    ) -> String {
+        let response = test_app
+            .post_expect_with_headers(
+                "/post_oracle_message",
+                Body::from(body.to_string()),
+                &[("Idempotency-Key", idempotency_key)],
+                expected_code,
+            )
+            .await;
+        String::from_utf8(response.to_vec()).unwrap()
+    }
+
+    #[sqlx::test]
+    async fn replays_response_to_retries(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let message = value_message_from_new_validator(1811093163);
+        let body = serde_json::to_string(&message).unwrap();
+        let first_response = post_with_key(&test_app, &body, "retry-1", StatusCode::OK).await;
+        let second_response = post_with_key(&test_app, &body, "retry-1", StatusCode::OK).await;
+        assert_eq!(first_response, second_response);
+
+        let response = test_app
+            .get_expect("/price_value_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceValueEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 1);
+
+        // Another key is a new submission
+        post_with_key(&test_app, &body, "retry-2", StatusCode::CONFLICT).await;
+        // As is one without a key
+        test_app
+            .post_oracle_message(&message, StatusCode::CONFLICT)
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn replays_rejections(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let message = value_message_from_new_validator(1811093163);
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
+        let body = serde_json::to_string(&message).unwrap();
+        let first_response = post_with_key(&test_app, &body, "retry-1", StatusCode::CONFLICT).await;
+        let second_response =
+            post_with_key(&test_app, &body, "retry-1", StatusCode::CONFLICT).await;
+        assert_eq!(first_response, second_response);
+        assert!(!first_response.is_empty());
+    }
+
+    #[sqlx::test]
+    async fn rejects_reuse_of_key_for_another_message(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let private_key = SecretKey::random();
+        let message = value_message(&private_key, TEST_MESSAGE_SLOT, 1811093163);
+        let body = serde_json::to_string(&message).unwrap();
+        post_with_key(&test_app, &body, "retry-1", StatusCode::OK).await;
+
+        let other_message = value_message(&private_key, TEST_MESSAGE_SLOT + 1, 1811093163);
+        let other_body = serde_json::to_string(&other_message).unwrap();
+        post_with_key(
+            &test_app,
+            &other_body,
+            "retry-1",
+            StatusCode::UNPROCESSABLE_ENTITY,
+        )
+        .await;
+        post_with_key(&test_app, &other_body, "retry-2", StatusCode::OK).await;
+    }
+
+    #[sqlx::test]
+    async fn rejected_signatures_do_not_claim_the_key(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let message = value_message_from_new_validator(1811093163);
+        let mut forged_message = message.clone();
+        forged_message.value_message.signature =
+            signature_from_random_signer(&forged_message.value_message.message);
+        let forged_body = serde_json::to_string(&forged_message).unwrap();
+        post_with_key(&test_app, &forged_body, "retry-1", StatusCode::BAD_REQUEST).await;
+
+        let body = serde_json::to_string(&message).unwrap();
+        post_with_key(&test_app, &body, "retry-1", StatusCode::OK).await;
+    }
+
+    #[sqlx::test]
+    async fn deletes_expired_responses(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool.clone()).await;
+        let message = value_message_from_new_validator(1811093163);
+        let body = serde_json::to_string(&message).unwrap();
+        post_with_key(&test_app, &body, "retry-1", StatusCode::OK).await;
+
+        assert_eq!(delete_expired_responses(&db_pool, 60).await.unwrap(), 0);
+        assert_eq!(delete_expired_responses(&db_pool, 0).await.unwrap(), 1);
+        // The retry is a new submission now
+        post_with_key(&test_app, &body, "retry-1", StatusCode::CONFLICT).await;
+    }
+}
@@ -0,0 +1,229 @@
+//! Re-importing attestations collected earlier, e.g. when migrating to a new instance or
+//! recovering a wiped DB, without soliciting the validators again.
+
+use crate::attestations::{import_oracle_message, OracleMessage, OracleMessageError};
+use crate::scheme::find_min_sig_encoding;
+use crate::state::AppState;
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ImportSummary {
+    pub num_imported: usize,
+    /// Messages that were already stored.
+    pub num_duplicates: usize,
+    /// Messages that don't parse, have an invalid signature or are otherwise rejected.
+    pub num_rejected: usize,
+}
+
+/// Imports newline delimited JSON oracle messages, re-verifying their signatures and aggregating
+/// them like submitted ones, whatever their slot. Stops at the first DB error, importing the same
+/// dump again is safe as what is already stored is skipped. Large dumps need `MAX_BODY_BYTES`
+/// raised.
+pub async fn post_import(
+    State(state): State<Arc<AppState>>,
+    body: String,
+) -> Result<Json<ImportSummary>, StatusCode> {
+    let mut summary = ImportSummary {
+        num_imported: 0,
+        num_duplicates: 0,
+        num_rejected: 0,
+    };
+    let lines = body
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    for (index, line) in lines {
+        let message: OracleMessage = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(e) => {
+                // Name the field encoded for the min-sig variant rather than the serde error.
+                let reason = serde_json::from_str(line)
+                    .ok()
+                    .and_then(|message| find_min_sig_encoding(&message))
+                    .unwrap_or_else(|| e.to_string());
+                tracing::warn!("Rejecting unparsable import line {}: {}", index + 1, reason);
+                summary.num_rejected += 1;
+                continue;
+            }
+        };
+        match import_oracle_message(&state, &message).await {
+            Ok(()) => summary.num_imported += 1,
+            Err(OracleMessageError::Duplicate) => summary.num_duplicates += 1,
+            Err(OracleMessageError::Internal(e)) => {
+                tracing::error!("Error importing line {}: {:?}", index + 1, e);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+            Err(OracleMessageError::Unavailable) => return Err(StatusCode::SERVICE_UNAVAILABLE),
+            Err(e) => {
+                tracing::warn!("Rejecting import line {}: {}", index + 1, e);
+                summary.num_rejected += 1;
+            }
+        }
+    }
+    tracing::info!(
+        "Imported {} messages, skipped {} duplicates and rejected {}",
+        summary.num_imported,
+        summary.num_duplicates,
+        summary.num_rejected
+    );
+    Ok(Json(summary))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::attestations::{
+        AggregatePriceIntervalEntry, IntervalInclusionMessage, Price, PriceIntervalEntry,
+        PriceValueEntry, PriceValueMessage, SignedIntervalInclusionMessage,
+        SignedPriceValueMessage,
+    };
+    use crate::db::DbPool;
+    use crate::test_utils::{
+        admin_config, get_test_message, sign_oracle_message_with_new_key,
+        signature_from_random_signer, TestApp, ADMIN_AUTHORIZATION, TEST_MESSAGE_SLOT,
+    };
+    use axum::body::Body;
+    use bls::SecretKey;
+    use std::collections::HashMap;
+
+    /// Rebuilds the oracle messages from the exported attestations.
+    fn messages_from_export(
+        value_entries: Vec<PriceValueEntry>,
+        interval_entries: Vec<PriceIntervalEntry>,
+    ) -> Vec<OracleMessage> {
+        let mut interval_messages: HashMap<String, Vec<SignedIntervalInclusionMessage>> =
+            HashMap::new();
+        for entry in interval_entries {
+            interval_messages
+                .entry(entry.validator_public_key)
+                .or_default()
+                .push(SignedIntervalInclusionMessage {
+                    message: IntervalInclusionMessage {
+                        value: entry.value as u64,
+                        interval_size: entry.interval_size as u64,
+                        slot_number: entry.slot_number as u64,
+                    },
+                    signature: entry.signature.parse().unwrap(),
+                });
+        }
+        value_entries
+            .into_iter()
+            .map(|entry| OracleMessage {
+                asset: entry.asset,
+                value_message: SignedPriceValueMessage {
+                    message: PriceValueMessage {
+                        price: Price {
+                            value: entry.value as u64,
+                            timestamp: entry.price_timestamp.map(|timestamp| timestamp as u64),
+                        },
+                        slot_number: entry.slot_number as u64,
+                    },
+                    signature: entry.signature.parse().unwrap(),
+                },
+                interval_inclusion_messages: interval_messages
+                    .remove(&entry.validator_public_key)
+                    .unwrap_or_default(),
+                validator_public_key: entry.validator_public_key.parse().unwrap(),
+                unknown_fields: HashMap::new(),
+            })
+            .collect()
+    }
+
+    async fn get_sorted_aggregates(test_app: &TestApp) -> Vec<AggregatePriceIntervalEntry> {
+        let response = test_app
+            .get_expect("/aggregate_price_interval_attestations", StatusCode::OK)
+            .await;
+        let mut aggregates: Vec<AggregatePriceIntervalEntry> =
+            serde_json::from_slice(&response).unwrap();
+        for aggregate in aggregates.iter_mut() {
+            // Aggregation order depends on the order of import.
+            aggregate.public_keys.sort();
+        }
+        aggregates.sort_by_key(|aggregate| (aggregate.interval_size, aggregate.value));
+        aggregates
+    }
+
+    #[sqlx::test]
+    async fn imports_exported_attestations_into_wiped_db(db_pool: DbPool) {
+        let test_app = TestApp::with_config(db_pool.clone(), admin_config()).await;
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(3);
+        for _ in 0..3 {
+            let message =
+                sign_oracle_message_with_new_key(test_message.clone(), &SecretKey::random());
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+        }
+        let original_aggregates = get_sorted_aggregates(&test_app).await;
+        assert_eq!(original_aggregates.len(), 3);
+
+        let response = test_app
+            .get_expect("/price_value_attestations", StatusCode::OK)
+            .await;
+        let value_entries: Vec<PriceValueEntry> = serde_json::from_slice(&response).unwrap();
+        let response = test_app
+            .get_expect("/price_interval_attestations", StatusCode::OK)
+            .await;
+        let interval_entries: Vec<PriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        let messages = messages_from_export(value_entries, interval_entries);
+        let mut dump: String = messages
+            .iter()
+            .map(|message| format!("{}\n", serde_json::to_string(message).unwrap()))
+            .collect();
+
+        test_app
+            .post_expect_with_headers(
+                &format!("/admin/delete-slots?from={TEST_MESSAGE_SLOT}&to={TEST_MESSAGE_SLOT}"),
+                Body::empty(),
+                &[ADMIN_AUTHORIZATION],
+                StatusCode::OK,
+            )
+            .await;
+        assert!(get_sorted_aggregates(&test_app).await.is_empty());
+
+        // Long after the slot, when submitting its messages would be outside the window
+        let later_app =
+            TestApp::with_config_at_slot(db_pool, admin_config(), TEST_MESSAGE_SLOT + 1000).await;
+        let mut forged_message = messages[0].clone();
+        forged_message.value_message.signature =
+            signature_from_random_signer(&forged_message.value_message.message);
+        dump.push_str(&format!(
+            "{}\n",
+            serde_json::to_string(&forged_message).unwrap()
+        ));
+        dump.push_str("not json\n");
+        let response = later_app
+            .post_expect_with_headers(
+                "/admin/import",
+                Body::from(dump.clone()),
+                &[ADMIN_AUTHORIZATION],
+                StatusCode::OK,
+            )
+            .await;
+        let summary: ImportSummary = serde_json::from_slice(&response).unwrap();
+        assert_eq!(summary.num_imported, 3);
+        assert_eq!(summary.num_duplicates, 0);
+        assert_eq!(summary.num_rejected, 2);
+
+        let imported_aggregates = get_sorted_aggregates(&later_app).await;
+        assert_eq!(
+            serde_json::to_value(&imported_aggregates).unwrap(),
+            serde_json::to_value(&original_aggregates).unwrap()
+        );
+
+        // Importing again skips what is already stored
+        let response = later_app
+            .post_expect_with_headers(
+                "/admin/import",
+                Body::from(dump),
+                &[ADMIN_AUTHORIZATION],
+                StatusCode::OK,
+            )
+            .await;
+        let summary: ImportSummary = serde_json::from_slice(&response).unwrap();
+        assert_eq!(summary.num_imported, 0);
+        assert_eq!(summary.num_duplicates, 3);
+        assert_eq!(summary.num_rejected, 2);
+    }
+}
@@ -0,0 +1,107 @@
+//! Bounded cache evicting the least recently used entry once full.
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+pub struct LruCache<K, V> {
+    capacity: usize,
+    /// Values and the tick at which they were last used.
+    entries: HashMap<K, (V, u64)>,
+    /// Keys by the tick at which they were last used, oldest first.
+    recency: BTreeMap<u64, K>,
+    next_tick: u64,
+}
+
+impl<K: Clone + Eq + Hash, V: Copy> LruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        LruCache {
+            capacity,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            next_tick: 0,
+        }
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.next_tick += 1;
+        self.next_tick
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<V> {
+        let tick = self.tick();
+        let (value, last_used) = self.entries.get_mut(key)?;
+        let key = self
+            .recency
+            .remove(last_used)
+            .expect("expect every entry to have a recency");
+        *last_used = tick;
+        let value = *value;
+        self.recency.insert(tick, key);
+        Some(value)
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        let tick = self.tick();
+        if let Some((_, last_used)) = self.entries.get(&key) {
+            self.recency.remove(last_used);
+        } else if self.entries.len() >= self.capacity {
+            if let Some((_, oldest_key)) = self.recency.pop_first() {
+                self.entries.remove(&oldest_key);
+            }
+        }
+        self.recency.insert(tick, key.clone());
+        self.entries.insert(key, (value, tick));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::attestations::ingest_oracle_message;
+    use crate::config::Config;
+    use crate::db::DbPool;
+    use crate::test_utils::{state_at_slot, value_message, TEST_MESSAGE_SLOT};
+    use axum::http::StatusCode;
+    use bls::SecretKey;
+    use std::sync::atomic::Ordering;
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut cache = LruCache::new(2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        assert_eq!(cache.get(&"a"), Some(1));
+        cache.insert("c", 3);
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.get(&"a"), Some(1));
+        assert_eq!(cache.get(&"c"), Some(3));
+    }
+
+    #[sqlx::test]
+    async fn serves_repeated_verifications_from_cache(db_pool: DbPool) {
+        let config = Config {
+            signature_cache_capacity: Some(100),
+            ..Config::default()
+        };
+        let state = state_at_slot(db_pool, config, TEST_MESSAGE_SLOT);
+        let message = value_message(&SecretKey::random(), TEST_MESSAGE_SLOT, 1811093163);
+        ingest_oracle_message(&state, &message).await.unwrap();
+        assert_eq!(state.num_signature_verifications.load(Ordering::Relaxed), 1);
+
+        // The retry still gets rejected as a duplicate, without verifying again
+        assert_eq!(
+            ingest_oracle_message(&state, &message)
+                .await
+                .map_err(|err| err.status_code()),
+            Err(StatusCode::CONFLICT)
+        );
+        assert_eq!(state.num_signature_verifications.load(Ordering::Relaxed), 1);
+
+        let other_message = value_message(&SecretKey::random(), TEST_MESSAGE_SLOT, 1811093163);
+        ingest_oracle_message(&state, &other_message).await.unwrap();
+        assert_eq!(state.num_signature_verifications.load(Ordering::Relaxed), 2);
+    }
+}
@@ -1,18 +1,65 @@
-use std::net::SocketAddr;
+use shutdown::{serve_until_shutdown, shutdown_signal};
+use std::net::{SocketAddr, TcpListener};
+mod admin;
+mod aggregates;
+mod api_keys;
 mod app;
+mod assets;
 mod attestations;
+mod bloom;
+mod bundle;
+mod canonical;
+mod config;
+mod consensus;
+mod cors;
+mod csv;
 mod db;
+mod deferred;
+mod encoding;
 mod env;
+mod export;
+mod finalization;
+mod health;
+mod idempotency;
+mod import;
+mod lru_cache;
+mod metrics;
+mod notifications;
+mod price;
+mod proof;
+mod pruning;
+mod quarantine;
+mod quorum;
+mod rate_limit;
+mod reconciliation;
+mod registry;
+mod request_id;
+mod scheme;
+mod shutdown;
+mod slashing;
+mod slot_clock;
+mod slots;
 mod state;
+mod stats;
+mod stream;
+#[cfg(test)]
+mod test_utils;
+mod validators;
+mod verify;
 
 #[tokio::main]
 async fn main() {
     tracing_subscriber::fmt::init();
-    let app = app::get_router().await;
+    let config = config::Config::from_env();
+    let db_pool = db::get_db_pool(&config).await;
+    let app = app::get_router(db_pool.clone(), config).await;
     let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
+    let listener = TcpListener::bind(addr).expect("expect to bind server address");
     tracing::info!("Listening on {}", addr);
-    axum::Server::bind(&addr)
-        .serve(app.into_make_service())
+    serve_until_shutdown(listener, app, shutdown_signal())
         .await
         .unwrap();
+    // Only once in-flight requests are done, so none of them lose their connection mid-write.
+    db_pool.close().await;
+    tracing::info!("Shut down");
 }
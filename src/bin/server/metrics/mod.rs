@@ -0,0 +1,221 @@
+//! Prometheus metrics, kept in atomics in the app state and rendered in the text exposition
+//! format on `GET /metrics`.
+
+use crate::state::AppState;
+use axum::{
+    extract::State,
+    http::{header::CONTENT_TYPE, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Upper bounds in seconds of the latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 10] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0];
+
+#[derive(Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    pub fn inc(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Latency histogram over the fixed `LATENCY_BUCKETS`.
+#[derive(Default)]
+pub struct Histogram {
+    /// Observations per bucket, not cumulative, the last one for those beyond every bound.
+    bucket_counts: [AtomicU64; LATENCY_BUCKETS.len() + 1],
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    pub fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.bucket_counts[bucket].fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.bucket_counts
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .sum()
+    }
+}
+
+#[derive(Default)]
+pub struct Metrics {
+    pub messages_received: Counter,
+    pub signature_failures: Counter,
+    pub db_errors: Counter,
+    pub aggregates_created: Counter,
+    pub aggregates_extended: Counter,
+    pub db_insert_seconds: Histogram,
+    pub request_seconds: Histogram,
+}
+
+fn write_counter(output: &mut String, name: &str, help: &str, value: u64) {
+    let _ = writeln!(output, "# HELP {name} {help}");
+    let _ = writeln!(output, "# TYPE {name} counter");
+    let _ = writeln!(output, "{name} {value}");
+}
+
+fn write_histogram(output: &mut String, name: &str, help: &str, histogram: &Histogram) {
+    let _ = writeln!(output, "# HELP {name} {help}");
+    let _ = writeln!(output, "# TYPE {name} histogram");
+    let mut cumulative_count = 0;
+    for (bound, count) in LATENCY_BUCKETS.iter().zip(&histogram.bucket_counts) {
+        cumulative_count += count.load(Ordering::Relaxed);
+        let _ = writeln!(output, "{name}_bucket{{le=\"{bound}\"}} {cumulative_count}");
+    }
+    let count = histogram.count();
+    let _ = writeln!(output, "{name}_bucket{{le=\"+Inf\"}} {count}");
+    let sum = histogram.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+    let _ = writeln!(output, "{name}_sum {sum}");
+    let _ = writeln!(output, "{name}_count {count}");
+}
+
+pub fn render_metrics(state: &AppState) -> String {
+    let metrics = &state.metrics;
+    let mut output = String::new();
+    write_counter(
+        &mut output,
+        "oracle_messages_received_total",
+        "Oracle messages submitted, on their own or in a batch.",
+        metrics.messages_received.get(),
+    );
+    write_counter(
+        &mut output,
+        "oracle_signature_failures_total",
+        "Oracle messages rejected for an invalid signature.",
+        metrics.signature_failures.get(),
+    );
+    write_counter(
+        &mut output,
+        "oracle_db_errors_total",
+        "Oracle messages that failed on a database error.",
+        metrics.db_errors.get(),
+    );
+    write_counter(
+        &mut output,
+        "oracle_aggregates_created_total",
+        "Interval aggregates created.",
+        metrics.aggregates_created.get(),
+    );
+    write_counter(
+        &mut output,
+        "oracle_aggregates_extended_total",
+        "Signatures added to existing interval aggregates.",
+        metrics.aggregates_extended.get(),
+    );
+    write_counter(
+        &mut output,
+        "oracle_dedup_lookups_total",
+        "Times the database was asked for an earlier value attestation.",
+        state.num_dedup_lookups.load(Ordering::Relaxed),
+    );
+    write_counter(
+        &mut output,
+        "oracle_value_flips_total",
+        "Times the quorum-reaching value of a slot and interval size changed.",
+        state.num_value_flips.load(Ordering::Relaxed),
+    );
+    write_histogram(
+        &mut output,
+        "oracle_db_insert_seconds",
+        "Latency of inserting an attestation.",
+        &metrics.db_insert_seconds,
+    );
+    write_histogram(
+        &mut output,
+        "oracle_request_seconds",
+        "Time spent handling a request.",
+        &metrics.request_seconds,
+    );
+    output
+}
+
+pub async fn get_metrics(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        render_metrics(&state),
+    )
+}
+
+pub async fn track_request_duration<B>(
+    State(state): State<Arc<AppState>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let started_at = Instant::now();
+    let response = next.run(request).await;
+    state.metrics.request_seconds.observe(started_at.elapsed());
+    response
+}
+
+#[cfg(test)]
+mod test {
+    use crate::db::DbPool;
+    use crate::test_utils::{
+        get_test_message, signature_from_random_signer, value_message_from_new_validator, TestApp,
+    };
+    use axum::http::StatusCode;
+
+    fn get_metric(metrics: &str, name: &str) -> f64 {
+        metrics
+            .lines()
+            .find_map(|line| line.strip_prefix(&format!("{name} ")))
+            .unwrap_or_else(|| panic!("expect metric {name}"))
+            .parse()
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn counts_valid_and_invalid_messages(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        test_app
+            .post_oracle_message(&get_test_message(), StatusCode::OK)
+            .await;
+        let mut invalid_message = value_message_from_new_validator(1811093163);
+        invalid_message.value_message.signature =
+            signature_from_random_signer(&invalid_message.value_message.message);
+        test_app
+            .post_oracle_message(&invalid_message, StatusCode::BAD_REQUEST)
+            .await;
+
+        let response = test_app.get_expect("/metrics", StatusCode::OK).await;
+        let metrics = String::from_utf8(response.to_vec()).unwrap();
+        assert_eq!(get_metric(&metrics, "oracle_messages_received_total"), 2.0);
+        assert_eq!(get_metric(&metrics, "oracle_signature_failures_total"), 1.0);
+        assert_eq!(get_metric(&metrics, "oracle_db_errors_total"), 0.0);
+        let num_intervals = get_test_message().interval_inclusion_messages.len() as f64;
+        assert_eq!(
+            get_metric(&metrics, "oracle_aggregates_created_total"),
+            num_intervals
+        );
+        assert_eq!(
+            get_metric(&metrics, "oracle_aggregates_extended_total"),
+            0.0
+        );
+        // The value attestation and every interval attestation
+        assert_eq!(
+            get_metric(&metrics, "oracle_db_insert_seconds_count"),
+            num_intervals + 1.0
+        );
+        assert_eq!(get_metric(&metrics, "oracle_request_seconds_count"), 2.0);
+    }
+}
@@ -0,0 +1,271 @@
+//! Webhook notifications, queued in the database so failed deliveries are retried with
+//! exponential backoff, also across restarts. Notifications that keep failing are dead-lettered
+//! after the configured number of attempts.
+
+use crate::attestations::AggregatePriceIntervalEntry;
+use crate::state::AppState;
+use axum::{
+    body::Body,
+    http::{header::CONTENT_TYPE, Request},
+};
+use hyper::{client::HttpConnector, Client};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use std::sync::Arc;
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct QuorumNotification {
+    pub event: String,
+    #[serde(flatten)]
+    pub aggregate: AggregatePriceIntervalEntry,
+}
+
+/// Queues a notification to the configured webhook about an aggregate that just reached quorum.
+pub async fn enqueue_quorum_notification(
+    state: &AppState,
+    connection: &mut PgConnection,
+    aggregate: AggregatePriceIntervalEntry,
+) -> eyre::Result<()> {
+    enqueue_notification(state, connection, "quorum_reached", aggregate).await
+}
+
+/// Queues a notification of the event about the aggregate to the configured webhook, if any.
+pub async fn enqueue_notification(
+    state: &AppState,
+    connection: &mut PgConnection,
+    event: &str,
+    aggregate: AggregatePriceIntervalEntry,
+) -> eyre::Result<()> {
+    let url = match &state.config.webhook_url {
+        Some(url) => url,
+        None => return Ok(()),
+    };
+    let payload = serde_json::to_string(&QuorumNotification {
+        event: event.to_string(),
+        aggregate,
+    })?;
+    sqlx::query!(
+        "
+        INSERT INTO pending_notifications(
+            url,
+            payload
+        )
+        VALUES (
+            $1,
+            $2
+        );
+        ",
+        url,
+        payload,
+    )
+    .execute(connection)
+    .await?;
+    Ok(())
+}
+
+pub async fn run_notification_worker(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = process_due_notifications(&state).await {
+            tracing::error!("Error processing notifications: {:?}", e);
+        }
+    }
+}
+
+/// Attempts to deliver every notification whose next attempt is due.
+pub async fn process_due_notifications(state: &AppState) -> eyre::Result<()> {
+    let db_pool = &state.db_pool;
+    let due_notifications = sqlx::query!(
+        "
+        SELECT
+            id,
+            url,
+            payload,
+            attempts
+        FROM
+            pending_notifications
+        WHERE
+            dead_lettered_at IS NULL
+        AND
+            next_attempt_at <= NOW()
+        ORDER BY
+            id;
+        "
+    )
+    .fetch_all(db_pool)
+    .await?;
+
+    let client = Client::new();
+    for notification in due_notifications {
+        match deliver(&client, &notification.url, notification.payload).await {
+            Ok(()) => {
+                sqlx::query!(
+                    "
+                    DELETE FROM pending_notifications
+                    WHERE
+                        id = $1;
+                    ",
+                    notification.id
+                )
+                .execute(db_pool)
+                .await?;
+            }
+            Err(e) => {
+                let attempts = notification.attempts + 1;
+                tracing::warn!(
+                    "Delivering notification {} to {} failed (attempt {}): {:?}",
+                    notification.id,
+                    notification.url,
+                    attempts,
+                    e
+                );
+                record_failure(state, notification.id, attempts, &e.to_string()).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn deliver(client: &Client<HttpConnector>, url: &str, payload: String) -> eyre::Result<()> {
+    let request = Request::post(url)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(payload))?;
+    let response = tokio::time::timeout(DELIVERY_TIMEOUT, client.request(request)).await??;
+    if !response.status().is_success() {
+        return Err(eyre::eyre!("Webhook responded with {}", response.status()));
+    }
+    Ok(())
+}
+
+async fn record_failure(state: &AppState, id: i64, attempts: i32, error: &str) -> eyre::Result<()> {
+    let db_pool = &state.db_pool;
+    if attempts >= state.config.webhook_max_attempts {
+        tracing::error!(
+            "Dead-lettering notification {} after {} attempts",
+            id,
+            attempts
+        );
+        sqlx::query!(
+            "
+            UPDATE pending_notifications
+            SET
+                attempts = $1,
+                last_error = $2,
+                dead_lettered_at = NOW()
+            WHERE
+                id = $3;
+            ",
+            attempts,
+            error,
+            id,
+        )
+        .execute(db_pool)
+        .await?;
+    } else {
+        let delay_seconds =
+            state.config.webhook_retry_delay_seconds as f64 * 2f64.powi(attempts - 1);
+        sqlx::query!(
+            "
+            UPDATE pending_notifications
+            SET
+                attempts = $1,
+                last_error = $2,
+                next_attempt_at = NOW() + make_interval(secs => $3::DOUBLE PRECISION)
+            WHERE
+                id = $4;
+            ",
+            attempts,
+            error,
+            delay_seconds,
+            id,
+        )
+        .execute(db_pool)
+        .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Config;
+    use crate::db::DbPool;
+    use crate::test_utils::{get_test_message, TestApp};
+    use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct Webhook {
+        calls: AtomicUsize,
+        notifications: std::sync::Mutex<Vec<QuorumNotification>>,
+    }
+
+    /// Serves a webhook that fails the first `failures` calls.
+    async fn serve_webhook(failures: usize) -> (SocketAddr, Arc<Webhook>) {
+        let webhook = Arc::new(Webhook::default());
+        let router = Router::new()
+            .route(
+                "/",
+                post(
+                    move |State(webhook): State<Arc<Webhook>>,
+                          Json(notification): Json<QuorumNotification>| async move {
+                        if webhook.calls.fetch_add(1, Ordering::SeqCst) < failures {
+                            return StatusCode::INTERNAL_SERVER_ERROR;
+                        }
+                        webhook.notifications.lock().unwrap().push(notification);
+                        StatusCode::OK
+                    },
+                ),
+            )
+            .with_state(webhook.clone());
+        let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+            .serve(router.into_make_service());
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        (addr, webhook)
+    }
+
+    #[sqlx::test]
+    async fn retries_failed_webhook_notifications(db_pool: DbPool) {
+        let (addr, webhook) = serve_webhook(2).await;
+        let config = Config {
+            quorum_threshold: Some(1),
+            webhook_url: Some(format!("http://{addr}/")),
+            webhook_retry_delay_seconds: 0,
+            ..Config::default()
+        };
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(1);
+        let test_app = TestApp::with_config(db_pool.clone(), config.clone()).await;
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+
+        let state = AppState::new(db_pool.clone(), config);
+        for _ in 0..3 {
+            process_due_notifications(&state).await.unwrap();
+        }
+        assert_eq!(webhook.calls.load(Ordering::SeqCst), 3);
+        {
+            let notifications = webhook.notifications.lock().unwrap();
+            assert_eq!(notifications.len(), 1);
+            assert_eq!(notifications[0].event, "quorum_reached");
+            assert_eq!(
+                notifications[0].aggregate.value,
+                test_message.interval_inclusion_messages[0].message.value as i64
+            );
+        }
+
+        let num_pending: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM pending_notifications;")
+            .fetch_one(&db_pool)
+            .await
+            .unwrap();
+        assert_eq!(num_pending, 0);
+    }
+}
@@ -0,0 +1,684 @@
+use crate::attestations::DEFAULT_ASSET;
+use crate::attestations::{
+    get_most_common_interval_size, get_price_aggregate_for_params, AggregatePriceIntervalEntry,
+};
+use crate::consensus::{
+    get_consensus_value, get_quorum_aggregates, get_values, interval_bounds, pick_best_aggregate,
+    ConsensusMethod, ConsensusQuery,
+};
+use crate::db::DbPool;
+use crate::quorum::get_quorum_threshold;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LatestPrice {
+    #[serde(flatten)]
+    pub aggregate: AggregatePriceIntervalEntry,
+    pub current_slot: u64,
+    /// Number of slots between the current slot and the slot of the price.
+    pub age_slots: u64,
+    pub stale: bool,
+    pub method: ConsensusMethod,
+    /// Value attestations of the slot combined using the consensus method.
+    pub consensus_value: Option<i64>,
+}
+
+/// The price aggregate for the most recent slot with aggregates, flagged as stale when it is
+/// older than the configured maximum.
+pub async fn get_latest(
+    Query(query): Query<ConsensusQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<LatestPrice>, StatusCode> {
+    let db_pool = &state.db_pool;
+    let slot_number = get_latest_aggregate_slot_number(db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting latest slot number: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let interval_size = get_most_common_interval_size(db_pool, slot_number)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting most common interval size: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let aggregate = get_price_aggregate_for_params(db_pool, slot_number, interval_size)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting aggregate price: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let values = get_values(db_pool, slot_number).await.map_err(|e| {
+        tracing::error!("Error getting value attestations: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let method = query.method(&state);
+    let consensus_value = get_consensus_value(&values, method, state.config.consensus_trim_percent);
+
+    let current_slot = state.slot_clock.current_slot();
+    let age_slots = current_slot.saturating_sub(slot_number as u64);
+    Ok(Json(LatestPrice {
+        aggregate,
+        current_slot,
+        age_slots,
+        stale: age_slots > state.config.max_staleness_slots,
+        method,
+        consensus_value,
+    }))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConsensusPrice {
+    pub value: i64,
+    pub slot_number: i64,
+    pub num_validators: i64,
+    pub aggregate_signature: String,
+    pub interval_size: i64,
+}
+
+/// The value of the aggregate with the most validators in the most recent slot with aggregates.
+/// Ties go to the lowest value, then the smallest interval size.
+pub async fn get_price_latest(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ConsensusPrice>, StatusCode> {
+    let db_pool = &state.db_pool;
+    let slot_number = get_latest_aggregate_slot_number(db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting latest slot number: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let consensus_price = sqlx::query_as!(
+        ConsensusPrice,
+        "
+        SELECT
+            value,
+            slot_number,
+            num_validators,
+            aggregate_signature,
+            interval_size
+        FROM
+            aggregate_interval_attestations
+        WHERE
+            slot_number = $1
+        ORDER BY
+            num_validators DESC,
+            value ASC,
+            interval_size ASC
+        LIMIT 1;
+        ",
+        slot_number
+    )
+    .fetch_optional(db_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error getting aggregates: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(consensus_price))
+}
+
+/// Median of the values, averaging the two middle values of an even number of values.
+pub fn compute_median(values: &[i64]) -> Option<i64> {
+    let sorted_values: Vec<i64> = values.iter().copied().sorted().collect();
+    let middle = sorted_values.len() / 2;
+    match sorted_values.len() {
+        0 => None,
+        len if len % 2 == 1 => Some(sorted_values[middle]),
+        _ => {
+            let (lower, upper) = (sorted_values[middle - 1], sorted_values[middle]);
+            Some(lower + (upper - lower) / 2)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MedianPriceQuery {
+    pub slot: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MedianPrice {
+    pub slot_number: i64,
+    pub median: i64,
+    pub count: usize,
+    pub min: i64,
+    pub max: i64,
+}
+
+/// Median of the value attestations of the slot, responding with 404 if there are none.
+pub async fn get_price_median(
+    Query(query): Query<MedianPriceQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<MedianPrice>, StatusCode> {
+    let values = get_values(&state.db_pool, query.slot).await.map_err(|e| {
+        tracing::error!("Error getting value attestations: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let median = compute_median(&values).ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(MedianPrice {
+        slot_number: query.slot,
+        median,
+        count: values.len(),
+        // Values come in ascending order.
+        min: values[0],
+        max: values[values.len() - 1],
+    }))
+}
+
+/// The range of prices the tightest quorum-reaching interval of a slot spans.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConfidenceBand {
+    pub lower: i64,
+    pub upper: i64,
+    pub aggregate: AggregatePriceIntervalEntry,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PriceConfidence {
+    pub slot_number: i64,
+    pub median: i64,
+    pub num_value_attestations: usize,
+    /// Null if no interval reached quorum.
+    pub confidence_band: Option<ConfidenceBand>,
+}
+
+/// Median of the value attestations of the slot as the point estimate, with the tightest interval
+/// reaching quorum as the uncertainty around it. Of several equally tight intervals, the one with
+/// the most validators is picked, the median by value if that is still a tie.
+pub async fn get_price_confidence(
+    Path(slot_number): Path<i64>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<PriceConfidence>, StatusCode> {
+    let db_pool = &state.db_pool;
+    let values = get_values(db_pool, slot_number).await.map_err(|e| {
+        tracing::error!("Error getting value attestations: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let median = compute_median(&values).ok_or(StatusCode::NOT_FOUND)?;
+    // Consensus doesn't distinguish assets yet, the default asset's threshold applies.
+    let quorum_threshold = get_quorum_threshold(&state, DEFAULT_ASSET, slot_number).unwrap_or(1);
+    let min_operators = state.config.quorum_min_operators.unwrap_or(0);
+    let quorum_aggregates =
+        get_quorum_aggregates(db_pool, slot_number, quorum_threshold, min_operators)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting quorum aggregates: {:?}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+    let tightest_aggregates = quorum_aggregates
+        .into_iter()
+        .min_set_by_key(|aggregate| aggregate.interval_size);
+    let confidence_band = pick_best_aggregate(tightest_aggregates).map(|aggregate| {
+        let (lower, upper) = interval_bounds(aggregate.value, aggregate.interval_size);
+        ConfidenceBand {
+            lower,
+            upper,
+            aggregate,
+        }
+    });
+    Ok(Json(PriceConfidence {
+        slot_number,
+        median,
+        num_value_attestations: values.len(),
+        confidence_band,
+    }))
+}
+
+async fn get_latest_aggregate_slot_number(db_pool: &DbPool) -> eyre::Result<Option<i64>> {
+    let slot_number = sqlx::query!(
+        "
+        SELECT
+            MAX(slot_number) AS slot_number
+        FROM
+            aggregate_interval_attestations;
+        "
+    )
+    .fetch_one(db_pool)
+    .await?
+    .slot_number;
+    Ok(slot_number)
+}
+
+const MAX_TWAP_SLOT_RANGE: i64 = 7200;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TwapQuery {
+    pub from_slot: i64,
+    /// Inclusive.
+    pub to_slot: i64,
+}
+
+/// Consecutive slots without any value attestations, inclusive on both ends.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct SlotGap {
+    pub from_slot: i64,
+    pub to_slot: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Twap {
+    /// Mean of the slot prices, `None` if no slot in the range has one.
+    pub twap: Option<f64>,
+    pub num_slots_with_data: usize,
+    /// Slots left out of the average as they have no price.
+    pub gaps: Vec<SlotGap>,
+}
+
+/// Averages the prices of the slots, each slot weighing the same. Slots of the range without a
+/// price are left out of the average and reported as gaps rather than filled in.
+pub fn compute_twap(slot_prices: &[(i64, i64)], from_slot: i64, to_slot: i64) -> Twap {
+    let slot_prices: Vec<&(i64, i64)> = slot_prices
+        .iter()
+        .filter(|(slot_number, _)| (from_slot..=to_slot).contains(slot_number))
+        .sorted_by_key(|(slot_number, _)| *slot_number)
+        .dedup_by(|(slot_a, _), (slot_b, _)| slot_a == slot_b)
+        .collect();
+    let mut gaps = Vec::new();
+    let mut next_slot = from_slot;
+    for (slot_number, _) in &slot_prices {
+        if *slot_number > next_slot {
+            gaps.push(SlotGap {
+                from_slot: next_slot,
+                to_slot: slot_number - 1,
+            });
+        }
+        next_slot = slot_number + 1;
+    }
+    if next_slot <= to_slot {
+        gaps.push(SlotGap {
+            from_slot: next_slot,
+            to_slot,
+        });
+    }
+    let num_slots_with_data = slot_prices.len();
+    let twap = (num_slots_with_data > 0).then(|| {
+        slot_prices
+            .iter()
+            .map(|(_, value)| *value as f64)
+            .sum::<f64>()
+            / num_slots_with_data as f64
+    });
+    Twap {
+        twap,
+        num_slots_with_data,
+        gaps,
+    }
+}
+
+/// Time weighted average of the median value attestation of each slot in the range, harder to
+/// move than the price of any single slot.
+pub async fn get_price_twap(
+    Query(query): Query<TwapQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Twap>, (StatusCode, String)> {
+    let num_slots = query.to_slot - query.from_slot + 1;
+    if !(1..=MAX_TWAP_SLOT_RANGE).contains(&num_slots) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("slot range must span 1 to {MAX_TWAP_SLOT_RANGE} slots"),
+        ));
+    }
+    let rows = sqlx::query!(
+        "
+        SELECT
+            slot_number,
+            value
+        FROM
+            price_value_attestations
+        WHERE
+            slot_number BETWEEN $1 AND $2
+        ORDER BY
+            slot_number;
+        ",
+        query.from_slot,
+        query.to_slot,
+    )
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error getting value attestations: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "error getting value attestations".to_string(),
+        )
+    })?;
+    let slot_prices: Vec<(i64, i64)> = rows
+        .into_iter()
+        .group_by(|row| row.slot_number)
+        .into_iter()
+        .filter_map(|(slot_number, rows)| {
+            let values: Vec<i64> = rows.map(|row| row.value).collect();
+            compute_median(&values).map(|median| (slot_number, median))
+        })
+        .collect();
+    Ok(Json(compute_twap(
+        &slot_prices,
+        query.from_slot,
+        query.to_slot,
+    )))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_utils::{
+        get_test_message, sign_oracle_message_with_new_key, value_message,
+        value_message_from_new_validator, TestApp, TEST_MESSAGE_SLOT,
+    };
+    use bls::SecretKey;
+
+    #[test]
+    fn averages_contiguous_slots() {
+        let twap = compute_twap(&[(10, 100), (11, 200), (12, 600)], 10, 12);
+        assert_eq!(
+            twap,
+            Twap {
+                twap: Some(300.0),
+                num_slots_with_data: 3,
+                gaps: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn reports_gaps_instead_of_filling_them() {
+        let twap = compute_twap(&[(12, 100), (15, 200)], 10, 17);
+        assert_eq!(twap.twap, Some(150.0));
+        assert_eq!(twap.num_slots_with_data, 2);
+        assert_eq!(
+            twap.gaps,
+            [
+                SlotGap {
+                    from_slot: 10,
+                    to_slot: 11
+                },
+                SlotGap {
+                    from_slot: 13,
+                    to_slot: 14
+                },
+                SlotGap {
+                    from_slot: 16,
+                    to_slot: 17
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn has_no_twap_without_prices() {
+        let twap = compute_twap(&[(20, 100)], 10, 12);
+        assert_eq!(
+            twap,
+            Twap {
+                twap: None,
+                num_slots_with_data: 0,
+                gaps: vec![SlotGap {
+                    from_slot: 10,
+                    to_slot: 12
+                }],
+            }
+        );
+    }
+
+    #[sqlx::test]
+    async fn averages_slot_medians(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let slot_values = [
+            (TEST_MESSAGE_SLOT - 2, 1000),
+            (TEST_MESSAGE_SLOT - 2, 1200),
+            (TEST_MESSAGE_SLOT - 2, 5000),
+            (TEST_MESSAGE_SLOT, 2000),
+        ];
+        for (slot_number, value) in slot_values {
+            let message = value_message(&SecretKey::random(), slot_number, value);
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+        }
+
+        let response = test_app
+            .get_expect(
+                &format!(
+                    "/price/twap?from_slot={}&to_slot={TEST_MESSAGE_SLOT}",
+                    TEST_MESSAGE_SLOT - 2
+                ),
+                StatusCode::OK,
+            )
+            .await;
+        let twap: Twap = serde_json::from_slice(&response).unwrap();
+        assert_eq!(twap.twap, Some(1600.0));
+        assert_eq!(twap.num_slots_with_data, 2);
+        let gap_slot = TEST_MESSAGE_SLOT as i64 - 1;
+        assert_eq!(
+            twap.gaps,
+            [SlotGap {
+                from_slot: gap_slot,
+                to_slot: gap_slot
+            }]
+        );
+
+        test_app
+            .get_expect(
+                &format!("/price/twap?from_slot={TEST_MESSAGE_SLOT}&to_slot=0"),
+                StatusCode::BAD_REQUEST,
+            )
+            .await;
+    }
+
+    fn staleness_config() -> Config {
+        Config {
+            max_staleness_slots: 5,
+            ..Config::default()
+        }
+    }
+
+    #[sqlx::test]
+    async fn bands_price_by_tightest_quorum_interval(db_pool: DbPool) {
+        let config = Config {
+            quorum_threshold: Some(2),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool.clone(), config).await;
+        let mut message = get_test_message();
+        let interval_message = message.interval_inclusion_messages[362].clone();
+        let mut wide_interval_message = interval_message.clone();
+        wide_interval_message.message.interval_size = 50;
+        // Two validators attest to both the tight and the wide interval, a third only to the
+        // wide one
+        message.interval_inclusion_messages = vec![interval_message, wide_interval_message.clone()];
+        for _ in 0..2 {
+            let message = sign_oracle_message_with_new_key(message.clone(), &SecretKey::random());
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+        }
+        message.interval_inclusion_messages = vec![wide_interval_message];
+        let message = sign_oracle_message_with_new_key(message, &SecretKey::random());
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
+
+        let uri = format!("/price/{TEST_MESSAGE_SLOT}/confidence");
+        let response = test_app.get_expect(&uri, StatusCode::OK).await;
+        let confidence: PriceConfidence = serde_json::from_slice(&response).unwrap();
+        assert_eq!(confidence.median, 1811093163);
+        assert_eq!(confidence.num_value_attestations, 3);
+        let band = confidence.confidence_band.unwrap();
+        assert_eq!(band.aggregate.interval_size, 20);
+        assert_eq!(band.aggregate.num_validators, 2);
+        assert!(band.lower <= confidence.median && confidence.median <= band.upper);
+
+        // Only the wide interval reaches a higher threshold
+        let config = Config {
+            quorum_threshold: Some(3),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        let response = test_app.get_expect(&uri, StatusCode::OK).await;
+        let confidence: PriceConfidence = serde_json::from_slice(&response).unwrap();
+        let wide_band = confidence.confidence_band.unwrap();
+        assert_eq!(wide_band.aggregate.interval_size, 50);
+        assert!(wide_band.lower < band.lower && band.upper < wide_band.upper);
+
+        test_app
+            .get_expect(
+                &format!("/price/{}/confidence", TEST_MESSAGE_SLOT + 1),
+                StatusCode::NOT_FOUND,
+            )
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn flags_fresh_latest_price_as_not_stale(db_pool: DbPool) {
+        let test_app = TestApp::with_config(db_pool, staleness_config()).await;
+        test_app
+            .post_oracle_message(&get_test_message(), StatusCode::OK)
+            .await;
+
+        let response = test_app.get_expect("/latest", StatusCode::OK).await;
+        let latest: LatestPrice = serde_json::from_slice(&response).unwrap();
+        assert_eq!(latest.aggregate.slot_number, TEST_MESSAGE_SLOT as i64);
+        assert_eq!(latest.age_slots, 0);
+        assert!(!latest.stale);
+    }
+
+    #[sqlx::test]
+    async fn flags_old_latest_price_as_stale(db_pool: DbPool) {
+        let test_app = TestApp::with_config(db_pool.clone(), staleness_config()).await;
+        test_app
+            .post_oracle_message(&get_test_message(), StatusCode::OK)
+            .await;
+
+        let later_app =
+            TestApp::with_config_at_slot(db_pool, staleness_config(), TEST_MESSAGE_SLOT + 6).await;
+        let response = later_app.get_expect("/latest", StatusCode::OK).await;
+        let latest: LatestPrice = serde_json::from_slice(&response).unwrap();
+        assert_eq!(latest.current_slot, TEST_MESSAGE_SLOT + 6);
+        assert_eq!(latest.age_slots, 6);
+        assert!(latest.stale);
+    }
+
+    #[sqlx::test]
+    async fn combines_latest_values_with_requested_method(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        test_app
+            .post_oracle_message(&get_test_message(), StatusCode::OK)
+            .await;
+        for value in [1811093163, 1000000000] {
+            test_app
+                .post_oracle_message(&value_message_from_new_validator(value), StatusCode::OK)
+                .await;
+        }
+
+        let response = test_app.get_expect("/latest", StatusCode::OK).await;
+        let latest: LatestPrice = serde_json::from_slice(&response).unwrap();
+        assert_eq!(latest.method, ConsensusMethod::Median);
+        assert_eq!(latest.consensus_value, Some(1811093163));
+
+        let response = test_app
+            .get_expect("/latest?method=trimmed_mean", StatusCode::OK)
+            .await;
+        let latest: LatestPrice = serde_json::from_slice(&response).unwrap();
+        assert_eq!(latest.method, ConsensusMethod::TrimmedMean);
+        assert_eq!(latest.consensus_value, Some(1540728775));
+    }
+
+    #[sqlx::test]
+    async fn returns_not_found_without_aggregates(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        test_app.get_expect("/latest", StatusCode::NOT_FOUND).await;
+    }
+
+    #[sqlx::test]
+    async fn returns_best_supported_price(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        test_app
+            .get_expect("/price/latest", StatusCode::NOT_FOUND)
+            .await;
+
+        // The first interval is supported by three validators, the second by two
+        let test_message = get_test_message();
+        for num_intervals in [3, 2, 1] {
+            let mut message = test_message.clone();
+            message.interval_inclusion_messages.truncate(num_intervals);
+            let message = sign_oracle_message_with_new_key(message, &SecretKey::random());
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+        }
+        let response = test_app.get_expect("/price/latest", StatusCode::OK).await;
+        let price: ConsensusPrice = serde_json::from_slice(&response).unwrap();
+        assert_eq!(price.slot_number, TEST_MESSAGE_SLOT as i64);
+        assert_eq!(
+            price.value,
+            test_message.interval_inclusion_messages[0].message.value as i64
+        );
+        assert_eq!(price.num_validators, 3);
+        assert_eq!(
+            price.interval_size,
+            test_message.interval_inclusion_messages[0]
+                .message
+                .interval_size as i64
+        );
+    }
+
+    #[sqlx::test]
+    async fn resolves_price_ties_to_the_lower_value(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let test_message = get_test_message();
+        for index in [2, 1] {
+            let mut message = test_message.clone();
+            message.interval_inclusion_messages =
+                vec![test_message.interval_inclusion_messages[index].clone()];
+            let message = sign_oracle_message_with_new_key(message, &SecretKey::random());
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+        }
+        let response = test_app.get_expect("/price/latest", StatusCode::OK).await;
+        let price: ConsensusPrice = serde_json::from_slice(&response).unwrap();
+        assert_eq!(price.num_validators, 1);
+        assert_eq!(
+            price.value,
+            test_message.interval_inclusion_messages[1].message.value as i64
+        );
+    }
+
+    #[test]
+    fn computes_median_of_odd_and_even_counts() {
+        assert_eq!(compute_median(&[300, 100, 200]), Some(200));
+        assert_eq!(compute_median(&[400, 100, 300, 200]), Some(250));
+        assert_eq!(compute_median(&[100, 101]), Some(100));
+        assert_eq!(compute_median(&[100]), Some(100));
+        assert_eq!(compute_median(&[]), None);
+    }
+
+    #[sqlx::test]
+    async fn returns_median_price_of_slot(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let uri = format!("/price/median?slot={TEST_MESSAGE_SLOT}");
+        test_app.get_expect(&uri, StatusCode::NOT_FOUND).await;
+
+        for value in [1811093163, 1811093263, 1811093363, 1811093563] {
+            test_app
+                .post_oracle_message(&value_message_from_new_validator(value), StatusCode::OK)
+                .await;
+        }
+        let response = test_app.get_expect(&uri, StatusCode::OK).await;
+        let median_price: MedianPrice = serde_json::from_slice(&response).unwrap();
+        assert_eq!(median_price.slot_number, TEST_MESSAGE_SLOT as i64);
+        assert_eq!(median_price.median, 1811093313);
+        assert_eq!(median_price.count, 4);
+        assert_eq!(median_price.min, 1811093163);
+        assert_eq!(median_price.max, 1811093563);
+    }
+}
@@ -0,0 +1,216 @@
+//! Everything needed to verify the oracle's price for a slot on-chain, in a single response.
+
+use crate::attestations::{AggregatePriceIntervalEntry, SignedEntry};
+use crate::consensus::pick_best_aggregate;
+use crate::state::AppState;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use std::sync::Arc;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PriceProofQuery {
+    pub slot: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PriceProof {
+    pub slot_number: i64,
+    pub asset: String,
+    pub value: i64,
+    pub interval_size: i64,
+    pub aggregate_signature: String,
+    pub aggregate_public_key: String,
+    /// Public keys of the validators whose signatures were aggregated.
+    pub public_keys: Vec<String>,
+    /// Hex encoded digest the aggregate signature is over.
+    pub digest: String,
+}
+
+async fn get_slot_aggregates(
+    connection: &mut PgConnection,
+    slot_number: i64,
+) -> sqlx::Result<Vec<AggregatePriceIntervalEntry>> {
+    sqlx::query_as(
+        "
+        SELECT
+            asset,
+            value,
+            slot_number,
+            aggregate_signature,
+            aggregate_public_key,
+            public_keys,
+            interval_size,
+            num_validators,
+            num_operators
+        FROM
+            aggregate_interval_attestations
+        WHERE
+            slot_number = $1;
+        ",
+    )
+    .bind(slot_number)
+    .fetch_all(connection)
+    .await
+}
+
+/// Public keys of the aggregate's validators that have a stored interval attestation for it.
+async fn get_contributing_public_keys(
+    connection: &mut PgConnection,
+    aggregate: &AggregatePriceIntervalEntry,
+) -> sqlx::Result<Vec<String>> {
+    sqlx::query_scalar(
+        "
+        SELECT
+            validator_public_key
+        FROM
+            price_interval_attestations
+        WHERE
+            asset = $1
+        AND
+            slot_number = $2
+        AND
+            interval_size = $3
+        AND
+            aggregate_value = $4
+        AND
+            validator_public_key = ANY($5);
+        ",
+    )
+    .bind(&aggregate.asset)
+    .bind(aggregate.slot_number)
+    .bind(aggregate.interval_size)
+    .bind(aggregate.value)
+    .bind(&aggregate.public_keys)
+    .fetch_all(connection)
+    .await
+}
+
+/// The aggregate of the slot with the most support, see `pick_best_aggregate`, bundled with the
+/// digest its signature is over. Aggregate and attestations are read from the same snapshot, so
+/// the proof is never assembled from an aggregate that is being extended concurrently.
+pub async fn get_price_proof(
+    Query(query): Query<PriceProofQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<PriceProof>, StatusCode> {
+    let mut transaction = state.db_pool.begin().await.map_err(|e| {
+        tracing::error!("Error starting transaction: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ READ ONLY;")
+        .execute(&mut transaction)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error setting transaction isolation level: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let aggregates = get_slot_aggregates(&mut transaction, query.slot)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting aggregates: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let aggregate = pick_best_aggregate(aggregates).ok_or(StatusCode::NOT_FOUND)?;
+    let contributing_public_keys = get_contributing_public_keys(&mut transaction, &aggregate)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting contributing public keys: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    transaction.commit().await.map_err(|e| {
+        tracing::error!("Error finishing transaction: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if contributing_public_keys.len() != aggregate.public_keys.len() {
+        tracing::error!(
+            "Aggregate of slot {}, interval size {} and value {} lists {} validators but {} have attestations for it",
+            aggregate.slot_number,
+            aggregate.interval_size,
+            aggregate.value,
+            aggregate.public_keys.len(),
+            contributing_public_keys.len()
+        );
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let digest = aggregate.message_digest(state.config.digest_config());
+    Ok(Json(PriceProof {
+        digest: format!("0x{}", hex::encode(digest.as_bytes())),
+        slot_number: aggregate.slot_number,
+        asset: aggregate.asset,
+        value: aggregate.value,
+        interval_size: aggregate.interval_size,
+        aggregate_signature: aggregate.aggregate_signature,
+        aggregate_public_key: aggregate.aggregate_public_key,
+        public_keys: aggregate.public_keys,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::DbPool;
+    use crate::test_utils::{
+        get_test_message, sign_oracle_message_with_new_key, TestApp, TEST_MESSAGE_SLOT,
+    };
+    use bls::{AggregatePublicKey, AggregateSignature, Hash256, PublicKey, SecretKey};
+
+    #[sqlx::test]
+    async fn returns_independently_verifiable_proof(db_pool: DbPool) {
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(2);
+        let test_app = TestApp::new(db_pool).await;
+        let private_keys = [SecretKey::random(), SecretKey::random()];
+        for private_key in &private_keys {
+            let message = sign_oracle_message_with_new_key(test_message.clone(), private_key);
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+        }
+        // A third validator only supports the first interval, making it the winning one.
+        let mut minority_message = test_message.clone();
+        minority_message.interval_inclusion_messages.truncate(1);
+        let minority_key = SecretKey::random();
+        let message = sign_oracle_message_with_new_key(minority_message, &minority_key);
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
+
+        let response = test_app
+            .get_expect(&format!("/proof?slot={TEST_MESSAGE_SLOT}"), StatusCode::OK)
+            .await;
+        let proof: PriceProof = serde_json::from_slice(&response).unwrap();
+        let winning_message = &test_message.interval_inclusion_messages[0].message;
+        assert_eq!(proof.slot_number, TEST_MESSAGE_SLOT as i64);
+        assert_eq!(proof.interval_size, winning_message.interval_size as i64);
+        assert_eq!(proof.public_keys.len(), 3);
+
+        let aggregate_signature =
+            AggregateSignature::deserialize(&hex::decode(&proof.aggregate_signature).unwrap())
+                .unwrap();
+        let public_keys: Vec<PublicKey> = proof
+            .public_keys
+            .iter()
+            .map(|public_key| public_key.parse().unwrap())
+            .collect();
+        let digest =
+            Hash256::from_slice(&hex::decode(proof.digest.strip_prefix("0x").unwrap()).unwrap());
+        assert!(aggregate_signature
+            .fast_aggregate_verify(digest, &public_keys.iter().collect::<Vec<_>>()));
+        let aggregate_public_key = AggregatePublicKey::aggregate(&public_keys)
+            .unwrap()
+            .to_public_key();
+        assert_eq!(
+            hex::encode(aggregate_public_key.serialize()),
+            proof.aggregate_public_key
+        );
+
+        test_app
+            .get_expect(
+                &format!("/proof?slot={}", TEST_MESSAGE_SLOT - 1),
+                StatusCode::NOT_FOUND,
+            )
+            .await;
+    }
+}
@@ -0,0 +1,170 @@
+//! Attestations outside the retention window are pruned periodically, keeping the tables from
+//! growing without bound. The per-validator rows are rarely needed once aggregated, so aggregates
+//! are kept unless configured otherwise.
+
+use crate::state::AppState;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Number of rows deleted by a pruning pass, per table.
+#[derive(Debug, Default, PartialEq)]
+pub struct PrunedRows {
+    pub value_attestations: u64,
+    pub interval_attestations: u64,
+    pub aggregates: u64,
+}
+
+pub async fn run_pruning_worker(state: Arc<AppState>) {
+    let prune_interval_seconds = match state.config.prune_interval_seconds {
+        Some(prune_interval_seconds) if state.config.retention_slots.is_some() => {
+            prune_interval_seconds
+        }
+        _ => return,
+    };
+    let mut interval = tokio::time::interval(Duration::from_secs(prune_interval_seconds));
+    loop {
+        interval.tick().await;
+        match prune_old_attestations(&state).await {
+            Ok(pruned_rows) => tracing::info!(
+                "Pruned {} value attestations, {} interval attestations and {} aggregates",
+                pruned_rows.value_attestations,
+                pruned_rows.interval_attestations,
+                pruned_rows.aggregates
+            ),
+            Err(e) => tracing::error!("Error pruning attestations: {:?}", e),
+        }
+    }
+}
+
+/// Deletes the attestations of slots before the retention window, along with their aggregates
+/// if configured.
+pub async fn prune_old_attestations(state: &AppState) -> eyre::Result<PrunedRows> {
+    let retention_slots = match state.config.retention_slots {
+        Some(retention_slots) => retention_slots,
+        None => return Ok(PrunedRows::default()),
+    };
+    let before_slot = state.slot_clock.current_slot() as i64 - retention_slots as i64;
+    let mut transaction = state.db_pool.begin().await?;
+    let value_attestations = sqlx::query!(
+        "
+        DELETE FROM price_value_attestations
+        WHERE
+            slot_number < $1;
+        ",
+        before_slot
+    )
+    .execute(&mut transaction)
+    .await?
+    .rows_affected();
+    let interval_attestations = sqlx::query!(
+        "
+        DELETE FROM price_interval_attestations
+        WHERE
+            slot_number < $1;
+        ",
+        before_slot
+    )
+    .execute(&mut transaction)
+    .await?
+    .rows_affected();
+    let aggregates = if state.config.prune_aggregates {
+        sqlx::query!(
+            "
+            DELETE FROM aggregate_interval_attestations
+            WHERE
+                slot_number < $1;
+            ",
+            before_slot
+        )
+        .execute(&mut transaction)
+        .await?
+        .rows_affected()
+    } else {
+        0
+    };
+    transaction.commit().await?;
+    Ok(PrunedRows {
+        value_attestations,
+        interval_attestations,
+        aggregates,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Config;
+    use crate::db::DbPool;
+    use crate::test_utils::{
+        get_test_message, state_at_slot, value_message, TestApp, TEST_MESSAGE_SLOT,
+    };
+    use axum::http::StatusCode;
+    use bls::SecretKey;
+
+    async fn count_rows(db_pool: &DbPool, table: &str) -> i64 {
+        sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {table};"))
+            .fetch_one(db_pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn prunes_attestations_before_retention_window(db_pool: DbPool) {
+        let config = Config {
+            retention_slots: Some(10),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool.clone(), config.clone()).await;
+        let mut old_message = get_test_message();
+        old_message.interval_inclusion_messages.truncate(2);
+        test_app
+            .post_oracle_message(&old_message, StatusCode::OK)
+            .await;
+        let recent_slot = TEST_MESSAGE_SLOT + 15;
+        let test_app =
+            TestApp::with_config_at_slot(db_pool.clone(), config.clone(), recent_slot).await;
+        let recent_message = value_message(&SecretKey::random(), recent_slot, 1811093163);
+        test_app
+            .post_oracle_message(&recent_message, StatusCode::OK)
+            .await;
+
+        let state = state_at_slot(db_pool.clone(), config.clone(), TEST_MESSAGE_SLOT + 20);
+        assert_eq!(
+            prune_old_attestations(&state).await.unwrap(),
+            PrunedRows {
+                value_attestations: 1,
+                interval_attestations: 2,
+                aggregates: 0,
+            }
+        );
+        assert_eq!(count_rows(&db_pool, "price_value_attestations").await, 1);
+        assert_eq!(count_rows(&db_pool, "price_interval_attestations").await, 0);
+        // Aggregates are kept by default
+        assert_eq!(
+            count_rows(&db_pool, "aggregate_interval_attestations").await,
+            2
+        );
+        let response = test_app
+            .get_expect("/price_value_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<serde_json::Value> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries[0]["slot_number"], recent_slot);
+
+        let config = Config {
+            prune_aggregates: true,
+            ..config
+        };
+        let state = state_at_slot(db_pool.clone(), config, TEST_MESSAGE_SLOT + 20);
+        assert_eq!(
+            prune_old_attestations(&state).await.unwrap(),
+            PrunedRows {
+                aggregates: 2,
+                ..PrunedRows::default()
+            }
+        );
+        assert_eq!(
+            count_rows(&db_pool, "aggregate_interval_attestations").await,
+            0
+        );
+    }
+}
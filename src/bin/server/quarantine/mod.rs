@@ -0,0 +1,303 @@
+//! Validators that keep submitting validly signed but out-of-bounds or equivocating messages are
+//! quarantined, rejecting their submissions until an admin clears them.
+
+use crate::canonical::encode_public_key;
+use crate::state::AppState;
+use axum::{extract::State, http::StatusCode, Json};
+use bls::PublicKey;
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use std::sync::Arc;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Offence {
+    /// A message for a slot outside of the accepted window.
+    OutOfBounds,
+    /// A value message conflicting with an earlier one for the same slot.
+    Equivocation,
+}
+
+impl Offence {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Offence::OutOfBounds => "out_of_bounds",
+            Offence::Equivocation => "equivocation",
+        }
+    }
+
+    fn threshold(&self, state: &AppState) -> Option<i64> {
+        match self {
+            Offence::OutOfBounds => state.config.quarantine_out_of_bounds_threshold,
+            Offence::Equivocation => state.config.quarantine_equivocation_threshold,
+        }
+    }
+}
+
+pub async fn is_quarantined(
+    connection: &mut PgConnection,
+    validator_public_key: &PublicKey,
+) -> eyre::Result<bool> {
+    let pk_string = encode_public_key(validator_public_key);
+    let is_quarantined = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT
+                1
+            FROM
+                quarantined_validators
+            WHERE
+                validator_public_key = $1
+        ) AS "exists!";
+        "#,
+        pk_string
+    )
+    .fetch_one(connection)
+    .await?
+    .exists;
+    Ok(is_quarantined)
+}
+
+/// Records an offence by a validator, and quarantines them once they reach the configured number
+/// of offences of this kind.
+pub async fn record_offence(
+    state: &AppState,
+    connection: &mut PgConnection,
+    validator_public_key: &PublicKey,
+    offence: Offence,
+    slot_number: u64,
+) -> eyre::Result<()> {
+    let pk_string = encode_public_key(validator_public_key);
+    let slot_number = slot_number as i64;
+    sqlx::query!(
+        "
+        INSERT INTO validator_offences(
+            validator_public_key,
+            offence,
+            slot_number
+        )
+        VALUES (
+            $1,
+            $2,
+            $3
+        );
+        ",
+        pk_string,
+        offence.as_str(),
+        slot_number,
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    let threshold = match offence.threshold(state) {
+        Some(threshold) => threshold,
+        None => return Ok(()),
+    };
+    let num_offences = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) AS "count!"
+        FROM
+            validator_offences
+        WHERE
+            validator_public_key = $1
+        AND
+            offence = $2;
+        "#,
+        pk_string,
+        offence.as_str(),
+    )
+    .fetch_one(&mut *connection)
+    .await?
+    .count;
+
+    if num_offences >= threshold {
+        tracing::warn!(
+            "Quarantining validator {} after {} {} offences",
+            pk_string,
+            num_offences,
+            offence.as_str()
+        );
+        sqlx::query!(
+            "
+            INSERT INTO quarantined_validators(
+                validator_public_key,
+                reason
+            )
+            VALUES (
+                $1,
+                $2
+            )
+            ON CONFLICT (validator_public_key) DO NOTHING;
+            ",
+            pk_string,
+            offence.as_str(),
+        )
+        .execute(&mut *connection)
+        .await?;
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ClearQuarantineRequest {
+    pub validator_public_key: PublicKey,
+}
+
+/// Lifts the quarantine of a validator, forgetting their past offences.
+pub async fn post_clear_quarantine(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<ClearQuarantineRequest>,
+) -> Result<(), StatusCode> {
+    let pk_string = encode_public_key(&request.validator_public_key);
+    let mut transaction = state.db_pool.begin().await.map_err(|e| {
+        tracing::error!("Error starting transaction: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let cleared = sqlx::query!(
+        "
+        DELETE FROM quarantined_validators
+        WHERE
+            validator_public_key = $1;
+        ",
+        pk_string
+    )
+    .execute(&mut transaction)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error clearing quarantine: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .rows_affected();
+    if cleared == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    sqlx::query!(
+        "
+        DELETE FROM validator_offences
+        WHERE
+            validator_public_key = $1;
+        ",
+        pk_string
+    )
+    .execute(&mut transaction)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error clearing offences: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    transaction.commit().await.map_err(|e| {
+        tracing::error!("Error committing transaction: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    tracing::info!("Cleared quarantine of validator {}", pk_string);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Config;
+    use crate::db::DbPool;
+    use crate::test_utils::{
+        admin_config, value_message, TestApp, ADMIN_AUTHORIZATION, TEST_MESSAGE_SLOT,
+    };
+    use axum::body::Body;
+    use bls::SecretKey;
+
+    #[sqlx::test]
+    async fn quarantines_validator_after_out_of_bounds_messages(db_pool: DbPool) {
+        let config = Config {
+            quarantine_out_of_bounds_threshold: Some(2),
+            ..admin_config()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        let private_key = SecretKey::random();
+
+        let out_of_bounds_message = value_message(&private_key, TEST_MESSAGE_SLOT + 100, 1);
+        for _ in 0..2 {
+            test_app
+                .post_oracle_message(&out_of_bounds_message, StatusCode::BAD_REQUEST)
+                .await;
+        }
+
+        let valid_message = value_message(&private_key, TEST_MESSAGE_SLOT, 1);
+        test_app
+            .post_oracle_message(&valid_message, StatusCode::FORBIDDEN)
+            .await;
+
+        let body = serde_json::to_string(&ClearQuarantineRequest {
+            validator_public_key: private_key.public_key(),
+        })
+        .unwrap();
+        test_app
+            .post_expect_with_headers(
+                "/admin/clear-quarantine",
+                Body::from(body.clone()),
+                &[ADMIN_AUTHORIZATION],
+                StatusCode::OK,
+            )
+            .await;
+        test_app
+            .post_oracle_message(&valid_message, StatusCode::OK)
+            .await;
+
+        test_app
+            .post_expect_with_headers(
+                "/admin/clear-quarantine",
+                Body::from(body),
+                &[ADMIN_AUTHORIZATION],
+                StatusCode::NOT_FOUND,
+            )
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn quarantines_validator_after_equivocation(db_pool: DbPool) {
+        let config = Config {
+            quarantine_equivocation_threshold: Some(1),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        let private_key = SecretKey::random();
+
+        test_app
+            .post_oracle_message(
+                &value_message(&private_key, TEST_MESSAGE_SLOT, 1),
+                StatusCode::OK,
+            )
+            .await;
+        test_app
+            .post_oracle_message(
+                &value_message(&private_key, TEST_MESSAGE_SLOT, 2),
+                StatusCode::CONFLICT,
+            )
+            .await;
+        test_app
+            .post_oracle_message(
+                &value_message(&private_key, TEST_MESSAGE_SLOT - 1, 1),
+                StatusCode::FORBIDDEN,
+            )
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn does_not_quarantine_without_threshold(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let private_key = SecretKey::random();
+
+        for _ in 0..3 {
+            test_app
+                .post_oracle_message(
+                    &value_message(&private_key, TEST_MESSAGE_SLOT + 100, 1),
+                    StatusCode::BAD_REQUEST,
+                )
+                .await;
+        }
+        test_app
+            .post_oracle_message(
+                &value_message(&private_key, TEST_MESSAGE_SLOT, 1),
+                StatusCode::OK,
+            )
+            .await;
+    }
+}
@@ -0,0 +1,304 @@
+//! With a relative quorum threshold the number of validators needed depends on the size of the
+//! validator set, which can change between restarts. The allowlist size is recorded on startup
+//! so that every slot is judged by the set that was in effect for it.
+
+use crate::attestations::{AggregatePriceIntervalEntry, DEFAULT_ASSET};
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Records the allowlist size from the current slot on if it changed, and loads the history of
+/// sizes into memory.
+pub async fn record_validator_set_size(state: &AppState) -> eyre::Result<()> {
+    let db_pool = &state.db_pool;
+    let num_validators = state.config.validator_allowlist.len() as i64;
+    let latest_num_validators = sqlx::query!(
+        "
+        SELECT
+            num_validators
+        FROM
+            validator_set_sizes
+        ORDER BY
+            from_slot DESC
+        LIMIT 1;
+        "
+    )
+    .fetch_optional(db_pool)
+    .await?
+    .map(|row| row.num_validators);
+    if num_validators > 0 && latest_num_validators != Some(num_validators) {
+        let from_slot = state.slot_clock.current_slot() as i64;
+        tracing::info!(
+            "Recording validator set size {} from slot {}",
+            num_validators,
+            from_slot
+        );
+        sqlx::query!(
+            "
+            INSERT INTO validator_set_sizes(
+                from_slot,
+                num_validators
+            )
+            VALUES (
+                $1,
+                $2
+            )
+            ON CONFLICT (from_slot) DO UPDATE SET
+                num_validators = $2,
+                recorded_at = NOW();
+            ",
+            from_slot,
+            num_validators,
+        )
+        .execute(db_pool)
+        .await?;
+    }
+
+    let validator_set_sizes = sqlx::query!(
+        "
+        SELECT
+            from_slot,
+            num_validators
+        FROM
+            validator_set_sizes
+        ORDER BY
+            from_slot;
+        "
+    )
+    .fetch_all(db_pool)
+    .await?
+    .into_iter()
+    .map(|row| (row.from_slot, row.num_validators))
+    .collect();
+    *state.validator_set_sizes.lock().unwrap() = validator_set_sizes;
+    Ok(())
+}
+
+/// Size of the validator set in effect for the slot, if one was recorded by then.
+pub fn get_validator_set_size(state: &AppState, slot_number: i64) -> Option<i64> {
+    state
+        .validator_set_sizes
+        .lock()
+        .unwrap()
+        .iter()
+        .rev()
+        .find(|(from_slot, _)| *from_slot <= slot_number)
+        .map(|(_, num_validators)| *num_validators)
+}
+
+/// The threshold configured for the asset if any. Otherwise the relative quorum threshold applied
+/// to the validator set of the slot if configured and known, the absolute one otherwise.
+pub fn get_quorum_threshold(state: &AppState, asset: &str, slot_number: i64) -> Option<i64> {
+    if let Some(asset_threshold) = state.config.asset_quorum_thresholds.get(asset) {
+        return Some(*asset_threshold);
+    }
+    let relative_threshold = state
+        .config
+        .quorum_threshold_percent
+        .zip(get_validator_set_size(state, slot_number))
+        .map(|(percent, num_validators)| (num_validators * percent as i64 + 99) / 100);
+    relative_threshold.or(state.config.quorum_threshold)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct QuorumThresholdQuery {
+    /// Defaults to the default asset.
+    pub asset: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct QuorumThreshold {
+    pub asset: String,
+    pub slot_number: i64,
+    pub validator_set_size: Option<i64>,
+    /// Null if no quorum threshold applies.
+    pub quorum_threshold: Option<i64>,
+}
+
+pub async fn get_slot_quorum_threshold(
+    Path(slot_number): Path<i64>,
+    Query(query): Query<QuorumThresholdQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Json<QuorumThreshold> {
+    let asset = query.asset.unwrap_or_else(|| DEFAULT_ASSET.to_string());
+    Json(QuorumThreshold {
+        quorum_threshold: get_quorum_threshold(&state, &asset, slot_number),
+        asset,
+        slot_number,
+        validator_set_size: get_validator_set_size(&state, slot_number),
+    })
+}
+
+/// Aggregates of the slot that are one validator short of the quorum threshold of their asset, so
+/// operators can nudge the validators that are missing.
+pub async fn get_near_quorum_aggregates(
+    Path(slot_number): Path<i64>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<AggregatePriceIntervalEntry>>, StatusCode> {
+    let entries = sqlx::query!(
+        "
+        SELECT
+            asset,
+            value,
+            slot_number,
+            aggregate_signature,
+            aggregate_public_key,
+            public_keys,
+            interval_size,
+            num_validators,
+            num_operators
+        FROM
+            aggregate_interval_attestations
+        WHERE
+            slot_number = $1
+        ORDER BY
+            asset,
+            interval_size,
+            value;
+        ",
+        slot_number
+    )
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error getting aggregates: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .into_iter()
+    .map(|row| AggregatePriceIntervalEntry {
+        asset: row.asset,
+        value: row.value,
+        slot_number: row.slot_number,
+        aggregate_signature: row.aggregate_signature,
+        aggregate_public_key: row.aggregate_public_key,
+        public_keys: row.public_keys,
+        interval_size: row.interval_size,
+        num_validators: row.num_validators,
+        num_operators: row.num_operators,
+        finalized: None,
+    })
+    .filter(|entry| {
+        get_quorum_threshold(&state, &entry.asset, slot_number)
+            .is_some_and(|quorum_threshold| entry.num_validators == quorum_threshold - 1)
+    })
+    .collect();
+    Ok(Json(entries))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::attestations::reaches_quorum;
+    use crate::config::Config;
+    use crate::db::DbPool;
+    use crate::test_utils::{
+        get_test_message, sign_oracle_message_with_new_key, state_at_slot, TestApp,
+        TEST_MESSAGE_SLOT,
+    };
+    use bls::{PublicKey, SecretKey};
+
+    fn config_with_allowlist(num_validators: usize) -> Config {
+        let validator_allowlist: Vec<PublicKey> = (0..num_validators)
+            .map(|_| SecretKey::random().public_key())
+            .collect();
+        Config {
+            validator_allowlist,
+            quorum_threshold_percent: Some(50),
+            ..Config::default()
+        }
+    }
+
+    async fn get_threshold_at(test_app: &TestApp, slot_number: u64) -> QuorumThreshold {
+        let response = test_app
+            .get_expect(&format!("/quorum-threshold/{slot_number}"), StatusCode::OK)
+            .await;
+        serde_json::from_slice(&response).unwrap()
+    }
+
+    #[sqlx::test]
+    async fn applies_per_asset_thresholds(db_pool: DbPool) {
+        let config = Config {
+            quorum_threshold: Some(1),
+            asset_quorum_thresholds: [("BTCUSD".to_string(), 3)].into(),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool.clone(), config.clone()).await;
+        let response = test_app
+            .get_expect(
+                &format!("/quorum-threshold/{TEST_MESSAGE_SLOT}?asset=BTCUSD"),
+                StatusCode::OK,
+            )
+            .await;
+        let threshold: QuorumThreshold = serde_json::from_slice(&response).unwrap();
+        assert_eq!(threshold.asset, "BTCUSD");
+        assert_eq!(threshold.quorum_threshold, Some(3));
+        // Other assets fall back to the global threshold
+        let threshold = get_threshold_at(&test_app, TEST_MESSAGE_SLOT).await;
+        assert_eq!(threshold.asset, DEFAULT_ASSET);
+        assert_eq!(threshold.quorum_threshold, Some(1));
+
+        let state = state_at_slot(db_pool, config, TEST_MESSAGE_SLOT);
+        let slot_number = TEST_MESSAGE_SLOT as i64;
+        assert!(reaches_quorum(&state, DEFAULT_ASSET, slot_number, 1, 1));
+        assert!(!reaches_quorum(&state, "BTCUSD", slot_number, 2, 2));
+        assert!(reaches_quorum(&state, "BTCUSD", slot_number, 3, 3));
+    }
+
+    #[sqlx::test]
+    async fn applies_historical_validator_set_size(db_pool: DbPool) {
+        TestApp::with_config_at_slot(
+            db_pool.clone(),
+            config_with_allowlist(5),
+            TEST_MESSAGE_SLOT - 100,
+        )
+        .await;
+        let test_app = TestApp::with_config(db_pool, config_with_allowlist(10)).await;
+
+        let threshold = get_threshold_at(&test_app, TEST_MESSAGE_SLOT - 50).await;
+        assert_eq!(threshold.validator_set_size, Some(5));
+        // Rounded up
+        assert_eq!(threshold.quorum_threshold, Some(3));
+
+        let threshold = get_threshold_at(&test_app, TEST_MESSAGE_SLOT).await;
+        assert_eq!(threshold.validator_set_size, Some(10));
+        assert_eq!(threshold.quorum_threshold, Some(5));
+
+        // Before any recorded set there is nothing to apply the percentage to
+        let threshold = get_threshold_at(&test_app, TEST_MESSAGE_SLOT - 200).await;
+        assert_eq!(threshold.validator_set_size, None);
+        assert_eq!(threshold.quorum_threshold, None);
+    }
+
+    #[sqlx::test]
+    async fn lists_aggregates_one_validator_short_of_quorum(db_pool: DbPool) {
+        let config = Config {
+            quorum_threshold: Some(3),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        // The first interval reaches quorum, the second is one short and the third two short
+        let test_message = get_test_message();
+        for num_intervals in [3, 2, 1] {
+            let mut message = test_message.clone();
+            message.interval_inclusion_messages.truncate(num_intervals);
+            let message = sign_oracle_message_with_new_key(message, &SecretKey::random());
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+        }
+
+        let response = test_app
+            .get_expect(&format!("/near-quorum/{TEST_MESSAGE_SLOT}"), StatusCode::OK)
+            .await;
+        let entries: Vec<AggregatePriceIntervalEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].num_validators, 2);
+        assert_eq!(
+            entries[0].value,
+            test_message.interval_inclusion_messages[1].message.value as i64
+        );
+    }
+}
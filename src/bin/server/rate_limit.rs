@@ -0,0 +1,103 @@
+//! Token buckets limiting how many messages each validator may submit, so that a single validator
+//! can't keep the server busy verifying its signatures.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+struct TokenBucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Lets every key take up to `capacity` tokens at once, refilling them at a steady rate over
+/// `refill_period`.
+pub struct RateLimiter<K> {
+    capacity: f64,
+    tokens_per_second: f64,
+    buckets: HashMap<K, TokenBucket>,
+}
+
+impl<K: Eq + Hash> RateLimiter<K> {
+    pub fn new(capacity: u64, refill_period: Duration) -> Self {
+        RateLimiter {
+            capacity: capacity as f64,
+            tokens_per_second: capacity as f64 / refill_period.as_secs_f64(),
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// Takes a token of the key if it has one left.
+    pub fn try_acquire(&mut self, key: K, now: Instant) -> bool {
+        let capacity = self.capacity;
+        let bucket = self.buckets.entry(key).or_insert(TokenBucket {
+            tokens: capacity,
+            refilled_at: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.refilled_at);
+        bucket.tokens =
+            (bucket.tokens + elapsed.as_secs_f64() * self.tokens_per_second).min(capacity);
+        bucket.refilled_at = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Config;
+    use crate::db::DbPool;
+    use crate::test_utils::{value_message, TestApp, TEST_MESSAGE_SLOT};
+    use axum::http::StatusCode;
+    use bls::SecretKey;
+
+    #[test]
+    fn refills_tokens_over_time() {
+        let mut rate_limiter = RateLimiter::new(2, Duration::from_secs(12));
+        let start = Instant::now();
+        assert!(rate_limiter.try_acquire("a", start));
+        assert!(rate_limiter.try_acquire("a", start));
+        assert!(!rate_limiter.try_acquire("a", start));
+        // Keys have their own buckets
+        assert!(rate_limiter.try_acquire("b", start));
+
+        assert!(!rate_limiter.try_acquire("a", start + Duration::from_secs(5)));
+        assert!(rate_limiter.try_acquire("a", start + Duration::from_secs(7)));
+        assert!(!rate_limiter.try_acquire("a", start + Duration::from_secs(7)));
+        // Never more than the capacity
+        let later = start + Duration::from_secs(600);
+        assert!(rate_limiter.try_acquire("a", later));
+        assert!(rate_limiter.try_acquire("a", later));
+        assert!(!rate_limiter.try_acquire("a", later));
+    }
+
+    #[sqlx::test]
+    async fn rejects_messages_past_budget(db_pool: DbPool) {
+        let config = Config {
+            rate_limit_messages_per_slot: Some(2),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        let private_key = SecretKey::random();
+        let message = value_message(&private_key, TEST_MESSAGE_SLOT, 1811093163);
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
+        // Rejected messages use up the budget too
+        test_app
+            .post_oracle_message(&message, StatusCode::CONFLICT)
+            .await;
+        test_app
+            .post_oracle_message(&message, StatusCode::TOO_MANY_REQUESTS)
+            .await;
+
+        // Other validators have their own budget
+        let other_message = value_message(&SecretKey::random(), TEST_MESSAGE_SLOT, 1811093163);
+        test_app
+            .post_oracle_message(&other_message, StatusCode::OK)
+            .await;
+    }
+}
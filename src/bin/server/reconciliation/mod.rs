@@ -0,0 +1,218 @@
+//! Periodically compares the aggregates of the slots within the interval window against the
+//! individual interval attestations they were built from, to catch drift after e.g. a crash
+//! between storing an attestation and updating its aggregate. Only validator counts are compared,
+//! recomputing every aggregate signature would be too expensive.
+
+use crate::aggregates::repair_aggregate;
+use crate::attestations::AggregatePriceIntervalEntry;
+use crate::state::AppState;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconciliationMode {
+    #[default]
+    Off,
+    /// Only log discrepancies.
+    Detect,
+    /// Log discrepancies and repair the aggregates.
+    Fix,
+}
+
+impl FromStr for ReconciliationMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(ReconciliationMode::Off),
+            "detect" => Ok(ReconciliationMode::Detect),
+            "fix" => Ok(ReconciliationMode::Fix),
+            _ => Err(format!("unknown reconciliation mode {s}")),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Discrepancy {
+    pub aggregate: AggregatePriceIntervalEntry,
+    /// Number of distinct validators with a stored attestation for the aggregate.
+    pub num_contributions: i64,
+}
+
+pub async fn run_reconciliation_worker(state: Arc<AppState>) {
+    if state.config.reconciliation_mode == ReconciliationMode::Off {
+        return;
+    }
+    let mut interval = tokio::time::interval(Duration::from_secs(
+        state.config.reconciliation_interval_seconds,
+    ));
+    loop {
+        interval.tick().await;
+        if let Err(e) = reconcile(&state).await {
+            tracing::error!("Error reconciling aggregates: {:?}", e);
+        }
+    }
+}
+
+/// Finds the aggregates within the interval window whose validator count doesn't match their
+/// stored attestations, repairing them if configured to.
+pub async fn reconcile(state: &AppState) -> eyre::Result<Vec<Discrepancy>> {
+    let current_slot = state.slot_clock.current_slot();
+    let window_slots = state.config.interval_window_slots;
+    let from_slot = current_slot.saturating_sub(window_slots) as i64;
+    let to_slot = (current_slot + window_slots) as i64;
+    let discrepancies: Vec<Discrepancy> = sqlx::query!(
+        r#"
+        SELECT
+            aggregate.asset,
+            aggregate.value,
+            aggregate.slot_number,
+            aggregate.aggregate_signature,
+            aggregate.aggregate_public_key,
+            aggregate.public_keys,
+            aggregate.interval_size,
+            aggregate.num_validators,
+            aggregate.num_operators,
+            COUNT(DISTINCT attestation.validator_public_key) AS "num_contributions!"
+        FROM
+            aggregate_interval_attestations aggregate
+        LEFT JOIN
+            price_interval_attestations attestation
+        ON
+            attestation.asset = aggregate.asset
+        AND
+            attestation.slot_number = aggregate.slot_number
+        AND
+            attestation.interval_size = aggregate.interval_size
+        AND
+            attestation.aggregate_value = aggregate.value
+        WHERE
+            aggregate.slot_number BETWEEN $1 AND $2
+        GROUP BY
+            aggregate.asset,
+            aggregate.value,
+            aggregate.slot_number,
+            aggregate.aggregate_signature,
+            aggregate.aggregate_public_key,
+            aggregate.public_keys,
+            aggregate.interval_size,
+            aggregate.num_validators,
+            aggregate.num_operators
+        HAVING
+            aggregate.num_validators <> COUNT(DISTINCT attestation.validator_public_key);
+        "#,
+        from_slot,
+        to_slot,
+    )
+    .fetch_all(&state.db_pool)
+    .await?
+    .into_iter()
+    .map(|row| Discrepancy {
+        aggregate: AggregatePriceIntervalEntry {
+            asset: row.asset,
+            value: row.value,
+            slot_number: row.slot_number,
+            aggregate_signature: row.aggregate_signature,
+            aggregate_public_key: row.aggregate_public_key,
+            public_keys: row.public_keys,
+            interval_size: row.interval_size,
+            num_validators: row.num_validators,
+            num_operators: row.num_operators,
+            finalized: None,
+        },
+        num_contributions: row.num_contributions,
+    })
+    .collect();
+
+    for discrepancy in &discrepancies {
+        let aggregate = &discrepancy.aggregate;
+        tracing::warn!(
+            "Aggregate of value {} with interval size {} in slot {} counts {} validators, but has {} stored contributions",
+            aggregate.value,
+            aggregate.interval_size,
+            aggregate.slot_number,
+            aggregate.num_validators,
+            discrepancy.num_contributions
+        );
+        if state.config.reconciliation_mode == ReconciliationMode::Fix {
+            if let Err(e) = repair_aggregate(state, aggregate.clone()).await {
+                tracing::error!("Error repairing aggregate: {:?}", e);
+            }
+        }
+    }
+    Ok(discrepancies)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::aggregates::get_aggregate;
+    use crate::config::Config;
+    use crate::db::DbPool;
+    use crate::test_utils::{
+        get_test_message, sign_oracle_message_with_new_key, state_at_slot, TestApp,
+        TEST_MESSAGE_SLOT,
+    };
+    use axum::http::StatusCode;
+    use bls::SecretKey;
+
+    #[sqlx::test]
+    async fn detects_and_fixes_drifted_aggregate(db_pool: DbPool) {
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(2);
+        let test_app = TestApp::new(db_pool.clone()).await;
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+        let message = sign_oracle_message_with_new_key(test_message.clone(), &SecretKey::random());
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
+
+        let interval_message = &test_message.interval_inclusion_messages[0].message;
+        let (slot_number, interval_size, value) = (
+            interval_message.slot_number as i64,
+            interval_message.interval_size as i64,
+            interval_message.value as i64,
+        );
+        sqlx::query(
+            "
+            UPDATE aggregate_interval_attestations
+            SET
+                num_validators = 1
+            WHERE
+                value = $1;
+            ",
+        )
+        .bind(value)
+        .execute(&db_pool)
+        .await
+        .unwrap();
+
+        let detect_config = Config {
+            reconciliation_mode: ReconciliationMode::Detect,
+            ..Config::default()
+        };
+        let state = state_at_slot(db_pool.clone(), detect_config, TEST_MESSAGE_SLOT);
+        let discrepancies = reconcile(&state).await.unwrap();
+        assert_eq!(discrepancies.len(), 1);
+        assert_eq!(discrepancies[0].aggregate.value, value);
+        assert_eq!(discrepancies[0].num_contributions, 2);
+        // Detecting leaves the aggregate as is
+        assert_eq!(reconcile(&state).await.unwrap().len(), 1);
+
+        let fix_config = Config {
+            reconciliation_mode: ReconciliationMode::Fix,
+            ..Config::default()
+        };
+        let state = state_at_slot(db_pool.clone(), fix_config, TEST_MESSAGE_SLOT);
+        assert_eq!(reconcile(&state).await.unwrap().len(), 1);
+        assert!(reconcile(&state).await.unwrap().is_empty());
+        let aggregate = get_aggregate(&db_pool, slot_number, interval_size, value)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(aggregate.num_validators, 2);
+    }
+}
@@ -0,0 +1,139 @@
+//! Only registered validators may submit once a registry file is configured. The file lists one
+//! public key per line and is reread on startup and whenever an admin asks for it, so validators
+//! can be added or removed without a restart.
+
+use crate::state::AppState;
+use axum::{extract::State, http::StatusCode, Json};
+use bls::PublicKey;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Parses a registry file, skipping blank lines and `#` comments.
+fn parse_registered_validators(contents: &str) -> eyre::Result<HashSet<PublicKey>> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            line.parse()
+                .map_err(|e| eyre::eyre!("invalid public key {line}: {e:?}"))
+        })
+        .collect()
+}
+
+/// Rereads the registry file if one is configured, returning the number of registered
+/// validators. The previous registry stays in effect if the file can't be read.
+pub async fn load_registered_validators(state: &AppState) -> eyre::Result<Option<usize>> {
+    let Some(path) = &state.config.registered_validators_file else {
+        return Ok(None);
+    };
+    let contents = tokio::fs::read_to_string(path).await?;
+    let registered_validators = parse_registered_validators(&contents)?;
+    let num_validators = registered_validators.len();
+    tracing::info!("Loaded {} registered validators", num_validators);
+    *state.registered_validators.lock().unwrap() = Some(registered_validators);
+    Ok(Some(num_validators))
+}
+
+/// Whether the validator may submit, which all validators may without a registry.
+pub fn is_registered(state: &AppState, validator_public_key: &PublicKey) -> bool {
+    state
+        .registered_validators
+        .lock()
+        .unwrap()
+        .as_ref()
+        .is_none_or(|registered_validators| registered_validators.contains(validator_public_key))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReloadedValidators {
+    pub num_validators: usize,
+}
+
+/// Rereads the registry file, responding with 404 if none is configured.
+pub async fn post_reload_validators(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ReloadedValidators>, StatusCode> {
+    let num_validators = load_registered_validators(&state)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error loading registered validators: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(ReloadedValidators { num_validators }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Config;
+    use crate::db::DbPool;
+    use crate::test_utils::{
+        admin_config, value_message, TestApp, ADMIN_AUTHORIZATION, TEST_MESSAGE_SLOT,
+    };
+    use axum::body::Body;
+    use bls::SecretKey;
+    use rand::RngCore;
+
+    fn write_registry_file(path: &std::path::Path, public_keys: &[PublicKey]) {
+        let contents: String = public_keys
+            .iter()
+            .map(|public_key| format!("{public_key}\n"))
+            .collect();
+        std::fs::write(path, format!("# Registered validators\n{contents}")).unwrap();
+    }
+
+    #[sqlx::test]
+    async fn rejects_unregistered_validators(db_pool: DbPool) {
+        let path = std::env::temp_dir().join(format!(
+            "registered-validators-{}",
+            rand::thread_rng().next_u64()
+        ));
+        let registered_key = SecretKey::random();
+        let unregistered_key = SecretKey::random();
+        write_registry_file(&path, &[registered_key.public_key()]);
+        let config = Config {
+            registered_validators_file: Some(path.to_string_lossy().to_string()),
+            ..admin_config()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+
+        test_app
+            .post_oracle_message(
+                &value_message(&unregistered_key, TEST_MESSAGE_SLOT, 1811093163),
+                StatusCode::FORBIDDEN,
+            )
+            .await;
+        test_app
+            .post_oracle_message(
+                &value_message(&registered_key, TEST_MESSAGE_SLOT, 1811093163),
+                StatusCode::OK,
+            )
+            .await;
+
+        // Registering the validator takes effect once reloaded
+        write_registry_file(
+            &path,
+            &[registered_key.public_key(), unregistered_key.public_key()],
+        );
+        let response = test_app
+            .post_expect_with_headers(
+                "/admin/reload-validators",
+                Body::empty(),
+                &[ADMIN_AUTHORIZATION],
+                StatusCode::OK,
+            )
+            .await;
+        let reloaded: ReloadedValidators = serde_json::from_slice(&response).unwrap();
+        assert_eq!(reloaded.num_validators, 2);
+        test_app
+            .post_oracle_message(
+                &value_message(&unregistered_key, TEST_MESSAGE_SLOT, 1811093163),
+                StatusCode::OK,
+            )
+            .await;
+        std::fs::remove_file(path).unwrap();
+    }
+}
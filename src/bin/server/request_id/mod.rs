@@ -0,0 +1,141 @@
+//! Request ids tie together the log lines of a request across services. An id passed by the
+//! caller is kept, otherwise one is generated, and either way it is echoed in the response.
+
+use axum::{
+    http::{HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use rand::RngCore;
+use tracing::Instrument;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// A random (version 4) UUID.
+fn generate_request_id() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Runs the request in a span carrying its request id, so every log line of the request has it.
+pub async fn propagate_request_id<B>(request: Request<B>, next: Next<B>) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .filter(|value| !value.is_empty())
+        .cloned()
+        .unwrap_or_else(|| {
+            HeaderValue::from_str(&generate_request_id())
+                .expect("expect generated request id to be a valid header value")
+        });
+    let span = tracing::info_span!(
+        "request",
+        request_id = request_id.to_str().unwrap_or_default(),
+        method = %request.method(),
+        path = request.uri().path(),
+    );
+    let mut response = next.run(request).instrument(span).await;
+    response.headers_mut().insert(REQUEST_ID_HEADER, request_id);
+    response
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::DbPool;
+    use crate::test_utils::{
+        signature_from_random_signer, value_message_from_new_validator, TestApp,
+    };
+    use axum::{body::Body, http::StatusCode};
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    /// Collects everything logged while it is the default subscriber's writer.
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[sqlx::test]
+    async fn echoes_provided_request_id(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let response = test_app
+            .get_response("/slot", &[("X-Request-Id", "trace-123")])
+            .await;
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "trace-123");
+    }
+
+    #[sqlx::test]
+    async fn generates_request_id_when_absent(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let response = test_app.get_response("/slot", &[]).await;
+        let request_id = response.headers()[REQUEST_ID_HEADER].to_str().unwrap();
+        assert_eq!(request_id.len(), 36);
+        assert_eq!(request_id.as_bytes()[14], b'4');
+
+        // A new one for every request
+        let other_response = test_app.get_response("/slot", &[]).await;
+        assert_ne!(other_response.headers()[REQUEST_ID_HEADER], request_id);
+    }
+
+    #[sqlx::test]
+    async fn logs_rejections_with_request_id(db_pool: DbPool) {
+        let logs = CapturedLogs::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer({
+                let logs = logs.clone();
+                move || logs.clone()
+            })
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let test_app = TestApp::new(db_pool).await;
+        let mut message = value_message_from_new_validator(1811093163);
+        message.value_message.signature =
+            signature_from_random_signer(&message.value_message.message);
+        test_app
+            .post_expect_with_headers(
+                "/post_oracle_message",
+                Body::from(serde_json::to_string(&message).unwrap()),
+                &[("X-Request-Id", "trace-456")],
+                StatusCode::BAD_REQUEST,
+            )
+            .await;
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        let rejection = logs
+            .lines()
+            .find(|line| line.contains("Rejecting oracle message"))
+            .unwrap();
+        assert!(rejection.contains("WARN"));
+        assert!(rejection.contains("trace-456"));
+        let public_key = message.validator_public_key.to_string();
+        assert!(rejection.contains(&public_key[..18]));
+        assert!(!logs.contains(&public_key));
+        assert!(rejection.contains(&format!(
+            "slot={}",
+            message.value_message.message.slot_number
+        )));
+    }
+}
@@ -0,0 +1,226 @@
+//! Validators sign with keys in G1 and signatures in G2 (min-pk). A client on the min-sig variant
+//! swaps the two groups, which would otherwise only show up as an opaque deserialization error.
+
+use crate::state::AppState;
+use axum::{
+    body::{Body, Bytes},
+    extract::{FromRequest, State},
+    http::{Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::Value;
+use std::sync::Arc;
+
+const G1_POINT_BYTES: usize = 48;
+const G2_POINT_BYTES: usize = 96;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SignatureScheme {
+    /// Public keys in G1, signatures in G2.
+    MinPk,
+    /// Signatures in G1, public keys in G2.
+    MinSig,
+}
+
+impl SignatureScheme {
+    pub fn from_public_key_length(num_bytes: usize) -> Option<Self> {
+        match num_bytes {
+            G1_POINT_BYTES => Some(SignatureScheme::MinPk),
+            G2_POINT_BYTES => Some(SignatureScheme::MinSig),
+            _ => None,
+        }
+    }
+
+    pub fn from_signature_length(num_bytes: usize) -> Option<Self> {
+        match num_bytes {
+            G2_POINT_BYTES => Some(SignatureScheme::MinPk),
+            G1_POINT_BYTES => Some(SignatureScheme::MinSig),
+            _ => None,
+        }
+    }
+}
+
+fn hex_length(value: &Value) -> Option<usize> {
+    let hex_string = value.as_str()?;
+    let hex_string = hex_string.strip_prefix("0x").unwrap_or(hex_string);
+    hex::decode(hex_string).ok().map(|bytes| bytes.len())
+}
+
+/// Describes the first key or signature of an oracle message that is encoded for the min-sig
+/// variant.
+pub fn find_min_sig_encoding(message: &Value) -> Option<String> {
+    let public_key = &message["validator_public_key"];
+    if hex_length(public_key).and_then(SignatureScheme::from_public_key_length)
+        == Some(SignatureScheme::MinSig)
+    {
+        return Some(format!(
+            "validator_public_key is a {G2_POINT_BYTES} byte min-sig public key, expected a {G1_POINT_BYTES} byte min-pk public key"
+        ));
+    }
+
+    let value_signature = ("value_message".to_string(), &message["value_message"]);
+    let interval_signatures = message["interval_inclusion_messages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .map(|(index, interval_message)| {
+            (
+                format!("interval_inclusion_messages[{index}]"),
+                interval_message,
+            )
+        });
+    std::iter::once(value_signature)
+        .chain(interval_signatures)
+        .find(|(_, signed_message)| {
+            hex_length(&signed_message["signature"]).and_then(SignatureScheme::from_signature_length)
+                == Some(SignatureScheme::MinSig)
+        })
+        .map(|(field, _)| {
+            format!(
+                "{field}.signature is a {G1_POINT_BYTES} byte min-sig signature, expected a {G2_POINT_BYTES} byte min-pk signature"
+            )
+        })
+}
+
+/// Like `find_min_sig_encoding`, for a single oracle message or a batch of them.
+fn find_min_sig_encoding_in_body(body: &Value) -> Option<String> {
+    match body.as_array() {
+        Some(messages) => messages.iter().enumerate().find_map(|(index, message)| {
+            find_min_sig_encoding(message).map(|error| format!("[{index}].{error}"))
+        }),
+        None => find_min_sig_encoding(body),
+    }
+}
+
+/// Rejects JSON oracle messages, or batches of them, encoded for the min-sig variant with an
+/// error naming the offending field, if enforced. The SSZ encoding has fixed length keys and
+/// signatures, so min-sig ones don't decode in the first place.
+pub async fn require_min_pk_scheme(
+    State(state): State<Arc<AppState>>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Result<Response, Response> {
+    if !state.config.enforce_signature_scheme {
+        return Ok(next.run(request).await);
+    }
+    let (parts, body) = request.into_parts();
+    let body = Bytes::from_request(Request::new(body), &state)
+        .await
+        .map_err(|rejection| rejection.into_response())?;
+    // Leave malformed JSON to the handler to reject.
+    if let Ok(message) = serde_json::from_slice::<Value>(&body) {
+        if let Some(error) = find_min_sig_encoding_in_body(&message) {
+            tracing::warn!("Rejecting message with wrong signature scheme: {}", error);
+            return Err((StatusCode::UNPROCESSABLE_ENTITY, error).into_response());
+        }
+    }
+    Ok(next.run(Request::from_parts(parts, Body::from(body))).await)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::Config;
+    use crate::db::DbPool;
+    use crate::test_utils::{get_test_message, TestApp};
+    use axum::{body::Body, http::StatusCode};
+
+    /// A G1 point, as a min-sig signature would be.
+    const MIN_SIG_SIGNATURE: &str = "0xa491d1b0ecd9bb917989f0e74f0dea0422eac4a873e5e2644f368dffb9a6e20fd6e10c1b77654d067c0618f6e5a7f79a";
+
+    fn test_message_json_with_value_signature(signature: &str) -> String {
+        let mut message = serde_json::to_value(get_test_message()).unwrap();
+        message["value_message"]["signature"] = serde_json::Value::from(signature);
+        message.to_string()
+    }
+
+    #[sqlx::test]
+    async fn rejects_min_sig_signature_when_enforced(db_pool: DbPool) {
+        let config = Config {
+            enforce_signature_scheme: true,
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+
+        let response = test_app
+            .post_expect(
+                "/post_oracle_message",
+                Body::from(test_message_json_with_value_signature(MIN_SIG_SIGNATURE)),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            )
+            .await;
+        assert_eq!(
+            String::from_utf8(response.to_vec()).unwrap(),
+            "value_message.signature is a 48 byte min-sig signature, expected a 96 byte min-pk signature"
+        );
+
+        let mut message = serde_json::to_value(get_test_message()).unwrap();
+        message["interval_inclusion_messages"][3]["signature"] =
+            serde_json::Value::from(MIN_SIG_SIGNATURE);
+        let response = test_app
+            .post_expect(
+                "/post_oracle_message",
+                Body::from(message.to_string()),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            )
+            .await;
+        assert!(String::from_utf8(response.to_vec()).unwrap().starts_with(
+            "interval_inclusion_messages[3].signature is a 48 byte min-sig signature"
+        ));
+
+        test_app
+            .post_oracle_message(&get_test_message(), StatusCode::OK)
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn rejects_min_sig_signature_in_batch_when_enforced(db_pool: DbPool) {
+        let config = Config {
+            enforce_signature_scheme: true,
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        let valid_message = serde_json::to_string(&get_test_message()).unwrap();
+        let batch = format!(
+            "[{valid_message},{}]",
+            test_message_json_with_value_signature(MIN_SIG_SIGNATURE)
+        );
+
+        let response = test_app
+            .post_expect(
+                "/oracle_messages/batch",
+                Body::from(batch),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            )
+            .await;
+        assert_eq!(
+            String::from_utf8(response.to_vec()).unwrap(),
+            "[1].value_message.signature is a 48 byte min-sig signature, expected a 96 byte min-pk signature"
+        );
+
+        test_app
+            .post_expect(
+                "/oracle_messages/batch",
+                Body::from(format!("[{valid_message}]")),
+                StatusCode::OK,
+            )
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn leaves_min_sig_signature_to_deserialization_by_default(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+
+        let response = test_app
+            .post_expect(
+                "/post_oracle_message",
+                Body::from(test_message_json_with_value_signature(MIN_SIG_SIGNATURE)),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            )
+            .await;
+        assert!(!String::from_utf8(response.to_vec())
+            .unwrap()
+            .contains("min-sig"));
+    }
+}
@@ -0,0 +1,100 @@
+//! On SIGTERM or SIGINT the server stops accepting connections but lets in-flight requests
+//! finish, so a stop doesn't cut a submission off halfway through its writes.
+
+use axum::Router;
+use std::future::Future;
+use std::net::TcpListener;
+
+/// Resolves on the first SIGINT or SIGTERM.
+pub async fn shutdown_signal() {
+    let interrupt = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("expect SIGINT handler to install");
+    };
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("expect SIGTERM handler to install")
+            .recv()
+            .await;
+    };
+    tokio::select! {
+        _ = interrupt => tracing::info!("Received SIGINT, shutting down"),
+        _ = terminate => tracing::info!("Received SIGTERM, shutting down"),
+    }
+}
+
+/// Serves the app until `shutdown` resolves, then drains the open connections.
+pub async fn serve_until_shutdown(
+    listener: TcpListener,
+    app: Router,
+    shutdown: impl Future<Output = ()>,
+) -> hyper::Result<()> {
+    axum::Server::from_tcp(listener)?
+        .serve(app.into_make_service())
+        .with_graceful_shutdown(shutdown)
+        .await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::{http::StatusCode, routing::get};
+    use std::net::{SocketAddr, TcpStream};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::{oneshot, Notify};
+
+    async fn refuses_connections(addr: SocketAddr) -> bool {
+        for _ in 0..100 {
+            if TcpStream::connect(addr).is_err() {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn finishes_active_request_after_shutdown_signal() {
+        let started = Arc::new(Notify::new());
+        let release = Arc::new(Notify::new());
+        let app = Router::new().route(
+            "/slow",
+            get({
+                let started = started.clone();
+                let release = release.clone();
+                || async move {
+                    started.notify_one();
+                    release.notified().await;
+                    "done"
+                }
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (shutdown_sender, shutdown_receiver) = oneshot::channel::<()>();
+        let server = tokio::spawn(serve_until_shutdown(listener, app, async {
+            shutdown_receiver.await.ok();
+        }));
+
+        let request = tokio::spawn(async move {
+            let uri = format!("http://{addr}/slow").parse().unwrap();
+            let response = hyper::Client::new().get(uri).await.unwrap();
+            let status = response.status();
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            (status, body)
+        });
+        started.notified().await;
+
+        shutdown_sender.send(()).unwrap();
+        assert!(refuses_connections(addr).await);
+        assert!(!server.is_finished());
+
+        release.notify_one();
+        let (status, body) = request.await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(&body[..], b"done");
+        server.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,551 @@
+//! Validators signing messages that contradict each other, reported with the signed messages
+//! themselves so the report can be submitted as a slashing proof.
+
+use crate::attestations::get_list_limit;
+use crate::consensus::interval_message_contains;
+use crate::db::DbPool;
+use crate::state::AppState;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+const MAX_SLASHING_REPORT_SLOT_RANGE: i64 = 7200;
+
+/// A price value as signed by a validator.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SignedValue {
+    pub value: i64,
+    pub signature: String,
+}
+
+/// An interval as signed by a validator.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SignedInterval {
+    pub value: i64,
+    pub interval_size: i64,
+    pub signature: String,
+}
+
+/// Stores both value messages of a validator equivocating on a slot, as only the first one makes
+/// it into the attestations.
+pub async fn record_equivocation(
+    connection: &mut PgConnection,
+    validator_public_key: &str,
+    asset: &str,
+    slot_number: i64,
+    stored: &SignedValue,
+    conflicting: &SignedValue,
+) -> eyre::Result<()> {
+    tracing::warn!(
+        "Validator {} equivocated on slot {} with values {} and {}",
+        validator_public_key,
+        slot_number,
+        stored.value,
+        conflicting.value
+    );
+    sqlx::query!(
+        "
+        INSERT INTO equivocations(
+            validator_public_key,
+            asset,
+            slot_number,
+            value,
+            signature,
+            conflicting_value,
+            conflicting_signature
+        )
+        VALUES (
+            $1,
+            $2,
+            $3,
+            $4,
+            $5,
+            $6,
+            $7
+        )
+        ON CONFLICT DO NOTHING;
+        ",
+        validator_public_key,
+        asset,
+        slot_number,
+        stored.value,
+        stored.signature,
+        conflicting.value,
+        conflicting.signature,
+    )
+    .execute(connection)
+    .await?;
+    Ok(())
+}
+
+/// Two value messages a validator signed for the same slot.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Equivocation {
+    pub validator_public_key: String,
+    pub asset: String,
+    pub slot_number: i64,
+    /// The message that was stored as attestation.
+    pub stored: SignedValue,
+    /// The message that was rejected.
+    pub conflicting: SignedValue,
+    /// Unix timestamp (in milliseconds) at which the conflicting message was received.
+    pub detected_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EquivocationsQuery {
+    pub from_slot: Option<i64>,
+    /// Inclusive.
+    pub to_slot: Option<i64>,
+    /// Defaults to `DEFAULT_LIST_LIMIT`, capped at `MAX_LIST_LIMIT`.
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// The recorded equivocations, oldest first.
+pub async fn get_equivocations(
+    Query(query): Query<EquivocationsQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<Equivocation>>, StatusCode> {
+    let equivocations = sqlx::query!(
+        r#"
+        SELECT
+            validator_public_key,
+            asset,
+            slot_number,
+            value,
+            signature,
+            conflicting_value,
+            conflicting_signature,
+            (EXTRACT(EPOCH FROM detected_at) * 1000)::BIGINT AS "detected_at!"
+        FROM
+            equivocations
+        WHERE
+            ($1::BIGINT IS NULL OR slot_number >= $1)
+        AND
+            ($2::BIGINT IS NULL OR slot_number <= $2)
+        ORDER BY
+            detected_at,
+            validator_public_key
+        LIMIT $3
+        OFFSET $4;
+        "#,
+        query.from_slot,
+        query.to_slot,
+        get_list_limit(query.limit),
+        query.offset.unwrap_or(0),
+    )
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error getting equivocations: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .into_iter()
+    .map(|row| Equivocation {
+        validator_public_key: row.validator_public_key,
+        asset: row.asset,
+        slot_number: row.slot_number,
+        stored: SignedValue {
+            value: row.value,
+            signature: row.signature,
+        },
+        conflicting: SignedValue {
+            value: row.conflicting_value,
+            signature: row.conflicting_signature,
+        },
+        detected_at: row.detected_at,
+    })
+    .collect();
+    Ok(Json(equivocations))
+}
+
+/// Two messages of a validator that cannot both be honest.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Conflict {
+    /// Two value messages with different values for the same slot.
+    ConflictingValues {
+        asset: String,
+        slot_number: i64,
+        first: SignedValue,
+        second: SignedValue,
+    },
+    /// A value message outside all of the validator's interval messages for the slot.
+    ContradictingIntervals {
+        asset: String,
+        slot_number: i64,
+        value_message: SignedValue,
+        interval_messages: Vec<SignedInterval>,
+    },
+}
+
+impl Conflict {
+    fn slot_number(&self) -> i64 {
+        match self {
+            Conflict::ConflictingValues { slot_number, .. }
+            | Conflict::ContradictingIntervals { slot_number, .. } => *slot_number,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SlashableValidator {
+    pub validator_public_key: String,
+    /// Ordered by slot.
+    pub conflicts: Vec<Conflict>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SlashingReportQuery {
+    pub from_slot: i64,
+    /// Inclusive.
+    pub to_slot: i64,
+}
+
+async fn get_conflicting_values(
+    db_pool: &DbPool,
+    from_slot: i64,
+    to_slot: i64,
+) -> sqlx::Result<Vec<(String, Conflict)>> {
+    let conflicts = sqlx::query!(
+        "
+        SELECT
+            validator_public_key,
+            asset,
+            slot_number,
+            value,
+            signature,
+            conflicting_value,
+            conflicting_signature
+        FROM
+            equivocations
+        WHERE
+            slot_number BETWEEN $1 AND $2
+        ORDER BY
+            detected_at;
+        ",
+        from_slot,
+        to_slot,
+    )
+    .fetch_all(db_pool)
+    .await?
+    .into_iter()
+    .map(|row| {
+        let conflict = Conflict::ConflictingValues {
+            asset: row.asset,
+            slot_number: row.slot_number,
+            first: SignedValue {
+                value: row.value,
+                signature: row.signature,
+            },
+            second: SignedValue {
+                value: row.conflicting_value,
+                signature: row.conflicting_signature,
+            },
+        };
+        (row.validator_public_key, conflict)
+    })
+    .collect();
+    Ok(conflicts)
+}
+
+async fn get_contradicting_intervals(
+    db_pool: &DbPool,
+    from_slot: i64,
+    to_slot: i64,
+) -> sqlx::Result<Vec<(String, Conflict)>> {
+    let rows = sqlx::query!(
+        "
+        SELECT
+            value_attestation.validator_public_key,
+            value_attestation.asset,
+            value_attestation.slot_number,
+            value_attestation.value,
+            value_attestation.signature,
+            interval_attestation.value AS interval_value,
+            interval_attestation.interval_size,
+            interval_attestation.signature AS interval_signature
+        FROM
+            price_value_attestations value_attestation
+        JOIN
+            price_interval_attestations interval_attestation
+        ON
+            interval_attestation.validator_public_key = value_attestation.validator_public_key
+        AND
+            interval_attestation.asset = value_attestation.asset
+        AND
+            interval_attestation.slot_number = value_attestation.slot_number
+        WHERE
+            value_attestation.slot_number BETWEEN $1 AND $2
+        ORDER BY
+            value_attestation.validator_public_key,
+            value_attestation.asset,
+            value_attestation.slot_number,
+            interval_attestation.interval_size,
+            interval_attestation.value;
+        ",
+        from_slot,
+        to_slot,
+    )
+    .fetch_all(db_pool)
+    .await?;
+
+    let conflicts = rows
+        .into_iter()
+        .group_by(|row| {
+            (
+                row.validator_public_key.clone(),
+                row.asset.clone(),
+                row.slot_number,
+                row.value,
+                row.signature.clone(),
+            )
+        })
+        .into_iter()
+        .filter_map(
+            |((validator_public_key, asset, slot_number, value, signature), rows)| {
+                let interval_messages: Vec<SignedInterval> = rows
+                    .map(|row| SignedInterval {
+                        value: row.interval_value,
+                        interval_size: row.interval_size,
+                        signature: row.interval_signature,
+                    })
+                    .collect();
+                let is_consistent = interval_messages.iter().any(|interval_message| {
+                    interval_message_contains(
+                        interval_message.value,
+                        interval_message.interval_size,
+                        value,
+                    )
+                });
+                (!is_consistent).then_some((
+                    validator_public_key,
+                    Conflict::ContradictingIntervals {
+                        asset,
+                        slot_number,
+                        value_message: SignedValue { value, signature },
+                        interval_messages,
+                    },
+                ))
+            },
+        )
+        .collect();
+    Ok(conflicts)
+}
+
+/// Per validator, the pairs of signed messages in the slot range that contradict each other:
+/// equivocating value messages, and value messages outside all of the validator's own interval
+/// messages.
+pub async fn get_slashing_report(
+    Query(query): Query<SlashingReportQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<SlashableValidator>>, (StatusCode, String)> {
+    let num_slots = query.to_slot - query.from_slot + 1;
+    if !(1..=MAX_SLASHING_REPORT_SLOT_RANGE).contains(&num_slots) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("slot range must span 1 to {MAX_SLASHING_REPORT_SLOT_RANGE} slots"),
+        ));
+    }
+    let internal_error = |e: sqlx::Error| {
+        tracing::error!("Error getting conflicting attestations: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "error getting conflicting attestations".to_string(),
+        )
+    };
+    let conflicting_values = get_conflicting_values(&state.db_pool, query.from_slot, query.to_slot)
+        .await
+        .map_err(internal_error)?;
+    let contradicting_intervals =
+        get_contradicting_intervals(&state.db_pool, query.from_slot, query.to_slot)
+            .await
+            .map_err(internal_error)?;
+
+    let mut conflicts_by_validator: BTreeMap<String, Vec<Conflict>> = BTreeMap::new();
+    for (validator_public_key, conflict) in conflicting_values
+        .into_iter()
+        .chain(contradicting_intervals)
+    {
+        conflicts_by_validator
+            .entry(validator_public_key)
+            .or_default()
+            .push(conflict);
+    }
+    let report = conflicts_by_validator
+        .into_iter()
+        .map(|(validator_public_key, mut conflicts)| {
+            conflicts.sort_by_key(Conflict::slot_number);
+            SlashableValidator {
+                validator_public_key,
+                conflicts,
+            }
+        })
+        .collect();
+    Ok(Json(report))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::attestations::{PriceValueEntry, DEFAULT_ASSET};
+    use crate::test_utils::{
+        get_test_message, sign_oracle_message_with_new_key, value_message, TestApp,
+        TEST_MESSAGE_SLOT,
+    };
+    use bls::SecretKey;
+
+    #[sqlx::test]
+    async fn lists_recorded_equivocations(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let private_key = SecretKey::random();
+        let first = value_message(&private_key, TEST_MESSAGE_SLOT, 1811093163);
+        test_app.post_oracle_message(&first, StatusCode::OK).await;
+        let second = value_message(&private_key, TEST_MESSAGE_SLOT, 1811093164);
+        test_app
+            .post_oracle_message(&second, StatusCode::CONFLICT)
+            .await;
+        // Resubmitting the stored message is no equivocation
+        test_app
+            .post_oracle_message(&first, StatusCode::CONFLICT)
+            .await;
+
+        let response = test_app.get_expect("/equivocations", StatusCode::OK).await;
+        let equivocations: Vec<Equivocation> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(equivocations.len(), 1);
+        let equivocation = &equivocations[0];
+        assert_eq!(
+            equivocation.validator_public_key,
+            private_key.public_key().to_string()
+        );
+        assert_eq!(equivocation.slot_number, TEST_MESSAGE_SLOT as i64);
+        assert_eq!(
+            equivocation.stored,
+            SignedValue {
+                value: 1811093163,
+                signature: first.value_message.signature.to_string(),
+            }
+        );
+        assert_eq!(
+            equivocation.conflicting,
+            SignedValue {
+                value: 1811093164,
+                signature: second.value_message.signature.to_string(),
+            }
+        );
+
+        // Only the first message made it into the attestations
+        let response = test_app
+            .get_expect("/price_value_attestations", StatusCode::OK)
+            .await;
+        let entries: Vec<PriceValueEntry> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].value, 1811093163);
+
+        let response = test_app
+            .get_expect(
+                &format!("/equivocations?from_slot={}", TEST_MESSAGE_SLOT + 1),
+                StatusCode::OK,
+            )
+            .await;
+        let equivocations: Vec<Equivocation> = serde_json::from_slice(&response).unwrap();
+        assert!(equivocations.is_empty());
+    }
+
+    #[sqlx::test]
+    async fn reports_both_conflicting_messages(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let equivocator = SecretKey::random();
+        let first = value_message(&equivocator, TEST_MESSAGE_SLOT, 1811093163);
+        test_app.post_oracle_message(&first, StatusCode::OK).await;
+        let second = value_message(&equivocator, TEST_MESSAGE_SLOT, 1811093164);
+        test_app
+            .post_oracle_message(&second, StatusCode::CONFLICT)
+            .await;
+
+        // A value message outside the only interval message of the validator
+        let mut message = get_test_message();
+        message.interval_inclusion_messages.truncate(1);
+        let contradictor = SecretKey::random();
+        let contradicting = sign_oracle_message_with_new_key(message, &contradictor);
+        test_app
+            .post_oracle_message(&contradicting, StatusCode::OK)
+            .await;
+        // An honest validator is not reported
+        test_app
+            .post_oracle_message(&get_test_message(), StatusCode::OK)
+            .await;
+
+        let response = test_app
+            .get_expect(
+                &format!(
+                    "/slashing-report?from_slot={TEST_MESSAGE_SLOT}&to_slot={TEST_MESSAGE_SLOT}"
+                ),
+                StatusCode::OK,
+            )
+            .await;
+        let report: Vec<SlashableValidator> = serde_json::from_slice(&response).unwrap();
+        assert_eq!(report.len(), 2);
+
+        let equivocation = report
+            .iter()
+            .find(|validator| {
+                validator.validator_public_key == equivocator.public_key().to_string()
+            })
+            .unwrap();
+        assert_eq!(
+            equivocation.conflicts,
+            [Conflict::ConflictingValues {
+                asset: DEFAULT_ASSET.to_string(),
+                slot_number: TEST_MESSAGE_SLOT as i64,
+                first: SignedValue {
+                    value: 1811093163,
+                    signature: first.value_message.signature.to_string(),
+                },
+                second: SignedValue {
+                    value: 1811093164,
+                    signature: second.value_message.signature.to_string(),
+                },
+            }]
+        );
+
+        let contradiction = report
+            .iter()
+            .find(|validator| {
+                validator.validator_public_key == contradictor.public_key().to_string()
+            })
+            .unwrap();
+        let interval_message = &contradicting.interval_inclusion_messages[0];
+        assert_eq!(
+            contradiction.conflicts,
+            [Conflict::ContradictingIntervals {
+                asset: DEFAULT_ASSET.to_string(),
+                slot_number: TEST_MESSAGE_SLOT as i64,
+                value_message: SignedValue {
+                    value: contradicting.value_message.message.price.value as i64,
+                    signature: contradicting.value_message.signature.to_string(),
+                },
+                interval_messages: vec![SignedInterval {
+                    value: interval_message.message.value as i64,
+                    interval_size: interval_message.message.interval_size as i64,
+                    signature: interval_message.signature.to_string(),
+                }],
+            }]
+        );
+
+        test_app
+            .get_expect(
+                "/slashing-report?from_slot=0&to_slot=100000",
+                StatusCode::BAD_REQUEST,
+            )
+            .await;
+    }
+}
@@ -0,0 +1,101 @@
+//! Translates wall clock time into beacon chain slots.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub struct SlotClock {
+    /// Adjustable at runtime in case the configured genesis time turns out to be wrong.
+    genesis_time: AtomicU64,
+    seconds_per_slot: u64,
+    /// Fixed unix timestamp to use instead of the system time, used to test slot dependent logic.
+    frozen_time: Option<u64>,
+}
+
+impl SlotClock {
+    pub fn new(genesis_time: u64, seconds_per_slot: u64) -> Self {
+        SlotClock {
+            genesis_time: AtomicU64::new(genesis_time),
+            seconds_per_slot,
+            frozen_time: None,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn frozen(genesis_time: u64, seconds_per_slot: u64, now: u64) -> Self {
+        SlotClock {
+            genesis_time: AtomicU64::new(genesis_time),
+            seconds_per_slot,
+            frozen_time: Some(now),
+        }
+    }
+
+    fn now(&self) -> u64 {
+        self.frozen_time.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("expect system time to be after the unix epoch")
+                .as_secs()
+        })
+    }
+
+    pub fn genesis_time(&self) -> u64 {
+        self.genesis_time.load(Ordering::Relaxed)
+    }
+
+    pub fn set_genesis_time(&self, genesis_time: u64) {
+        self.genesis_time.store(genesis_time, Ordering::Relaxed);
+    }
+
+    pub fn seconds_per_slot(&self) -> u64 {
+        self.seconds_per_slot
+    }
+
+    pub fn current_slot(&self) -> u64 {
+        self.now().saturating_sub(self.genesis_time()) / self.seconds_per_slot
+    }
+
+    /// Unix timestamp at which the given slot starts, saturating for slots too far out to
+    /// represent.
+    pub fn slot_start_time(&self, slot_number: u64) -> u64 {
+        slot_to_timestamp(slot_number, self.genesis_time(), self.seconds_per_slot)
+            .unwrap_or(u64::MAX)
+    }
+
+    /// Whether the given slot lies at most `window_slots` before or after the current slot.
+    pub fn is_within_window(&self, slot_number: u64, window_slots: u64) -> bool {
+        self.current_slot().abs_diff(slot_number) <= window_slots
+    }
+}
+
+/// Unix timestamp (in seconds) at which the given slot starts, `None` if it doesn't fit a `u64`.
+pub fn slot_to_timestamp(
+    slot_number: u64,
+    genesis_time: u64,
+    seconds_per_slot: u64,
+) -> Option<u64> {
+    slot_number
+        .checked_mul(seconds_per_slot)
+        .and_then(|offset| offset.checked_add(genesis_time))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn converts_slots_to_timestamps() {
+        let genesis_time = 1606824023;
+        assert_eq!(slot_to_timestamp(0, genesis_time, 12), Some(genesis_time));
+        assert_eq!(slot_to_timestamp(1, genesis_time, 12), Some(1606824035));
+        assert_eq!(
+            slot_to_timestamp(6556020, genesis_time, 12),
+            Some(1685496263)
+        );
+        assert_eq!(
+            slot_to_timestamp(u64::MAX / 12, 0, 12),
+            Some(u64::MAX / 12 * 12)
+        );
+        assert_eq!(slot_to_timestamp(u64::MAX / 12, genesis_time, 12), None);
+        assert_eq!(slot_to_timestamp(u64::MAX, genesis_time, 12), None);
+    }
+}
@@ -0,0 +1,496 @@
+use crate::canonical::encode_public_key;
+use crate::db::DbPool;
+use crate::slot_clock::SlotClock;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use bls::PublicKey;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CurrentSlot {
+    pub slot_number: u64,
+    pub genesis_time: u64,
+    pub seconds_per_slot: u64,
+}
+
+impl CurrentSlot {
+    pub fn from_slot_clock(slot_clock: &SlotClock) -> Self {
+        CurrentSlot {
+            slot_number: slot_clock.current_slot(),
+            genesis_time: slot_clock.genesis_time(),
+            seconds_per_slot: slot_clock.seconds_per_slot(),
+        }
+    }
+}
+
+pub async fn get_slot(State(state): State<Arc<AppState>>) -> Json<CurrentSlot> {
+    Json(CurrentSlot::from_slot_clock(&state.slot_clock))
+}
+
+/// How far back attestations are available, for clients syncing history.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Retention {
+    /// Oldest slot with a value attestation, null if there are none.
+    pub oldest_available_slot: Option<i64>,
+    /// Null if attestations are retained indefinitely.
+    pub retention_slots: Option<u64>,
+    pub current_slot: u64,
+}
+
+pub async fn get_retention(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Retention>, StatusCode> {
+    let oldest_available_slot = sqlx::query!(
+        "
+        SELECT
+            MIN(slot_number) AS oldest_slot
+        FROM
+            price_value_attestations;
+        "
+    )
+    .fetch_one(&state.db_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error getting oldest available slot: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .oldest_slot;
+    Ok(Json(Retention {
+        oldest_available_slot,
+        retention_slots: state.config.retention_slots,
+        current_slot: state.slot_clock.current_slot(),
+    }))
+}
+
+/// Participation of the allowlisted validators in a slot.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SlotCompleteness {
+    pub slot_number: i64,
+    pub num_expected: usize,
+    pub num_submitted: usize,
+    /// Fraction of allowlisted validators that submitted a value attestation.
+    pub completeness: f64,
+    pub missing_validator_public_keys: Vec<PublicKey>,
+}
+
+/// Responds with 404 if there is no validator allowlist to measure completeness against.
+pub async fn get_slot_completeness(
+    Path(slot_number): Path<i64>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<SlotCompleteness>, StatusCode> {
+    let allowlist = &state.config.validator_allowlist;
+    if allowlist.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let submitters = get_submitters(&state.db_pool, slot_number)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting submitters: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let missing_validator_public_keys: Vec<PublicKey> = allowlist
+        .iter()
+        .filter(|public_key| !submitters.contains(&encode_public_key(public_key)))
+        .cloned()
+        .collect();
+    let num_submitted = allowlist.len() - missing_validator_public_keys.len();
+    Ok(Json(SlotCompleteness {
+        slot_number,
+        num_expected: allowlist.len(),
+        num_submitted,
+        completeness: num_submitted as f64 / allowlist.len() as f64,
+        missing_validator_public_keys,
+    }))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ParticipationQuery {
+    pub slot: i64,
+}
+
+/// Who attested a slot, and which allowlisted validators didn't.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Participation {
+    pub slot_number: i64,
+    /// Every validator with a value attestation for the slot, allowlisted or not.
+    pub participating_validator_public_keys: Vec<String>,
+    pub missing_validator_public_keys: Vec<PublicKey>,
+    /// Fraction of allowlisted validators that participated, null without an allowlist.
+    pub participation_ratio: Option<f64>,
+}
+
+pub async fn get_participation(
+    Query(query): Query<ParticipationQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Participation>, StatusCode> {
+    let submitters = get_submitters(&state.db_pool, query.slot)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting submitters: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let allowlist = &state.config.validator_allowlist;
+    let missing_validator_public_keys: Vec<PublicKey> = allowlist
+        .iter()
+        .filter(|public_key| !submitters.contains(&encode_public_key(public_key)))
+        .cloned()
+        .collect();
+    let participation_ratio = (!allowlist.is_empty()).then(|| {
+        let num_participating = allowlist.len() - missing_validator_public_keys.len();
+        num_participating as f64 / allowlist.len() as f64
+    });
+    let mut participating_validator_public_keys: Vec<String> = submitters.into_iter().collect();
+    participating_validator_public_keys.sort();
+    Ok(Json(Participation {
+        slot_number: query.slot,
+        participating_validator_public_keys,
+        missing_validator_public_keys,
+        participation_ratio,
+    }))
+}
+
+async fn get_submitters(db_pool: &DbPool, slot_number: i64) -> eyre::Result<HashSet<String>> {
+    let submitters = sqlx::query!(
+        "
+        SELECT DISTINCT
+            validator_public_key
+        FROM
+            price_value_attestations
+        WHERE
+            slot_number = $1;
+        ",
+        slot_number
+    )
+    .fetch_all(db_pool)
+    .await?
+    .into_iter()
+    .map(|row| row.validator_public_key)
+    .collect();
+    Ok(submitters)
+}
+
+/// Distribution of the arrival times of a slot's value attestations, in milliseconds after the
+/// start of the slot. Interval attestations arrive in the same messages and aren't counted
+/// separately.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SlotTiming {
+    pub slot_number: u64,
+    pub num_attestations: usize,
+    pub min_offset_ms: i64,
+    pub p50_offset_ms: i64,
+    pub p90_offset_ms: i64,
+    pub max_offset_ms: i64,
+}
+
+pub async fn get_slot_timing(
+    Path(slot_number): Path<u64>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<SlotTiming>, StatusCode> {
+    let received_at_millis = get_received_at_millis(&state.db_pool, slot_number as i64)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting arrival times: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if received_at_millis.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let slot_start_millis = state.slot_clock.slot_start_time(slot_number) as i64 * 1000;
+    let offsets: Vec<i64> = received_at_millis
+        .iter()
+        .map(|received_at| received_at - slot_start_millis)
+        .collect();
+    Ok(Json(SlotTiming {
+        slot_number,
+        num_attestations: offsets.len(),
+        min_offset_ms: offsets[0],
+        p50_offset_ms: percentile(&offsets, 50),
+        p90_offset_ms: percentile(&offsets, 90),
+        max_offset_ms: offsets[offsets.len() - 1],
+    }))
+}
+
+/// Nearest-rank percentile of non-empty ascending values.
+fn percentile(values: &[i64], percent: usize) -> i64 {
+    let rank = (values.len() * percent).div_ceil(100);
+    values[rank.saturating_sub(1)]
+}
+
+/// Arrival times of the slot's value attestations as unix milliseconds, in ascending order.
+async fn get_received_at_millis(db_pool: &DbPool, slot_number: i64) -> eyre::Result<Vec<i64>> {
+    let received_at_millis = sqlx::query!(
+        r#"
+        SELECT
+            (EXTRACT(EPOCH FROM received_at) * 1000)::BIGINT AS "received_at!"
+        FROM
+            price_value_attestations
+        WHERE
+            slot_number = $1
+        ORDER BY
+            received_at;
+        "#,
+        slot_number
+    )
+    .fetch_all(db_pool)
+    .await?
+    .into_iter()
+    .map(|row| row.received_at)
+    .collect();
+    Ok(received_at_millis)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_utils::{
+        value_message, value_message_from_new_validator, TestApp, TEST_MESSAGE_SLOT,
+    };
+    use bls::SecretKey;
+
+    #[sqlx::test]
+    async fn reports_oldest_slot_within_retention(db_pool: DbPool) {
+        let config = Config {
+            retention_slots: Some(10),
+            ..Config::default()
+        };
+        let retention_boundary = TEST_MESSAGE_SLOT - 10;
+        for slot_number in [TEST_MESSAGE_SLOT - 100, retention_boundary] {
+            let test_app =
+                TestApp::with_config_at_slot(db_pool.clone(), config.clone(), slot_number).await;
+            test_app
+                .post_oracle_message(
+                    &value_message(&SecretKey::random(), slot_number, 1811093163),
+                    StatusCode::OK,
+                )
+                .await;
+        }
+        let test_app = TestApp::with_config(db_pool.clone(), config).await;
+        test_app
+            .post_oracle_message(
+                &value_message_from_new_validator(1811093163),
+                StatusCode::OK,
+            )
+            .await;
+
+        // Prune everything before the retention boundary
+        sqlx::query("DELETE FROM price_value_attestations WHERE slot_number < $1;")
+            .bind(retention_boundary as i64)
+            .execute(&db_pool)
+            .await
+            .unwrap();
+
+        let response = test_app.get_expect("/retention", StatusCode::OK).await;
+        let retention: Retention = serde_json::from_slice(&response).unwrap();
+        assert_eq!(
+            retention.oldest_available_slot,
+            Some(retention_boundary as i64)
+        );
+        assert_eq!(retention.retention_slots, Some(10));
+        assert_eq!(retention.current_slot, TEST_MESSAGE_SLOT);
+    }
+
+    #[sqlx::test]
+    async fn computes_completeness_of_partial_participation(db_pool: DbPool) {
+        let private_keys = [
+            SecretKey::random(),
+            SecretKey::random(),
+            SecretKey::random(),
+        ];
+        let config = Config {
+            validator_allowlist: private_keys.iter().map(|key| key.public_key()).collect(),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        for private_key in &private_keys[..2] {
+            test_app
+                .post_oracle_message(
+                    &value_message(private_key, TEST_MESSAGE_SLOT, 1811093163),
+                    StatusCode::OK,
+                )
+                .await;
+        }
+        // Validators outside of the allowlist don't count
+        test_app
+            .post_oracle_message(
+                &value_message_from_new_validator(1811093163),
+                StatusCode::OK,
+            )
+            .await;
+
+        let response = test_app
+            .get_expect(
+                &format!("/slot/{TEST_MESSAGE_SLOT}/completeness"),
+                StatusCode::OK,
+            )
+            .await;
+        let completeness: SlotCompleteness = serde_json::from_slice(&response).unwrap();
+        assert_eq!(completeness.num_expected, 3);
+        assert_eq!(completeness.num_submitted, 2);
+        assert!((completeness.completeness - 2.0 / 3.0).abs() < f64::EPSILON);
+        assert_eq!(
+            completeness.missing_validator_public_keys,
+            vec![private_keys[2].public_key()]
+        );
+
+        let response = test_app
+            .get_expect(
+                &format!("/slot/{}/completeness", TEST_MESSAGE_SLOT + 1),
+                StatusCode::OK,
+            )
+            .await;
+        let completeness: SlotCompleteness = serde_json::from_slice(&response).unwrap();
+        assert_eq!(completeness.num_submitted, 0);
+        assert_eq!(completeness.missing_validator_public_keys.len(), 3);
+    }
+
+    async fn get_participation_of(test_app: &TestApp, slot_number: u64) -> Participation {
+        let response = test_app
+            .get_expect(
+                &format!("/participation?slot={slot_number}"),
+                StatusCode::OK,
+            )
+            .await;
+        serde_json::from_slice(&response).unwrap()
+    }
+
+    fn allowlist_config(private_keys: &[SecretKey]) -> Config {
+        Config {
+            validator_allowlist: private_keys.iter().map(|key| key.public_key()).collect(),
+            ..Config::default()
+        }
+    }
+
+    #[sqlx::test]
+    async fn reports_full_participation(db_pool: DbPool) {
+        let private_keys = [SecretKey::random(), SecretKey::random()];
+        let test_app = TestApp::with_config(db_pool, allowlist_config(&private_keys)).await;
+        for private_key in &private_keys {
+            test_app
+                .post_oracle_message(
+                    &value_message(private_key, TEST_MESSAGE_SLOT, 1811093163),
+                    StatusCode::OK,
+                )
+                .await;
+        }
+
+        let participation = get_participation_of(&test_app, TEST_MESSAGE_SLOT).await;
+        let mut expected: Vec<String> = private_keys
+            .iter()
+            .map(|key| key.public_key().to_string())
+            .collect();
+        expected.sort();
+        assert_eq!(participation.participating_validator_public_keys, expected);
+        assert!(participation.missing_validator_public_keys.is_empty());
+        assert_eq!(participation.participation_ratio, Some(1.0));
+    }
+
+    #[sqlx::test]
+    async fn reports_partial_participation(db_pool: DbPool) {
+        let private_keys = [
+            SecretKey::random(),
+            SecretKey::random(),
+            SecretKey::random(),
+            SecretKey::random(),
+        ];
+        let test_app = TestApp::with_config(db_pool, allowlist_config(&private_keys)).await;
+        test_app
+            .post_oracle_message(
+                &value_message(&private_keys[0], TEST_MESSAGE_SLOT, 1811093163),
+                StatusCode::OK,
+            )
+            .await;
+        // Listed as participating, but not counted towards the ratio
+        let outsider = SecretKey::random();
+        test_app
+            .post_oracle_message(
+                &value_message(&outsider, TEST_MESSAGE_SLOT, 1811093163),
+                StatusCode::OK,
+            )
+            .await;
+
+        let participation = get_participation_of(&test_app, TEST_MESSAGE_SLOT).await;
+        assert_eq!(participation.participating_validator_public_keys.len(), 2);
+        assert!(participation
+            .participating_validator_public_keys
+            .contains(&outsider.public_key().to_string()));
+        assert_eq!(
+            participation.missing_validator_public_keys,
+            private_keys[1..]
+                .iter()
+                .map(|key| key.public_key())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(participation.participation_ratio, Some(0.25));
+    }
+
+    #[sqlx::test]
+    async fn reports_no_participation_in_unknown_slot(db_pool: DbPool) {
+        let private_keys = [SecretKey::random(), SecretKey::random()];
+        let test_app = TestApp::with_config(db_pool.clone(), allowlist_config(&private_keys)).await;
+        let participation = get_participation_of(&test_app, TEST_MESSAGE_SLOT + 1).await;
+        assert_eq!(participation.slot_number, TEST_MESSAGE_SLOT as i64 + 1);
+        assert!(participation.participating_validator_public_keys.is_empty());
+        assert_eq!(participation.missing_validator_public_keys.len(), 2);
+        assert_eq!(participation.participation_ratio, Some(0.0));
+
+        // Without an allowlist nothing is expected
+        let test_app = TestApp::new(db_pool).await;
+        let participation = get_participation_of(&test_app, TEST_MESSAGE_SLOT + 1).await;
+        assert!(participation.missing_validator_public_keys.is_empty());
+        assert_eq!(participation.participation_ratio, None);
+    }
+
+    #[sqlx::test]
+    async fn computes_arrival_time_percentiles(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool.clone()).await;
+        let config = Config::default();
+        let slot_start_millis =
+            (config.genesis_time + TEST_MESSAGE_SLOT * config.seconds_per_slot) as i64 * 1000;
+        // Arrives 100ms, 200ms, ..., 1000ms into the slot
+        for offset_ms in (1..=10).rev().map(|i| i * 100) {
+            let message = value_message_from_new_validator(1811093163);
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+            sqlx::query(
+                "
+                UPDATE price_value_attestations
+                SET
+                    received_at = to_timestamp($1 / 1000.0)
+                WHERE
+                    validator_public_key = $2;
+                ",
+            )
+            .bind(slot_start_millis + offset_ms)
+            .bind(message.validator_public_key.to_string())
+            .execute(&db_pool)
+            .await
+            .unwrap();
+        }
+
+        let response = test_app
+            .get_expect(&format!("/timing/{TEST_MESSAGE_SLOT}"), StatusCode::OK)
+            .await;
+        let timing: SlotTiming = serde_json::from_slice(&response).unwrap();
+        assert_eq!(timing.num_attestations, 10);
+        assert_eq!(timing.min_offset_ms, 100);
+        assert_eq!(timing.p50_offset_ms, 500);
+        assert_eq!(timing.p90_offset_ms, 900);
+        assert_eq!(timing.max_offset_ms, 1000);
+
+        test_app
+            .get_expect(
+                &format!("/timing/{}", TEST_MESSAGE_SLOT + 1),
+                StatusCode::NOT_FOUND,
+            )
+            .await;
+    }
+}
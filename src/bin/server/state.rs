@@ -1,5 +1,103 @@
+use crate::attestations::PriceValueEntry;
+use crate::bloom::RecentBloomFilter;
+use crate::bundle::parse_bundle_signing_key;
+use crate::config::Config;
 use crate::db::DbPool;
+use crate::lru_cache::LruCache;
+use crate::metrics::Metrics;
+use crate::rate_limit::RateLimiter;
+use crate::slot_clock::SlotClock;
+use crate::stream::new_value_attestation_channel;
+use bls::{Hash256, PublicKey, SecretKey};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, AtomicUsize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+/// Asset, slot and interval size of aggregates competing for the same price.
+pub type AggregateGroup = (String, i64, i64);
+
+/// Digest, signature and public key of a signature verification.
+pub type SignatureCacheKey = (Hash256, [u8; 96], PublicKey);
 
 pub struct AppState {
     pub db_pool: DbPool,
+    pub config: Config,
+    pub slot_clock: SlotClock,
+    /// Nonces handed out to admins to sign their next request with, and when they were issued.
+    pub admin_challenges: Mutex<HashMap<Vec<u8>, Instant>>,
+    /// Most recent value attestation of each validator, filled as values are stored or looked up.
+    pub latest_values: Mutex<HashMap<PublicKey, PriceValueEntry>>,
+    /// Recently stored value attestations, if enabled, to skip the duplicate check in the DB for
+    /// those that are definitely new.
+    pub seen_values: Option<Mutex<RecentBloomFilter>>,
+    /// Number of times the DB was asked for an earlier value attestation.
+    pub num_dedup_lookups: AtomicU64,
+    /// Results of recent signature verifications, if enabled.
+    pub signature_cache: Option<Mutex<LruCache<SignatureCacheKey, bool>>>,
+    /// Number of signatures verified, not counting those served from the cache.
+    pub num_signature_verifications: AtomicU64,
+    /// Submission budgets of the validators, if rate limited.
+    pub rate_limiter: Option<Mutex<RateLimiter<PublicKey>>>,
+    /// Recorded validator set sizes and the slot from which on they were in effect, ascending.
+    pub validator_set_sizes: Mutex<Vec<(i64, i64)>>,
+    /// Validators allowed to submit, if a registry file is configured.
+    pub registered_validators: Mutex<Option<HashSet<PublicKey>>>,
+    /// Key slot bundles are signed with, if configured.
+    pub bundle_signing_key: Option<SecretKey>,
+    /// Quorum-reaching value with the most validators and its number of validators, by asset,
+    /// slot and interval size, for the recent slots. Only tracked when detecting value flips.
+    pub quorum_values: Mutex<HashMap<AggregateGroup, (i64, i64)>>,
+    /// Number of times the quorum-reaching value of a slot and interval size changed.
+    pub num_value_flips: AtomicU64,
+    /// Newly accepted value attestations, for the attestation stream.
+    pub value_attestations: broadcast::Sender<PriceValueEntry>,
+    /// Number of clients currently subscribed to the attestation stream.
+    pub num_stream_connections: AtomicUsize,
+    pub metrics: Metrics,
+}
+
+impl AppState {
+    pub fn new(db_pool: DbPool, config: Config) -> Self {
+        let slot_clock = SlotClock::new(config.genesis_time, config.seconds_per_slot);
+        let seen_values = config
+            .dedup_bloom_capacity
+            .map(|capacity| Mutex::new(RecentBloomFilter::new(capacity)));
+        let signature_cache = config
+            .signature_cache_capacity
+            .map(|capacity| Mutex::new(LruCache::new(capacity)));
+        let rate_limiter = config
+            .rate_limit_messages_per_slot
+            .map(|messages_per_slot| {
+                Mutex::new(RateLimiter::new(
+                    messages_per_slot,
+                    Duration::from_secs(config.seconds_per_slot),
+                ))
+            });
+        let bundle_signing_key = config
+            .bundle_signing_key
+            .as_deref()
+            .map(parse_bundle_signing_key);
+        AppState {
+            db_pool,
+            config,
+            slot_clock,
+            admin_challenges: Mutex::new(HashMap::new()),
+            latest_values: Mutex::new(HashMap::new()),
+            seen_values,
+            num_dedup_lookups: AtomicU64::new(0),
+            signature_cache,
+            num_signature_verifications: AtomicU64::new(0),
+            rate_limiter,
+            validator_set_sizes: Mutex::new(Vec::new()),
+            registered_validators: Mutex::new(None),
+            bundle_signing_key,
+            quorum_values: Mutex::new(HashMap::new()),
+            num_value_flips: AtomicU64::new(0),
+            value_attestations: new_value_attestation_channel(),
+            num_stream_connections: AtomicUsize::new(0),
+            metrics: Metrics::default(),
+        }
+    }
 }
@@ -0,0 +1,338 @@
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ValueDiversity {
+    pub slot_number: i64,
+    pub distinct_values: i64,
+    pub num_attestations: i64,
+}
+
+/// A high number of distinct values for a slot signals disagreement between validators.
+pub async fn get_value_diversity(
+    Path(slot_number): Path<i64>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ValueDiversity>, StatusCode> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(DISTINCT value) AS "distinct_values!",
+            COUNT(*) AS "num_attestations!"
+        FROM
+            price_value_attestations
+        WHERE
+            slot_number = $1;
+        "#,
+        slot_number
+    )
+    .fetch_one(&state.db_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error getting value diversity: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(ValueDiversity {
+        slot_number,
+        distinct_values: row.distinct_values,
+        num_attestations: row.num_attestations,
+    }))
+}
+
+const DEFAULT_SIGMA: f64 = 2.0;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OutliersQuery {
+    pub sigma: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Outlier {
+    pub validator_public_key: String,
+    pub value: i64,
+    /// Distance from the mean in standard deviations, negative below the mean.
+    pub deviation: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Outliers {
+    pub slot_number: i64,
+    pub mean: f64,
+    pub standard_deviation: f64,
+    pub sigma: f64,
+    pub outliers: Vec<Outlier>,
+}
+
+/// Validators whose value for the slot is more than `sigma` standard deviations from the mean.
+pub async fn get_outliers(
+    Path(slot_number): Path<i64>,
+    Query(query): Query<OutliersQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Outliers>, StatusCode> {
+    let sigma = query.sigma.unwrap_or(DEFAULT_SIGMA);
+    if !sigma.is_finite() || sigma < 0.0 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let rows = sqlx::query!(
+        "
+        SELECT
+            validator_public_key,
+            value
+        FROM
+            price_value_attestations
+        WHERE
+            slot_number = $1
+        ORDER BY
+            value;
+        ",
+        slot_number
+    )
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error getting value attestations: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if rows.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let num_values = rows.len() as f64;
+    let mean = rows.iter().map(|row| row.value as f64).sum::<f64>() / num_values;
+    let variance = rows
+        .iter()
+        .map(|row| (row.value as f64 - mean).powi(2))
+        .sum::<f64>()
+        / num_values;
+    let standard_deviation = variance.sqrt();
+    // With all values equal there is nothing to deviate from.
+    let outliers = if standard_deviation == 0.0 {
+        Vec::new()
+    } else {
+        rows.into_iter()
+            .map(|row| Outlier {
+                deviation: (row.value as f64 - mean) / standard_deviation,
+                validator_public_key: row.validator_public_key,
+                value: row.value,
+            })
+            .filter(|outlier| outlier.deviation.abs() > sigma)
+            .collect()
+    };
+    Ok(Json(Outliers {
+        slot_number,
+        mean,
+        standard_deviation,
+        sigma,
+        outliers,
+    }))
+}
+
+/// Number of rows in each of the attestation tables.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct TableCounts {
+    pub price_value_attestations: i64,
+    pub price_interval_attestations: i64,
+    pub aggregate_interval_attestations: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Summary {
+    /// Validators that submitted at least one value or interval attestation.
+    pub distinct_validators: i64,
+    /// Earliest slot with an attestation, `None` while there are none.
+    pub min_slot: Option<i64>,
+    /// Latest slot with an attestation, `None` while there are none.
+    pub max_slot: Option<i64>,
+    pub table_counts: TableCounts,
+}
+
+/// Scalar facts about the whole dataset, for monitoring.
+pub async fn get_summary(State(state): State<Arc<AppState>>) -> Result<Json<Summary>, StatusCode> {
+    let row = sqlx::query!(
+        r#"
+        WITH attestations AS (
+            SELECT validator_public_key, slot_number FROM price_value_attestations
+            UNION ALL
+            SELECT validator_public_key, slot_number FROM price_interval_attestations
+        )
+        SELECT
+            (SELECT COUNT(DISTINCT validator_public_key) FROM attestations) AS "distinct_validators!",
+            (SELECT MIN(slot_number) FROM attestations) AS min_slot,
+            (SELECT MAX(slot_number) FROM attestations) AS max_slot,
+            (SELECT COUNT(*) FROM price_value_attestations) AS "price_value_attestations!",
+            (SELECT COUNT(*) FROM price_interval_attestations) AS "price_interval_attestations!",
+            (SELECT COUNT(*) FROM aggregate_interval_attestations) AS "aggregate_interval_attestations!";
+        "#
+    )
+    .fetch_one(&state.db_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error getting summary: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(Summary {
+        distinct_validators: row.distinct_validators,
+        min_slot: row.min_slot,
+        max_slot: row.max_slot,
+        table_counts: TableCounts {
+            price_value_attestations: row.price_value_attestations,
+            price_interval_attestations: row.price_interval_attestations,
+            aggregate_interval_attestations: row.aggregate_interval_attestations,
+        },
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::DbPool;
+    use crate::test_utils::{
+        get_test_message, value_message, value_message_from_new_validator, TestApp,
+        TEST_MESSAGE_SLOT,
+    };
+    use bls::SecretKey;
+
+    #[sqlx::test]
+    async fn counts_clustered_values(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        for value in [1811093163, 1811093163, 1811093163] {
+            test_app
+                .post_oracle_message(&value_message_from_new_validator(value), StatusCode::OK)
+                .await;
+        }
+
+        let response = test_app
+            .get_expect(
+                &format!("/value-diversity/{TEST_MESSAGE_SLOT}"),
+                StatusCode::OK,
+            )
+            .await;
+        let diversity: ValueDiversity = serde_json::from_slice(&response).unwrap();
+        assert_eq!(diversity.distinct_values, 1);
+        assert_eq!(diversity.num_attestations, 3);
+    }
+
+    #[sqlx::test]
+    async fn counts_spread_values(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        for value in [1811093163, 1700000000, 1900000000, 1700000000] {
+            test_app
+                .post_oracle_message(&value_message_from_new_validator(value), StatusCode::OK)
+                .await;
+        }
+
+        let response = test_app
+            .get_expect(
+                &format!("/value-diversity/{TEST_MESSAGE_SLOT}"),
+                StatusCode::OK,
+            )
+            .await;
+        let diversity: ValueDiversity = serde_json::from_slice(&response).unwrap();
+        assert_eq!(diversity.distinct_values, 3);
+        assert_eq!(diversity.num_attestations, 4);
+
+        let response = test_app
+            .get_expect(
+                &format!("/value-diversity/{}", TEST_MESSAGE_SLOT + 1),
+                StatusCode::OK,
+            )
+            .await;
+        let diversity: ValueDiversity = serde_json::from_slice(&response).unwrap();
+        assert_eq!(diversity.distinct_values, 0);
+        assert_eq!(diversity.num_attestations, 0);
+    }
+
+    async fn get_outliers_with_sigma(test_app: &TestApp, sigma: f64) -> Outliers {
+        let response = test_app
+            .get_expect(
+                &format!("/outliers/{TEST_MESSAGE_SLOT}?sigma={sigma}"),
+                StatusCode::OK,
+            )
+            .await;
+        serde_json::from_slice(&response).unwrap()
+    }
+
+    #[sqlx::test]
+    async fn flags_clear_outlier(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        for value in [100, 100, 101, 99, 100, 100] {
+            test_app
+                .post_oracle_message(&value_message_from_new_validator(value), StatusCode::OK)
+                .await;
+        }
+        let outlier_key = SecretKey::random();
+        test_app
+            .post_oracle_message(
+                &value_message(&outlier_key, TEST_MESSAGE_SLOT, 1000),
+                StatusCode::OK,
+            )
+            .await;
+
+        // The outlier is about 2.4 standard deviations above the mean
+        let outliers = get_outliers_with_sigma(&test_app, 2.0).await;
+        assert_eq!(outliers.outliers.len(), 1);
+        assert_eq!(
+            outliers.outliers[0].validator_public_key,
+            outlier_key.public_key().to_string()
+        );
+        assert_eq!(outliers.outliers[0].value, 1000);
+        assert!(outliers.outliers[0].deviation > 2.0);
+        assert!(get_outliers_with_sigma(&test_app, 3.0)
+            .await
+            .outliers
+            .is_empty());
+
+        test_app
+            .get_expect(
+                &format!("/outliers/{TEST_MESSAGE_SLOT}?sigma=-1"),
+                StatusCode::BAD_REQUEST,
+            )
+            .await;
+        test_app
+            .get_expect(
+                &format!("/outliers/{}", TEST_MESSAGE_SLOT + 1),
+                StatusCode::NOT_FOUND,
+            )
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn summarizes_dataset(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let response = test_app.get_expect("/summary", StatusCode::OK).await;
+        let summary: Summary = serde_json::from_slice(&response).unwrap();
+        assert_eq!(summary.distinct_validators, 0);
+        assert_eq!(summary.min_slot, None);
+        assert_eq!(summary.max_slot, None);
+
+        let mut test_message = get_test_message();
+        test_message.interval_inclusion_messages.truncate(3);
+        test_app
+            .post_oracle_message(&test_message, StatusCode::OK)
+            .await;
+        let private_key = SecretKey::random();
+        for slot_number in [TEST_MESSAGE_SLOT - 2, TEST_MESSAGE_SLOT + 1] {
+            let message = value_message(&private_key, slot_number, 1811093163);
+            test_app.post_oracle_message(&message, StatusCode::OK).await;
+        }
+
+        let response = test_app.get_expect("/summary", StatusCode::OK).await;
+        let summary: Summary = serde_json::from_slice(&response).unwrap();
+        assert_eq!(summary.distinct_validators, 2);
+        assert_eq!(summary.min_slot, Some(TEST_MESSAGE_SLOT as i64 - 2));
+        assert_eq!(summary.max_slot, Some(TEST_MESSAGE_SLOT as i64 + 1));
+        assert_eq!(
+            summary.table_counts,
+            TableCounts {
+                price_value_attestations: 3,
+                price_interval_attestations: 3,
+                aggregate_interval_attestations: 3,
+            }
+        );
+    }
+}
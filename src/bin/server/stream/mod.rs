@@ -0,0 +1,141 @@
+//! Live feed of accepted attestations over Server-Sent Events. Every subscriber reads from a
+//! bounded broadcast channel, so a consumer that falls too far behind misses the oldest events
+//! and is told so with a `lagged` event, instead of the server buffering without limit.
+
+use crate::attestations::PriceValueEntry;
+use crate::state::AppState;
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures_util::stream::{self, Stream};
+use std::convert::Infallible;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Number of events kept for subscribers that haven't read them yet.
+pub const STREAM_CAPACITY: usize = 1024;
+
+pub fn new_value_attestation_channel() -> broadcast::Sender<PriceValueEntry> {
+    broadcast::channel(STREAM_CAPACITY).0
+}
+
+/// Counts a subscriber as connected for as long as it lives.
+struct StreamConnection(Arc<AppState>);
+
+impl StreamConnection {
+    /// Connects a subscriber, returning the number of subscribers connected before it.
+    fn connect(state: Arc<AppState>) -> (Self, usize) {
+        let num_connections = state.num_stream_connections.fetch_add(1, Ordering::SeqCst);
+        (StreamConnection(state), num_connections)
+    }
+}
+
+impl Drop for StreamConnection {
+    fn drop(&mut self) {
+        self.0.num_stream_connections.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Streams a `value_attestation` event with each newly accepted value attestation. A subscriber
+/// that falls more than `STREAM_CAPACITY` events behind gets a `lagged` event with the number of
+/// events it missed, and continues with the oldest event still kept. Subscribers beyond the
+/// configured max are rejected with a 503.
+pub async fn get_attestation_stream(
+    State(state): State<Arc<AppState>>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, String)> {
+    let (connection, num_connections) = StreamConnection::connect(state.clone());
+    if let Some(max_connections) = state.config.max_stream_connections {
+        if num_connections >= max_connections {
+            tracing::warn!(
+                "Rejecting attestation stream subscriber, {} are connected already",
+                num_connections
+            );
+            return Err((
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!("attestation stream is limited to {max_connections} subscribers"),
+            ));
+        }
+    }
+    let receiver = state.value_attestations.subscribe();
+    let events = stream::unfold(
+        (receiver, connection),
+        |(mut receiver, connection)| async move {
+            let event = match receiver.recv().await {
+                Ok(entry) => Event::default()
+                    .event("value_attestation")
+                    .json_data(entry)
+                    .expect("expect value attestation to serialize"),
+                Err(RecvError::Lagged(num_missed)) => {
+                    tracing::warn!("Attestation stream subscriber missed {} events", num_missed);
+                    Event::default()
+                        .event("lagged")
+                        .data(num_missed.to_string())
+                }
+                Err(RecvError::Closed) => return None,
+            };
+            Some((Ok(event), (receiver, connection)))
+        },
+    );
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::Config;
+    use crate::db::DbPool;
+    use crate::test_utils::{value_message_from_new_validator, TestApp};
+    use axum::http::StatusCode;
+    use hyper::body::HttpBody;
+    use std::time::Duration;
+
+    #[sqlx::test]
+    async fn streams_accepted_value_attestations(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let response = test_app.get_response("/attestations/stream", &[]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+        let message = value_message_from_new_validator(1811093163);
+        test_app.post_oracle_message(&message, StatusCode::OK).await;
+
+        let mut body = response.into_body();
+        let chunk = tokio::time::timeout(Duration::from_secs(5), body.data())
+            .await
+            .expect("expect event before timeout")
+            .unwrap()
+            .unwrap();
+        let event = String::from_utf8(chunk.to_vec()).unwrap();
+        assert!(event.starts_with("event:value_attestation\n"));
+        assert!(event.contains(&message.validator_public_key.to_string()));
+        assert!(event.contains("\"value\":1811093163"));
+    }
+
+    #[sqlx::test]
+    async fn limits_concurrent_subscribers(db_pool: DbPool) {
+        let config = Config {
+            max_stream_connections: Some(2),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        let first = test_app.get_response("/attestations/stream", &[]).await;
+        let second = test_app.get_response("/attestations/stream", &[]).await;
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(second.status(), StatusCode::OK);
+
+        let response = test_app
+            .get_expect("/attestations/stream", StatusCode::SERVICE_UNAVAILABLE)
+            .await;
+        assert_eq!(
+            String::from_utf8(response.to_vec()).unwrap(),
+            "attestation stream is limited to 2 subscribers"
+        );
+
+        // Disconnecting makes room again
+        drop(first);
+        let third = test_app.get_response("/attestations/stream", &[]).await;
+        assert_eq!(third.status(), StatusCode::OK);
+    }
+}
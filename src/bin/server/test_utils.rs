@@ -0,0 +1,219 @@
+//! Helpers shared by the endpoint tests.
+
+use crate::app::get_router_with_state;
+use crate::attestations::{get_message_digest, DigestConfig, OracleMessage, SignedMessage};
+use crate::config::{AdminToken, Config};
+use crate::db::DbPool;
+use crate::slot_clock::SlotClock;
+use crate::state::AppState;
+use axum::{body::Body, http::Request, response::Response, Router};
+use bls::{SecretKey, Signature};
+use bytes::Bytes;
+use hyper::http::StatusCode;
+use std::sync::{Arc, OnceLock};
+use tower::ServiceExt;
+
+enum TestRequest {
+    Get(),
+    Post(Body),
+    Options(),
+}
+pub struct TestApp {
+    router: Router,
+}
+
+pub const TEST_MESSAGE_SLOT: u64 = 6556020;
+
+/// State with a slot clock frozen at the given slot, for calling into background tasks directly.
+pub fn state_at_slot(db_pool: DbPool, config: Config, slot: u64) -> AppState {
+    let now = config.genesis_time + slot * config.seconds_per_slot;
+    AppState {
+        slot_clock: SlotClock::frozen(config.genesis_time, config.seconds_per_slot, now),
+        ..AppState::new(db_pool, config)
+    }
+}
+
+impl TestApp {
+    pub async fn new(db_pool: DbPool) -> Self {
+        Self::with_config(db_pool, Config::default()).await
+    }
+
+    /// Create an app whose slot clock is frozen at the slot of the test message.
+    pub async fn with_config(db_pool: DbPool, config: Config) -> Self {
+        Self::with_config_at_slot(db_pool, config, TEST_MESSAGE_SLOT).await
+    }
+
+    pub async fn with_config_at_slot(db_pool: DbPool, config: Config, slot: u64) -> Self {
+        let state = state_at_slot(db_pool, config, slot);
+        crate::app::initialize_state(&state).await.unwrap();
+        TestApp {
+            router: get_router_with_state(Arc::new(state)),
+        }
+    }
+
+    pub async fn get_expect(&self, uri: &str, expected_code: StatusCode) -> Bytes {
+        self.send_request(TestRequest::Get(), uri, &[], expected_code)
+            .await
+    }
+
+    pub async fn get_expect_with_headers(
+        &self,
+        uri: &str,
+        headers: &[(&str, &str)],
+        expected_code: StatusCode,
+    ) -> Bytes {
+        self.send_request(TestRequest::Get(), uri, headers, expected_code)
+            .await
+    }
+
+    pub async fn post_expect(&self, uri: &str, body: Body, expected_code: StatusCode) -> Bytes {
+        self.send_request(TestRequest::Post(body), uri, &[], expected_code)
+            .await
+    }
+
+    pub async fn post_oracle_message(
+        &self,
+        message: &OracleMessage,
+        expected_code: StatusCode,
+    ) -> Bytes {
+        let body = Body::from(serde_json::to_string(message).unwrap());
+        self.post_expect("/post_oracle_message", body, expected_code)
+            .await
+    }
+
+    pub async fn post_expect_with_headers(
+        &self,
+        uri: &str,
+        body: Body,
+        headers: &[(&str, &str)],
+        expected_code: StatusCode,
+    ) -> Bytes {
+        self.send_request(TestRequest::Post(body), uri, headers, expected_code)
+            .await
+    }
+
+    /// The full response to a GET request, for checking its headers.
+    pub async fn get_response(&self, uri: &str, headers: &[(&str, &str)]) -> Response {
+        self.send_request_for_response(TestRequest::Get(), uri, headers)
+            .await
+    }
+
+    pub async fn options_response(&self, uri: &str, headers: &[(&str, &str)]) -> Response {
+        self.send_request_for_response(TestRequest::Options(), uri, headers)
+            .await
+    }
+
+    async fn send_request(
+        &self,
+        request: TestRequest,
+        uri: &str,
+        headers: &[(&str, &str)],
+        expected_code: StatusCode,
+    ) -> Bytes {
+        let response = self.send_request_for_response(request, uri, headers).await;
+        assert_eq!(response.status(), expected_code);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        body
+    }
+
+    async fn send_request_for_response(
+        &self,
+        request: TestRequest,
+        uri: &str,
+        headers: &[(&str, &str)],
+    ) -> Response {
+        let app = self.router.clone();
+        let mut builder = match request {
+            TestRequest::Get() => Request::builder().uri(uri).method("GET"),
+            TestRequest::Options() => Request::builder().uri(uri).method("OPTIONS"),
+            TestRequest::Post(_) => {
+                let builder = Request::builder().uri(uri).method("POST");
+                if headers
+                    .iter()
+                    .any(|(name, _)| name.eq_ignore_ascii_case("Content-Type"))
+                {
+                    builder
+                } else {
+                    builder.header("Content-Type", "application/json")
+                }
+            }
+        };
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        let req = match request {
+            TestRequest::Get() | TestRequest::Options() => builder.body(Body::empty()).unwrap(),
+            TestRequest::Post(body) => builder.body(body).unwrap(),
+        };
+
+        app.oneshot(req).await.unwrap()
+    }
+}
+
+/// The test data signed again with a fixed key, as its signatures predate the domains of the
+/// message types.
+pub fn get_test_message() -> OracleMessage {
+    static TEST_MESSAGE: OnceLock<OracleMessage> = OnceLock::new();
+    TEST_MESSAGE
+        .get_or_init(|| {
+            let test_data_file = std::fs::File::open("./test_data/input/6556020.json").unwrap();
+            let message = serde_json::from_reader(test_data_file).unwrap();
+            let mut key_bytes = [0; 32];
+            key_bytes[31] = 1;
+            sign_oracle_message_with_new_key(message, &SecretKey::deserialize(&key_bytes).unwrap())
+        })
+        .clone()
+}
+
+pub fn sign_oracle_message_with_new_key(
+    mut message: OracleMessage,
+    private_key: &SecretKey,
+) -> OracleMessage {
+    let digest_config = DigestConfig::default().for_asset(&message.asset);
+    message.validator_public_key = private_key.public_key();
+    message.value_message.signature = private_key.sign(get_message_digest(
+        &message.value_message.message,
+        digest_config,
+    ));
+    for interval_message in message.interval_inclusion_messages.iter_mut() {
+        interval_message.signature =
+            private_key.sign(get_message_digest(&interval_message.message, digest_config));
+    }
+    message
+}
+
+/// The test message without interval messages, with the given price, signed by a new validator.
+pub fn value_message_from_new_validator(value: u64) -> OracleMessage {
+    value_message(&SecretKey::random(), TEST_MESSAGE_SLOT, value)
+}
+
+/// The test message without interval messages, for the given slot and price.
+pub fn value_message(private_key: &SecretKey, slot_number: u64, value: u64) -> OracleMessage {
+    let mut message = get_test_message();
+    message.value_message.message.slot_number = slot_number;
+    message.value_message.message.price.value = value;
+    message.interval_inclusion_messages.clear();
+    sign_oracle_message_with_new_key(message, private_key)
+}
+
+pub fn signature_from_random_signer<T: SignedMessage>(message: &T) -> Signature {
+    let private_key = SecretKey::random();
+    sign_message(message, &private_key)
+}
+
+pub fn sign_message<T: SignedMessage>(message: &T, private_key: &SecretKey) -> Signature {
+    let message_digest = get_message_digest(message, DigestConfig::default());
+    private_key.sign(message_digest)
+}
+
+pub fn admin_config() -> Config {
+    Config {
+        admin_tokens: vec![AdminToken {
+            id: "test-admin".to_string(),
+            token: "test-token".to_string(),
+        }],
+        ..Config::default()
+    }
+}
+
+pub const ADMIN_AUTHORIZATION: (&str, &str) = ("Authorization", "Bearer test-token");
@@ -0,0 +1,515 @@
+use crate::attestations::{
+    cache_latest_value, AggregatePriceIntervalEntry, PriceValueEntry, DEFAULT_ASSET,
+};
+use crate::canonical::{encode_public_key, PublicKeyParam};
+use crate::consensus::{interval_contains, pick_best_aggregate};
+use crate::db::DbPool;
+use crate::quorum::get_quorum_threshold;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Widest slot range a single consensus agreement request may span, about a day.
+const MAX_AGREEMENT_SLOT_RANGE: i64 = 7200;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ValidatorStreak {
+    pub validator_public_key: String,
+    /// Number of consecutive slots attested up to the validator's most recent slot, or zero if
+    /// they missed the slot before the current one.
+    pub current_streak: u64,
+    pub longest_streak: u64,
+    pub last_slot_number: Option<i64>,
+}
+
+/// Participation streaks of a validator, based on the slots they submitted a value message for.
+pub async fn get_validator_streak(
+    Path(PublicKeyParam(validator_public_key)): Path<PublicKeyParam>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ValidatorStreak>, StatusCode> {
+    let pk_string = encode_public_key(&validator_public_key);
+    let slot_numbers = get_attested_slot_numbers(&state.db_pool, &pk_string)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting attested slots: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let streaks = get_streak_lengths(&slot_numbers);
+    let last_slot_number = slot_numbers.last().copied();
+    let current_slot = state.slot_clock.current_slot() as i64;
+    let current_streak = match last_slot_number {
+        Some(last_slot_number) if last_slot_number >= current_slot - 1 => {
+            streaks.last().copied().unwrap_or(0)
+        }
+        _ => 0,
+    };
+    Ok(Json(ValidatorStreak {
+        validator_public_key: pk_string,
+        current_streak,
+        longest_streak: streaks.iter().max().copied().unwrap_or(0),
+        last_slot_number,
+    }))
+}
+
+/// Lengths of the runs of consecutive slots, in order, given distinct ascending slot numbers.
+fn get_streak_lengths(slot_numbers: &[i64]) -> Vec<u64> {
+    let mut streaks = Vec::new();
+    let mut previous_slot_number = None;
+    for &slot_number in slot_numbers {
+        match (previous_slot_number, streaks.last_mut()) {
+            (Some(previous), Some(streak)) if slot_number == previous + 1 => *streak += 1,
+            _ => streaks.push(1),
+        }
+        previous_slot_number = Some(slot_number);
+    }
+    streaks
+}
+
+/// The validator's value attestation for their most recent slot, served from memory when possible.
+pub async fn get_validator_latest(
+    Path(PublicKeyParam(validator_public_key)): Path<PublicKeyParam>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<PriceValueEntry>, StatusCode> {
+    let cached_entry = state
+        .latest_values
+        .lock()
+        .unwrap()
+        .get(&validator_public_key)
+        .cloned();
+    if let Some(entry) = cached_entry {
+        return Ok(Json(entry));
+    }
+
+    let entry = get_latest_value(&state.db_pool, &encode_public_key(&validator_public_key))
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting latest value: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    cache_latest_value(&state, &validator_public_key, entry.clone());
+    Ok(Json(entry))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConsensusAgreementQuery {
+    pub from_slot: i64,
+    /// Inclusive.
+    pub to_slot: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct SlotAgreement {
+    pub slot_number: i64,
+    pub value: i64,
+    /// Value and size of the interval aggregate with the most validators among those reaching
+    /// quorum, null if none did.
+    pub consensus_interval_value: Option<i64>,
+    pub consensus_interval_size: Option<i64>,
+    /// Whether the validator's value falls within the consensus interval, null without one.
+    pub agrees: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConsensusAgreement {
+    pub validator_public_key: String,
+    /// The slots in the range the validator attested a value for, ascending.
+    pub slots: Vec<SlotAgreement>,
+    /// Percentage of the slots with a consensus the validator agreed with, null if there are
+    /// none.
+    pub agreement_percent: Option<f64>,
+}
+
+/// How often a validator's value fell within the quorum consensus interval of the slot, for
+/// scoring validators.
+pub async fn get_validator_consensus_agreement(
+    Path(PublicKeyParam(validator_public_key)): Path<PublicKeyParam>,
+    Query(query): Query<ConsensusAgreementQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ConsensusAgreement>, (StatusCode, String)> {
+    let num_slots = query.to_slot - query.from_slot + 1;
+    if !(1..=MAX_AGREEMENT_SLOT_RANGE).contains(&num_slots) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("slot range must span 1 to {MAX_AGREEMENT_SLOT_RANGE} slots"),
+        ));
+    }
+    let internal_error = |e: sqlx::Error| {
+        tracing::error!("Error getting consensus agreement: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "error getting consensus agreement".to_string(),
+        )
+    };
+    let pk_string = encode_public_key(&validator_public_key);
+    let values = sqlx::query!(
+        "
+        SELECT
+            slot_number,
+            value
+        FROM
+            price_value_attestations
+        WHERE
+            validator_public_key = $1
+        AND
+            asset = $2
+        AND
+            slot_number BETWEEN $3 AND $4
+        ORDER BY
+            slot_number;
+        ",
+        pk_string,
+        DEFAULT_ASSET,
+        query.from_slot,
+        query.to_slot,
+    )
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(internal_error)?;
+
+    // Consensus doesn't distinguish assets yet, the default asset's threshold applies.
+    let min_operators = state.config.quorum_min_operators.unwrap_or(0);
+    let mut quorum_aggregates: HashMap<i64, Vec<AggregatePriceIntervalEntry>> = sqlx::query!(
+        "
+        SELECT
+            asset,
+            value,
+            slot_number,
+            aggregate_signature,
+            aggregate_public_key,
+            public_keys,
+            interval_size,
+            num_validators,
+            num_operators
+        FROM
+            aggregate_interval_attestations
+        WHERE
+            slot_number BETWEEN $1 AND $2
+        AND
+            num_operators >= $3;
+        ",
+        query.from_slot,
+        query.to_slot,
+        min_operators,
+    )
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(internal_error)?
+    .into_iter()
+    .map(|row| AggregatePriceIntervalEntry {
+        asset: row.asset,
+        value: row.value,
+        slot_number: row.slot_number,
+        aggregate_signature: row.aggregate_signature,
+        aggregate_public_key: row.aggregate_public_key,
+        public_keys: row.public_keys,
+        interval_size: row.interval_size,
+        num_validators: row.num_validators,
+        num_operators: row.num_operators,
+        finalized: None,
+    })
+    .filter(|entry| {
+        let quorum_threshold =
+            get_quorum_threshold(&state, DEFAULT_ASSET, entry.slot_number).unwrap_or(1);
+        entry.num_validators >= quorum_threshold
+    })
+    .into_group_map_by(|entry| entry.slot_number);
+
+    let slots: Vec<SlotAgreement> = values
+        .into_iter()
+        .map(|row| {
+            let consensus_aggregate = quorum_aggregates
+                .remove(&row.slot_number)
+                .and_then(pick_best_aggregate);
+            SlotAgreement {
+                slot_number: row.slot_number,
+                value: row.value,
+                consensus_interval_value: consensus_aggregate.as_ref().map(|entry| entry.value),
+                consensus_interval_size: consensus_aggregate
+                    .as_ref()
+                    .map(|entry| entry.interval_size),
+                agrees: consensus_aggregate
+                    .as_ref()
+                    .map(|entry| interval_contains(entry, row.value)),
+            }
+        })
+        .collect();
+    let judged: Vec<bool> = slots.iter().filter_map(|slot| slot.agrees).collect();
+    let agreement_percent = (!judged.is_empty()).then(|| {
+        judged.iter().filter(|agrees| **agrees).count() as f64 * 100.0 / judged.len() as f64
+    });
+    Ok(Json(ConsensusAgreement {
+        validator_public_key: pk_string,
+        slots,
+        agreement_percent,
+    }))
+}
+
+async fn get_latest_value(
+    db_pool: &DbPool,
+    pk_string: &str,
+) -> eyre::Result<Option<PriceValueEntry>> {
+    let entry = sqlx::query!(
+        r#"
+        SELECT
+            validator_public_key,
+            asset,
+            value,
+            slot_number,
+            signature,
+            signing_preimage,
+            price_timestamp,
+            (EXTRACT(EPOCH FROM received_at) * 1000)::BIGINT AS "received_at!"
+        FROM
+            price_value_attestations
+        WHERE
+            validator_public_key = $1
+        ORDER BY
+            slot_number DESC,
+            received_at DESC
+        LIMIT 1;
+        "#,
+        pk_string
+    )
+    .fetch_optional(db_pool)
+    .await?
+    .map(|row| PriceValueEntry {
+        validator_public_key: row.validator_public_key,
+        asset: row.asset,
+        value: row.value,
+        slot_number: row.slot_number,
+        signature: row.signature,
+        signing_preimage: row.signing_preimage,
+        price_timestamp: row.price_timestamp,
+        received_at: row.received_at,
+    });
+    Ok(entry)
+}
+
+async fn get_attested_slot_numbers(db_pool: &DbPool, pk_string: &str) -> eyre::Result<Vec<i64>> {
+    let slot_numbers = sqlx::query!(
+        "
+        SELECT DISTINCT
+            slot_number
+        FROM
+            price_value_attestations
+        WHERE
+            validator_public_key = $1
+        ORDER BY
+            slot_number;
+        ",
+        pk_string
+    )
+    .fetch_all(db_pool)
+    .await?
+    .into_iter()
+    .map(|row| row.slot_number)
+    .collect();
+    Ok(slot_numbers)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::attestations::OracleMessage;
+    use crate::config::Config;
+    use crate::test_utils::{
+        get_test_message, sign_oracle_message_with_new_key, value_message, TestApp,
+        TEST_MESSAGE_SLOT,
+    };
+    use bls::SecretKey;
+
+    #[sqlx::test]
+    async fn computes_streaks_around_missed_slot(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool).await;
+        let private_key = SecretKey::random();
+        // Misses the slot two before the current one
+        for slot_number in [
+            TEST_MESSAGE_SLOT - 5,
+            TEST_MESSAGE_SLOT - 4,
+            TEST_MESSAGE_SLOT - 3,
+            TEST_MESSAGE_SLOT - 1,
+            TEST_MESSAGE_SLOT,
+        ] {
+            test_app
+                .post_oracle_message(
+                    &value_message(&private_key, slot_number, 1811093163),
+                    StatusCode::OK,
+                )
+                .await;
+        }
+
+        let response = test_app
+            .get_expect(
+                &format!("/validators/{}/streak", private_key.public_key()),
+                StatusCode::OK,
+            )
+            .await;
+        let streak: ValidatorStreak = serde_json::from_slice(&response).unwrap();
+        assert_eq!(streak.current_streak, 2);
+        assert_eq!(streak.longest_streak, 3);
+        assert_eq!(streak.last_slot_number, Some(TEST_MESSAGE_SLOT as i64));
+
+        let response = test_app
+            .get_expect(
+                &format!("/validators/{}/streak", SecretKey::random().public_key()),
+                StatusCode::OK,
+            )
+            .await;
+        let streak: ValidatorStreak = serde_json::from_slice(&response).unwrap();
+        assert_eq!(streak.current_streak, 0);
+        assert_eq!(streak.longest_streak, 0);
+        assert_eq!(streak.last_slot_number, None);
+    }
+
+    async fn get_latest_via(test_app: &TestApp, private_key: &SecretKey) -> PriceValueEntry {
+        let response = test_app
+            .get_expect(
+                &format!("/validators/{}/latest", private_key.public_key()),
+                StatusCode::OK,
+            )
+            .await;
+        serde_json::from_slice(&response).unwrap()
+    }
+
+    #[sqlx::test]
+    async fn serves_latest_value_matching_db(db_pool: DbPool) {
+        let test_app = TestApp::new(db_pool.clone()).await;
+        let private_keys = [SecretKey::random(), SecretKey::random()];
+        // Submitted out of slot order, the latest slot should win
+        for (index, slot_number, value) in [
+            (0, TEST_MESSAGE_SLOT - 2, 100),
+            (1, TEST_MESSAGE_SLOT - 1, 200),
+            (0, TEST_MESSAGE_SLOT, 300),
+            (0, TEST_MESSAGE_SLOT - 1, 400),
+            (1, TEST_MESSAGE_SLOT - 3, 500),
+        ] {
+            test_app
+                .post_oracle_message(
+                    &value_message(&private_keys[index], slot_number, value),
+                    StatusCode::OK,
+                )
+                .await;
+        }
+
+        // A restarted app has an empty cache, falling back to the DB
+        let restarted_app = TestApp::new(db_pool.clone()).await;
+        for private_key in &private_keys {
+            let db_entry = get_latest_value(&db_pool, &private_key.public_key().to_string())
+                .await
+                .unwrap()
+                .unwrap();
+            let cached_entry = get_latest_via(&test_app, private_key).await;
+            let fallback_entry = get_latest_via(&restarted_app, private_key).await;
+            for entry in [cached_entry, fallback_entry] {
+                assert_eq!(entry.validator_public_key, db_entry.validator_public_key);
+                assert_eq!(entry.slot_number, db_entry.slot_number);
+                assert_eq!(entry.value, db_entry.value);
+                assert_eq!(entry.signature, db_entry.signature);
+                assert_eq!(entry.received_at, db_entry.received_at);
+            }
+        }
+        assert_eq!(get_latest_via(&test_app, &private_keys[0]).await.value, 300);
+        assert_eq!(get_latest_via(&test_app, &private_keys[1]).await.value, 200);
+
+        test_app
+            .get_expect(
+                &format!("/validators/{}/latest", SecretKey::random().public_key()),
+                StatusCode::NOT_FOUND,
+            )
+            .await;
+    }
+
+    /// The test message for the slot and value, with its first three interval messages.
+    fn message_with_intervals(
+        private_key: &SecretKey,
+        slot_number: u64,
+        value: u64,
+    ) -> OracleMessage {
+        let mut message = get_test_message();
+        message.value_message.message.slot_number = slot_number;
+        message.value_message.message.price.value = value;
+        message.interval_inclusion_messages.truncate(3);
+        for interval_message in &mut message.interval_inclusion_messages {
+            interval_message.message.slot_number = slot_number;
+        }
+        sign_oracle_message_with_new_key(message, private_key)
+    }
+
+    #[sqlx::test]
+    async fn scores_agreement_with_consensus(db_pool: DbPool) {
+        let config = Config {
+            quorum_threshold: Some(2),
+            ..Config::default()
+        };
+        let test_app = TestApp::with_config(db_pool, config).await;
+        let private_key = SecretKey::random();
+        let other_key = SecretKey::random();
+        let price = 1811093163;
+        let messages = [
+            // Agrees with the consensus
+            message_with_intervals(&private_key, TEST_MESSAGE_SLOT, price),
+            message_with_intervals(&other_key, TEST_MESSAGE_SLOT, price),
+            // Helps reach quorum with a value far outside the consensus interval
+            message_with_intervals(&private_key, TEST_MESSAGE_SLOT - 1, 1),
+            message_with_intervals(&other_key, TEST_MESSAGE_SLOT - 1, price),
+            // No quorum at all
+            value_message(&private_key, TEST_MESSAGE_SLOT - 2, price),
+        ];
+        for message in &messages {
+            test_app.post_oracle_message(message, StatusCode::OK).await;
+        }
+
+        let response = test_app
+            .get_expect(
+                &format!(
+                    "/validators/{}/consensus-agreement?from_slot={}&to_slot={}",
+                    private_key.public_key(),
+                    TEST_MESSAGE_SLOT - 2,
+                    TEST_MESSAGE_SLOT
+                ),
+                StatusCode::OK,
+            )
+            .await;
+        let agreement: ConsensusAgreement = serde_json::from_slice(&response).unwrap();
+        let agrees: Vec<(i64, Option<bool>)> = agreement
+            .slots
+            .iter()
+            .map(|slot| (slot.slot_number, slot.agrees))
+            .collect();
+        let slot = TEST_MESSAGE_SLOT as i64;
+        assert_eq!(
+            agrees,
+            vec![
+                (slot - 2, None),
+                (slot - 1, Some(false)),
+                (slot, Some(true))
+            ]
+        );
+        // The median of the three equally supported intervals
+        let consensus_interval = &messages[0].interval_inclusion_messages[1].message;
+        assert_eq!(
+            agreement.slots[2].consensus_interval_value,
+            Some(consensus_interval.value as i64)
+        );
+        assert_eq!(agreement.agreement_percent, Some(50.0));
+
+        test_app
+            .get_expect(
+                &format!(
+                    "/validators/{}/consensus-agreement?from_slot=0&to_slot={TEST_MESSAGE_SLOT}",
+                    private_key.public_key()
+                ),
+                StatusCode::BAD_REQUEST,
+            )
+            .await;
+    }
+}
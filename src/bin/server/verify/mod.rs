@@ -0,0 +1,262 @@
+//! Offline verification of attestations collected elsewhere, e.g. a data dump of another node.
+//! Nothing is stored.
+
+use crate::attestations::{
+    default_asset, get_message_digest, validate_message, DigestConfig, IntervalInclusionMessage,
+    OracleMessage, PriceValueMessage, SignedMessage,
+};
+use crate::state::AppState;
+use axum::{extract::State, Json};
+use bls::{PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ValueAttestation {
+    #[serde(default = "default_asset")]
+    pub asset: String,
+    pub validator_public_key: PublicKey,
+    pub message: PriceValueMessage,
+    pub signature: Signature,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IntervalAttestation {
+    #[serde(default = "default_asset")]
+    pub asset: String,
+    pub validator_public_key: PublicKey,
+    pub message: IntervalInclusionMessage,
+    pub signature: Signature,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct VerifyBatchRequest {
+    #[serde(default)]
+    pub value_attestations: Vec<ValueAttestation>,
+    #[serde(default)]
+    pub interval_attestations: Vec<IntervalAttestation>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VerifyBatchResponse {
+    /// Signature validity of each value attestation, in the order of the request.
+    pub value_results: Vec<bool>,
+    /// Signature validity of each interval attestation, in the order of the request.
+    pub interval_results: Vec<bool>,
+    pub num_valid: usize,
+    pub num_invalid: usize,
+}
+
+pub async fn post_verify_batch(
+    State(state): State<Arc<AppState>>,
+    Json(batch): Json<VerifyBatchRequest>,
+) -> Json<VerifyBatchResponse> {
+    let digest_config = state.config.digest_config();
+    let value_results: Vec<bool> = batch
+        .value_attestations
+        .iter()
+        .map(|attestation| {
+            validate_message(
+                &attestation.validator_public_key,
+                &attestation.message,
+                &attestation.signature,
+                digest_config.for_asset(&attestation.asset),
+            )
+        })
+        .collect();
+    let interval_results: Vec<bool> = batch
+        .interval_attestations
+        .iter()
+        .map(|attestation| {
+            validate_message(
+                &attestation.validator_public_key,
+                &attestation.message,
+                &attestation.signature,
+                digest_config.for_asset(&attestation.asset),
+            )
+        })
+        .collect();
+
+    let num_valid = value_results
+        .iter()
+        .chain(interval_results.iter())
+        .filter(|valid| **valid)
+        .count();
+    let num_invalid = value_results.len() + interval_results.len() - num_valid;
+    Json(VerifyBatchResponse {
+        value_results,
+        interval_results,
+        num_valid,
+        num_invalid,
+    })
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ComponentVerification {
+    /// Digest the signature is checked against, hex encoded.
+    pub digest: String,
+    pub valid: bool,
+}
+
+impl ComponentVerification {
+    fn verify<T: SignedMessage>(
+        validator_public_key: &PublicKey,
+        message: &T,
+        signature: &Signature,
+        digest_config: DigestConfig,
+    ) -> Self {
+        let digest = get_message_digest(message, digest_config);
+        ComponentVerification {
+            digest: format!("0x{}", hex::encode(digest.as_bytes())),
+            valid: signature.verify(validator_public_key, digest),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OracleMessageInspection {
+    pub value_message: ComponentVerification,
+    /// In the order of the message.
+    pub interval_inclusion_messages: Vec<ComponentVerification>,
+    /// Whether every signature verifies.
+    pub valid: bool,
+}
+
+/// Verifies every signature of an oracle message without storing it, so client developers can
+/// tell which part of a rejected message is off.
+pub async fn post_inspect_oracle_message(
+    State(state): State<Arc<AppState>>,
+    Json(message): Json<OracleMessage>,
+) -> Json<OracleMessageInspection> {
+    let digest_config = state.config.digest_config().for_asset(&message.asset);
+    let validator_public_key = &message.validator_public_key;
+    let value_message = ComponentVerification::verify(
+        validator_public_key,
+        &message.value_message.message,
+        &message.value_message.signature,
+        digest_config,
+    );
+    let interval_inclusion_messages: Vec<ComponentVerification> = message
+        .interval_inclusion_messages
+        .iter()
+        .map(|interval_message| {
+            ComponentVerification::verify(
+                validator_public_key,
+                &interval_message.message,
+                &interval_message.signature,
+                digest_config,
+            )
+        })
+        .collect();
+    let valid = value_message.valid
+        && interval_inclusion_messages
+            .iter()
+            .all(|verification| verification.valid);
+    Json(OracleMessageInspection {
+        value_message,
+        interval_inclusion_messages,
+        valid,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::DbPool;
+    use crate::test_utils::{get_test_message, signature_from_random_signer, TestApp};
+    use axum::{body::Body, http::StatusCode};
+    use bls::SecretKey;
+
+    #[sqlx::test]
+    async fn verifies_mixed_validity_batch(db_pool: DbPool) {
+        let test_message = get_test_message();
+        let validator_public_key = test_message.validator_public_key.clone();
+        let interval_attestation = |index: usize| {
+            let interval_message = &test_message.interval_inclusion_messages[index];
+            IntervalAttestation {
+                asset: test_message.asset.clone(),
+                validator_public_key: validator_public_key.clone(),
+                message: interval_message.message.clone(),
+                signature: interval_message.signature.clone(),
+            }
+        };
+        let mut forged_interval_attestation = interval_attestation(1);
+        forged_interval_attestation.signature =
+            signature_from_random_signer(&forged_interval_attestation.message);
+        let batch = VerifyBatchRequest {
+            value_attestations: vec![
+                ValueAttestation {
+                    asset: test_message.asset.clone(),
+                    validator_public_key: validator_public_key.clone(),
+                    message: test_message.value_message.message.clone(),
+                    signature: test_message.value_message.signature.clone(),
+                },
+                ValueAttestation {
+                    asset: test_message.asset.clone(),
+                    validator_public_key: SecretKey::random().public_key(),
+                    message: test_message.value_message.message.clone(),
+                    signature: test_message.value_message.signature.clone(),
+                },
+            ],
+            interval_attestations: vec![interval_attestation(0), forged_interval_attestation],
+        };
+
+        let test_app = TestApp::new(db_pool).await;
+        let response = test_app
+            .post_expect(
+                "/verify/batch",
+                Body::from(serde_json::to_string(&batch).unwrap()),
+                StatusCode::OK,
+            )
+            .await;
+        let result: VerifyBatchResponse = serde_json::from_slice(&response).unwrap();
+        assert_eq!(result.value_results, vec![true, false]);
+        assert_eq!(result.interval_results, vec![true, false]);
+        assert_eq!(result.num_valid, 2);
+        assert_eq!(result.num_invalid, 2);
+
+        // Nothing is stored
+        let response = test_app
+            .get_expect("/price_value_attestations", StatusCode::OK)
+            .await;
+        assert_eq!(&response[..], b"[]");
+    }
+
+    #[sqlx::test]
+    async fn inspects_each_signature_of_a_message(db_pool: DbPool) {
+        let mut message = get_test_message();
+        message.interval_inclusion_messages.truncate(3);
+        message.interval_inclusion_messages[1].signature =
+            signature_from_random_signer(&message.interval_inclusion_messages[1].message);
+
+        let test_app = TestApp::new(db_pool).await;
+        let response = test_app
+            .post_expect(
+                "/oracle-message/inspect",
+                Body::from(serde_json::to_string(&message).unwrap()),
+                StatusCode::OK,
+            )
+            .await;
+        let inspection: OracleMessageInspection = serde_json::from_slice(&response).unwrap();
+        assert!(inspection.value_message.valid);
+        let expected_digest =
+            get_message_digest(&message.value_message.message, DigestConfig::default());
+        assert_eq!(
+            inspection.value_message.digest,
+            format!("0x{}", hex::encode(expected_digest.as_bytes()))
+        );
+        let interval_results: Vec<bool> = inspection
+            .interval_inclusion_messages
+            .iter()
+            .map(|verification| verification.valid)
+            .collect();
+        assert_eq!(interval_results, vec![true, false, true]);
+        assert!(!inspection.valid);
+
+        // Nothing is stored
+        let response = test_app
+            .get_expect("/price_value_attestations", StatusCode::OK)
+            .await;
+        assert_eq!(&response[..], b"[]");
+    }
+}